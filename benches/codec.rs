@@ -0,0 +1,92 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use reed_solomon_codec::{EncoderConfig, GaloisField, ReedSolomonCodec, Shard};
+
+const PAYLOAD_SIZES: [usize; 3] = [4 << 10, 1 << 20, 16 << 20];
+
+const CONFIGS: [(GaloisField, usize, usize); 5] = [
+    (GaloisField::Gf8, 4, 2),
+    (GaloisField::Gf8, 10, 4),
+    (GaloisField::Gf8, 20, 10),
+    (GaloisField::Gf16, 10, 4),
+    (GaloisField::Gf16, 300, 100),
+];
+
+fn codec_for(field: GaloisField, data_shards: usize, parity_shards: usize) -> ReedSolomonCodec {
+    let config: EncoderConfig =
+        EncoderConfig::with_field(data_shards, parity_shards, field).unwrap();
+    ReedSolomonCodec::with_config(config).unwrap()
+}
+
+fn label(field: GaloisField, data_shards: usize, parity_shards: usize, size: usize) -> String {
+    format!(
+        "{:?}/{}+{}/{}KiB",
+        field,
+        data_shards,
+        parity_shards,
+        size >> 10
+    )
+}
+
+fn payload(size: usize) -> Vec<u8> {
+    (0..size).map(|i: usize| (i % 251) as u8).collect()
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    group.sample_size(10);
+
+    for (field, data_shards, parity_shards) in CONFIGS {
+        let codec: ReedSolomonCodec = codec_for(field, data_shards, parity_shards);
+        for size in PAYLOAD_SIZES {
+            let data: Vec<u8> = payload(size);
+            let label: String = label(field, data_shards, parity_shards, size);
+            group.throughput(Throughput::Bytes(size as u64));
+
+            group.bench_with_input(BenchmarkId::new("container", &label), &data, |b, data| {
+                b.iter(|| codec.encode(black_box(data)).unwrap())
+            });
+
+            group.bench_with_input(BenchmarkId::new("shards", &label), &data, |b, data| {
+                b.iter(|| codec.encode_shards(black_box(data)).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+
+    for (field, data_shards, parity_shards) in CONFIGS {
+        let codec: ReedSolomonCodec = codec_for(field, data_shards, parity_shards);
+        for size in PAYLOAD_SIZES {
+            let data: Vec<u8> = payload(size);
+            let label: String = label(field, data_shards, parity_shards, size);
+            group.throughput(Throughput::Bytes(size as u64));
+
+            let encoded: Vec<u8> = codec.encode(&data).unwrap();
+            group.bench_with_input(
+                BenchmarkId::new("container", &label),
+                &encoded,
+                |b, encoded| b.iter(|| codec.decode(black_box(encoded)).unwrap()),
+            );
+
+            let mut shards: Vec<Shard> = codec.encode_shards(&data).unwrap();
+            shards.drain(..parity_shards);
+            group.bench_with_input(
+                BenchmarkId::new("shards_degraded", &label),
+                &shards,
+                |b, shards| b.iter(|| codec.decode_shards(black_box(shards.clone())).unwrap()),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_decode);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use reed_solomon_codec::{EncoderConfig, ReedSolomonCodec};
+
+const PAYLOAD_SIZE: usize = 64 << 20;
+const BLOCK_SIZE: usize = 1 << 20;
+
+fn bench_encode(c: &mut Criterion) {
+    let config: EncoderConfig = EncoderConfig::new(10, 4)
+        .and_then(|config: EncoderConfig| config.with_block_size(BLOCK_SIZE))
+        .unwrap();
+    let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config).unwrap();
+    let data: Vec<u8> = (0..PAYLOAD_SIZE).map(|i: usize| (i % 251) as u8).collect();
+
+    let mut group = c.benchmark_group("encode_64MiB");
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+    group.sample_size(10);
+
+    group.bench_function("sequential", |b| {
+        b.iter(|| codec.encode_blocks(black_box(&data)).unwrap())
+    });
+
+    group.bench_function("parallel", |b| {
+        b.iter(|| codec.encode_parallel(black_box(&data)).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode);
+criterion_main!(benches);
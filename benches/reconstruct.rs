@@ -0,0 +1,58 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use reed_solomon_codec::{ReedSolomonCodec, Shard};
+
+const PAYLOAD_SIZE: usize = 4 << 20;
+
+fn lost_shards(codec: &ReedSolomonCodec, data: &[u8]) -> Vec<Option<Vec<u8>>> {
+    let data_shards: usize = codec.config().data_shards();
+    let mut shards: Vec<Option<Vec<u8>>> = codec
+        .encode_shards(data)
+        .unwrap()
+        .into_iter()
+        .map(|shard: Shard| Some(shard.payload))
+        .collect();
+
+    shards[0] = None;
+    shards[data_shards] = None;
+    shards
+}
+
+fn bench_reconstruct(c: &mut Criterion) {
+    let data: Vec<u8> = (0..PAYLOAD_SIZE).map(|i: usize| (i % 251) as u8).collect();
+
+    let mut group = c.benchmark_group("reconstruct_4MiB");
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+
+    for (data_shards, parity_shards) in [(10, 4), (20, 10)] {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(data_shards, parity_shards).unwrap();
+        let shards: Vec<Option<Vec<u8>>> = lost_shards(&codec, &data);
+        let label: String = format!("{}+{}", data_shards, parity_shards);
+
+        group.bench_with_input(BenchmarkId::new("full", &label), &shards, |b, shards| {
+            b.iter(|| {
+                let mut shards: Vec<Option<Vec<u8>>> = shards.clone();
+                codec.reconstruct(black_box(&mut shards)).unwrap();
+                shards
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("data_only", &label),
+            &shards,
+            |b, shards| {
+                b.iter(|| {
+                    let mut shards: Vec<Option<Vec<u8>>> = shards.clone();
+                    codec.reconstruct_data(black_box(&mut shards)).unwrap();
+                    shards
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_reconstruct);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use reed_solomon_codec::ReedSolomonCodec;
+
+fuzz_target!(|data: &[u8]| {
+    let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2).unwrap();
+    let _ = codec.decode(data);
+});
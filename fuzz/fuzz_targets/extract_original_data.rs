@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use reed_solomon_codec::processor::{LEGACY_PREFIX_SIZE, PREFIX_SIZE};
+use reed_solomon_codec::DataProcessor;
+
+fuzz_target!(|data: &[u8]| {
+    for prefix_size in [PREFIX_SIZE, LEGACY_PREFIX_SIZE] {
+        if let Ok(original) = DataProcessor::extract_original_data(data, prefix_size) {
+            assert_eq!(original, data[prefix_size..prefix_size + original.len()]);
+        }
+    }
+});
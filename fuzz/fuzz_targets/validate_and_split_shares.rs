@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use reed_solomon_codec::DataProcessor;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((total_shards, data)) = data.split_first() else {
+        return;
+    };
+
+    if let Ok(shares) = DataProcessor::validate_and_split_shares(data, *total_shards as usize) {
+        assert_eq!(shares.len(), *total_shards as usize);
+        assert_eq!(shares.concat(), data);
+    }
+});
@@ -0,0 +1,78 @@
+#ifndef REED_SOLOMON_CODEC_H
+#define REED_SOLOMON_CODEC_H
+
+/* Generated with cbindgen; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum RsStatus {
+  RS_STATUS_OK = 0,
+  RS_STATUS_NULL_POINTER = 1,
+  RS_STATUS_INVALID_SHARD_COUNT = 2,
+  RS_STATUS_INVALID_DATA_SIZE = 3,
+  RS_STATUS_CODEC_ERROR = 4,
+  RS_STATUS_ENCODING_ERROR = 5,
+  RS_STATUS_DECODING_ERROR = 6,
+  RS_STATUS_INVALID_HEADER = 7,
+  RS_STATUS_INVALID_MANIFEST = 8,
+  RS_STATUS_IO = 9,
+} RsStatus;
+
+typedef struct RsCodec RsCodec;
+
+/**
+ * Creates a codec, returning null if the shard counts are invalid.
+ * Release it with `rs_codec_free`.
+ */
+struct RsCodec *rs_codec_new(size_t data_shards, size_t parity_shards);
+
+/**
+ * # Safety
+ *
+ * `codec` must be null or a pointer returned by `rs_codec_new` that has not
+ * been freed yet.
+ */
+void rs_codec_free(struct RsCodec *codec);
+
+/**
+ * Encodes `data_len` bytes into a container. On success `*out`/`*out_len`
+ * receive a buffer that must be released with `rs_free`.
+ *
+ * # Safety
+ *
+ * `codec` must come from `rs_codec_new`, `data` must be valid for reads of
+ * `data_len` bytes, and `out`/`out_len` must be valid for writes.
+ */
+enum RsStatus rs_encode(const struct RsCodec *codec,
+                        const uint8_t *data,
+                        size_t data_len,
+                        uint8_t **out,
+                        size_t *out_len);
+
+/**
+ * Decodes a container produced by `rs_encode`. On success `*out`/`*out_len`
+ * receive a buffer that must be released with `rs_free`.
+ *
+ * # Safety
+ *
+ * Same requirements as `rs_encode`.
+ */
+enum RsStatus rs_decode(const struct RsCodec *codec,
+                        const uint8_t *data,
+                        size_t data_len,
+                        uint8_t **out,
+                        size_t *out_len);
+
+/**
+ * # Safety
+ *
+ * `buffer` must be null or a buffer returned by `rs_encode`/`rs_decode`
+ * together with its exact `len`, and must not be freed twice.
+ */
+void rs_free(uint8_t *buffer, size_t len);
+
+#endif  /* REED_SOLOMON_CODEC_H */
@@ -0,0 +1,439 @@
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"RSAR";
+pub const ARCHIVE_VERSION: u8 = 1;
+
+const ENTRY_END: u8 = 0;
+const ENTRY_DIRECTORY: u8 = 1;
+const ENTRY_FILE: u8 = 2;
+
+const ENTRY_HEADER_SIZE: usize = 3;
+const FILE_SIZE_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    pub files: u64,
+    pub directories: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    source: PathBuf,
+    size: Option<u64>,
+}
+
+/// Packs a directory tree into a single stream: the magic and version, then
+/// one entry per directory or regular file (kind, `/`-separated relative
+/// path, and for files the size followed by the contents), then an end
+/// marker. The tree is walked up front in sorted order and file contents
+/// are read lazily. Symlinks and special files are skipped, and
+/// permissions and timestamps are not kept.
+#[derive(Debug)]
+pub struct ArchiveReader {
+    entries: VecDeque<Entry>,
+    pending: Vec<u8>,
+    position: usize,
+    file: Option<io::Take<File>>,
+    summary: ArchiveSummary,
+    total_len: u64,
+    finished: bool,
+}
+
+impl ArchiveReader {
+    pub fn new(dir: &Path) -> Result<Self, ReedSolomonError> {
+        let mut entries: Vec<Entry> = Vec::new();
+        walk(dir, "", &mut entries)?;
+
+        let mut summary: ArchiveSummary = ArchiveSummary::default();
+        let mut total_len: u64 = (ARCHIVE_MAGIC.len() + 2) as u64;
+        for entry in &entries {
+            total_len += (ENTRY_HEADER_SIZE + entry.name.len()) as u64;
+            match entry.size {
+                Some(size) => {
+                    summary.files += 1;
+                    summary.bytes += size;
+                    total_len += FILE_SIZE_LEN as u64 + size;
+                }
+                None => summary.directories += 1,
+            }
+        }
+
+        let mut pending: Vec<u8> = ARCHIVE_MAGIC.to_vec();
+        pending.push(ARCHIVE_VERSION);
+        Ok(Self {
+            entries: entries.into(),
+            pending,
+            position: 0,
+            file: None,
+            summary,
+            total_len,
+            finished: false,
+        })
+    }
+
+    pub fn summary(&self) -> ArchiveSummary {
+        self.summary
+    }
+
+    /// Length of the whole stream, as long as no file changes size while
+    /// it is read.
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    fn next_entry(&mut self) -> io::Result<bool> {
+        self.pending.clear();
+        self.position = 0;
+
+        let Some(entry) = self.entries.pop_front() else {
+            if self.finished {
+                return Ok(false);
+            }
+            self.pending.push(ENTRY_END);
+            self.finished = true;
+            return Ok(true);
+        };
+
+        let mut header: [u8; ENTRY_HEADER_SIZE] = [0u8; ENTRY_HEADER_SIZE];
+        header[0] = if entry.size.is_some() {
+            ENTRY_FILE
+        } else {
+            ENTRY_DIRECTORY
+        };
+        BigEndian::write_u16(&mut header[1..], entry.name.len() as u16);
+        self.pending.extend_from_slice(&header);
+        self.pending.extend_from_slice(entry.name.as_bytes());
+
+        if let Some(size) = entry.size {
+            let mut len: [u8; FILE_SIZE_LEN] = [0u8; FILE_SIZE_LEN];
+            BigEndian::write_u64(&mut len, size);
+            self.pending.extend_from_slice(&len);
+            self.file = Some(File::open(&entry.source)?.take(size));
+        }
+        Ok(true)
+    }
+}
+
+impl Read for ArchiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.position < self.pending.len() {
+                let read: usize = (&self.pending[self.position..]).read(buf)?;
+                self.position += read;
+                return Ok(read);
+            }
+
+            if let Some(file) = &mut self.file {
+                let read: usize = file.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+                if file.limit() > 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "file shrank while it was archived",
+                    ));
+                }
+                self.file = None;
+            }
+
+            if !self.next_entry()? {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+fn walk(dir: &Path, prefix: &str, entries: &mut Vec<Entry>) -> Result<(), ReedSolomonError> {
+    let mut children: Vec<fs::DirEntry> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    children.sort_by_key(fs::DirEntry::file_name);
+
+    for child in children {
+        let path: PathBuf = child.path();
+        let name: &str = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .ok_or_else(|| ReedSolomonError::InvalidFileName(path.clone()))?;
+        let name: String = format!("{}{}", prefix, name);
+        if name.len() > u16::MAX as usize {
+            return Err(ReedSolomonError::InvalidFileName(path));
+        }
+
+        let file_type: fs::FileType = child.file_type()?;
+        if file_type.is_dir() {
+            entries.push(Entry {
+                name: name.clone(),
+                source: path.clone(),
+                size: None,
+            });
+            walk(&path, &format!("{}/", name), entries)?;
+        } else if file_type.is_file() {
+            entries.push(Entry {
+                name,
+                size: Some(child.metadata()?.len()),
+                source: path,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Unpacks a stream written by `ArchiveReader` into a directory as the
+/// bytes arrive, so it can sit directly behind `decode_stream`. Entry
+/// paths that would escape the directory are rejected.
+#[derive(Debug)]
+pub struct ArchiveWriter {
+    root: PathBuf,
+    header: Vec<u8>,
+    file: Option<(File, u64)>,
+    summary: ArchiveSummary,
+    started: bool,
+    finished: bool,
+}
+
+impl ArchiveWriter {
+    pub fn new(root: &Path) -> Result<Self, ReedSolomonError> {
+        fs::create_dir_all(root)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            header: Vec::new(),
+            file: None,
+            summary: ArchiveSummary::default(),
+            started: false,
+            finished: false,
+        })
+    }
+
+    /// Fails unless the end marker has been written.
+    pub fn finish(mut self) -> Result<ArchiveSummary, ReedSolomonError> {
+        if !self.finished {
+            return Err(
+                io::Error::new(io::ErrorKind::UnexpectedEof, "archive is incomplete").into(),
+            );
+        }
+        self.flush()?;
+        Ok(self.summary)
+    }
+
+    fn wanted(&self) -> usize {
+        if !self.started {
+            return ARCHIVE_MAGIC.len() + 1;
+        }
+        match self.header.first() {
+            Some(&ENTRY_DIRECTORY) | Some(&ENTRY_FILE)
+                if self.header.len() >= ENTRY_HEADER_SIZE =>
+            {
+                let name_len: usize = BigEndian::read_u16(&self.header[1..]) as usize;
+                let size_len: usize = if self.header[0] == ENTRY_FILE {
+                    FILE_SIZE_LEN
+                } else {
+                    0
+                };
+                ENTRY_HEADER_SIZE + name_len + size_len
+            }
+            Some(&ENTRY_DIRECTORY) | Some(&ENTRY_FILE) => ENTRY_HEADER_SIZE,
+            _ => 1,
+        }
+    }
+
+    fn finish_header(&mut self) -> io::Result<()> {
+        let header: Vec<u8> = std::mem::take(&mut self.header);
+        if !self.started {
+            if header[..ARCHIVE_MAGIC.len()] != ARCHIVE_MAGIC
+                || header[ARCHIVE_MAGIC.len()] != ARCHIVE_VERSION
+            {
+                return Err(invalid_data("not a directory archive"));
+            }
+            self.started = true;
+            return Ok(());
+        }
+
+        match header[0] {
+            ENTRY_END => self.finished = true,
+            ENTRY_DIRECTORY => {
+                fs::create_dir_all(self.target(&header[ENTRY_HEADER_SIZE..])?)?;
+                self.summary.directories += 1;
+            }
+            ENTRY_FILE => {
+                let name_end: usize = header.len() - FILE_SIZE_LEN;
+                let target: PathBuf = self.target(&header[ENTRY_HEADER_SIZE..name_end])?;
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let size: u64 = BigEndian::read_u64(&header[name_end..]);
+                let file: File = File::create(target)?;
+                if size > 0 {
+                    self.file = Some((file, size));
+                }
+                self.summary.files += 1;
+                self.summary.bytes += size;
+            }
+            kind => {
+                return Err(invalid_data(&format!(
+                    "unknown archive entry kind {}",
+                    kind
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn target(&self, name: &[u8]) -> io::Result<PathBuf> {
+        let name: &str =
+            std::str::from_utf8(name).map_err(|_| invalid_data("archive path is not UTF-8"))?;
+        let mut target: PathBuf = self.root.clone();
+        for part in name.split('/') {
+            let mut components = Path::new(part).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(part)), None) => target.push(part),
+                _ => return Err(invalid_data(&format!("unsafe archive path {:?}", name))),
+            }
+        }
+        Ok(target)
+    }
+}
+
+impl Write for ArchiveWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut input: &[u8] = buf;
+        while !input.is_empty() {
+            if let Some((file, remaining)) = &mut self.file {
+                let take: usize = (*remaining).min(input.len() as u64) as usize;
+                file.write_all(&input[..take])?;
+                *remaining -= take as u64;
+                input = &input[take..];
+                if *remaining == 0 {
+                    self.file = None;
+                }
+                continue;
+            }
+
+            if self.finished {
+                return Err(invalid_data("data after the end of the archive"));
+            }
+
+            let take: usize = (self.wanted() - self.header.len()).min(input.len());
+            self.header.extend_from_slice(&input[..take]);
+            input = &input[take..];
+            if self.header.len() == self.wanted() {
+                self.finish_header()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some((file, _)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl ReedSolomonCodec {
+    /// Packs `dir` with `ArchiveReader` and encodes it like `encode_stream`.
+    pub fn encode_dir<W: Write>(&self, dir: &Path, writer: W) -> Result<u64, ReedSolomonError> {
+        self.encode_stream(ArchiveReader::new(dir)?, writer)
+    }
+
+    /// Decodes an `encode_dir` stream and unpacks it into `dest`.
+    pub fn decode_dir<R: Read>(
+        &self,
+        reader: R,
+        dest: &Path,
+    ) -> Result<ArchiveSummary, ReedSolomonError> {
+        let mut unpacker: ArchiveWriter = ArchiveWriter::new(dest)?;
+        self.decode_stream(reader, &mut unpacker)?;
+        unpacker.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::header::HEADER_SIZE;
+    use crate::test_util::temp_dir;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_directory_roundtrip() -> Result<(), ReedSolomonError> {
+        let source: PathBuf = temp_dir("archive-source");
+        let dest: PathBuf = temp_dir("archive-dest");
+        fs::create_dir_all(source.join("docs/empty"))?;
+        fs::create_dir_all(source.join("src"))?;
+        fs::write(source.join("README"), b"top level")?;
+        fs::write(source.join("docs/blank"), b"")?;
+        let large: Vec<u8> = (0..20_000u32).map(|i: u32| (i % 251) as u8).collect();
+        fs::write(source.join("src/data.bin"), &large)?;
+
+        let reader: ArchiveReader = ArchiveReader::new(&source)?;
+        assert_eq!(
+            reader.summary(),
+            ArchiveSummary {
+                files: 3,
+                directories: 3,
+                bytes: 20_009,
+            }
+        );
+        let total_len: u64 = reader.total_len();
+
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(4096)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_dir(&source, &mut encoded)?;
+
+        let mut packed: Vec<u8> = Vec::new();
+        codec.decode_stream(Cursor::new(&encoded), &mut packed)?;
+        assert_eq!(packed.len() as u64, total_len);
+
+        encoded[HEADER_SIZE + 10] ^= 0xff;
+        let summary: ArchiveSummary = codec.decode_dir(Cursor::new(&encoded), &dest)?;
+        assert_eq!(summary, ArchiveReader::new(&source)?.summary());
+        assert_eq!(fs::read(dest.join("README"))?, b"top level");
+        assert_eq!(fs::read(dest.join("docs/blank"))?, b"");
+        assert_eq!(fs::read(dest.join("src/data.bin"))?, large);
+        assert!(dest.join("docs/empty").is_dir());
+
+        fs::remove_dir_all(&source)?;
+        fs::remove_dir_all(&dest)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_unsafe_or_truncated_archives() -> Result<(), ReedSolomonError> {
+        let dest: PathBuf = temp_dir("archive-unsafe");
+        let mut archive: Vec<u8> = ARCHIVE_MAGIC.to_vec();
+        archive.push(ARCHIVE_VERSION);
+        archive.extend_from_slice(&[ENTRY_DIRECTORY, 0, 2]);
+        archive.extend_from_slice(b"..");
+
+        let mut unpacker: ArchiveWriter = ArchiveWriter::new(&dest)?;
+        assert!(unpacker.write_all(&archive).is_err());
+
+        let mut unpacker: ArchiveWriter = ArchiveWriter::new(&dest)?;
+        unpacker.write_all(&archive[..ARCHIVE_MAGIC.len() + 1])?;
+        assert!(unpacker.finish().is_err());
+
+        let mut unpacker: ArchiveWriter = ArchiveWriter::new(&dest)?;
+        assert!(unpacker.write_all(b"PK\x03\x04\x14").is_err());
+
+        fs::remove_dir_all(&dest)?;
+        Ok(())
+    }
+}
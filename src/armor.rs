@@ -0,0 +1,323 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::config::EncoderConfig;
+use crate::error::{ArmorError, ReedSolomonError};
+use crate::processor::crc32c_checksum;
+use crate::shard::Shard;
+
+/// Base64 characters per body line.
+pub const LINE_LEN: usize = 64;
+
+/// The label of a block made by `armor_shard`.
+pub const SHARD_LABEL: &str = "REED-SOLOMON SHARD";
+
+/// The label for any other bytes, such as a whole shard file.
+pub const DATA_LABEL: &str = "REED-SOLOMON DATA";
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Bytes as text that survives email, tickets and paper: a BEGIN line,
+/// `Key: Value` headers, a blank line, wrapped base64 and an END line.
+/// The length and a CRC32C of the bytes are added as headers, so a
+/// mistyped transcription is caught before anything is decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArmoredBlock {
+    pub label: String,
+    /// Headers in order, without the `Length` and `Checksum` lines.
+    pub headers: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+impl ArmoredBlock {
+    pub fn new(label: &str, data: Vec<u8>) -> Self {
+        Self {
+            label: label.to_string(),
+            headers: Vec::new(),
+            data,
+        }
+    }
+
+    pub fn with_header(mut self, key: &str, value: impl ToString) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _): &&(String, String)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value): &(String, String)| value.as_str())
+    }
+
+    /// Every armored block in `text`, in order. Text around the blocks is
+    /// ignored, as are `>` quote markers at the start of lines.
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, ArmorError> {
+        let mut blocks: Vec<Self> = Vec::new();
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(number, line): (usize, &str)| {
+                (
+                    number + 1,
+                    line.trim_start_matches(['>', ' ', '\t']).trim_end(),
+                )
+            });
+
+        while let Some((_, line)) = lines.next() {
+            let Some(label) = line
+                .strip_prefix("-----BEGIN ")
+                .and_then(|rest: &str| rest.strip_suffix("-----"))
+            else {
+                continue;
+            };
+            let end: String = format!("-----END {}-----", label);
+
+            let mut headers: Vec<(String, String)> = Vec::new();
+            let mut body: String = String::new();
+            let mut in_body: bool = false;
+            let mut terminated: bool = false;
+            let mut last_line: usize = 0;
+            for (number, line) in lines.by_ref() {
+                last_line = number;
+                if line == end {
+                    terminated = true;
+                    break;
+                }
+                if !in_body {
+                    if line.is_empty() {
+                        in_body = true;
+                        continue;
+                    }
+                    let (key, value) = line
+                        .split_once(':')
+                        .ok_or_else(|| ArmorError::MalformedHeader(line.to_string()))?;
+                    headers.push((key.trim().to_string(), value.trim().to_string()));
+                } else if !line.is_empty() {
+                    if !line
+                        .bytes()
+                        .all(|byte: u8| byte == b'=' || decode_char(byte).is_some())
+                    {
+                        return Err(ArmorError::InvalidBase64 { line: number });
+                    }
+                    body.push_str(line);
+                }
+            }
+            if !terminated {
+                return Err(ArmorError::Unterminated);
+            }
+
+            let data: Vec<u8> =
+                decode_base64(&body).ok_or(ArmorError::InvalidBase64 { line: last_line })?;
+            let length: usize = take_header(&mut headers, "Length")?
+                .parse()
+                .map_err(|_| ArmorError::InvalidValue("Length".to_string()))?;
+            let checksum: u32 = u32::from_str_radix(&take_header(&mut headers, "Checksum")?, 16)
+                .map_err(|_| ArmorError::InvalidValue("Checksum".to_string()))?;
+            if data.len() != length || crc32c_checksum(&data) != checksum {
+                return Err(ArmorError::ChecksumMismatch);
+            }
+
+            blocks.push(Self {
+                label: label.to_string(),
+                headers,
+                data,
+            });
+        }
+
+        if blocks.is_empty() {
+            return Err(ArmorError::NoBlock);
+        }
+        Ok(blocks)
+    }
+}
+
+impl fmt::Display for ArmoredBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "-----BEGIN {}-----", self.label)?;
+        for (key, value) in &self.headers {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+        writeln!(f, "Length: {}", self.data.len())?;
+        writeln!(f, "Checksum: {:08x}", crc32c_checksum(&self.data))?;
+        writeln!(f)?;
+        let encoded: String = encode_base64(&self.data);
+        for line in encoded.as_bytes().chunks(LINE_LEN) {
+            writeln!(f, "{}", core::str::from_utf8(line).map_err(|_| fmt::Error)?)?;
+        }
+        writeln!(f, "-----END {}-----", self.label)
+    }
+}
+
+/// Armors one stored shard with its index and the shard counts.
+pub fn armor_shard(shard: &Shard, config: &EncoderConfig) -> String {
+    ArmoredBlock::new(SHARD_LABEL, shard.payload.clone())
+        .with_header("Index", shard.index)
+        .with_header("Data-Shards", config.data_shards())
+        .with_header("Parity-Shards", config.parity_shards())
+        .to_string()
+}
+
+/// Reads back every shard armored by `armor_shard` in `text`, skipping
+/// blocks with other labels.
+pub fn dearmor_shards(text: &str) -> Result<Vec<Shard>, ReedSolomonError> {
+    ArmoredBlock::parse_all(text)?
+        .into_iter()
+        .filter(|block: &ArmoredBlock| block.label == SHARD_LABEL)
+        .map(|block: ArmoredBlock| {
+            let index: usize = parse_header(&block, "Index")?;
+            let data_shards: usize = parse_header(&block, "Data-Shards")?;
+            Ok(Shard::new(index, data_shards, block.data))
+        })
+        .collect()
+}
+
+fn parse_header(block: &ArmoredBlock, key: &'static str) -> Result<usize, ArmorError> {
+    block
+        .header(key)
+        .ok_or(ArmorError::MissingHeader(key))?
+        .parse()
+        .map_err(|_| ArmorError::InvalidValue(key.to_string()))
+}
+
+fn take_header(
+    headers: &mut Vec<(String, String)>,
+    key: &'static str,
+) -> Result<String, ArmorError> {
+    let position: usize = headers
+        .iter()
+        .position(|(name, _): &(String, String)| name.eq_ignore_ascii_case(key))
+        .ok_or(ArmorError::MissingHeader(key))?;
+    Ok(headers.remove(position).1)
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut encoded: String = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits: u32 = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits: u32, (i, byte): (usize, &u8)| {
+                bits | (*byte as u32) << (16 - 8 * i)
+            });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn decode_char(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text: &[u8] = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut decoded: Vec<u8> = Vec::with_capacity(text.len() / 4 * 3);
+    for (n, chunk) in text.chunks(4).enumerate() {
+        let last: bool = n + 1 == text.len() / 4;
+        let padding: usize = chunk
+            .iter()
+            .rev()
+            .take_while(|byte: &&u8| **byte == b'=')
+            .count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut bits: u32 = 0;
+        for (i, byte) in chunk[..4 - padding].iter().enumerate() {
+            bits |= (decode_char(*byte)? as u32) << (18 - 6 * i);
+        }
+        decoded.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+
+    #[test]
+    fn test_base64() {
+        for (data, text) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode_base64(data), text);
+            assert_eq!(decode_base64(text).as_deref(), Some(data));
+        }
+        assert_eq!(decode_base64("Zg=a"), None);
+        assert_eq!(decode_base64("Zg==Zg=="), None);
+        assert_eq!(decode_base64("Zm9"), None);
+    }
+
+    #[test]
+    fn test_shards_round_trip_through_quoted_text() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: Vec<u8> = (0..500u32).map(|i: u32| (i * 13) as u8).collect();
+        let shards: Vec<Shard> = codec.encode_shards(&original_data)?;
+
+        let text: String = shards[1..]
+            .iter()
+            .map(|shard: &Shard| armor_shard(shard, codec.config()))
+            .collect();
+        assert!(text.lines().all(|line: &str| line.len() <= LINE_LEN));
+        let quoted: String = format!(
+            "Here are the shards:\n\n{}\nThanks",
+            text.lines()
+                .map(|line: &str| format!("> {}\n", line))
+                .collect::<String>()
+        );
+
+        let restored: Vec<Shard> = dearmor_shards(&quoted)?;
+        assert_eq!(restored, shards[1..]);
+        assert_eq!(codec.decode_shards(restored)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_damage_is_reported() -> Result<(), ArmorError> {
+        let text: String = ArmoredBlock::new(DATA_LABEL, b"paper backup".to_vec())
+            .with_header("Name", "notes.txt")
+            .to_string();
+        let blocks: Vec<ArmoredBlock> = ArmoredBlock::parse_all(&text)?;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].header("name"), Some("notes.txt"));
+        assert_eq!(blocks[0].data, b"paper backup");
+
+        let mistyped: String = text.replace("cGFwZXIg", "cGFwZXIh");
+        assert_eq!(
+            ArmoredBlock::parse_all(&mistyped),
+            Err(ArmorError::ChecksumMismatch)
+        );
+        let bad_char: String = text.replace("cGFwZXIg", "cGFwZX!g");
+        assert_eq!(
+            ArmoredBlock::parse_all(&bad_char),
+            Err(ArmorError::InvalidBase64 { line: 6 })
+        );
+        let cut: &str = &text[..text.find("-----END").unwrap_or_default()];
+        assert_eq!(ArmoredBlock::parse_all(cut), Err(ArmorError::Unterminated));
+        assert_eq!(ArmoredBlock::parse_all("hello"), Err(ArmorError::NoBlock));
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::stream::pending_unit_len;
+use crate::trailer::is_trailer;
+
+impl ReedSolomonCodec {
+    pub async fn encode_async<R, W>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<u64, ReedSolomonError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let group_size: usize = self.group_size()?;
+        let mut group: Vec<u8> = vec![0u8; group_size];
+        let mut encoded: Vec<u8> = self.pool().take(0);
+        let mut written: u64 = 0;
+
+        loop {
+            let filled: usize = read_full_async(&mut reader, &mut group).await?;
+            if filled == 0 {
+                break;
+            }
+
+            self.encode_unit_into(&group[..filled], &mut encoded)?;
+            writer.write_all(&encoded).await?;
+            written += encoded.len() as u64;
+
+            if filled < group_size {
+                break;
+            }
+        }
+
+        self.pool().give(encoded);
+        writer.flush().await?;
+        Ok(written)
+    }
+
+    pub async fn decode_async<R, W>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<u64, ReedSolomonError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut unit: Vec<u8> = self.pool().take(0);
+        let mut written: u64 = 0;
+
+        while read_unit_async(&mut reader, &mut unit).await? {
+            let decoded: Vec<u8> = self.decode_unit(&unit)?;
+            writer.write_all(&decoded).await?;
+            written += decoded.len() as u64;
+            self.pool().give(decoded);
+        }
+
+        self.pool().give(unit);
+        writer.flush().await?;
+        Ok(written)
+    }
+}
+
+async fn read_unit_async<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    unit: &mut Vec<u8>,
+) -> Result<bool, ReedSolomonError> {
+    unit.clear();
+
+    loop {
+        let pending: usize = pending_unit_len(unit)?;
+        if pending == 0 {
+            return Ok(!is_trailer(unit));
+        }
+
+        let read: usize = (&mut *reader)
+            .take(pending as u64)
+            .read_to_end(unit)
+            .await?;
+        if read < pending {
+            if unit.is_empty() {
+                return Ok(false);
+            }
+            return Err(ReedSolomonError::Truncated {
+                needed: unit.len() - read + pending,
+                given: unit.len(),
+            });
+        }
+    }
+}
+
+async fn read_full_async<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buffer: &mut [u8],
+) -> io::Result<usize> {
+    let mut filled: usize = 0;
+
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]).await {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+
+    #[tokio::test]
+    async fn test_async_roundtrip_matches_sync_format() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 233) as u8).collect();
+
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_async(&original_data[..], &mut encoded).await?;
+        assert_eq!(encoded, codec.encode_blocks(&original_data)?);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let written: u64 = codec.decode_async(&encoded[..], &mut decoded).await?;
+
+        assert_eq!(written, original_data.len() as u64);
+        assert_eq!(decoded, original_data);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_interleaved_roundtrip() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?
+            .with_block_size(256)?
+            .with_interleave_depth(3)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..2000u32).map(|i: u32| (i % 227) as u8).collect();
+
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_async(&original_data[..], &mut encoded).await?;
+        assert_eq!(encoded, codec.encode_blocks(&original_data)?);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        codec.decode_async(&encoded[..], &mut decoded).await?;
+        assert_eq!(decoded, original_data);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_rejects_truncated_input() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let encoded: Vec<u8> = codec.encode_blocks(b"Hello, World!")?;
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let result: Result<u64, ReedSolomonError> = codec
+            .decode_async(&encoded[..encoded.len() - 2], &mut decoded)
+            .await;
+
+        assert!(result.is_err());
+        Ok(())
+    }
+}
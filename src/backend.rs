@@ -0,0 +1,343 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use reed_solomon_erasure::{galois_16, galois_8};
+
+use crate::config::{EncoderConfig, ErasureCode, GaloisField};
+use crate::error::ReedSolomonError;
+#[cfg(feature = "leopard")]
+use crate::leopard::LeopardBackend;
+use crate::polynomial::{PolynomialBackend, DEFAULT_POLYNOMIAL};
+use crate::replication::ReplicationBackend;
+#[cfg(feature = "std")]
+use crate::simd::SimdBackend;
+
+/// The erasure code underneath `ReedSolomonCodec`. Shards are raw byte
+/// slices of equal length; checksums, length prefixes and the container
+/// format are handled by the codec, so a backend only does the arithmetic.
+pub trait ErasureBackend: fmt::Debug + Send + Sync {
+    fn data_shards(&self) -> usize;
+
+    fn parity_shards(&self) -> usize;
+
+    /// Fills the parity shards from the data shards in front of them.
+    fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError>;
+
+    /// Fills in the missing shards, or only the missing data shards when
+    /// `data_only` is set.
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError>;
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError>;
+}
+
+/// The built-in backend for the config's erasure code and field, sized to
+/// include any hidden data shards.
+pub fn backend_for(config: &EncoderConfig) -> Result<Box<dyn ErasureBackend>, ReedSolomonError> {
+    let parity_shards: usize =
+        config.parity_shards() + config.layout().hidden_shards(config.data_shards());
+    match config.erasure_code() {
+        ErasureCode::Matrix => match config.polynomial() {
+            Some(polynomial) if polynomial != DEFAULT_POLYNOMIAL => Ok(Box::new(
+                PolynomialBackend::new(config.data_shards(), parity_shards, polynomial)?,
+            )),
+            _ => galois_backend(config.field(), config.data_shards(), parity_shards),
+        },
+        ErasureCode::Replication => Ok(Box::new(ReplicationBackend::new(
+            config.data_shards() + parity_shards,
+        )?)),
+        #[cfg(feature = "leopard")]
+        ErasureCode::Leopard => Ok(Box::new(LeopardBackend::new(
+            config.data_shards(),
+            parity_shards,
+        )?)),
+        #[cfg(not(feature = "leopard"))]
+        code => Err(ReedSolomonError::ErasureCodeUnavailable(code)),
+    }
+}
+
+pub fn galois_backend(
+    field: GaloisField,
+    data_shards: usize,
+    parity_shards: usize,
+) -> Result<Box<dyn ErasureBackend>, ReedSolomonError> {
+    let backend: Result<Box<dyn ErasureBackend>, reed_solomon_erasure::Error> = match field {
+        #[cfg(feature = "std")]
+        GaloisField::Gf8 => SimdBackend::new(data_shards, parity_shards)
+            .map(|codec: SimdBackend| Box::new(codec) as Box<dyn ErasureBackend>),
+        #[cfg(not(feature = "std"))]
+        GaloisField::Gf8 => galois_8::ReedSolomon::new(data_shards, parity_shards)
+            .map(|codec: galois_8::ReedSolomon| Box::new(codec) as Box<dyn ErasureBackend>),
+        GaloisField::Gf16 => galois_16::ReedSolomon::new(data_shards, parity_shards)
+            .map(|codec: galois_16::ReedSolomon| Box::new(codec) as Box<dyn ErasureBackend>),
+    };
+    backend.map_err(|error: reed_solomon_erasure::Error| ReedSolomonError::CodecError { error })
+}
+
+fn encoding_error(error: reed_solomon_erasure::Error) -> ReedSolomonError {
+    ReedSolomonError::EncodingError { error }
+}
+
+fn decoding_error(error: reed_solomon_erasure::Error) -> ReedSolomonError {
+    ReedSolomonError::DecodingError { error }
+}
+
+impl ErasureBackend for galois_8::ReedSolomon {
+    fn data_shards(&self) -> usize {
+        self.data_shard_count()
+    }
+
+    fn parity_shards(&self) -> usize {
+        self.parity_shard_count()
+    }
+
+    fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+        self.encode(shards).map_err(encoding_error)
+    }
+
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        if data_only {
+            self.reconstruct_data(shards).map_err(decoding_error)
+        } else {
+            galois_8::ReedSolomon::reconstruct(self, shards).map_err(decoding_error)
+        }
+    }
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+        galois_8::ReedSolomon::verify(self, shards).map_err(decoding_error)
+    }
+}
+
+/// GF(2^16) works on pairs of bytes, so shards are regrouped into 16-bit
+/// elements on the way in and flattened again on the way out.
+impl ErasureBackend for galois_16::ReedSolomon {
+    fn data_shards(&self) -> usize {
+        self.data_shard_count()
+    }
+
+    fn parity_shards(&self) -> usize {
+        self.parity_shard_count()
+    }
+
+    fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+        let mut wide: Vec<Vec<[u8; 2]>> = shards
+            .iter()
+            .map(|shard: &&mut [u8]| to_wide(shard))
+            .collect();
+        self.encode(&mut wide).map_err(encoding_error)?;
+
+        for (shard, wide) in shards.iter_mut().zip(wide) {
+            for (pair, value) in shard.chunks_exact_mut(2).zip(wide) {
+                pair.copy_from_slice(&value);
+            }
+        }
+        Ok(())
+    }
+
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        if shards
+            .iter()
+            .flatten()
+            .any(|shard: &Vec<u8>| !shard.len().is_multiple_of(2))
+        {
+            return Err(decoding_error(
+                reed_solomon_erasure::Error::IncorrectShardSize,
+            ));
+        }
+
+        let mut wide: Vec<Option<Vec<[u8; 2]>>> = shards
+            .iter()
+            .map(|shard: &Option<Vec<u8>>| shard.as_deref().map(to_wide))
+            .collect();
+        if data_only {
+            self.reconstruct_data(&mut wide).map_err(decoding_error)?;
+        } else {
+            galois_16::ReedSolomon::reconstruct(self, &mut wide).map_err(decoding_error)?;
+        }
+
+        for (shard, wide) in shards.iter_mut().zip(wide) {
+            if shard.is_none() {
+                *shard = wide.map(from_wide);
+            }
+        }
+        Ok(())
+    }
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+        if shards
+            .iter()
+            .any(|shard: &&[u8]| !shard.len().is_multiple_of(2))
+        {
+            return Err(decoding_error(
+                reed_solomon_erasure::Error::IncorrectShardSize,
+            ));
+        }
+
+        let wide: Vec<Vec<[u8; 2]>> = shards.iter().map(|shard: &&[u8]| to_wide(shard)).collect();
+        galois_16::ReedSolomon::verify(self, &wide).map_err(decoding_error)
+    }
+}
+
+fn to_wide(shard: &[u8]) -> Vec<[u8; 2]> {
+    shard
+        .chunks_exact(2)
+        .map(|pair: &[u8]| [pair[0], pair[1]])
+        .collect()
+}
+
+fn from_wide(shard: Vec<[u8; 2]>) -> Vec<u8> {
+    shard.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::codec::{EncodeCheck, ReedSolomonCodec};
+    use crate::config::{EncoderConfig, ShardLayout};
+    use crate::header::HEADER_SIZE;
+
+    #[derive(Debug)]
+    struct CountingBackend {
+        inner: Box<dyn ErasureBackend>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl ErasureBackend for CountingBackend {
+        fn data_shards(&self) -> usize {
+            self.inner.data_shards()
+        }
+
+        fn parity_shards(&self) -> usize {
+            self.inner.parity_shards()
+        }
+
+        fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.inner.encode_shards(shards)
+        }
+
+        fn reconstruct(
+            &self,
+            shards: &mut [Option<Vec<u8>>],
+            data_only: bool,
+        ) -> Result<(), ReedSolomonError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.inner.reconstruct(shards, data_only)
+        }
+
+        fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+            self.inner.verify(shards)
+        }
+    }
+
+    #[test]
+    fn test_codec_runs_on_a_custom_backend() -> Result<(), ReedSolomonError> {
+        let calls: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let backend: CountingBackend = CountingBackend {
+            inner: galois_backend(GaloisField::Gf8, 4, 2)?,
+            calls: Arc::clone(&calls),
+        };
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_backend(EncoderConfig::new(4, 2)?, Box::new(backend))?;
+
+        let mut encoded: Vec<u8> = codec.encode(b"any arithmetic will do")?;
+        assert_eq!(codec.decode(&encoded)?, b"any arithmetic will do");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        encoded[HEADER_SIZE] ^= 0xff;
+        assert_eq!(codec.decode(&encoded)?, b"any arithmetic will do");
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        Ok(())
+    }
+
+    /// Leaves the parity shards unwritten.
+    #[derive(Debug)]
+    struct SkipParityBackend {
+        inner: Box<dyn ErasureBackend>,
+    }
+
+    impl ErasureBackend for SkipParityBackend {
+        fn data_shards(&self) -> usize {
+            self.inner.data_shards()
+        }
+
+        fn parity_shards(&self) -> usize {
+            self.inner.parity_shards()
+        }
+
+        fn encode_shards(&self, _shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+            Ok(())
+        }
+
+        fn reconstruct(
+            &self,
+            shards: &mut [Option<Vec<u8>>],
+            data_only: bool,
+        ) -> Result<(), ReedSolomonError> {
+            self.inner.reconstruct(shards, data_only)
+        }
+
+        fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+            self.inner.verify(shards)
+        }
+    }
+
+    #[test]
+    fn test_encode_verified_catches_a_faulty_backend() -> Result<(), ReedSolomonError> {
+        let backend: SkipParityBackend = SkipParityBackend {
+            inner: galois_backend(GaloisField::Gf8, 4, 2)?,
+        };
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_backend(EncoderConfig::new(4, 2)?, Box::new(backend))?;
+
+        assert_eq!(codec.encode_shards(b"looks fine")?.len(), 6);
+        assert!(matches!(
+            codec.encode_verified(b"looks fine", EncodeCheck::Parity),
+            Err(ReedSolomonError::VerificationFailed)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_backend_must_match_config() -> Result<(), ReedSolomonError> {
+        assert!(matches!(
+            ReedSolomonCodec::with_backend(
+                EncoderConfig::new(4, 2)?,
+                galois_backend(GaloisField::Gf8, 4, 3)?
+            ),
+            Err(ReedSolomonError::ShardCountMismatch {
+                expected: 6,
+                given: 7
+            })
+        ));
+
+        let non_systematic: EncoderConfig =
+            EncoderConfig::new(4, 2)?.with_layout(ShardLayout::NonSystematic)?;
+        assert!(ReedSolomonCodec::with_backend(
+            non_systematic,
+            galois_backend(GaloisField::Gf8, 4, 2)?
+        )
+        .is_err());
+        assert!(ReedSolomonCodec::with_backend(
+            non_systematic,
+            galois_backend(GaloisField::Gf8, 4, 6)?
+        )
+        .is_ok());
+        Ok(())
+    }
+}
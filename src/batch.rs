@@ -0,0 +1,155 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::DataSizeLimits;
+use crate::error::ReedSolomonError;
+
+/// The containers `encode_batch` made, back to back in one buffer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodedBatch {
+    bytes: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl EncodedBatch {
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// The container encoded from the payload at `index`.
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let end: usize = *self.ends.get(index)?;
+        let start: usize = index.checked_sub(1).map_or(0, |i: usize| self.ends[i]);
+        Some(&self.bytes[start..end])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (0..self.len()).filter_map(|index: usize| self.get(index))
+    }
+
+    /// Every container, concatenated in payload order.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_vecs(self) -> Vec<Vec<u8>> {
+        self.iter().map(<[u8]>::to_vec).collect()
+    }
+}
+
+impl ReedSolomonCodec {
+    /// Encodes every payload into its own container, exactly as `encode`
+    /// would, for callers with many small records. The containers share
+    /// one output buffer and the codec's encoding matrix, and with the
+    /// `parallel` feature they are encoded on the rayon thread pool.
+    pub fn encode_batch(&self, payloads: &[&[u8]]) -> Result<EncodedBatch, ReedSolomonError> {
+        let limits: DataSizeLimits = self.config().data_size_limits();
+        let capacities: Vec<usize> = payloads
+            .iter()
+            .map(|payload: &&[u8]| {
+                limits.check(payload.len())?;
+                Ok(self.encoded_len(payload.len()))
+            })
+            .collect::<Result<_, ReedSolomonError>>()?;
+
+        let mut bytes: Vec<u8> = vec![0u8; capacities.iter().sum()];
+        let mut slots: Vec<&mut [u8]> = Vec::with_capacity(payloads.len());
+        let mut rest: &mut [u8] = &mut bytes;
+        for capacity in &capacities {
+            let (slot, tail) = rest.split_at_mut(*capacity);
+            slots.push(slot);
+            rest = tail;
+        }
+
+        #[cfg(feature = "parallel")]
+        let written: Vec<usize> = payloads
+            .par_iter()
+            .zip(slots.into_par_iter())
+            .map(|(payload, slot): (&&[u8], &mut [u8])| self.encode_into(payload, slot))
+            .collect::<Result<_, ReedSolomonError>>()?;
+        #[cfg(not(feature = "parallel"))]
+        let written: Vec<usize> = payloads
+            .iter()
+            .zip(slots)
+            .map(|(payload, slot): (&&[u8], &mut [u8])| self.encode_into(payload, slot))
+            .collect::<Result<_, ReedSolomonError>>()?;
+
+        // Compressed containers can come out shorter than their slot.
+        let mut ends: Vec<usize> = Vec::with_capacity(payloads.len());
+        let (mut start, mut end): (usize, usize) = (0, 0);
+        for (capacity, written) in capacities.iter().zip(written) {
+            bytes.copy_within(start..start + written, end);
+            end += written;
+            ends.push(end);
+            start += capacity;
+        }
+        bytes.truncate(end);
+
+        Ok(EncodedBatch { bytes, ends })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_matches_single_encodes() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let records: Vec<Vec<u8>> = (1..50usize)
+            .map(|len: usize| (0..len).map(|i: usize| (i * len % 251) as u8).collect())
+            .collect();
+        let payloads: Vec<&[u8]> = records.iter().map(Vec::as_slice).collect();
+
+        let batch: EncodedBatch = codec.encode_batch(&payloads)?;
+        assert_eq!(batch.len(), records.len());
+        for (container, record) in batch.iter().zip(&records) {
+            assert_eq!(container.len(), codec.encode(record)?.len());
+            assert_eq!(codec.decode(container)?, *record);
+        }
+        assert_eq!(batch.as_bytes(), batch.clone().into_vecs().concat());
+        assert_eq!(batch.get(records.len()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_rejects_empty_payloads() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        assert!(codec.encode_batch(&[])?.is_empty());
+        assert!(matches!(
+            codec.encode_batch(&[b"record".as_slice(), b""]),
+            Err(ReedSolomonError::InvalidDataSize { .. })
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_batch_packs_compressed_containers() -> Result<(), ReedSolomonError> {
+        use crate::config::{Compression, EncoderConfig};
+
+        let config: EncoderConfig =
+            EncoderConfig::new(4, 2)?.with_compression(Compression::Zstd)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let payloads: [&[u8]; 3] = [&[0u8; 4096], b"short", &[7u8; 4096]];
+
+        let batch: EncodedBatch = codec.encode_batch(&payloads)?;
+        let capacity: usize = payloads
+            .iter()
+            .map(|payload: &&[u8]| codec.encoded_len(payload.len()))
+            .sum();
+        assert!(batch.as_bytes().len() < capacity);
+        for (container, payload) in batch.iter().zip(payloads) {
+            assert_eq!(codec.decode(container)?, payload);
+        }
+        Ok(())
+    }
+}
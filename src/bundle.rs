@@ -0,0 +1,471 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::{ManifestError, ReedSolomonError};
+use crate::files::ShardManifest;
+use crate::store::{decode_from_shard_store, FileStore, ShardId, ShardStore};
+
+/// Key of the manifest lines that list the files of a bundle.
+pub const BUNDLE_FILE_KEY: &str = "file";
+
+/// One input file of a bundle and the payload bytes it occupies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleEntry {
+    pub name: String,
+    pub offset: u64,
+    pub len: u64,
+}
+
+impl BundleEntry {
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.offset + self.len
+    }
+}
+
+/// The files whose contents make up the payload of a shard set, back to
+/// back in this order. It is kept in the set's manifest as one
+/// `file=<offset>,<len>,<name>` line per file, which `ShardManifest::parse`
+/// skips, so each file can be found again without a separate index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleIndex {
+    pub entries: Vec<BundleEntry>,
+}
+
+impl BundleIndex {
+    /// Lists `paths` under their file names, which have to differ, with the
+    /// sizes they have now.
+    pub fn scan(paths: &[PathBuf]) -> Result<Self, ReedSolomonError> {
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        let mut entries: Vec<BundleEntry> = Vec::new();
+        let mut offset: u64 = 0;
+        for path in paths {
+            let name: String = path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .filter(|name: &&str| !name.contains(['\n', '\r']))
+                .map(str::to_string)
+                .ok_or_else(|| ReedSolomonError::InvalidFileName(path.clone()))?;
+            if !names.insert(name.clone()) {
+                return Err(ReedSolomonError::InvalidFileName(path.clone()));
+            }
+            let len: u64 = fs::metadata(path)?.len();
+            entries.push(BundleEntry { name, offset, len });
+            offset += len;
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn payload_len(&self) -> u64 {
+        self.entries
+            .last()
+            .map_or(0, |entry: &BundleEntry| entry.offset + entry.len)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&BundleEntry> {
+        self.entries
+            .iter()
+            .find(|entry: &&BundleEntry| entry.name == name)
+    }
+
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry: &BundleEntry| {
+                format!(
+                    "{}={},{},{}\n",
+                    BUNDLE_FILE_KEY, entry.offset, entry.len, entry.name
+                )
+            })
+            .collect()
+    }
+
+    /// Reads the `file` lines of a manifest and ignores every other line.
+    /// The files have to follow each other without gaps.
+    pub fn parse(text: &str) -> Result<Self, ReedSolomonError> {
+        let invalid = || ManifestError::InvalidValue(BUNDLE_FILE_KEY.to_string());
+        let mut entries: Vec<BundleEntry> = Vec::new();
+        let mut next_offset: u64 = 0;
+        for line in text.lines().map(str::trim) {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if key.trim() != BUNDLE_FILE_KEY {
+                continue;
+            }
+            let mut fields: std::str::SplitN<'_, char> = value.splitn(3, ',');
+            let (Some(offset), Some(len), Some(name)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(ManifestError::MalformedLine(line.to_string()).into());
+            };
+            let offset: u64 = offset.trim().parse().map_err(|_| invalid())?;
+            let len: u64 = len.trim().parse().map_err(|_| invalid())?;
+            if offset != next_offset || name.is_empty() {
+                return Err(invalid().into());
+            }
+            next_offset = offset.checked_add(len).ok_or_else(invalid)?;
+            entries.push(BundleEntry {
+                name: name.to_string(),
+                offset,
+                len,
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    /// The bundle listed in the manifest of the set `basename`, empty when
+    /// the set holds a single payload.
+    pub fn read<S: ShardStore + ?Sized>(
+        store: &S,
+        basename: &str,
+    ) -> Result<Self, ReedSolomonError> {
+        let id: ShardId = ShardId::manifest(basename);
+        let text: Vec<u8> = store
+            .get(&id)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, id.to_string()))?;
+        let text: String = String::from_utf8_lossy(&text).into_owned();
+        let index: BundleIndex = Self::parse(&text)?;
+        if !index.is_empty() && index.payload_len() != ShardManifest::parse(&text)?.payload_len {
+            return Err(ManifestError::InvalidValue(BUNDLE_FILE_KEY.to_string()).into());
+        }
+        Ok(index)
+    }
+
+    /// Stores `manifest` with this bundle listed in it.
+    pub fn write<S: ShardStore + ?Sized>(
+        &self,
+        store: &S,
+        basename: &str,
+        manifest: &ShardManifest,
+    ) -> Result<(), ReedSolomonError> {
+        let text: String = manifest.to_text() + &self.to_text();
+        store.put(&ShardId::manifest(basename), text.as_bytes())
+    }
+}
+
+/// Reads the files of a bundle one after another, failing if one changes
+/// size while it is read.
+#[derive(Debug)]
+pub struct BundleReader {
+    files: VecDeque<(PathBuf, u64)>,
+    file: Option<io::Take<File>>,
+}
+
+impl BundleReader {
+    pub fn new(paths: &[PathBuf], index: &BundleIndex) -> Self {
+        Self {
+            files: paths
+                .iter()
+                .cloned()
+                .zip(index.entries.iter().map(|entry: &BundleEntry| entry.len))
+                .collect(),
+            file: None,
+        }
+    }
+}
+
+impl Read for BundleReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(file) = &mut self.file {
+                let read: usize = file.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+                if file.limit() > 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "file shrank while it was encoded",
+                    ));
+                }
+                self.file = None;
+            }
+
+            let Some((path, len)) = self.files.pop_front() else {
+                return Ok(0);
+            };
+            self.file = Some(File::open(path)?.take(len));
+        }
+    }
+}
+
+/// Passes on the bytes of `range` of what is written to it and drops the
+/// rest.
+struct RangeWriter<W: Write> {
+    inner: W,
+    range: Range<u64>,
+    position: u64,
+}
+
+impl<W: Write> Write for RangeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let start: u64 = self.position;
+        self.position += buf.len() as u64;
+        let from: u64 = start.max(self.range.start);
+        let to: u64 = self.position.min(self.range.end);
+        if from < to {
+            self.inner
+                .write_all(&buf[(from - start) as usize..(to - start) as usize])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Splits a decoded bundle back into its files inside `dir`.
+struct BundleWriter<'a> {
+    dir: &'a Path,
+    entries: &'a [BundleEntry],
+    next: usize,
+    file: Option<(BufWriter<File>, u64)>,
+}
+
+impl BundleWriter<'_> {
+    fn open_next(&mut self) -> io::Result<()> {
+        if let Some((mut file, _)) = self.file.take() {
+            file.flush()?;
+        }
+        let entry: &BundleEntry = &self.entries[self.next];
+        // Only a plain file name, so a manifest cannot write outside `dir`.
+        if entry.name.starts_with('.')
+            || Path::new(&entry.name).file_name() != Some(OsStr::new(&entry.name))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsafe bundle file name {:?}", entry.name),
+            ));
+        }
+        self.file = Some((
+            BufWriter::new(File::create(self.dir.join(&entry.name))?),
+            entry.len,
+        ));
+        self.next += 1;
+        Ok(())
+    }
+
+    /// Creates the empty files at the end, which no bytes arrive for.
+    fn finish(mut self) -> io::Result<()> {
+        while self.next < self.entries.len() {
+            self.open_next()?;
+        }
+        match self.file.take() {
+            Some((_, remaining)) if remaining > 0 => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bundle ends inside a file",
+            )),
+            Some((mut file, _)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Write for BundleWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut input: &[u8] = buf;
+        while !input.is_empty() {
+            if let Some((file, remaining)) = &mut self.file {
+                if *remaining > 0 {
+                    let take: usize = (*remaining).min(input.len() as u64) as usize;
+                    file.write_all(&input[..take])?;
+                    *remaining -= take as u64;
+                    input = &input[take..];
+                    continue;
+                }
+            }
+
+            if self.next == self.entries.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "data after the last bundled file",
+                ));
+            }
+            self.open_next()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some((file, _)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ReedSolomonCodec {
+    /// Encodes the contents of `paths` back to back into one shard set, like
+    /// `encode_to_file_store`, and lists each file in its manifest.
+    pub fn encode_bundle_to_file_store(
+        &self,
+        paths: &[PathBuf],
+        store: &FileStore,
+        basename: &str,
+    ) -> Result<(ShardManifest, BundleIndex), ReedSolomonError> {
+        let index: BundleIndex = BundleIndex::scan(paths)?;
+        let manifest: ShardManifest =
+            self.encode_to_file_store(BundleReader::new(paths, &index), store, basename)?;
+        if manifest.payload_len != index.payload_len() {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: index.payload_len() as usize,
+                given: manifest.payload_len as usize,
+            });
+        }
+        index.write(store, basename, &manifest)?;
+        Ok((manifest, index))
+    }
+}
+
+/// Decodes the bundled file `name` of the set `basename` into `writer` and
+/// returns its length.
+pub fn decode_bundle_file<S: ShardStore + ?Sized, W: Write>(
+    store: &S,
+    basename: &str,
+    name: &str,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let index: BundleIndex = BundleIndex::read(store, basename)?;
+    let entry: &BundleEntry = index
+        .find(name)
+        .ok_or_else(|| ReedSolomonError::InvalidFileName(PathBuf::from(name)))?;
+    let mut range_writer: RangeWriter<W> = RangeWriter {
+        inner: writer,
+        range: entry.range(),
+        position: 0,
+    };
+    decode_from_shard_store(store, basename, &mut range_writer)?;
+    range_writer.flush()?;
+    Ok(entry.len)
+}
+
+/// Decodes the set `basename` and writes each of its bundled files into
+/// `dir` under its own name.
+pub fn decode_bundle<S: ShardStore + ?Sized>(
+    store: &S,
+    basename: &str,
+    dir: &Path,
+) -> Result<BundleIndex, ReedSolomonError> {
+    let index: BundleIndex = BundleIndex::read(store, basename)?;
+    if index.is_empty() {
+        return Err(ReedSolomonError::InvalidFileName(PathBuf::from(basename)));
+    }
+    fs::create_dir_all(dir)?;
+    let mut writer: BundleWriter<'_> = BundleWriter {
+        dir,
+        entries: &index.entries,
+        next: 0,
+        file: None,
+    };
+    decode_from_shard_store(store, basename, &mut writer)?;
+    writer.finish()?;
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{read_manifest, MemoryStore};
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_bundle_index_in_manifest() -> Result<(), ReedSolomonError> {
+        let index: BundleIndex = BundleIndex {
+            entries: vec![
+                BundleEntry {
+                    name: "a, b.txt".to_string(),
+                    offset: 0,
+                    len: 10,
+                },
+                BundleEntry {
+                    name: "empty".to_string(),
+                    offset: 10,
+                    len: 0,
+                },
+                BundleEntry {
+                    name: "c.bin".to_string(),
+                    offset: 10,
+                    len: 5,
+                },
+            ],
+        };
+        let manifest: ShardManifest = ReedSolomonCodec::new(2, 1)?.shard_manifest(15);
+        let store: MemoryStore = MemoryStore::new();
+        index.write(&store, "data", &manifest)?;
+        assert_eq!(read_manifest(&store, "data")?, manifest);
+        assert_eq!(BundleIndex::read(&store, "data")?, index);
+        assert_eq!(index.find("c.bin").map(BundleEntry::range), Some(10..15));
+
+        assert!(BundleIndex::parse("file=0,10,a\nfile=11,5,b\n").is_err());
+        assert!(BundleIndex::parse("file=0,10\n").is_err());
+        let wrong_len: ShardManifest = ReedSolomonCodec::new(2, 1)?.shard_manifest(14);
+        index.write(&store, "data", &wrong_len)?;
+        assert!(BundleIndex::read(&store, "data").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("bundle");
+        let sources: PathBuf = dir.join("sources");
+        fs::create_dir_all(&sources)?;
+        let contents: [Vec<u8>; 3] = [
+            (0..3000u32).map(|i: u32| (i % 251) as u8).collect(),
+            Vec::new(),
+            b"notes".to_vec(),
+        ];
+        let paths: Vec<PathBuf> = ["photo.raw", "empty.log", "notes.txt"]
+            .iter()
+            .map(|name: &&str| sources.join(name))
+            .collect();
+        for (path, data) in paths.iter().zip(&contents) {
+            fs::write(path, data)?;
+        }
+
+        let store: FileStore = FileStore::new(&dir.join("shards"));
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let (manifest, index): (ShardManifest, BundleIndex) =
+            codec.encode_bundle_to_file_store(&paths, &store, "set")?;
+        assert_eq!(manifest.payload_len, 3005);
+        assert_eq!(
+            index.find("notes.txt").map(BundleEntry::range),
+            Some(3000..3005)
+        );
+
+        let mut notes: Vec<u8> = Vec::new();
+        assert_eq!(
+            decode_bundle_file(&store, "set", "notes.txt", &mut notes)?,
+            5
+        );
+        assert_eq!(notes, b"notes");
+        assert!(decode_bundle_file(&store, "set", "missing", &mut notes).is_err());
+
+        fs::remove_file(store.path(&ShardId::shard("set", &manifest, 0)))?;
+        let restored: PathBuf = dir.join("restored");
+        decode_bundle(&store, "set", &restored)?;
+        for (name, data) in ["photo.raw", "empty.log", "notes.txt"]
+            .iter()
+            .zip(&contents)
+        {
+            assert_eq!(&fs::read(restored.join(name))?, data);
+        }
+
+        let resharded: ShardManifest =
+            ReedSolomonCodec::new(3, 3)?.reshard_files(store.dir(), "set")?;
+        assert_eq!(resharded.data_shards, 3);
+        assert_eq!(BundleIndex::read(&store, "set")?, index);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::error::{ManifestError, ReedSolomonError};
+use crate::files::ShardFileName;
+use crate::platform::replace_file;
+use crate::store::{FileWriter, ShardId, ShardStore, ShardWriter};
+
+pub const INDEX_EXTENSION: &str = "index";
+pub const OBJECTS_DIR: &str = "objects";
+
+/// A `ShardStore` that names every shard by the BLAKE3 hash of its bytes.
+/// Shards live in `objects/<hash>`, so identical shards are stored once,
+/// and `<basename>.index` maps each shard of a set to its hash in the
+/// `b3sum` format. A shard whose object was renamed, removed or no longer
+/// hashes to its name reads as missing. Manifests and recipes are stored
+/// as plain files. Deleting a shard only drops it from the index;
+/// `collect_garbage` removes the objects nothing refers to any more.
+#[derive(Debug)]
+pub struct ContentStore {
+    dir: PathBuf,
+    indexes: Mutex<()>,
+}
+
+impl ContentStore {
+    /// The directory is created on the first write.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            indexes: Mutex::new(()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(OBJECTS_DIR).join(hash)
+    }
+
+    pub fn index_path(&self, basename: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", basename, INDEX_EXTENSION))
+    }
+
+    /// The hex BLAKE3 hash of every shard of `basename`, by shard name.
+    pub fn index(
+        &self,
+        basename: &str,
+    ) -> Result<BTreeMap<ShardFileName, String>, ReedSolomonError> {
+        let text: String = match fs::read_to_string(self.index_path(basename)) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut index: BTreeMap<ShardFileName, String> = BTreeMap::new();
+        for line in text.lines().filter(|line: &&str| !line.is_empty()) {
+            let parsed: Option<(ShardFileName, String)> =
+                line.split_once("  ")
+                    .and_then(|(hash, name): (&str, &str)| {
+                        let name: ShardFileName = ShardFileName::parse(name)?;
+                        (is_hex_hash(hash) && name.basename == basename)
+                            .then(|| (name, hash.to_string()))
+                    });
+            let (name, hash) =
+                parsed.ok_or_else(|| ManifestError::MalformedLine(line.to_string()))?;
+            index.insert(name, hash);
+        }
+        Ok(index)
+    }
+
+    /// Removes every object no index refers to, returning how many.
+    pub fn collect_garbage(&self) -> Result<usize, ReedSolomonError> {
+        let _guard: MutexGuard<'_, ()> = self.lock();
+        let mut referenced: Vec<String> = Vec::new();
+        for basename in self.indexed_basenames()? {
+            referenced.extend(self.index(&basename)?.into_values());
+        }
+
+        let entries: fs::ReadDir = match fs::read_dir(self.dir.join(OBJECTS_DIR)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(error.into()),
+        };
+        let mut removed: usize = 0;
+        for entry in entries {
+            let entry: fs::DirEntry = entry?;
+            let name: String = entry.file_name().to_string_lossy().into_owned();
+            if is_hex_hash(&name) && !referenced.contains(&name) {
+                fs::remove_file(entry.path())?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn indexed_basenames(&self) -> Result<Vec<String>, ReedSolomonError> {
+        let entries: fs::ReadDir = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut basenames: Vec<String> = Vec::new();
+        for entry in entries {
+            let name: String = entry?.file_name().to_string_lossy().into_owned();
+            if let Some(basename) = name.strip_suffix(&format!(".{}", INDEX_EXTENSION)) {
+                basenames.push(basename.to_string());
+            }
+        }
+        basenames.sort();
+        Ok(basenames)
+    }
+
+    /// Points `name` at `hash`, or drops it from the index for `None`.
+    fn update_index(
+        &self,
+        name: &ShardFileName,
+        hash: Option<String>,
+    ) -> Result<(), ReedSolomonError> {
+        let _guard: MutexGuard<'_, ()> = self.lock();
+        let mut index: BTreeMap<ShardFileName, String> = self.index(&name.basename)?;
+        match hash {
+            Some(hash) => index.insert(name.clone(), hash),
+            None => index.remove(name),
+        };
+
+        let path: PathBuf = self.index_path(&name.basename);
+        if index.is_empty() {
+            return remove_if_present(&path);
+        }
+        let mut writer: FileWriter = FileWriter::create(path)?;
+        for (name, hash) in &index {
+            writeln!(writer, "{}  {}", hash, name)?;
+        }
+        Box::new(writer).commit()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.indexes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ShardStore for ContentStore {
+    fn put(&self, id: &ShardId, data: &[u8]) -> Result<(), ReedSolomonError> {
+        let mut writer: Box<dyn ShardWriter + '_> = self.writer(id)?;
+        writer.write_all(data)?;
+        writer.commit()
+    }
+
+    fn get(&self, id: &ShardId) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        let path: PathBuf = match id {
+            ShardId::Shard(name) => match self.index(&name.basename)?.remove(name) {
+                Some(hash) => self.object_path(&hash),
+                None => return Ok(None),
+            },
+            _ => self.dir.join(id.to_string()),
+        };
+        let data: Vec<u8> = match fs::read(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let renamed: bool = matches!(id, ShardId::Shard(_))
+            && !path.ends_with(blake3::hash(&data).to_hex().as_str());
+        Ok((!renamed).then_some(data))
+    }
+
+    fn list(&self) -> Result<Vec<ShardId>, ReedSolomonError> {
+        let mut ids: Vec<ShardId> = Vec::new();
+        for basename in self.indexed_basenames()? {
+            ids.extend(self.index(&basename)?.into_keys().map(ShardId::Shard));
+        }
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries {
+                let name: String = entry?.file_name().to_string_lossy().into_owned();
+                if let Some(id @ (ShardId::Manifest(_) | ShardId::Recipe(_))) =
+                    ShardId::parse(&name)
+                {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn delete(&self, id: &ShardId) -> Result<(), ReedSolomonError> {
+        match id {
+            ShardId::Shard(name) => self.update_index(name, None),
+            _ => remove_if_present(&self.dir.join(id.to_string())),
+        }
+    }
+
+    fn writer(&self, id: &ShardId) -> Result<Box<dyn ShardWriter + '_>, ReedSolomonError> {
+        match id {
+            ShardId::Shard(name) => {
+                let objects: PathBuf = self.dir.join(OBJECTS_DIR);
+                fs::create_dir_all(&objects)?;
+                let partial: PathBuf =
+                    objects.join(format!("{}.{}.partial", name, std::process::id()));
+                Ok(Box::new(ObjectWriter {
+                    store: self,
+                    name: name.clone(),
+                    file: BufWriter::new(File::create(&partial)?),
+                    hasher: blake3::Hasher::new(),
+                    partial,
+                    committed: false,
+                }))
+            }
+            _ => {
+                fs::create_dir_all(&self.dir)?;
+                Ok(Box::new(FileWriter::create(self.dir.join(id.to_string()))?))
+            }
+        }
+    }
+}
+
+/// Hashes a shard while writing it to a temporary object, which is
+/// renamed to its hash on commit unless an identical object exists.
+struct ObjectWriter<'a> {
+    store: &'a ContentStore,
+    name: ShardFileName,
+    file: BufWriter<File>,
+    hasher: blake3::Hasher,
+    partial: PathBuf,
+    committed: bool,
+}
+
+impl Write for ObjectWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written: usize = self.file.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl ShardWriter for ObjectWriter<'_> {
+    fn commit(mut self: Box<Self>) -> Result<(), ReedSolomonError> {
+        self.file.flush()?;
+        let hash: String = self.hasher.finalize().to_hex().to_string();
+        let path: PathBuf = self.store.object_path(&hash);
+        if is_intact(&path, &hash) {
+            fs::remove_file(&self.partial)?;
+        } else {
+            replace_file(&self.partial, &path)?;
+        }
+        self.committed = true;
+        self.store.update_index(&self.name, Some(hash))
+    }
+}
+
+impl Drop for ObjectWriter<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.partial);
+        }
+    }
+}
+
+/// Whether the object at `path` still hashes to `hash`.
+fn is_intact(path: &Path, hash: &str) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut hasher: blake3::Hasher = blake3::Hasher::new();
+    let mut buffer: Vec<u8> = vec![0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => {
+                hasher.update(&buffer[..read]);
+            }
+            Err(_) => return false,
+        }
+    }
+    hasher.finalize().to_hex().as_str() == hash
+}
+
+pub(crate) fn is_hex_hash(text: &str) -> bool {
+    text.len() == 64
+        && text
+            .bytes()
+            .all(|b: u8| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn remove_if_present(path: &Path) -> Result<(), ReedSolomonError> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+    use crate::config::EncoderConfig;
+    use crate::files::ShardManifest;
+    use crate::store::{decode_from_shard_store, repair_shard_store};
+    use crate::test_util::temp_dir;
+
+    fn object_count(dir: &Path) -> io::Result<usize> {
+        Ok(fs::read_dir(dir.join(OBJECTS_DIR))?.count())
+    }
+
+    #[test]
+    fn test_identical_shards_are_stored_once() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("cas-dedup");
+        let store: ContentStore = ContentStore::new(&dir);
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 227) as u8).collect();
+
+        let manifest: ShardManifest =
+            codec.encode_to_shard_store(original_data.as_slice(), &store, "first")?;
+        codec.encode_to_shard_store(original_data.as_slice(), &store, "second")?;
+        assert_eq!(object_count(&dir)?, 6);
+        assert_eq!(store.index("second")?.len(), 6);
+        assert_eq!(
+            store.index("first")?.into_values().collect::<Vec<String>>(),
+            store
+                .index("second")?
+                .into_values()
+                .collect::<Vec<String>>()
+        );
+        assert_eq!(store.list()?.len(), 14);
+
+        for index in 0..6 {
+            store.delete(&ShardId::shard("first", &manifest, index))?;
+        }
+        store.delete(&ShardId::manifest("first"))?;
+        assert!(!store.index_path("first").exists());
+        assert_eq!(store.collect_garbage()?, 0);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_shard_store(&store, "second", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        for index in 0..6 {
+            store.delete(&ShardId::shard("second", &manifest, index))?;
+        }
+        assert_eq!(store.collect_garbage()?, 6);
+        assert_eq!(object_count(&dir)?, 0);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_renamed_or_corrupted_objects_read_as_missing() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("cas-corruption");
+        let store: ContentStore = ContentStore::new(&dir);
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..3000u32).map(|i: u32| (i % 211) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_shard_store(original_data.as_slice(), &store, "data")?;
+
+        let index: BTreeMap<ShardFileName, String> = store.index("data")?;
+        let hashes: Vec<&String> = index.values().collect();
+        let corrupted: PathBuf = store.object_path(hashes[1]);
+        let mut bytes: Vec<u8> = fs::read(&corrupted)?;
+        bytes[0] ^= 1;
+        fs::write(&corrupted, bytes)?;
+        fs::rename(
+            store.object_path(hashes[4]),
+            dir.join(OBJECTS_DIR).join("renamed"),
+        )?;
+
+        let shard_1: ShardId = ShardId::shard("data", &manifest, 1);
+        assert!(store.get(&shard_1)?.is_none());
+        assert!(store.get(&ShardId::shard("data", &manifest, 4))?.is_none());
+        assert_eq!(repair_shard_store(&store, "data")?, vec![1, 4]);
+        assert!(store.get(&shard_1)?.is_some());
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_shard_store(&store, "data", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::write(store.index_path("data"), "not a hash  data.k4m2.s00.rssh\n")?;
+        assert!(matches!(
+            store.index("data"),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::MalformedLine(_)
+            ))
+        ));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
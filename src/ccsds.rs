@@ -0,0 +1,334 @@
+use alloc::vec::Vec;
+
+use crate::error::ReedSolomonError;
+use crate::presets::BlockCode;
+
+/// The CCSDS field polynomial, `x^8 + x^7 + x^2 + x + 1`.
+pub const CCSDS_POLYNOMIAL: u16 = 0x187;
+
+/// Symbols per codeword, before shortening.
+pub const CODEWORD_LEN: usize = 255;
+
+/// Data symbols per codeword, before shortening.
+pub const DATA_LEN: usize = 223;
+
+/// Check symbols per codeword; up to half of them can be corrected.
+pub const PARITY_LEN: usize = CODEWORD_LEN - DATA_LEN;
+
+/// The interleaving depths the standard allows.
+pub const INTERLEAVE_DEPTHS: [usize; 6] = [1, 2, 3, 4, 5, 8];
+
+/// The generator's roots are `(alpha^11)^j` for `j` in 112..144.
+const FIRST_ROOT: usize = 112;
+const ROOT_STEP: usize = 11;
+
+/// Rows of the matrix that takes a symbol from the conventional basis to
+/// the dual (Berlekamp) basis, most significant bit first.
+const DUAL_BASIS_ROWS: [u8; 8] = [0x8d, 0xef, 0xec, 0x86, 0xfa, 0x99, 0xaf, 0x7b];
+
+const TO_DUAL: [u8; 256] = to_dual_table();
+const FROM_DUAL: [u8; 256] = from_dual_table();
+
+const fn to_dual_table() -> [u8; 256] {
+    let mut table: [u8; 256] = [0u8; 256];
+    let mut value: usize = 0;
+    while value < 256 {
+        let mut bit: usize = 0;
+        while bit < 8 {
+            if value & (1 << bit) != 0 {
+                table[value] ^= DUAL_BASIS_ROWS[7 - bit];
+            }
+            bit += 1;
+        }
+        value += 1;
+    }
+    table
+}
+
+const fn from_dual_table() -> [u8; 256] {
+    let mut table: [u8; 256] = [0u8; 256];
+    let mut value: usize = 0;
+    while value < 256 {
+        table[TO_DUAL[value] as usize] = value as u8;
+        value += 1;
+    }
+    table
+}
+
+/// Converts a symbol from the conventional basis to the dual basis.
+pub fn to_dual_basis(symbol: u8) -> u8 {
+    TO_DUAL[symbol as usize]
+}
+
+/// Converts a symbol from the dual basis to the conventional basis.
+pub fn from_dual_basis(symbol: u8) -> u8 {
+    FROM_DUAL[symbol as usize]
+}
+
+/// How symbols are represented on the wire.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymbolBasis {
+    /// The Berlekamp dual basis the standard transmits.
+    #[default]
+    Dual,
+    /// Plain polynomial-basis bytes, as some non-conforming links use.
+    Conventional,
+}
+
+/// The CCSDS RS(255,223) code from the TM Synchronization and Channel
+/// Coding blue book, for encoding and decoding telemetry codeblocks.
+///
+/// Each codeword is a `BlockCode`, corrected on its own with errors at
+/// unknown positions, and its symbols are sent in the dual basis. A codeblock of
+/// depth `I` interleaves `I` codewords symbol by symbol, so the data comes
+/// first and the check symbols follow. Shortened codes leave out
+/// `virtual_fill` leading zero symbols of every codeword.
+#[derive(Debug, Clone)]
+pub struct CcsdsCode {
+    code: BlockCode,
+    interleave_depth: usize,
+    virtual_fill: usize,
+    basis: SymbolBasis,
+}
+
+impl CcsdsCode {
+    pub fn new(interleave_depth: usize) -> Result<Self, ReedSolomonError> {
+        if !INTERLEAVE_DEPTHS.contains(&interleave_depth) {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: interleave_depth,
+                min: INTERLEAVE_DEPTHS[0],
+                max: INTERLEAVE_DEPTHS[INTERLEAVE_DEPTHS.len() - 1],
+            });
+        }
+
+        Ok(Self {
+            code: Self::block_code(0)?,
+            interleave_depth,
+            virtual_fill: 0,
+            basis: SymbolBasis::Dual,
+        })
+    }
+
+    /// Shortens every codeword by `virtual_fill` symbols, which are taken
+    /// as zero and never transmitted.
+    pub fn with_virtual_fill(mut self, virtual_fill: usize) -> Result<Self, ReedSolomonError> {
+        if virtual_fill >= DATA_LEN {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: virtual_fill,
+                min: 0,
+                max: DATA_LEN - 1,
+            });
+        }
+        self.code = Self::block_code(virtual_fill)?;
+        self.virtual_fill = virtual_fill;
+        Ok(self)
+    }
+
+    pub fn with_basis(mut self, basis: SymbolBasis) -> Self {
+        self.basis = basis;
+        self
+    }
+
+    pub fn interleave_depth(&self) -> usize {
+        self.interleave_depth
+    }
+
+    pub fn virtual_fill(&self) -> usize {
+        self.virtual_fill
+    }
+
+    pub fn basis(&self) -> SymbolBasis {
+        self.basis
+    }
+
+    /// Data bytes per codeblock.
+    pub fn data_len(&self) -> usize {
+        self.interleave_depth * (DATA_LEN - self.virtual_fill)
+    }
+
+    /// Transmitted bytes per codeblock, data and check symbols together.
+    pub fn codeblock_len(&self) -> usize {
+        self.interleave_depth * (CODEWORD_LEN - self.virtual_fill)
+    }
+
+    /// Encodes exactly `data_len()` bytes into a codeblock.
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        if data.len() != self.data_len() {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: self.data_len(),
+                given: data.len(),
+            });
+        }
+
+        let depth: usize = self.interleave_depth;
+        let mut codeblock: Vec<u8> = Vec::with_capacity(self.codeblock_len());
+        codeblock.extend_from_slice(data);
+        codeblock.resize(self.codeblock_len(), 0);
+        for codeword in 0..depth {
+            let symbols: Vec<u8> = data[codeword..]
+                .iter()
+                .step_by(depth)
+                .map(|symbol: &u8| self.conventional(*symbol))
+                .collect();
+            let encoded: Vec<u8> = self.code.encode(&symbols)?;
+            for (j, value) in encoded[symbols.len()..].iter().enumerate() {
+                codeblock[data.len() + j * depth + codeword] = self.wire(*value);
+            }
+        }
+        Ok(codeblock)
+    }
+
+    /// Corrects a codeblock in place and returns how many symbols were
+    /// wrong. The data is then `codeblock[..data_len()]`. If any codeword
+    /// has more than 16 errors, returns `TooManyErrors` and leaves the
+    /// codeblock untouched.
+    pub fn decode(&self, codeblock: &mut [u8]) -> Result<usize, ReedSolomonError> {
+        if codeblock.len() != self.codeblock_len() {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: self.codeblock_len(),
+                given: codeblock.len(),
+            });
+        }
+
+        let depth: usize = self.interleave_depth;
+        let mut corrections: Vec<(usize, u8)> = Vec::new();
+        for codeword in 0..depth {
+            let received: Vec<u8> = codeblock[codeword..]
+                .iter()
+                .step_by(depth)
+                .map(|symbol: &u8| self.conventional(*symbol))
+                .collect();
+            let mut word: Vec<u8> = received.clone();
+            self.code.decode(&mut word)?;
+            corrections.extend(
+                word.iter()
+                    .zip(&received)
+                    .enumerate()
+                    .filter(|(_, (corrected, symbol)): &(usize, (&u8, &u8))| corrected != symbol)
+                    .map(|(index, (corrected, _)): (usize, (&u8, &u8))| {
+                        (index * depth + codeword, self.wire(*corrected))
+                    }),
+            );
+        }
+
+        for (offset, symbol) in &corrections {
+            codeblock[*offset] = *symbol;
+        }
+        Ok(corrections.len())
+    }
+
+    /// The code of one codeword, shortened by `virtual_fill` symbols.
+    fn block_code(virtual_fill: usize) -> Result<BlockCode, ReedSolomonError> {
+        BlockCode::new(
+            CCSDS_POLYNOMIAL,
+            CODEWORD_LEN - virtual_fill,
+            DATA_LEN - virtual_fill,
+            FIRST_ROOT,
+            ROOT_STEP,
+        )
+    }
+
+    fn conventional(&self, symbol: u8) -> u8 {
+        match self.basis {
+            SymbolBasis::Dual => from_dual_basis(symbol),
+            SymbolBasis::Conventional => symbol,
+        }
+    }
+
+    fn wire(&self, symbol: u8) -> u8 {
+        match self.basis {
+            SymbolBasis::Dual => to_dual_basis(symbol),
+            SymbolBasis::Conventional => symbol,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::correction::eval;
+    use crate::polynomial::Gf8Field;
+
+    #[test]
+    fn test_generator_and_basis_tables() -> Result<(), ReedSolomonError> {
+        // The generator in index form, as published with the standard.
+        const GENERATOR_LOGS: [usize; 33] = [
+            0, 249, 59, 66, 4, 43, 126, 251, 97, 30, 3, 213, 50, 66, 170, 5, 24, 5, 170, 66, 50,
+            213, 3, 30, 97, 251, 126, 43, 4, 66, 59, 249, 0,
+        ];
+        let code: CcsdsCode = CcsdsCode::new(1)?;
+        let field: Gf8Field = Gf8Field::new(CCSDS_POLYNOMIAL)?;
+        let expected: Vec<u8> = GENERATOR_LOGS
+            .iter()
+            .map(|log: &usize| field.pow(2, *log))
+            .collect();
+        assert_eq!(code.code.generator(), expected);
+        assert_eq!(
+            eval(&field, code.code.generator(), field.pow(2, 11 * 112)),
+            0
+        );
+
+        assert_eq!(
+            TO_DUAL[..8],
+            [0x00, 0x7b, 0xaf, 0xd4, 0x99, 0xe2, 0x36, 0x4d]
+        );
+        for symbol in 0..=255u8 {
+            assert_eq!(from_dual_basis(to_dual_basis(symbol)), symbol);
+        }
+
+        assert!(CcsdsCode::new(6).is_err());
+        assert!(CcsdsCode::new(4)?.with_virtual_fill(DATA_LEN).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrects_sixteen_errors_per_codeword() -> Result<(), ReedSolomonError> {
+        let code: CcsdsCode = CcsdsCode::new(5)?;
+        let data: Vec<u8> = (0..code.data_len())
+            .map(|i: usize| (i * 31 % 256) as u8)
+            .collect();
+        let encoded: Vec<u8> = code.encode(&data)?;
+        assert_eq!(encoded.len(), 1275);
+        assert_eq!(encoded[..data.len()], data[..]);
+
+        // An 80-byte burst is 16 errors in each of the 5 codewords.
+        let mut received: Vec<u8> = encoded.clone();
+        for byte in &mut received[600..680] {
+            *byte ^= 0xa5;
+        }
+        assert_eq!(code.decode(&mut received)?, 80);
+        assert_eq!(received, encoded);
+
+        received[0] ^= 1;
+        for byte in received[5..85].iter_mut().step_by(5) {
+            *byte ^= 0x3c;
+        }
+        let before: Vec<u8> = received.clone();
+        assert!(matches!(
+            code.decode(&mut received),
+            Err(ReedSolomonError::TooManyErrors { max: 16 })
+        ));
+        assert_eq!(received, before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shortened_conventional_basis() -> Result<(), ReedSolomonError> {
+        let code: CcsdsCode = CcsdsCode::new(2)?
+            .with_virtual_fill(200)?
+            .with_basis(SymbolBasis::Conventional);
+        assert_eq!(code.data_len(), 46);
+        assert_eq!(code.codeblock_len(), 110);
+
+        let data: Vec<u8> = (0..46u8).collect();
+        let encoded: Vec<u8> = code.encode(&data)?;
+        let mut received: Vec<u8> = encoded.clone();
+        received[3] = 0;
+        received[50] ^= 0xff;
+        received[109] ^= 0x10;
+        assert_eq!(code.decode(&mut received)?, 3);
+        assert_eq!(received, encoded);
+        assert!(code.encode(&data[1..]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,412 @@
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+
+use crate::cas::is_hex_hash;
+use crate::codec::ReedSolomonCodec;
+use crate::error::{ManifestError, ReedSolomonError};
+use crate::store::{decode_from_shard_store, ShardId, ShardStore};
+use crate::stream::read_full;
+
+pub const RECIPE_VERSION: u32 = 1;
+pub const MIN_CHUNK_SIZE: usize = 64;
+pub const MAX_CHUNK_SIZE: usize = 1 << 30;
+
+/// Random values for the gear hash, one per byte value.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table: [u64; 256] = [0; 256];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut i: usize = 0;
+    while i < 256 {
+        // splitmix64
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z: u64 = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// FastCDC content-defined chunking. Cut points depend only on the bytes
+/// around them, so an insertion or deletion moves the chunk boundaries
+/// near the edit and leaves the rest of the chunks unchanged. Chunks are
+/// at least `min_size` and at most `max_size` bytes, and normalized
+/// chunking keeps most of them close to `avg_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastCdc {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Default for FastCdc {
+    /// 256 KiB to 4 MiB chunks averaging 1 MiB, the default block size.
+    fn default() -> Self {
+        Self::new(256 << 10, 1 << 20, 4 << 20).expect("default chunk sizes are valid")
+    }
+}
+
+impl FastCdc {
+    pub fn new(
+        min_size: usize,
+        avg_size: usize,
+        max_size: usize,
+    ) -> Result<Self, ReedSolomonError> {
+        if !(MIN_CHUNK_SIZE..=avg_size).contains(&min_size) {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: min_size,
+                min: MIN_CHUNK_SIZE,
+                max: avg_size,
+            });
+        }
+        if !(avg_size..=MAX_CHUNK_SIZE).contains(&max_size) {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: max_size,
+                min: avg_size,
+                max: MAX_CHUNK_SIZE,
+            });
+        }
+
+        // The gear hash shifts left, so its top bits cover the most bytes.
+        let bits: u32 = avg_size.ilog2();
+        Ok(Self {
+            min_size,
+            avg_size,
+            max_size,
+            mask_small: !0u64 << (64 - (bits + 1)),
+            mask_large: !0u64 << (64 - (bits - 1)),
+        })
+    }
+
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// The length of the chunk at the start of `data`. All of `data` is
+    /// one chunk when it is no longer than `min_size`.
+    pub fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let end: usize = data.len().min(self.max_size);
+        let normal: usize = end.min(self.avg_size);
+
+        let mut hash: u64 = 0;
+        for (i, byte) in data.iter().enumerate().take(end).skip(self.min_size) {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            let mask: u64 = if i < normal {
+                self.mask_small
+            } else {
+                self.mask_large
+            };
+            if hash & mask == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+
+    pub fn chunks<R: Read>(&self, reader: R) -> Chunks<R> {
+        Chunks {
+            reader,
+            chunker: *self,
+            buffer: Vec::new(),
+            eof: false,
+        }
+    }
+}
+
+/// The chunks of a reader, as produced by `FastCdc::chunks`.
+#[derive(Debug)]
+pub struct Chunks<R> {
+    reader: R,
+    chunker: FastCdc,
+    buffer: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> Iterator for Chunks<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        let max_size: usize = self.chunker.max_size;
+        if !self.eof && self.buffer.len() < max_size {
+            let filled: usize = self.buffer.len();
+            self.buffer.resize(max_size, 0);
+            match read_full(&mut self.reader, &mut self.buffer[filled..]) {
+                Ok(read) => {
+                    self.buffer.truncate(filled + read);
+                    self.eof = filled + read < max_size;
+                }
+                Err(error) => {
+                    self.buffer.truncate(filled);
+                    return Some(Err(error));
+                }
+            }
+        }
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        let len: usize = self.chunker.cut(&self.buffer);
+        Some(Ok(self.buffer.drain(..len).collect()))
+    }
+}
+
+/// One chunk of a chunked encode: the BLAKE3 hash that names its shard
+/// set, and its length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkRef {
+    pub hash: String,
+    pub len: u64,
+}
+
+/// The chunks that make up a payload, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChunkRecipe {
+    pub chunks: Vec<ChunkRef>,
+}
+
+impl ChunkRecipe {
+    pub fn payload_len(&self) -> u64 {
+        self.chunks.iter().map(|chunk: &ChunkRef| chunk.len).sum()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text: String = format!("version={}\n", RECIPE_VERSION);
+        for chunk in &self.chunks {
+            let _ = writeln!(text, "chunk={} {}", chunk.hash, chunk.len);
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReedSolomonError> {
+        let mut version: Option<u32> = None;
+        let mut chunks: Vec<ChunkRef> = Vec::new();
+        for line in text
+            .lines()
+            .map(str::trim)
+            .filter(|line: &&str| !line.is_empty())
+        {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ManifestError::MalformedLine(line.to_string()))?;
+            match key {
+                "version" => {
+                    let parsed: u32 = value
+                        .parse()
+                        .map_err(|_| ManifestError::InvalidValue(key.to_string()))?;
+                    if parsed != RECIPE_VERSION {
+                        return Err(ManifestError::UnsupportedVersion(parsed as u64).into());
+                    }
+                    version = Some(parsed);
+                }
+                "chunk" => {
+                    let chunk: ChunkRef = value
+                        .split_once(' ')
+                        .and_then(|(hash, len): (&str, &str)| {
+                            Some(ChunkRef {
+                                hash: is_hex_hash(hash).then(|| hash.to_string())?,
+                                len: len.parse().ok()?,
+                            })
+                        })
+                        .ok_or_else(|| ManifestError::InvalidValue(key.to_string()))?;
+                    chunks.push(chunk);
+                }
+                _ => return Err(ManifestError::UnknownKey(key.to_string()).into()),
+            }
+        }
+        version.ok_or(ManifestError::MissingKey("version"))?;
+        Ok(Self { chunks })
+    }
+}
+
+/// The outcome of `encode_chunked`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkReport {
+    pub recipe: ChunkRecipe,
+    /// How many chunks already had a shard set in the store and were not
+    /// encoded again.
+    pub reused: usize,
+}
+
+impl ReedSolomonCodec {
+    /// Splits `reader` into content-defined chunks and stores each chunk
+    /// as its own shard set, named by the chunk's BLAKE3 hash. Chunks whose
+    /// shard set already has a manifest in `store` are not encoded again,
+    /// so re-encoding a slightly changed file only adds the chunks around
+    /// the change. The recipe listing the chunks is stored last.
+    pub fn encode_chunked<R: Read, S: ShardStore + ?Sized>(
+        &self,
+        reader: R,
+        store: &S,
+        basename: &str,
+        chunker: &FastCdc,
+    ) -> Result<ChunkReport, ReedSolomonError> {
+        let mut recipe: ChunkRecipe = ChunkRecipe::default();
+        let mut reused: usize = 0;
+        for chunk in chunker.chunks(reader) {
+            let chunk: Vec<u8> = chunk?;
+            let hash: String = blake3::hash(&chunk).to_hex().to_string();
+            if store.get(&ShardId::manifest(&hash))?.is_some() {
+                reused += 1;
+            } else {
+                self.encode_to_shard_store(chunk.as_slice(), store, &hash)?;
+            }
+            recipe.chunks.push(ChunkRef {
+                hash,
+                len: chunk.len() as u64,
+            });
+        }
+
+        store.put(&ShardId::recipe(basename), recipe.to_text().as_bytes())?;
+        Ok(ChunkReport { recipe, reused })
+    }
+}
+
+pub fn read_recipe<S: ShardStore + ?Sized>(
+    store: &S,
+    basename: &str,
+) -> Result<ChunkRecipe, ReedSolomonError> {
+    let id: ShardId = ShardId::recipe(basename);
+    let text: Vec<u8> = store
+        .get(&id)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, id.to_string()))?;
+    ChunkRecipe::parse(&String::from_utf8_lossy(&text))
+}
+
+/// Decodes every chunk listed by the recipe `basename` in order, checking
+/// each against its hash before writing it.
+pub fn decode_chunked<S: ShardStore + ?Sized, W: Write>(
+    store: &S,
+    basename: &str,
+    mut writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let recipe: ChunkRecipe = read_recipe(store, basename)?;
+    for chunk in &recipe.chunks {
+        let mut decoded: Vec<u8> = Vec::with_capacity(chunk.len as usize);
+        decode_from_shard_store(store, &chunk.hash, &mut decoded)?;
+        if decoded.len() as u64 != chunk.len
+            || blake3::hash(&decoded).to_hex().as_str() != chunk.hash
+        {
+            return Err(ReedSolomonError::DigestMismatch);
+        }
+        writer.write_all(&decoded)?;
+    }
+
+    writer.flush()?;
+    Ok(recipe.payload_len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::store::MemoryStore;
+
+    fn random_data(len: usize, seed: u64) -> Vec<u8> {
+        let mut state: u64 = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn chunk_hashes(chunker: &FastCdc, data: &[u8]) -> io::Result<Vec<String>> {
+        chunker
+            .chunks(data)
+            .map(|chunk: io::Result<Vec<u8>>| Ok(blake3::hash(&chunk?).to_hex().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_boundaries_survive_an_insertion() -> Result<(), ReedSolomonError> {
+        let chunker: FastCdc = FastCdc::new(2048, 8192, 32768)?;
+        let original: Vec<u8> = random_data(1 << 20, 0x9e37_79b9);
+        let chunks: Vec<Vec<u8>> = chunker
+            .chunks(original.as_slice())
+            .collect::<io::Result<_>>()?;
+        assert_eq!(chunks.concat(), original);
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|chunk: &Vec<u8>| (2048..=32768).contains(&chunk.len())));
+
+        let mut edited: Vec<u8> = original.clone();
+        edited.splice(500_000..500_000, random_data(100, 7));
+        let before: Vec<String> = chunk_hashes(&chunker, &original)?;
+        let after: Vec<String> = chunk_hashes(&chunker, &edited)?;
+        let shared: usize = after
+            .iter()
+            .filter(|hash: &&String| before.contains(hash))
+            .count();
+        assert!(shared + 3 >= before.len(), "{} of {}", shared, before.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reencoding_reuses_unchanged_chunks() -> Result<(), ReedSolomonError> {
+        let store: MemoryStore = MemoryStore::new();
+        let chunker: FastCdc = FastCdc::new(4096, 16384, 65536)?;
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(8192)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original: Vec<u8> = random_data(600_000, 0x1234_5678);
+
+        let first: ChunkReport =
+            codec.encode_chunked(original.as_slice(), &store, "v1", &chunker)?;
+        assert_eq!(first.reused, 0);
+        assert_eq!(first.recipe.payload_len(), original.len() as u64);
+
+        let mut edited: Vec<u8> = original.clone();
+        edited[300_000..300_010].copy_from_slice(b"0123456789");
+        let second: ChunkReport =
+            codec.encode_chunked(edited.as_slice(), &store, "v2", &chunker)?;
+        assert!(second.reused + 2 >= second.recipe.chunks.len());
+        assert_eq!(read_recipe(&store, "v2")?, second.recipe);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        assert_eq!(decode_chunked(&store, "v1", &mut decoded)?, 600_000);
+        assert_eq!(decoded, original);
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_chunked(&store, "v2", &mut decoded)?;
+        assert_eq!(decoded, edited);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_bad_sizes_and_recipes() {
+        assert!(FastCdc::new(32, 1024, 4096).is_err());
+        assert!(FastCdc::new(2048, 1024, 4096).is_err());
+        assert!(FastCdc::new(512, 1024, 512).is_err());
+        assert!(ChunkRecipe::parse("chunk=abc 12\n").is_err());
+        assert!(ChunkRecipe::parse("version=2\n").is_err());
+        assert!(matches!(
+            ChunkRecipe::parse("version=1\nblocks=3\n"),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::UnknownKey(_)
+            ))
+        ));
+        assert_eq!(
+            ChunkRecipe::parse("version=1\n")
+                .map(|recipe: ChunkRecipe| recipe.payload_len())
+                .ok(),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,2104 @@
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::IoSlice;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use crate::backend::{backend_for, ErasureBackend};
+#[cfg(feature = "compression")]
+use crate::compression;
+use crate::config::{Compression, DataSizeLimits, EncoderConfig, Encryption, ShardLayout};
+#[cfg(feature = "encryption")]
+use crate::encryption::{self, EncryptionKey, TAG_SIZE};
+use crate::error::ReedSolomonError;
+use crate::header::{ContainerHeader, DIGEST_SIZE, HEADER_SIZE};
+use crate::mac::MacKey;
+#[cfg(feature = "std")]
+use crate::metrics::{Metrics, MetricsSnapshot};
+#[cfg(feature = "std")]
+use crate::pool::{BufferPool, PoolStats};
+#[cfg(feature = "std")]
+use crate::processor::blake3_digest_parts;
+use crate::processor::{blake3_digest, crc32c_checksum, DataProcessor, CHECKSUM_SIZE, PREFIX_SIZE};
+#[cfg(feature = "std")]
+use crate::progress::{ProgressSink, ProgressUpdate};
+use crate::replication::HybridPolicy;
+use crate::report::DecodeReport;
+use crate::shard::{Shard, ShardKind};
+#[cfg(feature = "std")]
+use crate::throttle::RateLimiter;
+
+/// How much `encode_verified` checks before handing the shards back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodeCheck {
+    /// Recompute the parity and compare it with the encoded shards.
+    #[default]
+    Parity,
+    /// Also drop one shard and decode the data from the rest. Which shard
+    /// is dropped depends on the data, so different inputs exercise
+    /// different recovery paths.
+    TrialDecode,
+}
+
+/// How the length of the payload in a set of data shards is known.
+#[derive(Debug, Clone, Copy)]
+enum PayloadLen {
+    /// Read from a big-endian length prefix of this many bytes in front of
+    /// the payload.
+    Prefixed(usize),
+    /// Recorded outside the shards, in a container header.
+    Exact(u64),
+}
+
+/// Wraps the coder so callers only ever see the stored shards. In the
+/// non-systematic layout the data shards are hidden in front of them and
+/// every stored shard is parity.
+#[derive(Debug)]
+struct Backend {
+    coder: Box<dyn ErasureBackend>,
+    hidden_shards: usize,
+}
+
+impl Backend {
+    fn new(
+        config: &EncoderConfig,
+        coder: Box<dyn ErasureBackend>,
+    ) -> Result<Self, ReedSolomonError> {
+        let hidden_shards: usize = config.layout().hidden_shards(config.data_shards());
+        if coder.data_shards() != config.data_shards()
+            || coder.parity_shards() != config.parity_shards() + hidden_shards
+        {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: config.total_shards() + hidden_shards,
+                given: coder.data_shards() + coder.parity_shards(),
+            });
+        }
+
+        Ok(Self {
+            coder,
+            hidden_shards,
+        })
+    }
+
+    /// Expects the data in the first `data_shards` stored shards, as in the
+    /// systematic layout, and overwrites every stored shard when hidden.
+    fn encode(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+        if self.hidden_shards == 0 {
+            return self.coder.encode_shards(shards);
+        }
+
+        let mut data: Vec<Vec<u8>> = shards[..self.hidden_shards]
+            .iter()
+            .map(|shard: &&mut [u8]| shard.to_vec())
+            .collect();
+        let mut coded: Vec<&mut [u8]> = data
+            .iter_mut()
+            .map(Vec::as_mut_slice)
+            .chain(shards.iter_mut().map(|shard: &mut &mut [u8]| &mut **shard))
+            .collect();
+        self.coder.encode_shards(&mut coded)
+    }
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+        if self.hidden_shards == 0 {
+            return self.coder.verify(shards);
+        }
+
+        let mut coded: Vec<Option<Vec<u8>>> = self.expand(
+            shards
+                .iter()
+                .map(|shard: &&[u8]| Some(shard.to_vec()))
+                .collect(),
+        );
+        self.coder.reconstruct(&mut coded, true)?;
+        let coded: Vec<&[u8]> = coded.iter().flatten().map(Vec::as_slice).collect();
+        self.coder.verify(&coded)
+    }
+
+    /// With hidden data shards, `data_only` leaves the recovered data in the
+    /// first `data_shards` slots in place of the stored shards, which is
+    /// what decoding needs; otherwise the missing stored shards are rebuilt.
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        if self.hidden_shards == 0 {
+            return self.coder.reconstruct(shards, data_only);
+        }
+
+        let mut coded: Vec<Option<Vec<u8>>> =
+            self.expand(shards.iter_mut().map(Option::take).collect());
+        let result: Result<(), ReedSolomonError> = self.coder.reconstruct(&mut coded, data_only);
+
+        let skip: usize = if data_only && result.is_ok() {
+            0
+        } else {
+            self.hidden_shards
+        };
+        for (slot, shard) in shards.iter_mut().zip(coded.into_iter().skip(skip)) {
+            *slot = shard;
+        }
+        result
+    }
+
+    fn expand(&self, shards: Vec<Option<Vec<u8>>>) -> Vec<Option<Vec<u8>>> {
+        let mut coded: Vec<Option<Vec<u8>>> = vec![None; self.hidden_shards];
+        coded.extend(shards);
+        coded
+    }
+}
+
+#[derive(Debug)]
+pub struct ReedSolomonCodec {
+    codec: Backend,
+    config: EncoderConfig,
+    #[cfg(feature = "std")]
+    pool: Arc<BufferPool>,
+    #[cfg(feature = "encryption")]
+    key: Option<EncryptionKey>,
+    mac_key: Option<MacKey>,
+    #[cfg(feature = "std")]
+    progress: Option<Arc<dyn ProgressSink>>,
+    #[cfg(feature = "std")]
+    metrics: Arc<Metrics>,
+    #[cfg(feature = "std")]
+    rate_limit: Option<Arc<RateLimiter>>,
+    hybrid: Option<HybridPolicy>,
+    prefix_size: usize,
+}
+
+impl ReedSolomonCodec {
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(data_shards, parity_shards)?;
+        Self::with_config(config)
+    }
+
+    pub fn with_config(config: EncoderConfig) -> Result<Self, ReedSolomonError> {
+        let backend: Box<dyn ErasureBackend> = backend_for(&config)?;
+        Self::with_backend(config, backend)
+    }
+
+    pub fn with_backend(
+        config: EncoderConfig,
+        backend: Box<dyn ErasureBackend>,
+    ) -> Result<Self, ReedSolomonError> {
+        let codec: Backend = Backend::new(&config, backend)?;
+
+        Ok(Self {
+            codec,
+            config,
+            #[cfg(feature = "std")]
+            pool: Arc::new(BufferPool::default()),
+            #[cfg(feature = "encryption")]
+            key: None,
+            mac_key: None,
+            #[cfg(feature = "std")]
+            progress: None,
+            #[cfg(feature = "std")]
+            metrics: Arc::new(Metrics::new()),
+            #[cfg(feature = "std")]
+            rate_limit: None,
+            hybrid: None,
+            prefix_size: PREFIX_SIZE,
+        })
+    }
+
+    #[cfg(feature = "std")]
+    pub fn with_pool(mut self, pool: Arc<BufferPool>) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub fn with_progress(mut self, sink: Arc<dyn ProgressSink>) -> Self {
+        self.progress = Some(sink);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Holds the streaming and file APIs to the limiter's rate, counting
+    /// the bytes they write.
+    #[cfg(feature = "std")]
+    pub fn with_rate_limit(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limit = Some(limiter);
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    pub fn with_encryption_key(mut self, key: EncryptionKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    pub(crate) fn encryption_key(&self) -> Result<&EncryptionKey, ReedSolomonError> {
+        self.key.as_ref().ok_or(ReedSolomonError::MissingKey)
+    }
+
+    /// Sets the key that `encode_shards_authenticated` tags shards with and
+    /// `decode_shards_authenticated` checks them against.
+    pub fn with_mac_key(mut self, key: MacKey) -> Self {
+        self.mac_key = Some(key);
+        self
+    }
+
+    pub(crate) fn mac_key(&self) -> Result<&MacKey, ReedSolomonError> {
+        self.mac_key.as_ref().ok_or(ReedSolomonError::MissingMacKey)
+    }
+
+    /// Stores containers smaller than the policy's threshold as full
+    /// copies instead of erasure coding them. The header records which
+    /// was used, so any codec decodes either.
+    pub fn with_hybrid_policy(mut self, policy: HybridPolicy) -> Self {
+        self.hybrid = Some(policy);
+        self
+    }
+
+    pub fn hybrid_policy(&self) -> Option<HybridPolicy> {
+        self.hybrid
+    }
+
+    pub fn from_header(header: &ContainerHeader) -> Result<Self, ReedSolomonError> {
+        Self::with_config(header.config()?)
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn with_prefix_size(mut self, prefix_size: usize) -> Self {
+        self.prefix_size = prefix_size;
+        self
+    }
+
+    pub(crate) fn for_header(&self, header: &ContainerHeader) -> Result<Self, ReedSolomonError> {
+        self.for_config(header.config()?.with_limits_of(&self.config)?)
+    }
+
+    /// A codec for `config` that shares this one's keys, pool, progress,
+    /// metrics and rate limit.
+    pub(crate) fn for_config(&self, config: EncoderConfig) -> Result<Self, ReedSolomonError> {
+        let codec: Self = Self {
+            mac_key: self.mac_key.clone(),
+            ..Self::with_config(config)?
+        };
+        #[cfg(feature = "std")]
+        let codec: Self = Self {
+            pool: Arc::clone(&self.pool),
+            progress: self.progress.clone(),
+            metrics: Arc::clone(&self.metrics),
+            rate_limit: self.rate_limit.clone(),
+            ..codec
+        };
+        #[cfg(feature = "encryption")]
+        let codec: Self = Self {
+            key: self.key.clone(),
+            ..codec
+        };
+        Ok(codec)
+    }
+
+    pub fn config(&self) -> &EncoderConfig {
+        &self.config
+    }
+
+    #[cfg(feature = "std")]
+    pub fn pool(&self) -> &Arc<BufferPool> {
+        &self.pool
+    }
+
+    #[cfg(feature = "std")]
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    #[cfg(feature = "std")]
+    fn take_buffer(&self, len: usize) -> Vec<u8> {
+        self.pool.take(len)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn take_buffer(&self, len: usize) -> Vec<u8> {
+        vec![0u8; len]
+    }
+
+    #[cfg(feature = "std")]
+    fn recycle(&self, buffer: Vec<u8>) {
+        self.pool.give(buffer);
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn recycle(&self, _buffer: Vec<u8>) {}
+
+    #[cfg(feature = "std")]
+    fn report_progress(&self, bytes: usize) {
+        if let Some(sink) = &self.progress {
+            sink.report(ProgressUpdate {
+                bytes: bytes as u64,
+                shards: self.config.total_shards() as u64,
+            });
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn report_progress(&self, _bytes: usize) {}
+
+    #[cfg(feature = "std")]
+    pub(crate) fn throttle(&self, bytes: usize) {
+        if let Some(limiter) = &self.rate_limit {
+            limiter.acquire(bytes as u64);
+        }
+    }
+
+    fn stored_data_shards(&self) -> usize {
+        match self.config.layout() {
+            ShardLayout::Systematic => self.config.data_shards(),
+            ShardLayout::NonSystematic => 0,
+        }
+    }
+
+    /// Length of each shard `encode_shards` makes from `data_len` bytes.
+    pub fn shard_len(&self, data_len: usize) -> usize {
+        self.shard_len_with_prefix(data_len, self.prefix_size)
+    }
+
+    /// Containers record the exact payload length in their header, so
+    /// their data shards carry no length prefix.
+    fn container_shard_len(&self, payload_len: usize) -> usize {
+        self.shard_len_with_prefix(payload_len, 0)
+    }
+
+    fn shard_len_with_prefix(&self, data_len: usize, prefix_size: usize) -> usize {
+        (DataProcessor::shard_size(
+            data_len,
+            prefix_size,
+            self.config.data_shards(),
+            self.config.element_size(),
+        ) + CHECKSUM_SIZE)
+            .next_multiple_of(self.config.shard_alignment())
+    }
+
+    pub fn encoded_len(&self, data_len: usize) -> usize {
+        if let Ok(Some(replica)) = self.replica_codec(data_len) {
+            return replica.encoded_len(data_len);
+        }
+        self.coded_len(data_len)
+    }
+
+    /// `encoded_len` when the data is erasure coded whatever its size.
+    pub(crate) fn coded_len(&self, data_len: usize) -> usize {
+        #[cfg(feature = "encryption")]
+        if self.key.is_some() {
+            return HEADER_SIZE
+                + self.container_shard_len(data_len + TAG_SIZE) * self.config.total_shards();
+        }
+
+        HEADER_SIZE + self.container_shard_len(data_len) * self.config.total_shards()
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        self.config.data_size_limits().check(data.len())?;
+
+        let mut encoded: Vec<u8> = vec![0u8; self.encoded_len(data.len())];
+        let encoded_len: usize = self.encode_into(data, &mut encoded)?;
+        encoded.truncate(encoded_len);
+        Ok(encoded)
+    }
+
+    pub fn encode_into(&self, data: &[u8], out: &mut [u8]) -> Result<usize, ReedSolomonError> {
+        self.config.data_size_limits().check(data.len())?;
+        if let Some(replica) = self.replica_codec(data.len())? {
+            return replica.encode_into(data, out);
+        }
+        self.encode_coded_into(data, out)
+    }
+
+    /// `encode_into` without the hybrid policy.
+    pub(crate) fn encode_coded_into(
+        &self,
+        data: &[u8],
+        out: &mut [u8],
+    ) -> Result<usize, ReedSolomonError> {
+        #[cfg(feature = "compression")]
+        if self.config.compression() != Compression::None {
+            let compressed: Vec<u8> = compression::compress(self.config.compression(), data)?;
+            if compressed.len() < data.len() {
+                return self.encode_container_into(
+                    data,
+                    &compressed,
+                    self.config.compression(),
+                    out,
+                );
+            }
+        }
+
+        self.encode_container_into(data, data, Compression::None, out)
+    }
+
+    fn encode_container_into(
+        &self,
+        original: &[u8],
+        data: &[u8],
+        compression: Compression,
+        out: &mut [u8],
+    ) -> Result<usize, ReedSolomonError> {
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key {
+            let payload_len: usize = data.len() + TAG_SIZE;
+            let mut header: ContainerHeader = ContainerHeader::new(
+                &self.config,
+                self.container_shard_len(payload_len),
+                payload_len,
+            );
+            header.compression = compression;
+            let ciphertext: Vec<u8> = encryption::encrypt(key, &mut header, data)?;
+            return self.write_container(&header, &[&ciphertext], out);
+        }
+
+        let mut header: ContainerHeader = ContainerHeader::new(
+            &self.config,
+            self.container_shard_len(data.len()),
+            data.len(),
+        );
+        header.compression = compression;
+        header.digest = Some(blake3_digest(original));
+        self.write_container(&header, &[data], out)
+    }
+
+    /// Encodes the concatenation of `bufs` into the same container `encode`
+    /// would produce, without joining the buffers first. Compression and
+    /// encryption need the payload in one piece, so with either enabled the
+    /// buffers are joined anyway.
+    #[cfg(feature = "std")]
+    pub fn encode_vectored(&self, bufs: &[IoSlice<'_>]) -> Result<Vec<u8>, ReedSolomonError> {
+        let parts: Vec<&[u8]> = bufs.iter().map(|buf: &IoSlice<'_>| &**buf).collect();
+        let data_len: usize = parts.iter().map(|part: &&[u8]| part.len()).sum();
+        self.config.data_size_limits().check(data_len)?;
+
+        #[cfg(feature = "encryption")]
+        let joined: bool = self.key.is_some() || self.config.compression() != Compression::None;
+        #[cfg(not(feature = "encryption"))]
+        let joined: bool = self.config.compression() != Compression::None;
+        if joined || self.replica_codec(data_len)?.is_some() {
+            return self.encode(&parts.concat());
+        }
+
+        let mut header: ContainerHeader =
+            ContainerHeader::new(&self.config, self.container_shard_len(data_len), data_len);
+        header.digest = Some(blake3_digest_parts(&parts));
+        let mut encoded: Vec<u8> = vec![0u8; self.encoded_len(data_len)];
+        let encoded_len: usize = self.write_container(&header, &parts, &mut encoded)?;
+        encoded.truncate(encoded_len);
+        Ok(encoded)
+    }
+
+    fn write_container(
+        &self,
+        header: &ContainerHeader,
+        parts: &[&[u8]],
+        out: &mut [u8],
+    ) -> Result<usize, ReedSolomonError> {
+        let shard_len: usize = header.shard_size as usize;
+        let encoded_len: usize = header.size() + shard_len * self.config.total_shards();
+        if out.len() < encoded_len {
+            return Err(ReedSolomonError::BufferTooSmall {
+                given: out.len(),
+                needed: encoded_len,
+            });
+        }
+
+        let (header_bytes, body) = out[..encoded_len].split_at_mut(header.size());
+        header_bytes.copy_from_slice(&header.to_bytes());
+
+        let mut shards: Vec<&mut [u8]> = body.chunks_exact_mut(shard_len).collect();
+        let payload_len: usize = parts.iter().map(|part: &&[u8]| part.len()).sum();
+        self.seal_with(
+            payload_len,
+            &mut shards,
+            |payloads: &mut [&mut [u8]]| DataProcessor::fill_data_shards_vectored(parts, payloads),
+        )?;
+
+        Ok(encoded_len)
+    }
+
+    pub fn encode_shards(&self, data: &[u8]) -> Result<Vec<Shard>, ReedSolomonError> {
+        self.config.data_size_limits().check(data.len())?;
+
+        let mut buffers: Vec<Vec<u8>> =
+            vec![vec![0u8; self.shard_len(data.len())]; self.config.total_shards()];
+        let mut shards: Vec<&mut [u8]> = buffers.iter_mut().map(Vec::as_mut_slice).collect();
+        self.seal_into(data, self.prefix_size, &mut shards)?;
+
+        Ok(buffers
+            .into_iter()
+            .enumerate()
+            .map(|(i, shard): (usize, Vec<u8>)| Shard::new(i, self.stored_data_shards(), shard))
+            .collect())
+    }
+
+    /// Encodes like `encode_shards`, then checks the result before
+    /// returning it, so archival callers know the shards are recoverable
+    /// before they delete the original.
+    pub fn encode_verified(
+        &self,
+        data: &[u8],
+        check: EncodeCheck,
+    ) -> Result<Vec<Shard>, ReedSolomonError> {
+        let shards: Vec<Shard> = self.encode_shards(data)?;
+        let stored: Vec<Vec<u8>> = shards
+            .iter()
+            .map(|shard: &Shard| shard.payload.clone())
+            .collect();
+        if !self.verify(&stored)? {
+            return Err(ReedSolomonError::VerificationFailed);
+        }
+
+        if check == EncodeCheck::TrialDecode {
+            let dropped: usize = crc32c_checksum(data) as usize % stored.len();
+            let mut positional: Vec<Option<Vec<u8>>> = stored.into_iter().map(Some).collect();
+            positional[dropped] = None;
+            if self.decode_positional(positional)? != data {
+                return Err(ReedSolomonError::VerificationFailed);
+            }
+        }
+
+        Ok(shards)
+    }
+
+    /// Yields the shards one at a time. Parity has to be computed from all
+    /// the data up front, but each data shard is cut from `data` only when
+    /// the iterator reaches it, so a consumer that uploads and drops shards
+    /// as they come never holds more than the parity and one data shard.
+    pub fn encode_iter<'a>(
+        &'a self,
+        data: &'a [u8],
+    ) -> Result<impl Iterator<Item = Shard> + 'a, ReedSolomonError> {
+        self.config.data_size_limits().check(data.len())?;
+
+        let shard_len: usize = self.shard_len(data.len());
+        let data_shards: usize = self.stored_data_shards();
+        let mut scratch: Vec<Vec<u8>> = (0..data_shards)
+            .map(|_| self.take_buffer(shard_len))
+            .collect();
+        let mut parity: Vec<Vec<u8>> =
+            vec![vec![0u8; shard_len]; self.config.total_shards() - data_shards];
+
+        let mut shards: Vec<&mut [u8]> = scratch
+            .iter_mut()
+            .chain(parity.iter_mut())
+            .map(Vec::as_mut_slice)
+            .collect();
+        let sealed: Result<(), ReedSolomonError> =
+            self.seal_into(data, self.prefix_size, &mut shards);
+        for buffer in scratch {
+            self.recycle(buffer);
+        }
+        sealed?;
+
+        let prefix_size: usize = self.prefix_size;
+        let data_iter = (0..data_shards).map(move |index: usize| {
+            let mut payload: Vec<u8> = vec![0u8; shard_len];
+            DataProcessor::fill_data_shard(
+                data,
+                prefix_size,
+                index,
+                &mut payload[..shard_len - CHECKSUM_SIZE],
+            );
+            DataProcessor::write_checksum(&mut payload);
+            Shard::new(index, data_shards, payload)
+        });
+        let parity_iter =
+            parity
+                .into_iter()
+                .enumerate()
+                .map(move |(offset, payload): (usize, Vec<u8>)| {
+                    Shard::new(data_shards + offset, data_shards, payload)
+                });
+        Ok(data_iter.chain(parity_iter))
+    }
+
+    pub fn encode_shards_into(
+        &self,
+        data: &[u8],
+        shards: &mut [&mut [u8]],
+    ) -> Result<usize, ReedSolomonError> {
+        self.config.data_size_limits().check(data.len())?;
+
+        if shards.len() != self.config.total_shards() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.config.total_shards(),
+                given: shards.len(),
+            });
+        }
+
+        let shard_len: usize = self.shard_len(data.len());
+        if let Some(i) = shards
+            .iter()
+            .position(|shard: &&mut [u8]| shard.len() != shard_len)
+        {
+            return Err(ReedSolomonError::ShardSizeMismatch {
+                index: i,
+                given: shards[i].len(),
+                expected: shard_len,
+            });
+        }
+
+        self.seal_into(data, self.prefix_size, shards)?;
+        Ok(shard_len)
+    }
+
+    fn seal_into(
+        &self,
+        data: &[u8],
+        prefix_size: usize,
+        shards: &mut [&mut [u8]],
+    ) -> Result<(), ReedSolomonError> {
+        self.seal_with(data.len(), shards, |payloads: &mut [&mut [u8]]| {
+            DataProcessor::fill_data_shards(data, prefix_size, payloads)
+        })
+    }
+
+    fn seal_with(
+        &self,
+        data_len: usize,
+        shards: &mut [&mut [u8]],
+        fill: impl FnOnce(&mut [&mut [u8]]),
+    ) -> Result<(), ReedSolomonError> {
+        #[cfg(feature = "tracing")]
+        let _span: tracing::span::EnteredSpan = tracing::debug_span!(
+            "encode",
+            data_len = data_len,
+            data_shards = self.config.data_shards(),
+            parity_shards = self.config.parity_shards(),
+            shard_len = shards.first().map_or(0, |shard: &&mut [u8]| shard.len()),
+        )
+        .entered();
+        #[cfg(feature = "std")]
+        let started: Instant = Instant::now();
+
+        let mut payloads: Vec<&mut [u8]> = shards
+            .iter_mut()
+            .map(|shard: &mut &mut [u8]| {
+                let payload_len: usize = shard.len() - CHECKSUM_SIZE;
+                &mut shard[..payload_len]
+            })
+            .collect();
+
+        fill(&mut payloads[..self.config.data_shards()]);
+        self.codec.encode(&mut payloads)?;
+
+        for shard in shards.iter_mut() {
+            DataProcessor::write_checksum(shard);
+        }
+        #[cfg(feature = "std")]
+        self.metrics.record_encode(data_len, started.elapsed());
+        self.report_progress(data_len);
+        Ok(())
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_with_report(data)
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    pub fn decode_with_report(
+        &self,
+        data: &[u8],
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let header: ContainerHeader = ContainerHeader::read(data)?;
+        let body: &[u8] = &data[header.size()..];
+
+        // Reject impossible bodies before building a codec from an untrusted header.
+        let body_len: usize = header.body_len().ok_or(ReedSolomonError::SizeOverflow)?;
+        if body.len() != body_len {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: body_len,
+                given: body.len(),
+            });
+        }
+        if body.is_empty() {
+            let limits: DataSizeLimits = self.config.data_size_limits();
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: 0,
+                min: limits.min,
+                max: limits.max,
+            });
+        }
+
+        if header.matches(&self.config) {
+            self.decode_body(&header, body)
+        } else {
+            self.for_header(&header)?.decode_body(&header, body)
+        }
+    }
+
+    fn decode_body(
+        &self,
+        header: &ContainerHeader,
+        body: &[u8],
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let shards: Vec<Option<&[u8]>> = body
+            .chunks_exact(header.shard_size as usize)
+            .map(Some)
+            .collect();
+
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            self.decode_stored(&shards, PayloadLen::Exact(header.payload_len))?;
+        if decoded.len() as u64 != header.payload_len {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: header.payload_len as usize,
+                given: decoded.len(),
+            });
+        }
+
+        let decoded: Vec<u8> = self.decrypt_payload(header, decoded)?;
+        let decoded: Vec<u8> = match header.compression {
+            Compression::None => decoded,
+            #[cfg(feature = "compression")]
+            compression => {
+                compression::decompress(compression, &decoded, &self.config.data_size_limits())?
+            }
+            #[cfg(not(feature = "compression"))]
+            compression => return Err(ReedSolomonError::CompressionUnavailable(compression)),
+        };
+        self.config.data_size_limits().check(decoded.len())?;
+
+        if header
+            .digest
+            .is_some_and(|digest: [u8; DIGEST_SIZE]| digest != blake3_digest(&decoded))
+        {
+            return Err(ReedSolomonError::DigestMismatch);
+        }
+        Ok((decoded, report))
+    }
+
+    #[cfg(feature = "encryption")]
+    fn decrypt_payload(
+        &self,
+        header: &ContainerHeader,
+        payload: Vec<u8>,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        match (header.encryption, &self.key) {
+            (Encryption::None, None) => Ok(payload),
+            (Encryption::None, Some(_)) => Err(ReedSolomonError::NotEncrypted),
+            (_, None) => Err(ReedSolomonError::MissingKey),
+            (_, Some(key)) => encryption::decrypt(key, header, &payload),
+        }
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn decrypt_payload(
+        &self,
+        header: &ContainerHeader,
+        payload: Vec<u8>,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        match header.encryption {
+            Encryption::None => Ok(payload),
+            encryption => Err(ReedSolomonError::EncryptionUnavailable(encryption)),
+        }
+    }
+
+    pub fn decode_shards(&self, shards: Vec<Shard>) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_shards_with_report(shards)
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    pub fn decode_shards_with_report(
+        &self,
+        shards: Vec<Shard>,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let mut positional: Vec<Option<Vec<u8>>> = vec![None; self.config.total_shards()];
+
+        for shard in shards {
+            let index: usize = shard.index as usize;
+            if index >= positional.len() {
+                return Err(ReedSolomonError::ShardIndexOutOfRange {
+                    index,
+                    total: positional.len(),
+                });
+            }
+
+            if shard.kind != ShardKind::for_index(index, self.stored_data_shards()) {
+                return Err(ReedSolomonError::ShardKindMismatch {
+                    index,
+                    kind: shard.kind,
+                });
+            }
+
+            if positional[index].is_some() {
+                return Err(ReedSolomonError::DuplicateShard { index });
+            }
+            positional[index] = Some(shard.payload);
+        }
+
+        self.decode_positional_with_report(positional)
+    }
+
+    /// Decodes `shards` with the shards at `rejected` left out, reporting
+    /// those as corrupted rather than missing.
+    pub(crate) fn decode_shards_rejecting(
+        &self,
+        shards: Vec<Shard>,
+        rejected: Vec<usize>,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let (decoded, mut report): (Vec<u8>, DecodeReport) =
+            self.decode_shards_with_report(shards)?;
+        report
+            .missing
+            .retain(|index: &usize| !rejected.contains(index));
+        report.corrupted.extend(rejected);
+        report.corrupted.sort_unstable();
+        Ok((decoded, report))
+    }
+
+    pub fn decode_positional(
+        &self,
+        shards: Vec<Option<Vec<u8>>>,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_positional_with_report(shards)
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    pub fn decode_positional_with_report(
+        &self,
+        shards: Vec<Option<Vec<u8>>>,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let decoded: Result<(Vec<u8>, DecodeReport), ReedSolomonError> =
+            self.decode_stored(&shards, PayloadLen::Prefixed(self.prefix_size));
+        for shard in shards.into_iter().flatten() {
+            self.recycle(shard);
+        }
+        decoded
+    }
+
+    /// Decodes from the `data_shards` intact shards with the lowest `cost`,
+    /// ties going to the lower index, instead of the first intact
+    /// positions. Useful when shards cost differently to read, for example
+    /// when parity is on a slow remote host or data shards are on a busy
+    /// disk.
+    pub fn decode_positional_by_cost(
+        &self,
+        shards: Vec<Option<Vec<u8>>>,
+        cost: impl Fn(usize) -> u64,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_positional_by_cost_with_report(shards, cost)
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    /// Like `decode_positional_by_cost`. Intact shards left out of the
+    /// chosen subset are reported as missing.
+    pub fn decode_positional_by_cost_with_report(
+        &self,
+        mut shards: Vec<Option<Vec<u8>>>,
+        cost: impl Fn(usize) -> u64,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let mut intact: Vec<usize> = (0..shards.len())
+            .filter(|index: &usize| {
+                shards[*index]
+                    .as_deref()
+                    .and_then(DataProcessor::verify_checksum)
+                    .is_some()
+            })
+            .collect();
+        intact.sort_by_key(|index: &usize| (cost(*index), *index));
+
+        for index in intact.into_iter().skip(self.config.data_shards()) {
+            if let Some(shard) = shards[index].take() {
+                self.recycle(shard);
+            }
+        }
+        self.decode_positional_with_report(shards)
+    }
+
+    /// Decodes stored shards, checksums included, without taking ownership.
+    /// Intact data shards are read in place; only a reconstruction copies
+    /// the surviving shards into scratch buffers.
+    fn decode_stored<S: AsRef<[u8]>>(
+        &self,
+        shards: &[Option<S>],
+        payload_len: PayloadLen,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        #[cfg(feature = "tracing")]
+        let _span: tracing::span::EnteredSpan = tracing::debug_span!(
+            "decode",
+            data_shards = self.config.data_shards(),
+            parity_shards = self.config.parity_shards(),
+            shards_given = shards.iter().flatten().count(),
+        )
+        .entered();
+        #[cfg(feature = "std")]
+        let started: Instant = Instant::now();
+
+        if shards.len() != self.config.total_shards() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.config.total_shards(),
+                given: shards.len(),
+            });
+        }
+
+        let mut report: DecodeReport = DecodeReport::default();
+        let mut payloads: Vec<Option<&[u8]>> = Vec::with_capacity(shards.len());
+        for (index, slot) in shards.iter().enumerate() {
+            let Some(shard) = slot else {
+                report.missing.push(index);
+                payloads.push(None);
+                continue;
+            };
+
+            let payload: Option<&[u8]> = DataProcessor::verify_checksum(shard.as_ref());
+            if payload.is_none() {
+                report.corrupted.push(index);
+            }
+            payloads.push(payload);
+        }
+
+        report.reconstructed = payloads
+            .iter()
+            .take(self.stored_data_shards())
+            .enumerate()
+            .filter(|(_, slot): &(usize, &Option<&[u8]>)| slot.is_none())
+            .map(|(index, _): (usize, &Option<&[u8]>)| index)
+            .collect();
+        report.parity_used =
+            !report.reconstructed.is_empty() || self.config.layout() == ShardLayout::NonSystematic;
+        #[cfg(feature = "tracing")]
+        if !report.is_clean() {
+            tracing::debug!(
+                missing = report.missing.len(),
+                corrupted = report.corrupted.len(),
+                reconstructed = report.reconstructed.len(),
+                "decoding around lost shards"
+            );
+        }
+
+        let decoded: Result<Vec<u8>, ReedSolomonError> = if report.parity_used {
+            self.reconstruct_and_extract(&payloads, payload_len)
+        } else {
+            self.extract_payload(&payloads, payload_len)
+        };
+
+        if let Ok(decoded) = &decoded {
+            #[cfg(feature = "std")]
+            self.metrics.record_decode(decoded.len(), started.elapsed());
+            self.report_progress(decoded.len());
+        }
+        decoded.map(|decoded: Vec<u8>| (decoded, report))
+    }
+
+    fn reconstruct_and_extract(
+        &self,
+        payloads: &[Option<&[u8]>],
+        payload_len: PayloadLen,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut shards: Vec<Option<Vec<u8>>> = payloads
+            .iter()
+            .map(|payload: &Option<&[u8]>| {
+                payload.map(|payload: &[u8]| {
+                    let mut shard: Vec<u8> = self.take_buffer(payload.len());
+                    shard.copy_from_slice(payload);
+                    shard
+                })
+            })
+            .collect();
+
+        let extracted: Result<Vec<u8>, ReedSolomonError> =
+            self.reconstruct_shards(&mut shards, true).and_then(|()| {
+                let data: Vec<Option<&[u8]>> = shards.iter().map(Option::as_deref).collect();
+                self.extract_payload(&data, payload_len)
+            });
+
+        for shard in shards.into_iter().flatten() {
+            self.recycle(shard);
+        }
+        extracted
+    }
+
+    fn extract_payload(
+        &self,
+        shards: &[Option<&[u8]>],
+        payload_len: PayloadLen,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        let data: Vec<&[u8]> = shards
+            .iter()
+            .take(self.config.data_shards())
+            .enumerate()
+            .map(|(index, shard): (usize, &Option<&[u8]>)| {
+                shard.ok_or(ReedSolomonError::MissingDataShard { index })
+            })
+            .collect::<Result<_, _>>()?;
+        match payload_len {
+            PayloadLen::Prefixed(prefix_size) => {
+                DataProcessor::extract_from_shards(&data, prefix_size)
+            }
+            PayloadLen::Exact(len) => DataProcessor::extract_exact(&data, len),
+        }
+    }
+
+    pub fn verify(&self, shards: &[Vec<u8>]) -> Result<bool, ReedSolomonError> {
+        #[cfg(feature = "tracing")]
+        let _span: tracing::span::EnteredSpan = tracing::debug_span!(
+            "verify",
+            shards = shards.len(),
+            shard_len = shards.first().map_or(0, Vec::len),
+        )
+        .entered();
+
+        if shards.len() != self.config.total_shards() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.config.total_shards(),
+                given: shards.len(),
+            });
+        }
+
+        let mut payloads: Vec<&[u8]> = Vec::with_capacity(shards.len());
+        for shard in shards {
+            match DataProcessor::verify_checksum(shard) {
+                Some(payload) => payloads.push(payload),
+                None => return Ok(false),
+            }
+        }
+
+        if payloads
+            .iter()
+            .any(|payload: &&[u8]| payload.len() != payloads[0].len())
+        {
+            return Ok(false);
+        }
+
+        self.codec.verify(&payloads)
+    }
+
+    pub fn reconstruct(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), ReedSolomonError> {
+        self.reconstruct_shards(shards, false)
+    }
+
+    pub fn reconstruct_data(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), ReedSolomonError> {
+        self.reconstruct_shards(shards, self.config.layout() == ShardLayout::Systematic)
+    }
+
+    fn reconstruct_shards(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        #[cfg(feature = "tracing")]
+        let _span: tracing::span::EnteredSpan = tracing::debug_span!(
+            "reconstruct",
+            shards = shards.len(),
+            missing = shards
+                .iter()
+                .filter(|slot: &&Option<Vec<u8>>| slot.is_none())
+                .count(),
+            data_only = data_only,
+        )
+        .entered();
+
+        if shards.len() != self.config.total_shards() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.config.total_shards(),
+                given: shards.len(),
+            });
+        }
+
+        #[cfg(feature = "std")]
+        let rebuilt: usize = shards
+            .iter()
+            .take(if data_only {
+                self.stored_data_shards()
+            } else {
+                shards.len()
+            })
+            .filter(|slot: &&Option<Vec<u8>>| slot.is_none())
+            .count();
+
+        self.codec.reconstruct(shards, data_only)?;
+
+        #[cfg(feature = "std")]
+        if rebuilt > 0 {
+            self.metrics.record_reconstruction(rebuilt);
+        }
+        Ok(())
+    }
+
+    /// Recomputes every parity shard from the stored data shards, returning
+    /// them checksummed as `encode_shards` would. Only the systematic layout
+    /// stores data shards, so the non-systematic layout is rejected with
+    /// `UnsupportedLayout`.
+    pub fn regenerate_parity(
+        &self,
+        data_shards: &[Vec<u8>],
+    ) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        #[cfg(feature = "tracing")]
+        let _span: tracing::span::EnteredSpan = tracing::debug_span!(
+            "regenerate_parity",
+            data_shards = data_shards.len(),
+            parity_shards = self.config.parity_shards(),
+        )
+        .entered();
+
+        if self.config.layout() == ShardLayout::NonSystematic {
+            return Err(ReedSolomonError::UnsupportedLayout(
+                ShardLayout::NonSystematic,
+            ));
+        }
+        if data_shards.len() != self.config.data_shards() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.config.data_shards(),
+                given: data_shards.len(),
+            });
+        }
+
+        let mut data: Vec<Vec<u8>> = Vec::with_capacity(data_shards.len());
+        for (index, shard) in data_shards.iter().enumerate() {
+            let payload: &[u8] = DataProcessor::verify_checksum(shard)
+                .ok_or(ReedSolomonError::MissingDataShard { index })?;
+            if let Some(first) = data.first() {
+                if payload.len() != first.len() {
+                    return Err(ReedSolomonError::ShardSizeMismatch {
+                        index,
+                        given: shard.len(),
+                        expected: first.len() + CHECKSUM_SIZE,
+                    });
+                }
+            }
+            data.push(payload.to_vec());
+        }
+
+        let shard_len: usize = data[0].len() + CHECKSUM_SIZE;
+        let mut parity: Vec<Vec<u8>> = vec![vec![0u8; shard_len]; self.config.parity_shards()];
+        let mut payloads: Vec<&mut [u8]> = data
+            .iter_mut()
+            .map(Vec::as_mut_slice)
+            .chain(
+                parity
+                    .iter_mut()
+                    .map(|shard: &mut Vec<u8>| &mut shard[..shard_len - CHECKSUM_SIZE]),
+            )
+            .collect();
+        self.codec.encode(&mut payloads)?;
+
+        for shard in parity.iter_mut() {
+            DataProcessor::write_checksum(shard);
+        }
+        #[cfg(feature = "std")]
+        self.metrics.record_reconstruction(parity.len());
+        Ok(parity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GaloisField, ShardLimits, SHARD_LIMITS};
+    use crate::polynomial::{Gf8Field, DEFAULT_POLYNOMIAL};
+
+    #[test]
+    fn test_encode_decode_roundtrip() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?;
+        let original_data = b"Hello, World!".to_vec();
+
+        let encoded: Vec<u8> = codec.encode(&original_data)?;
+        let decoded: Vec<u8> = codec.decode(&encoded)?;
+
+        assert_eq!(original_data, decoded);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_into_reuses_buffer() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let mut buffer: Vec<u8> = vec![0xee; 256];
+
+        for payload in [
+            &b"first payload"[..],
+            &b"a somewhat longer second payload"[..],
+        ] {
+            let written: usize = codec.encode_into(payload, &mut buffer)?;
+
+            assert_eq!(written, codec.encoded_len(payload.len()));
+            assert_eq!(buffer[..written], codec.encode(payload)?);
+            assert_eq!(codec.decode(&buffer[..written])?, payload);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_into_rejects_short_buffer() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let mut buffer: Vec<u8> = vec![0u8; codec.encoded_len(13) - 1];
+
+        assert!(codec.encode_into(b"Hello, World!", &mut buffer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_shards_into_matches_encode_shards() -> Result<(), ReedSolomonError> {
+        for field in [GaloisField::Gf8, GaloisField::Gf16] {
+            let config: EncoderConfig = EncoderConfig::with_field(3, 2, field)?;
+            let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+            let data: &[u8] = b"caller-owned shard buffers";
+
+            let shard_len: usize = codec.shard_len(data.len());
+            let mut buffer: Vec<u8> = vec![0u8; shard_len * 5];
+            let mut shards: Vec<&mut [u8]> = buffer.chunks_exact_mut(shard_len).collect();
+            assert_eq!(codec.encode_shards_into(data, &mut shards)?, shard_len);
+
+            let expected: Vec<Vec<u8>> = codec
+                .encode_shards(data)?
+                .into_iter()
+                .map(|shard: Shard| shard.payload)
+                .collect();
+            assert_eq!(buffer, expected.concat());
+
+            let mut short: Vec<u8> = vec![0u8; shard_len * 5 - 1];
+            let mut uneven: Vec<&mut [u8]> = short.chunks_mut(shard_len).collect();
+            assert!(codec.encode_shards_into(data, &mut uneven).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_verified_returns_checked_shards() -> Result<(), ReedSolomonError> {
+        let systematic: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let non_systematic: ReedSolomonCodec = ReedSolomonCodec::with_config(
+            EncoderConfig::new(4, 2)?.with_layout(ShardLayout::NonSystematic)?,
+        )?;
+
+        for codec in [&systematic, &non_systematic] {
+            for len in 1..=32usize {
+                let original_data: Vec<u8> = (0..len).map(|i: usize| (i * 7) as u8).collect();
+                let shards: Vec<Shard> =
+                    codec.encode_verified(&original_data, EncodeCheck::TrialDecode)?;
+                assert_eq!(shards, codec.encode_shards(&original_data)?);
+                assert_eq!(codec.decode_shards(shards)?, original_data);
+            }
+            assert!(codec.encode_verified(b"", EncodeCheck::Parity).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_iter_matches_encode_shards() -> Result<(), ReedSolomonError> {
+        let systematic: EncoderConfig = EncoderConfig::new(5, 3)?;
+        let non_systematic: EncoderConfig = systematic.with_layout(ShardLayout::NonSystematic)?;
+        for config in [systematic, non_systematic] {
+            let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+            let data: Vec<u8> = (0..1000u32).map(|i: u32| (i % 241) as u8).collect();
+
+            let mut shards = codec.encode_iter(&data)?;
+            let first: Shard = shards.next().unwrap();
+            assert_eq!(first.index, 0);
+
+            let mut streamed: Vec<Shard> = vec![first];
+            streamed.extend(shards);
+            assert_eq!(streamed, codec.encode_shards(&data)?);
+
+            streamed.remove(1);
+            assert_eq!(codec.decode_shards(streamed)?, data);
+        }
+        assert!(ReedSolomonCodec::new(4, 2)?.encode_iter(b"").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_repeated_decodes_hit_pool() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let encoded: Vec<u8> = codec.encode(b"decode me again and again")?;
+        let mut damaged: Vec<u8> = encoded.clone();
+        damaged[HEADER_SIZE] ^= 0xff;
+
+        codec.decode(&damaged)?;
+        let warm: PoolStats = codec.pool_stats();
+        codec.decode(&damaged)?;
+        let after: PoolStats = codec.pool_stats();
+
+        assert_eq!(after.misses, warm.misses);
+        assert!(after.hits > warm.hits);
+
+        codec.decode(&encoded)?;
+        assert_eq!(codec.pool_stats(), after);
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_shared_pool_across_codecs() -> Result<(), ReedSolomonError> {
+        let pool: Arc<BufferPool> = Arc::new(BufferPool::new(16));
+        let encoder: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?.with_pool(Arc::clone(&pool));
+        let decoder: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?.with_pool(Arc::clone(&pool));
+
+        let mut encoded: Vec<u8> = encoder.encode(b"pooled across codecs")?;
+        encoded[HEADER_SIZE] ^= 0xff;
+        assert_eq!(decoder.decode(&encoded)?, b"pooled across codecs");
+
+        assert!(pool.stats().pooled > 0);
+        assert_eq!(encoder.pool_stats(), decoder.pool_stats());
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_metrics_track_encodes_decodes_and_reconstructions() -> Result<(), ReedSolomonError> {
+        let metrics: Arc<Metrics> = Arc::new(Metrics::new());
+        let encoder: ReedSolomonCodec =
+            ReedSolomonCodec::new(4, 2)?.with_metrics(Arc::clone(&metrics));
+        let decoder: ReedSolomonCodec =
+            ReedSolomonCodec::new(10, 4)?.with_metrics(Arc::clone(&metrics));
+        let original_data: Vec<u8> = vec![0x5a; 4096];
+
+        let mut encoded: Vec<u8> = encoder.encode(&original_data)?;
+        assert_eq!(decoder.decode(&encoded)?, original_data);
+
+        let shard_size: usize = (encoded.len() - HEADER_SIZE) / 6;
+        encoded[HEADER_SIZE + shard_size] ^= 0xff;
+        assert_eq!(decoder.decode(&encoded)?, original_data);
+
+        let snapshot: MetricsSnapshot = encoder.metrics();
+        assert_eq!(snapshot, decoder.metrics());
+        assert_eq!(snapshot.encodes, 1);
+        assert_eq!(snapshot.decodes, 2);
+        assert_eq!(snapshot.bytes_encoded, 4096);
+        assert_eq!(snapshot.bytes_decoded, 8192);
+        assert_eq!(snapshot.reconstructions, 1);
+        assert_eq!(snapshot.shards_reconstructed, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_digest_detects_silent_corruption() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let original_data: &[u8] = b"digest guards against wrong bytes";
+        let encoded: Vec<u8> = codec.encode(original_data)?;
+        let header: ContainerHeader = ContainerHeader::read(&encoded)?;
+        assert_eq!(header.digest, Some(blake3_digest(original_data)));
+
+        let mut corrupted: Vec<u8> = encoded.clone();
+        let shard: &mut [u8] =
+            &mut corrupted[HEADER_SIZE..HEADER_SIZE + header.shard_size as usize];
+        shard[5] ^= 0xff;
+        DataProcessor::write_checksum(shard);
+        assert!(codec.decode(&corrupted).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_aligned_shards_are_padded() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_shard_alignment(512)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..3000u32).map(|i: u32| (i % 251) as u8).collect();
+
+        assert_eq!(codec.shard_len(original_data.len()), 1024);
+        let encoded: Vec<u8> = codec.encode(&original_data)?;
+        let header: ContainerHeader = ContainerHeader::read(&encoded)?;
+        assert_eq!(header.shard_size, 1024);
+        assert_eq!(header.padding(), Some((1024 - 4) * 4 - 3000));
+
+        let mut damaged: Vec<u8> = encoded.clone();
+        damaged[HEADER_SIZE + 1024 + 7] ^= 0xff;
+        assert_eq!(
+            ReedSolomonCodec::new(4, 2)?.decode(&damaged)?,
+            original_data
+        );
+
+        let shards: Vec<Shard> = codec.encode_shards(&original_data)?;
+        assert!(shards
+            .iter()
+            .all(|shard: &Shard| shard.payload.len() == 1024));
+        assert_eq!(codec.decode_shards(shards)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_container_records_exact_payload_len() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let original_data: &[u8] = b"no length prefix in the shards";
+        let encoded: Vec<u8> = codec.encode(original_data)?;
+
+        let header: ContainerHeader = ContainerHeader::read(&encoded)?;
+        assert_eq!(header.payload_len, original_data.len() as u64);
+        assert_eq!(header.padding(), Some(2));
+        assert_eq!(&encoded[HEADER_SIZE..HEADER_SIZE + 8], &original_data[..8]);
+
+        let mut damaged: Vec<u8> = encoded.clone();
+        damaged[HEADER_SIZE] ^= 0xff;
+        assert_eq!(codec.decode(&damaged)?, original_data);
+
+        let mut overstated: ContainerHeader = header;
+        overstated.payload_len = 100;
+        overstated.digest = None;
+        let mut forged: Vec<u8> = overstated.to_bytes();
+        forged.extend_from_slice(&encoded[HEADER_SIZE..]);
+        assert!(matches!(
+            codec.decode(&forged),
+            Err(ReedSolomonError::Truncated { needed: 100, .. })
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_encode_vectored_matches_encode() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let head: Vec<u8> = (0..100u8).collect();
+        let tail: Vec<u8> = (0..777u32).map(|i: u32| (i % 251) as u8).collect();
+        let bufs: [IoSlice<'_>; 3] = [IoSlice::new(&head), IoSlice::new(&[]), IoSlice::new(&tail)];
+
+        let encoded: Vec<u8> = codec.encode_vectored(&bufs)?;
+        let joined: Vec<u8> = [head, tail].concat();
+        assert_eq!(encoded, codec.encode(&joined)?);
+        assert_eq!(codec.decode(&encoded)?, joined);
+        assert!(codec.encode_vectored(&[IoSlice::new(&[])]).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted_container_roundtrip() -> Result<(), ReedSolomonError> {
+        let key: EncryptionKey = EncryptionKey::generate();
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?.with_encryption_key(key.clone());
+        let original_data: &[u8] = b"stored on an untrusted host";
+
+        let mut encoded: Vec<u8> = codec.encode(original_data)?;
+        assert_eq!(encoded.len(), codec.encoded_len(original_data.len()));
+        assert_eq!(
+            ContainerHeader::read(&encoded)?.encryption,
+            Encryption::XChaCha20Poly1305
+        );
+
+        let decoder: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?.with_encryption_key(key);
+        assert_eq!(decoder.decode(&encoded)?, original_data);
+        assert!(ReedSolomonCodec::new(4, 2)?.decode(&encoded).is_err());
+        assert!(codec
+            .decode(&ReedSolomonCodec::new(4, 2)?.encode(original_data)?)
+            .is_err());
+
+        let other: ReedSolomonCodec =
+            ReedSolomonCodec::new(4, 2)?.with_encryption_key(EncryptionKey::new([9u8; 32]));
+        assert!(other.decode(&encoded).is_err());
+
+        let shard_size: usize = ContainerHeader::read(&encoded)?.shard_size as usize;
+        encoded[HEADER_SIZE] ^= 0xff;
+        encoded[HEADER_SIZE + shard_size + 1] ^= 0xff;
+        assert_eq!(codec.decode(&encoded)?, original_data);
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_container_roundtrip() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig =
+            EncoderConfig::new(4, 2)?.with_compression(Compression::Zstd)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let decoder: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+
+        let compressible: Vec<u8> = (0..4000u32).map(|i: u32| (i / 100) as u8).collect();
+        let encoded: Vec<u8> = codec.encode(&compressible)?;
+        assert_eq!(
+            ContainerHeader::read(&encoded)?.compression,
+            Compression::Zstd
+        );
+        assert!(encoded.len() < decoder.encode(&compressible)?.len());
+        assert_eq!(decoder.decode(&encoded)?, compressible);
+
+        let mut state: u32 = 7;
+        let noise: Vec<u8> = (0..4000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let encoded: Vec<u8> = codec.encode(&noise)?;
+        assert_eq!(
+            ContainerHeader::read(&encoded)?.compression,
+            Compression::None
+        );
+        assert_eq!(decoder.decode(&encoded)?, noise);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconstruct_missing_shards() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let original_data: Vec<u8> = b"The quick brown fox jumps over the lazy dog".to_vec();
+
+        let encoded: Vec<u8> = codec.encode(&original_data)?;
+        let mut shards: Vec<Option<Vec<u8>>> =
+            DataProcessor::validate_and_split_shares(&encoded[HEADER_SIZE..], 6)?
+                .into_iter()
+                .map(|shard: &[u8]| DataProcessor::verify_checksum(shard).map(<[u8]>::to_vec))
+                .collect();
+        let expected: Vec<Option<Vec<u8>>> = shards.clone();
+
+        shards[0] = None;
+        shards[4] = None;
+        codec.reconstruct(&mut shards)?;
+
+        assert_eq!(shards, expected);
+        let joined: Vec<u8> = DataProcessor::join_data_shards(&shards, 4)?;
+        assert_eq!(joined[..original_data.len()], original_data[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconstruct_data_leaves_parity_missing() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let mut shards: Vec<Option<Vec<u8>>> = codec
+            .encode_shards(b"only the data matters here")?
+            .iter()
+            .map(|shard: &Shard| DataProcessor::verify_checksum(&shard.payload).map(<[u8]>::to_vec))
+            .collect();
+        let expected: Vec<Option<Vec<u8>>> = shards.clone();
+
+        shards[1] = None;
+        shards[5] = None;
+        codec.reconstruct_data(&mut shards)?;
+
+        assert_eq!(shards[..4], expected[..4]);
+        assert_eq!(shards[5], None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_positional_by_cost() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let original_data: &[u8] = b"read the cheap shards, skip the dear ones";
+        let shards: Vec<Option<Vec<u8>>> = codec
+            .encode_shards(original_data)?
+            .into_iter()
+            .map(|shard: Shard| Some(shard.payload))
+            .collect();
+
+        let (decoded, report): (Vec<u8>, DecodeReport) = codec
+            .decode_positional_by_cost_with_report(shards.clone(), |index: usize| {
+                if index == 1 || index == 2 {
+                    10
+                } else {
+                    1
+                }
+            })?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.missing, vec![1, 2]);
+        assert_eq!(report.reconstructed, vec![1, 2]);
+
+        let mut damaged: Vec<Option<Vec<u8>>> = shards;
+        if let Some(shard) = damaged[4].as_mut() {
+            shard[0] ^= 0xff;
+        }
+        let (decoded, report): (Vec<u8>, DecodeReport) = codec
+            .decode_positional_by_cost_with_report(damaged, |index: usize| {
+                u64::from(index == 0) * 10
+            })?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.missing, vec![0]);
+        assert_eq!(report.corrupted, vec![4]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_regenerate_parity() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let shards: Vec<Vec<u8>> = codec
+            .encode_shards(b"parity can be rebuilt from data alone")?
+            .into_iter()
+            .map(|shard: Shard| shard.payload)
+            .collect();
+
+        let parity: Vec<Vec<u8>> = codec.regenerate_parity(&shards[..4])?;
+        assert_eq!(parity, shards[4..]);
+
+        let mut corrupted: Vec<Vec<u8>> = shards[..4].to_vec();
+        corrupted[2][0] ^= 0xff;
+        assert!(matches!(
+            codec.regenerate_parity(&corrupted),
+            Err(ReedSolomonError::MissingDataShard { index: 2 })
+        ));
+        assert!(matches!(
+            codec.regenerate_parity(&shards[..3]),
+            Err(ReedSolomonError::ShardCountMismatch {
+                expected: 4,
+                given: 3
+            })
+        ));
+
+        let non_systematic: ReedSolomonCodec = ReedSolomonCodec::with_config(
+            EncoderConfig::new(4, 2)?.with_layout(ShardLayout::NonSystematic)?,
+        )?;
+        assert!(matches!(
+            non_systematic.regenerate_parity(&shards[..4]),
+            Err(ReedSolomonError::UnsupportedLayout(
+                ShardLayout::NonSystematic
+            ))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconstruct_too_many_missing() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let encoded: Vec<u8> = codec.encode(b"Hello, World!")?;
+        let mut shards: Vec<Option<Vec<u8>>> =
+            DataProcessor::validate_and_split_shares(&encoded[HEADER_SIZE..], 6)?
+                .into_iter()
+                .map(|shard: &[u8]| Some(shard.to_vec()))
+                .collect();
+
+        shards[0] = None;
+        shards[1] = None;
+        shards[5] = None;
+
+        assert!(codec.reconstruct(&mut shards).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_positional_with_missing_positions() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: Vec<u8> = b"shards may go missing".to_vec();
+
+        let mut shards: Vec<Option<Vec<u8>>> = codec
+            .encode_shards(&original_data)?
+            .into_iter()
+            .map(|shard: Shard| Some(shard.payload))
+            .collect();
+        shards[1] = None;
+        shards[3] = None;
+
+        assert_eq!(codec.decode_positional(shards)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_shards_in_any_order() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: Vec<u8> = b"shards know where they belong".to_vec();
+
+        let mut shards: Vec<Shard> = codec.encode_shards(&original_data)?;
+        assert_eq!(
+            shards
+                .iter()
+                .filter(|shard: &&Shard| shard.is_data())
+                .count(),
+            3
+        );
+
+        shards.remove(0);
+        shards.reverse();
+
+        assert_eq!(codec.decode_shards(shards)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_shards_rejects_inconsistent_shards() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let shards: Vec<Shard> = codec.encode_shards(b"Hello, World!")?;
+
+        let mut duplicated: Vec<Shard> = shards.clone();
+        duplicated.push(shards[0].clone());
+        assert!(codec.decode_shards(duplicated).is_err());
+
+        let mut out_of_range: Vec<Shard> = shards.clone();
+        out_of_range[4].index = 5;
+        assert!(codec.decode_shards(out_of_range).is_err());
+
+        let mut mislabelled: Vec<Shard> = shards;
+        mislabelled[0].kind = ShardKind::Parity;
+        assert!(codec.decode_shards(mislabelled).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_treats_corrupt_shards_as_erasures() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let original_data: Vec<u8> = b"bit rot happens to the best of us".to_vec();
+
+        let mut encoded: Vec<u8> = codec.encode(&original_data)?;
+        let shard_size: usize = (encoded.len() - HEADER_SIZE) / 6;
+        encoded[HEADER_SIZE] ^= 0xff;
+        encoded[HEADER_SIZE + 3 * shard_size + 1] ^= 0xff;
+
+        assert_eq!(codec.decode(&encoded)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_report_describes_damage() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 3)?;
+        let original_data: Vec<u8> = b"how close did we come to losing it".to_vec();
+
+        let mut encoded: Vec<u8> = codec.encode(&original_data)?;
+        let (decoded, report): (Vec<u8>, DecodeReport) = codec.decode_with_report(&encoded)?;
+        assert_eq!(decoded, original_data);
+        assert!(report.is_clean());
+        assert!(!report.parity_used);
+
+        let shard_size: usize = (encoded.len() - HEADER_SIZE) / 7;
+        encoded[HEADER_SIZE + 2 * shard_size] ^= 0xff;
+        encoded[HEADER_SIZE + 5 * shard_size] ^= 0xff;
+        let (decoded, report): (Vec<u8>, DecodeReport) = codec.decode_with_report(&encoded)?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.corrupted, vec![2, 5]);
+        assert_eq!(report.reconstructed, vec![2]);
+        assert!(report.parity_used);
+        assert_eq!(report.margin(3), 1);
+
+        let mut shards: Vec<Shard> = codec.encode_shards(&original_data)?;
+        shards.retain(|shard: &Shard| shard.index != 0 && shard.index != 6);
+        let (decoded, report): (Vec<u8>, DecodeReport) = codec.decode_shards_with_report(shards)?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.missing, vec![0, 6]);
+        assert!(report.corrupted.is_empty());
+        assert_eq!(report.reconstructed, vec![0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_fails_when_too_many_shards_corrupt() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let mut encoded: Vec<u8> = codec.encode(b"Hello, World!")?;
+        let shard_size: usize = (encoded.len() - HEADER_SIZE) / 6;
+
+        for i in 0..3 {
+            encoded[HEADER_SIZE + i * shard_size] ^= 0xff;
+        }
+
+        assert!(codec.decode(&encoded).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_uses_header_config() -> Result<(), ReedSolomonError> {
+        let encoder: ReedSolomonCodec = ReedSolomonCodec::new(6, 3)?;
+        let decoder: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?;
+        let original_data: Vec<u8> = b"configuration travels with the data".to_vec();
+
+        let encoded: Vec<u8> = encoder.encode(&original_data)?;
+
+        assert_eq!(decoder.decode(&encoded)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_body() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let encoded: Vec<u8> = codec.encode(b"Hello, World!")?;
+
+        assert!(codec.decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(codec.decode(&encoded[..HEADER_SIZE - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rejects_forged_header_before_building_codec() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let mut header: ContainerHeader = ContainerHeader::new(codec.config(), 1, 1);
+        header.field = GaloisField::Gf16;
+        header.data_shards = 30000;
+        header.parity_shards = 30000;
+
+        assert!(matches!(
+            codec.decode(&header.to_bytes()),
+            Err(ReedSolomonError::LengthMismatch {
+                expected: 60000,
+                given: 0
+            })
+        ));
+
+        header.shard_size = 0;
+        assert!(matches!(
+            codec.decode(&header.to_bytes()),
+            Err(ReedSolomonError::InvalidDataSize { given: 0, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_encoding_matrix_reproduces_shards() -> Result<(), ReedSolomonError> {
+        let original_data: Vec<u8> = b"the matrix alone explains every stored shard".to_vec();
+        for polynomial in [DEFAULT_POLYNOMIAL, 0x187] {
+            let field: Gf8Field = Gf8Field::new(polynomial)?;
+            let systematic: EncoderConfig =
+                EncoderConfig::new(4, 3)?.with_polynomial(polynomial)?;
+            let data: Vec<Vec<u8>> = ReedSolomonCodec::with_config(systematic)?
+                .encode_shards(&original_data)?[..4]
+                .iter()
+                .filter_map(|shard: &Shard| {
+                    DataProcessor::verify_checksum(&shard.payload).map(<[u8]>::to_vec)
+                })
+                .collect();
+
+            for layout in [ShardLayout::Systematic, ShardLayout::NonSystematic] {
+                let config: EncoderConfig = systematic.with_layout(layout)?;
+                let matrix: Vec<Vec<u8>> = config
+                    .encoding_matrix()
+                    .ok_or(ReedSolomonError::SingularMatrix)?;
+                let shards: Vec<Shard> =
+                    ReedSolomonCodec::with_config(config)?.encode_shards(&original_data)?;
+                assert_eq!(matrix.len(), shards.len());
+
+                for (row, shard) in matrix.iter().zip(&shards) {
+                    let expected: Vec<u8> = (0..data[0].len())
+                        .map(|byte: usize| {
+                            row.iter().zip(&data).fold(
+                                0u8,
+                                |sum: u8, (coefficient, shard): (&u8, &Vec<u8>)| {
+                                    sum ^ field.mul(*coefficient, shard[byte])
+                                },
+                            )
+                        })
+                        .collect();
+                    assert_eq!(
+                        DataProcessor::verify_checksum(&shard.payload),
+                        Some(&expected[..])
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_systematic_shards_hide_the_data() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig =
+            EncoderConfig::new(4, 2)?.with_layout(ShardLayout::NonSystematic)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = b"nobody holding one shard can read this".to_vec();
+
+        let shards: Vec<Shard> = codec.encode_shards(&original_data)?;
+        assert!(shards.iter().all(Shard::is_parity));
+        let systematic: Vec<Shard> = ReedSolomonCodec::new(4, 2)?.encode_shards(&original_data)?;
+        for shard in &shards {
+            assert!(systematic[..4]
+                .iter()
+                .all(|data: &Shard| data.payload != shard.payload));
+        }
+
+        let stored: Vec<Vec<u8>> = shards
+            .iter()
+            .map(|shard: &Shard| shard.payload.clone())
+            .collect();
+        assert!(codec.verify(&stored)?);
+
+        let payloads: Vec<Vec<u8>> = stored
+            .iter()
+            .filter_map(|shard: &Vec<u8>| DataProcessor::verify_checksum(shard).map(<[u8]>::to_vec))
+            .collect();
+        let mut rebuilt: Vec<Option<Vec<u8>>> = payloads.iter().cloned().map(Some).collect();
+        rebuilt[0] = None;
+        rebuilt[3] = None;
+        codec.reconstruct_data(&mut rebuilt)?;
+        assert_eq!(rebuilt[0].as_ref(), Some(&payloads[0]));
+        assert_eq!(rebuilt[3].as_ref(), Some(&payloads[3]));
+
+        let mut positional: Vec<Option<Vec<u8>>> = stored.iter().cloned().map(Some).collect();
+
+        positional[1] = None;
+        positional[4] = None;
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            codec.decode_positional_with_report(positional)?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.missing, vec![1, 4]);
+        assert!(report.parity_used);
+
+        let encoded: Vec<u8> = codec.encode(&original_data)?;
+        assert_eq!(
+            ReedSolomonCodec::new(4, 2)?.decode(&encoded)?,
+            original_data
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_gf16_roundtrip_beyond_256_shards() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::with_field(300, 20, GaloisField::Gf16)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..2001u32).map(|i: u32| (i % 256) as u8).collect();
+
+        let shards: Vec<Shard> = codec
+            .encode_shards(&original_data)?
+            .into_iter()
+            .filter(|shard: &Shard| ![0, 7, 150, 299, 310].contains(&shard.index))
+            .collect();
+
+        assert_eq!(codec.decode_shards(shards)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gf16_container_carries_field() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::with_field(3, 2, GaloisField::Gf16)?;
+        let encoder: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let decoder: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+
+        let encoded: Vec<u8> = encoder.encode(b"wide field")?;
+
+        assert_eq!(ContainerHeader::read(&encoded)?.field, GaloisField::Gf16);
+        assert_eq!(decoder.decode(&encoded)?, b"wide field");
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_detects_stale_parity() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let mut shards: Vec<Vec<u8>> = codec
+            .encode_shards(b"scrub me regularly")?
+            .into_iter()
+            .map(|shard: Shard| shard.payload)
+            .collect();
+
+        assert!(codec.verify(&shards)?);
+
+        let payload_len: usize = shards[4].len() - CHECKSUM_SIZE;
+        let mut stale: Vec<u8> = shards[4][..payload_len].to_vec();
+        stale[0] ^= 0x01;
+        shards[4] = DataProcessor::append_checksum(stale);
+        assert!(!codec.verify(&shards)?);
+
+        shards[1][0] ^= 0x01;
+        assert!(!codec.verify(&shards)?);
+
+        assert!(codec.verify(&shards[..5]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_gf16() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::with_field(3, 2, GaloisField::Gf16)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let shards: Vec<Vec<u8>> = codec
+            .encode_shards(b"wide parity")?
+            .into_iter()
+            .map(|shard: Shard| shard.payload)
+            .collect();
+
+        assert!(codec.verify(&shards)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_positional_wrong_count() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let mut shards: Vec<Option<Vec<u8>>> = codec
+            .encode_shards(b"Hello, World!")?
+            .into_iter()
+            .map(|shard: Shard| Some(shard.payload))
+            .collect();
+        shards.pop();
+
+        assert!(codec.decode_positional(shards).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(ReedSolomonCodec::new(0, 1).is_err());
+        assert!(ReedSolomonCodec::new(1, SHARD_LIMITS.max + 1).is_err());
+        assert!(ReedSolomonCodec::new(SHARD_LIMITS.max / 2, SHARD_LIMITS.max / 2 + 1).is_err());
+    }
+
+    #[test]
+    fn test_codec_enforces_configured_limits() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig =
+            EncoderConfig::new(4, 2)?.with_data_size_limits(DataSizeLimits::new(1, 64))?;
+        let strict: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let relaxed: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+
+        assert!(matches!(
+            strict.encode(&[7u8; 65]),
+            Err(ReedSolomonError::InvalidDataSize { given: 65, .. })
+        ));
+        assert_eq!(strict.decode(&strict.encode(&[7u8; 64])?)?, [7u8; 64]);
+        assert!(strict.decode(&relaxed.encode(&[7u8; 65])?).is_err());
+
+        let config: EncoderConfig =
+            EncoderConfig::new(4, 2)?.with_shard_limits(ShardLimits::new(1, 8))?;
+        let strict: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let wide: Vec<u8> = ReedSolomonCodec::new(10, 4)?.encode(b"too many shards")?;
+        assert!(matches!(
+            strict.decode(&wide),
+            Err(ReedSolomonError::InvalidShardCount { given: 10, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_data_size() {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(10, 4).unwrap();
+        assert!(codec.encode(&[]).is_err());
+    }
+
+    #[cfg(all(feature = "tracing", feature = "std"))]
+    #[test]
+    fn test_tracing_spans_cover_each_stage() -> Result<(), ReedSolomonError> {
+        use std::sync::{Arc, Mutex, MutexGuard};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        /// Records the name of every span opened while it is the default.
+        struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+        impl tracing::Subscriber for SpanNames {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut names: MutexGuard<'_, Vec<&'static str>> = self.0.lock().unwrap();
+                names.push(span.metadata().name());
+                Id::from_u64(names.len() as u64)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let names: Arc<Mutex<Vec<&'static str>>> = Arc::new(Mutex::new(Vec::new()));
+        let subscriber: SpanNames = SpanNames(Arc::clone(&names));
+        tracing::subscriber::with_default(subscriber, || -> Result<(), ReedSolomonError> {
+            let shards: Vec<Shard> = codec.encode_shards(b"traced payload")?;
+            let payloads: Vec<Vec<u8>> = shards
+                .iter()
+                .map(|shard: &Shard| shard.payload.clone())
+                .collect();
+            assert!(codec.verify(&payloads)?);
+
+            let mut damaged: Vec<Option<Vec<u8>>> = payloads.into_iter().map(Some).collect();
+            damaged[1] = None;
+            assert_eq!(codec.decode_positional(damaged)?, b"traced payload");
+            Ok(())
+        })?;
+
+        let names: Vec<&'static str> = names.lock().unwrap().clone();
+        for stage in [
+            "encode",
+            "split_into_shards",
+            "verify",
+            "decode",
+            "reconstruct",
+        ] {
+            assert!(names.contains(&stage), "no {} span in {:?}", stage, names);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::processor::DataProcessor;
+use crate::report::DecodeReport;
+
+/// Gathers shards as they arrive, in any order, and decodes as soon as any
+/// `data_shards` intact ones are in hand. Shards that fail their checksum
+/// are dropped on arrival, so the slot can be filled again by a retry.
+#[derive(Debug)]
+pub struct ShardCollector<'a> {
+    codec: &'a ReedSolomonCodec,
+    shards: Vec<Option<Vec<u8>>>,
+    corrupted: Vec<usize>,
+    shard_len: Option<usize>,
+    intact: usize,
+}
+
+impl<'a> ShardCollector<'a> {
+    pub fn new(codec: &'a ReedSolomonCodec) -> Self {
+        Self {
+            codec,
+            shards: vec![None; codec.config().total_shards()],
+            corrupted: Vec::new(),
+            shard_len: None,
+            intact: 0,
+        }
+    }
+
+    /// Adds the shard stored at `index` and reports whether enough shards
+    /// have arrived to decode.
+    pub fn push(&mut self, index: usize, shard: Vec<u8>) -> Result<bool, ReedSolomonError> {
+        let total: usize = self.shards.len();
+        let slot: &mut Option<Vec<u8>> = self
+            .shards
+            .get_mut(index)
+            .ok_or(ReedSolomonError::ShardIndexOutOfRange { index, total })?;
+        if slot.is_some() {
+            return Err(ReedSolomonError::DuplicateShard { index });
+        }
+        if let Some(expected) = self
+            .shard_len
+            .filter(|expected: &usize| *expected != shard.len())
+        {
+            return Err(ReedSolomonError::ShardSizeMismatch {
+                index,
+                given: shard.len(),
+                expected,
+            });
+        }
+
+        if DataProcessor::verify_checksum(&shard).is_none() {
+            if !self.corrupted.contains(&index) {
+                self.corrupted.push(index);
+            }
+            return Ok(self.ready());
+        }
+
+        self.corrupted
+            .retain(|corrupted: &usize| *corrupted != index);
+        self.shard_len = Some(shard.len());
+        *slot = Some(shard);
+        self.intact += 1;
+        Ok(self.ready())
+    }
+
+    pub fn ready(&self) -> bool {
+        self.intact >= self.codec.config().data_shards()
+    }
+
+    pub fn received(&self) -> usize {
+        self.intact
+    }
+
+    /// Intact shards still needed before `ready` turns true.
+    pub fn needed(&self) -> usize {
+        self.codec
+            .config()
+            .data_shards()
+            .saturating_sub(self.intact)
+    }
+
+    pub fn decode(self) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_with_report()
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    pub fn decode_with_report(self) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        if !self.ready() {
+            return Err(ReedSolomonError::TooFewShards {
+                needed: self.codec.config().data_shards(),
+                given: self.intact,
+            });
+        }
+
+        let (decoded, mut report): (Vec<u8>, DecodeReport) =
+            self.codec.decode_positional_with_report(self.shards)?;
+        report
+            .missing
+            .retain(|index: &usize| !self.corrupted.contains(index));
+        report.corrupted = self.corrupted;
+        report.corrupted.sort_unstable();
+        Ok((decoded, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard::Shard;
+
+    #[test]
+    fn test_decodes_once_enough_shards_arrive() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 3)?;
+        let shards: Vec<Shard> = codec.encode_shards(b"shards trickle in over the network")?;
+
+        let mut collector: ShardCollector = ShardCollector::new(&codec);
+        assert!(!collector.push(6, shards[6].payload.clone())?);
+        assert!(!collector.push(2, shards[2].payload.clone())?);
+
+        let mut damaged: Vec<u8> = shards[0].payload.clone();
+        damaged[0] ^= 0xff;
+        assert!(!collector.push(0, damaged)?);
+        assert_eq!((collector.received(), collector.needed()), (2, 2));
+
+        assert!(!collector.push(5, shards[5].payload.clone())?);
+        assert!(collector.push(1, shards[1].payload.clone())?);
+
+        let (decoded, report): (Vec<u8>, DecodeReport) = collector.decode_with_report()?;
+        assert_eq!(decoded, b"shards trickle in over the network");
+        assert_eq!(report.corrupted, vec![0]);
+        assert_eq!(report.missing, vec![3, 4]);
+        assert_eq!(report.reconstructed, vec![0, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_bad_pushes() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let shards: Vec<Shard> = codec.encode_shards(b"one of each, please")?;
+
+        let mut collector: ShardCollector = ShardCollector::new(&codec);
+        collector.push(1, shards[1].payload.clone())?;
+        assert!(matches!(
+            collector.push(1, shards[1].payload.clone()),
+            Err(ReedSolomonError::DuplicateShard { index: 1 })
+        ));
+        assert!(matches!(
+            collector.push(6, shards[0].payload.clone()),
+            Err(ReedSolomonError::ShardIndexOutOfRange { index: 6, total: 6 })
+        ));
+        assert!(matches!(
+            collector.push(2, vec![0u8; 3]),
+            Err(ReedSolomonError::ShardSizeMismatch { index: 2, .. })
+        ));
+        assert!(matches!(
+            collector.decode(),
+            Err(ReedSolomonError::TooFewShards {
+                needed: 4,
+                given: 1
+            })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::Read;
+
+use crate::config::{Compression, DataSizeLimits};
+use crate::error::ReedSolomonError;
+
+pub const ZSTD_LEVEL: i32 = 3;
+
+pub fn compress(compression: Compression, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Zstd => {
+            zstd::bulk::compress(data, ZSTD_LEVEL).map_err(ReedSolomonError::CompressionFailed)
+        }
+    }
+}
+
+pub fn decompress(
+    compression: Compression,
+    data: &[u8],
+    limits: &DataSizeLimits,
+) -> Result<Vec<u8>, ReedSolomonError> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Zstd => {
+            let mut decompressed: Vec<u8> = Vec::new();
+            zstd::stream::Decoder::new(data)
+                .and_then(|decoder: zstd::stream::Decoder<'_, _>| {
+                    decoder
+                        .take(limits.max as u64 + 1)
+                        .read_to_end(&mut decompressed)
+                })
+                .map_err(ReedSolomonError::DecompressionFailed)?;
+
+            limits.check(decompressed.len())?;
+            Ok(decompressed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DATA_SIZE_LIMITS;
+
+    #[test]
+    fn test_zstd_roundtrip() -> Result<(), ReedSolomonError> {
+        let data: Vec<u8> = (0..4000u32).map(|i: u32| (i / 100) as u8).collect();
+        let compressed: Vec<u8> = compress(Compression::Zstd, &data)?;
+
+        assert!(compressed.len() < data.len());
+        assert_eq!(
+            decompress(Compression::Zstd, &compressed, &DATA_SIZE_LIMITS)?,
+            data
+        );
+        assert!(decompress(Compression::Zstd, b"garbage", &DATA_SIZE_LIMITS).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1227 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::ReedSolomonError;
+use crate::polynomial::{Gf8Field, DEFAULT_POLYNOMIAL};
+
+pub const SHARD_LIMITS: ShardLimits = ShardLimits::new(1, 256);
+pub const GF16_SHARD_LIMITS: ShardLimits = ShardLimits::new(1, 65536);
+pub const DATA_SIZE_LIMITS: DataSizeLimits = DataSizeLimits::new(1, isize::MAX as usize);
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+pub const MAX_INTERLEAVE_DEPTH: usize = u16::MAX as usize;
+pub const MIN_AUTO_SHARD_SIZE: usize = 4 << 10;
+pub const MAX_AUTO_SHARD_SIZE: usize = 16 << 20;
+/// How many blocks `auto_block_size` aims to split an input into.
+pub const AUTO_BLOCK_COUNT: u64 = 64;
+pub const LEOPARD_SHARD_LIMITS: ShardLimits = ShardLimits::new(1, 32768);
+pub const LEOPARD_SHARD_THRESHOLD: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShardLimits {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl ShardLimits {
+    pub const fn new(min: usize, max: usize) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, count: usize) -> bool {
+        (self.min..=self.max).contains(&count)
+    }
+
+    fn check(&self, count: usize) -> Result<(), ReedSolomonError> {
+        if !self.contains(count) {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: count,
+                min: self.min,
+                max: self.max,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataSizeLimits {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl DataSizeLimits {
+    pub const fn new(min: usize, max: usize) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, len: usize) -> bool {
+        (self.min..=self.max).contains(&len)
+    }
+
+    pub fn check(&self, len: usize) -> Result<(), ReedSolomonError> {
+        if !self.contains(len) {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: len,
+                min: self.min,
+                max: self.max,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum GaloisField {
+    #[default]
+    Gf8,
+    Gf16,
+}
+
+impl GaloisField {
+    pub fn shard_limits(&self) -> ShardLimits {
+        match self {
+            GaloisField::Gf8 => SHARD_LIMITS,
+            GaloisField::Gf16 => GF16_SHARD_LIMITS,
+        }
+    }
+
+    pub fn element_size(&self) -> usize {
+        match self {
+            GaloisField::Gf8 => 1,
+            GaloisField::Gf16 => 2,
+        }
+    }
+
+    pub fn bits(&self) -> u8 {
+        match self {
+            GaloisField::Gf8 => 8,
+            GaloisField::Gf16 => 16,
+        }
+    }
+
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            8 => Some(GaloisField::Gf8),
+            16 => Some(GaloisField::Gf16),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Compression {
+    #[default]
+    None,
+    Zstd,
+}
+
+impl Compression {
+    pub fn id(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zstd => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Zstd => cfg!(feature = "compression"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Encryption {
+    #[default]
+    None,
+    XChaCha20Poly1305,
+}
+
+impl Encryption {
+    pub fn id(&self) -> u8 {
+        match self {
+            Encryption::None => 0,
+            Encryption::XChaCha20Poly1305 => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Encryption::None),
+            1 => Some(Encryption::XChaCha20Poly1305),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ShardLayout {
+    #[default]
+    Systematic,
+    NonSystematic,
+}
+
+impl ShardLayout {
+    pub fn id(&self) -> u8 {
+        match self {
+            ShardLayout::Systematic => 0,
+            ShardLayout::NonSystematic => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(ShardLayout::Systematic),
+            1 => Some(ShardLayout::NonSystematic),
+            _ => None,
+        }
+    }
+
+    /// Shards that exist only inside the code: the data shards of a
+    /// non-systematic layout are never written out.
+    pub fn hidden_shards(&self, data_shards: usize) -> usize {
+        match self {
+            ShardLayout::Systematic => 0,
+            ShardLayout::NonSystematic => data_shards,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ErasureCode {
+    #[default]
+    Matrix,
+    Leopard,
+    /// Every stored shard is a copy of a single data shard.
+    Replication,
+}
+
+impl ErasureCode {
+    pub fn id(&self) -> u8 {
+        match self {
+            ErasureCode::Matrix => 0,
+            ErasureCode::Leopard => 1,
+            ErasureCode::Replication => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(ErasureCode::Matrix),
+            1 => Some(ErasureCode::Leopard),
+            2 => Some(ErasureCode::Replication),
+            _ => None,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        match self {
+            ErasureCode::Matrix | ErasureCode::Replication => true,
+            ErasureCode::Leopard => cfg!(feature = "leopard"),
+        }
+    }
+
+    /// Leopard works on 16-bit words whatever the field of the config.
+    pub fn element_size(&self) -> usize {
+        match self {
+            ErasureCode::Matrix | ErasureCode::Replication => 1,
+            ErasureCode::Leopard => 2,
+        }
+    }
+
+    /// Counts include any hidden shards. The matrix code is bounded by the
+    /// field alone, which the config already checks, and replication copies
+    /// a single data shard.
+    pub fn supports(&self, data_shards: usize, parity_shards: usize) -> bool {
+        match self {
+            ErasureCode::Matrix => true,
+            ErasureCode::Replication => data_shards == 1,
+            ErasureCode::Leopard => {
+                LEOPARD_SHARD_LIMITS.contains(data_shards)
+                    && LEOPARD_SHARD_LIMITS.contains(parity_shards)
+                    && data_shards + parity_shards <= GF16_SHARD_LIMITS.max
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "EncoderConfigFields", into = "EncoderConfigFields")
+)]
+pub struct EncoderConfig {
+    data_shards: usize,
+    parity_shards: usize,
+    total_shards: usize,
+    field: GaloisField,
+    polynomial: u16,
+    block_size: usize,
+    interleave_depth: usize,
+    shard_alignment: usize,
+    compression: Compression,
+    layout: ShardLayout,
+    erasure_code: Option<ErasureCode>,
+    shard_limits: Option<ShardLimits>,
+    data_size_limits: Option<DataSizeLimits>,
+}
+
+impl EncoderConfig {
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, ReedSolomonError> {
+        Self::with_field(data_shards, parity_shards, GaloisField::Gf8)
+    }
+
+    pub fn with_field(
+        data_shards: usize,
+        parity_shards: usize,
+        field: GaloisField,
+    ) -> Result<Self, ReedSolomonError> {
+        let total_shards: usize =
+            Self::validate_shard_counts(data_shards, parity_shards, &field.shard_limits())?;
+
+        Ok(Self {
+            data_shards,
+            parity_shards,
+            total_shards,
+            field,
+            polynomial: DEFAULT_POLYNOMIAL,
+            block_size: DEFAULT_BLOCK_SIZE,
+            interleave_depth: 1,
+            shard_alignment: 1,
+            compression: Compression::None,
+            layout: ShardLayout::Systematic,
+            erasure_code: None,
+            shard_limits: None,
+            data_size_limits: None,
+        })
+    }
+
+    pub fn with_shard_limits(mut self, limits: ShardLimits) -> Result<Self, ReedSolomonError> {
+        let field_limits: ShardLimits = self.field.shard_limits();
+        field_limits.check(limits.min)?;
+        field_limits.check(limits.max)?;
+        if limits.min > limits.max {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: limits.min,
+                min: field_limits.min,
+                max: limits.max,
+            });
+        }
+
+        Self::validate_shard_counts(self.data_shards, self.parity_shards, &limits)?;
+        Self::validate_layout(self.layout, self.data_shards, self.total_shards, &limits)?;
+        self.shard_limits = Some(limits);
+        Ok(self)
+    }
+
+    pub fn with_data_size_limits(
+        mut self,
+        limits: DataSizeLimits,
+    ) -> Result<Self, ReedSolomonError> {
+        DATA_SIZE_LIMITS.check(limits.min)?;
+        DATA_SIZE_LIMITS.check(limits.max)?;
+        if limits.min > limits.max {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: limits.min,
+                min: DATA_SIZE_LIMITS.min,
+                max: limits.max,
+            });
+        }
+
+        self.block_size = self.block_size.clamp(limits.min, limits.max);
+        self.data_size_limits = Some(limits);
+        Ok(self)
+    }
+
+    pub fn builder() -> EncoderConfigBuilder {
+        EncoderConfigBuilder::default()
+    }
+
+    pub fn with_block_size(mut self, block_size: usize) -> Result<Self, ReedSolomonError> {
+        self.data_size_limits().check(block_size)?;
+
+        self.block_size = block_size;
+        Ok(self)
+    }
+
+    /// A block size suited to an input of `input_len` bytes: about
+    /// [`AUTO_BLOCK_COUNT`] blocks whose shards hold a power of two between
+    /// [`MIN_AUTO_SHARD_SIZE`] and [`MAX_AUTO_SHARD_SIZE`] bytes, but never
+    /// more than the input rounded up to a whole row of elements, so small
+    /// inputs are not padded out to a full block. Kept within the data
+    /// size limits.
+    pub fn auto_block_size(&self, input_len: u64) -> usize {
+        let data_shards: u64 = self.data_shards as u64;
+        let row: u64 = data_shards * self.element_size() as u64;
+        let shard_size: u64 = input_len
+            .div_ceil(data_shards * AUTO_BLOCK_COUNT)
+            .clamp(MIN_AUTO_SHARD_SIZE as u64, MAX_AUTO_SHARD_SIZE as u64)
+            .next_power_of_two();
+        let block_size: u64 = (shard_size * data_shards).min(input_len.div_ceil(row).max(1) * row);
+
+        let limits: DataSizeLimits = self.data_size_limits();
+        (block_size.min(limits.max as u64) as usize).max(limits.min)
+    }
+
+    /// Sets the block size `auto_block_size` picks for `input_len` bytes.
+    pub fn with_auto_block_size(self, input_len: u64) -> Result<Self, ReedSolomonError> {
+        let block_size: usize = self.auto_block_size(input_len);
+        self.with_block_size(block_size)
+    }
+
+    pub fn with_interleave_depth(mut self, depth: usize) -> Result<Self, ReedSolomonError> {
+        if !(1..=MAX_INTERLEAVE_DEPTH).contains(&depth) {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: depth,
+                min: 1,
+                max: MAX_INTERLEAVE_DEPTH,
+            });
+        }
+
+        self.interleave_depth = depth;
+        Ok(self)
+    }
+
+    /// Rounds every shard, checksum included, up to a multiple of
+    /// `alignment` bytes by zero-padding the data, so shard files can be
+    /// written with `O_DIRECT` or mapped for DMA. The padding is recorded
+    /// in container headers and manifests like any other.
+    pub fn with_shard_alignment(mut self, alignment: usize) -> Result<Self, ReedSolomonError> {
+        if !alignment.is_power_of_two() {
+            return Err(ReedSolomonError::InvalidAlignment(alignment));
+        }
+
+        self.shard_alignment = alignment;
+        Ok(self)
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Result<Self, ReedSolomonError> {
+        if !compression.is_available() {
+            return Err(ReedSolomonError::CompressionUnavailable(compression));
+        }
+
+        self.compression = compression;
+        Ok(self)
+    }
+
+    pub fn with_layout(mut self, layout: ShardLayout) -> Result<Self, ReedSolomonError> {
+        Self::validate_layout(
+            layout,
+            self.data_shards,
+            self.total_shards,
+            &self.shard_limits(),
+        )?;
+        if let Some(code) = self.erasure_code {
+            Self::validate_erasure_code(code, layout, self.data_shards, self.parity_shards)?;
+        }
+
+        self.layout = layout;
+        Ok(self)
+    }
+
+    /// Pins the erasure code. Without this the code is picked by
+    /// [`erasure_code`](Self::erasure_code).
+    pub fn with_erasure_code(mut self, code: ErasureCode) -> Result<Self, ReedSolomonError> {
+        if !code.is_available() {
+            return Err(ReedSolomonError::ErasureCodeUnavailable(code));
+        }
+        Self::validate_erasure_code(code, self.layout, self.data_shards, self.parity_shards)?;
+        if code != ErasureCode::Matrix && self.polynomial != DEFAULT_POLYNOMIAL {
+            return Err(ReedSolomonError::InvalidPolynomial(self.polynomial));
+        }
+
+        self.erasure_code = Some(code);
+        Ok(self)
+    }
+
+    /// Builds GF(2^8) on `polynomial` instead of the default 0x11d, so
+    /// shards match libraries that use another field, such as 0x187. Only
+    /// the GF(2^8) matrix code supports this.
+    pub fn with_polynomial(mut self, polynomial: u16) -> Result<Self, ReedSolomonError> {
+        Gf8Field::new(polynomial)?;
+        let matrix: bool = self
+            .erasure_code
+            .is_none_or(|code: ErasureCode| code == ErasureCode::Matrix);
+        if polynomial != DEFAULT_POLYNOMIAL && (self.field != GaloisField::Gf8 || !matrix) {
+            return Err(ReedSolomonError::InvalidPolynomial(polynomial));
+        }
+
+        self.polynomial = polynomial;
+        Ok(self)
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    pub fn parity_shards(&self) -> usize {
+        self.parity_shards
+    }
+
+    pub fn total_shards(&self) -> usize {
+        self.total_shards
+    }
+
+    pub fn field(&self) -> GaloisField {
+        self.field
+    }
+
+    /// The field's primitive polynomial, or `None` for GF(2^16), which is
+    /// not built from a single polynomial over GF(2).
+    pub fn polynomial(&self) -> Option<u16> {
+        (self.field == GaloisField::Gf8).then_some(self.polynomial)
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn interleave_depth(&self) -> usize {
+        self.interleave_depth
+    }
+
+    pub fn shard_alignment(&self) -> usize {
+        self.shard_alignment
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn layout(&self) -> ShardLayout {
+        self.layout
+    }
+
+    /// The pinned erasure code, or Leopard once the coded shards reach
+    /// [`LEOPARD_SHARD_THRESHOLD`] and the `leopard` feature is enabled.
+    pub fn erasure_code(&self) -> ErasureCode {
+        self.erasure_code.unwrap_or_else(|| {
+            let parity_shards: usize =
+                self.parity_shards + self.layout.hidden_shards(self.data_shards);
+            let leopard: ErasureCode = ErasureCode::Leopard;
+            if leopard.is_available()
+                && self.polynomial == DEFAULT_POLYNOMIAL
+                && self.data_shards + parity_shards >= LEOPARD_SHARD_THRESHOLD
+                && leopard.supports(self.data_shards, parity_shards)
+            {
+                leopard
+            } else {
+                ErasureCode::Matrix
+            }
+        })
+    }
+
+    /// Shard sizes are rounded up to this many bytes.
+    pub fn element_size(&self) -> usize {
+        self.field
+            .element_size()
+            .max(self.erasure_code().element_size())
+    }
+
+    /// The matrix that turns the data shards into the stored shards: one
+    /// row of GF(2^8) coefficients per stored shard and one column per data
+    /// shard. `None` for GF(2^16), Leopard and replication, which are not
+    /// GF(2^8) matrix codes.
+    pub fn encoding_matrix(&self) -> Option<Vec<Vec<u8>>> {
+        if self.field != GaloisField::Gf8 || self.erasure_code() != ErasureCode::Matrix {
+            return None;
+        }
+
+        let field: Gf8Field = Gf8Field::new(self.polynomial).ok()?;
+        let hidden_shards: usize = self.layout.hidden_shards(self.data_shards);
+        let parity: Vec<Vec<u8>> =
+            field.parity_matrix(self.data_shards, self.parity_shards + hidden_shards);
+        if hidden_shards > 0 {
+            return Some(parity);
+        }
+
+        let mut matrix: Vec<Vec<u8>> = (0..self.data_shards)
+            .map(|row: usize| {
+                let mut identity: Vec<u8> = vec![0u8; self.data_shards];
+                identity[row] = 1;
+                identity
+            })
+            .collect();
+        matrix.extend(parity);
+        Some(matrix)
+    }
+
+    pub fn shard_limits(&self) -> ShardLimits {
+        self.shard_limits
+            .unwrap_or_else(|| self.field.shard_limits())
+    }
+
+    /// Probability that data becomes unrecoverable when each stored shard
+    /// is lost independently with probability `shard_failure_prob`, that
+    /// is, that more than `parity_shards` of them fail. Probabilities
+    /// outside `0.0..=1.0` are clamped.
+    pub fn durability(&self, shard_failure_prob: f64) -> f64 {
+        let p: f64 = shard_failure_prob.clamp(0.0, 1.0);
+        if p == 0.0 || p == 1.0 {
+            return p;
+        }
+
+        // Binomial terms relative to the most likely failure count, so
+        // none of them overflow and the far tails may harmlessly underflow.
+        let total: usize = self.total_shards;
+        let odds: f64 = p / (1.0 - p);
+        let mode: usize = (((total + 1) as f64 * p) as usize).min(total);
+        let mut lost: f64 = 0.0;
+        let mut all: f64 = 0.0;
+
+        let mut term: f64 = 1.0;
+        let mut failures: usize = mode;
+        loop {
+            all += term;
+            if failures > self.parity_shards {
+                lost += term;
+            }
+            if failures == total || term == 0.0 {
+                break;
+            }
+            term *= (total - failures) as f64 / (failures + 1) as f64 * odds;
+            failures += 1;
+        }
+
+        term = 1.0;
+        failures = mode;
+        while failures > 0 && term > 0.0 {
+            term *= failures as f64 / (total - failures + 1) as f64 / odds;
+            failures -= 1;
+            all += term;
+            if failures > self.parity_shards {
+                lost += term;
+            }
+        }
+
+        lost / all
+    }
+
+    pub fn data_size_limits(&self) -> DataSizeLimits {
+        self.data_size_limits.unwrap_or(DATA_SIZE_LIMITS)
+    }
+
+    pub(crate) fn with_limits_of(self, other: &EncoderConfig) -> Result<Self, ReedSolomonError> {
+        let config: Self = match other.shard_limits {
+            Some(limits) => self.with_shard_limits(limits)?,
+            None => self,
+        };
+        match other.data_size_limits {
+            Some(limits) => config.with_data_size_limits(limits),
+            None => Ok(config),
+        }
+    }
+
+    fn validate_shard_counts(
+        data_shards: usize,
+        parity_shards: usize,
+        limits: &ShardLimits,
+    ) -> Result<usize, ReedSolomonError> {
+        limits.check(data_shards)?;
+        limits.check(parity_shards)?;
+
+        let total_shards: usize = data_shards + parity_shards;
+        if total_shards > limits.max {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: total_shards,
+                min: limits.min,
+                max: limits.max,
+            });
+        }
+        Ok(total_shards)
+    }
+
+    fn validate_layout(
+        layout: ShardLayout,
+        data_shards: usize,
+        total_shards: usize,
+        limits: &ShardLimits,
+    ) -> Result<(), ReedSolomonError> {
+        let coded_shards: usize = total_shards + layout.hidden_shards(data_shards);
+        if coded_shards > limits.max {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: coded_shards,
+                min: limits.min,
+                max: limits.max,
+            });
+        }
+        Ok(())
+    }
+
+    fn validate_erasure_code(
+        code: ErasureCode,
+        layout: ShardLayout,
+        data_shards: usize,
+        parity_shards: usize,
+    ) -> Result<(), ReedSolomonError> {
+        let parity_shards: usize = parity_shards + layout.hidden_shards(data_shards);
+        if code.supports(data_shards, parity_shards) {
+            return Ok(());
+        }
+
+        Err(match code {
+            ErasureCode::Replication => ReedSolomonError::InvalidShardCount {
+                given: data_shards,
+                min: 1,
+                max: 1,
+            },
+            _ => ReedSolomonError::InvalidShardCount {
+                given: data_shards.max(parity_shards),
+                min: LEOPARD_SHARD_LIMITS.min,
+                max: LEOPARD_SHARD_LIMITS.max,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncoderConfigBuilder {
+    overhead_percent: usize,
+    max_total_shards: Option<usize>,
+    field: GaloisField,
+}
+
+impl EncoderConfigBuilder {
+    pub fn overhead_percent(mut self, overhead_percent: usize) -> Self {
+        self.overhead_percent = overhead_percent;
+        self
+    }
+
+    pub fn max_total_shards(mut self, max_total_shards: usize) -> Self {
+        self.max_total_shards = Some(max_total_shards);
+        self
+    }
+
+    pub fn field(mut self, field: GaloisField) -> Self {
+        self.field = field;
+        self
+    }
+
+    pub fn build(&self) -> Result<EncoderConfig, ReedSolomonError> {
+        let limits: ShardLimits = self.field.shard_limits();
+        let max_total: usize = self
+            .max_total_shards
+            .map_or(limits.max, |max_total: usize| max_total.min(limits.max));
+
+        let mut data_shards: usize =
+            max_total.saturating_mul(100) / self.overhead_percent.saturating_add(100);
+        while data_shards > 0 && data_shards + self.parity_for(data_shards) > max_total {
+            data_shards -= 1;
+        }
+
+        EncoderConfig::with_field(data_shards, self.parity_for(data_shards), self.field)
+    }
+
+    fn parity_for(&self, data_shards: usize) -> usize {
+        data_shards
+            .saturating_mul(self.overhead_percent)
+            .div_ceil(100)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EncoderConfigFields {
+    data_shards: usize,
+    parity_shards: usize,
+    #[serde(default)]
+    field: GaloisField,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    polynomial: Option<u16>,
+    #[serde(default = "default_block_size")]
+    block_size: usize,
+    #[serde(default = "default_interleave_depth")]
+    interleave_depth: usize,
+    #[serde(
+        default = "default_shard_alignment",
+        skip_serializing_if = "is_unaligned"
+    )]
+    shard_alignment: usize,
+    #[serde(default)]
+    compression: Compression,
+    #[serde(default, skip_serializing_if = "is_systematic")]
+    layout: ShardLayout,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    erasure_code: Option<ErasureCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shard_limits: Option<ShardLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_size_limits: Option<DataSizeLimits>,
+}
+
+#[cfg(feature = "serde")]
+fn default_block_size() -> usize {
+    DEFAULT_BLOCK_SIZE
+}
+
+#[cfg(feature = "serde")]
+fn default_interleave_depth() -> usize {
+    1
+}
+
+#[cfg(feature = "serde")]
+fn default_shard_alignment() -> usize {
+    1
+}
+
+#[cfg(feature = "serde")]
+fn is_unaligned(alignment: &usize) -> bool {
+    *alignment == 1
+}
+
+#[cfg(feature = "serde")]
+fn is_systematic(layout: &ShardLayout) -> bool {
+    *layout == ShardLayout::Systematic
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<EncoderConfigFields> for EncoderConfig {
+    type Error = ReedSolomonError;
+
+    fn try_from(fields: EncoderConfigFields) -> Result<Self, Self::Error> {
+        let mut config: EncoderConfig =
+            EncoderConfig::with_field(fields.data_shards, fields.parity_shards, fields.field)?;
+        if let Some(polynomial) = fields.polynomial {
+            config = config.with_polynomial(polynomial)?;
+        }
+        if let Some(limits) = fields.shard_limits {
+            config = config.with_shard_limits(limits)?;
+        }
+        if let Some(limits) = fields.data_size_limits {
+            config = config.with_data_size_limits(limits)?;
+        }
+        config = config
+            .with_block_size(fields.block_size)?
+            .with_interleave_depth(fields.interleave_depth)?
+            .with_shard_alignment(fields.shard_alignment)?
+            .with_compression(fields.compression)?
+            .with_layout(fields.layout)?;
+        match fields.erasure_code {
+            Some(code) => config.with_erasure_code(code),
+            None => Ok(config),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<EncoderConfig> for EncoderConfigFields {
+    fn from(config: EncoderConfig) -> Self {
+        Self {
+            data_shards: config.data_shards,
+            parity_shards: config.parity_shards,
+            field: config.field,
+            polynomial: (config.polynomial != DEFAULT_POLYNOMIAL).then_some(config.polynomial),
+            block_size: config.block_size,
+            interleave_depth: config.interleave_depth,
+            shard_alignment: config.shard_alignment,
+            compression: config.compression,
+            layout: config.layout,
+            erasure_code: config.erasure_code,
+            shard_limits: config.shard_limits,
+            data_size_limits: config.data_size_limits,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_config() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+
+        assert_eq!(config.data_shards(), 10);
+        assert_eq!(config.parity_shards(), 4);
+        assert_eq!(config.total_shards(), 14);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(matches!(
+            EncoderConfig::new(0, 1),
+            Err(ReedSolomonError::InvalidShardCount {
+                given: 0,
+                min: 1,
+                max: 256
+            })
+        ));
+        assert!(EncoderConfig::new(1, 0).is_err());
+        assert!(matches!(
+            EncoderConfig::new(SHARD_LIMITS.max / 2, SHARD_LIMITS.max / 2 + 1),
+            Err(ReedSolomonError::InvalidShardCount { given: 257, .. })
+        ));
+    }
+
+    #[test]
+    fn test_builder_splits_by_overhead() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::builder()
+            .overhead_percent(30)
+            .max_total_shards(64)
+            .build()?;
+        assert_eq!((config.data_shards(), config.parity_shards()), (49, 15));
+
+        let config: EncoderConfig = EncoderConfig::builder()
+            .overhead_percent(50)
+            .max_total_shards(6)
+            .build()?;
+        assert_eq!((config.data_shards(), config.parity_shards()), (4, 2));
+
+        let config: EncoderConfig = EncoderConfig::builder().overhead_percent(25).build()?;
+        assert_eq!((config.data_shards(), config.parity_shards()), (204, 51));
+
+        let config: EncoderConfig = EncoderConfig::builder()
+            .overhead_percent(10)
+            .max_total_shards(1000)
+            .field(GaloisField::Gf16)
+            .build()?;
+        assert_eq!((config.data_shards(), config.parity_shards()), (909, 91));
+
+        assert!(EncoderConfig::builder().build().is_err());
+        assert!(EncoderConfig::builder()
+            .overhead_percent(30)
+            .max_total_shards(1)
+            .build()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_limits() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(config.shard_limits(), SHARD_LIMITS);
+        assert_eq!(config.data_size_limits(), DATA_SIZE_LIMITS);
+
+        let config: EncoderConfig = config
+            .with_shard_limits(ShardLimits::new(2, 16))?
+            .with_data_size_limits(DataSizeLimits::new(1, 4096))?;
+        assert_eq!(config.shard_limits(), ShardLimits::new(2, 16));
+        assert_eq!(config.block_size(), 4096);
+        assert!(config.with_block_size(8192).is_err());
+        assert!(matches!(
+            config.data_size_limits().check(4097),
+            Err(ReedSolomonError::InvalidDataSize {
+                given: 4097,
+                min: 1,
+                max: 4096
+            })
+        ));
+
+        assert!(matches!(
+            config.with_shard_limits(ShardLimits::new(1, 12)),
+            Err(ReedSolomonError::InvalidShardCount { given: 14, .. })
+        ));
+        assert!(config.with_shard_limits(ShardLimits::new(1, 300)).is_err());
+        assert!(config.with_shard_limits(ShardLimits::new(20, 16)).is_err());
+        assert!(config
+            .with_data_size_limits(DataSizeLimits::new(0, 4096))
+            .is_err());
+        assert!(config
+            .with_data_size_limits(DataSizeLimits::new(1, DATA_SIZE_LIMITS.max + 1))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_size() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(config.block_size(), DEFAULT_BLOCK_SIZE);
+
+        let config: EncoderConfig = config.with_block_size(4 << 20)?;
+        assert_eq!(config.block_size(), 4 << 20);
+        assert!(config.with_block_size(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shard_alignment() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(config.shard_alignment(), 1);
+        assert_eq!(config.with_shard_alignment(4096)?.shard_alignment(), 4096);
+        assert!(matches!(
+            config.with_shard_alignment(0),
+            Err(ReedSolomonError::InvalidAlignment(0))
+        ));
+        assert!(config.with_shard_alignment(3000).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_block_size() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(config.auto_block_size(0), 10);
+        assert_eq!(config.auto_block_size(25), 30);
+        assert_eq!(config.auto_block_size(100_000), 10 * MIN_AUTO_SHARD_SIZE);
+        assert_eq!(config.auto_block_size(100 << 20), 10 * (256 << 10));
+        assert_eq!(config.auto_block_size(1 << 40), 10 * MAX_AUTO_SHARD_SIZE);
+
+        let gf16: EncoderConfig = EncoderConfig::with_field(10, 4, GaloisField::Gf16)?;
+        assert_eq!(gf16.auto_block_size(25), 40);
+
+        let limited: EncoderConfig =
+            config.with_data_size_limits(DataSizeLimits::new(64, 1 << 20))?;
+        assert_eq!(limited.auto_block_size(25), 64);
+        assert_eq!(limited.with_auto_block_size(1 << 40)?.block_size(), 1 << 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_interleave_depth() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(config.interleave_depth(), 1);
+
+        assert_eq!(config.with_interleave_depth(8)?.interleave_depth(), 8);
+        assert!(config.with_interleave_depth(0).is_err());
+        assert!(config
+            .with_interleave_depth(MAX_INTERLEAVE_DEPTH + 1)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression_ids() {
+        for compression in [Compression::None, Compression::Zstd] {
+            assert_eq!(Compression::from_id(compression.id()), Some(compression));
+        }
+        assert_eq!(Compression::from_id(3), None);
+
+        for encryption in [Encryption::None, Encryption::XChaCha20Poly1305] {
+            assert_eq!(Encryption::from_id(encryption.id()), Some(encryption));
+        }
+        assert_eq!(Encryption::from_id(2), None);
+    }
+
+    #[test]
+    fn test_non_systematic_layout_needs_room_for_hidden_shards() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig =
+            EncoderConfig::new(100, 56)?.with_layout(ShardLayout::NonSystematic)?;
+        assert_eq!(config.layout(), ShardLayout::NonSystematic);
+        assert_eq!(config.total_shards(), 156);
+
+        assert!(matches!(
+            EncoderConfig::new(100, 57)?.with_layout(ShardLayout::NonSystematic),
+            Err(ReedSolomonError::InvalidShardCount { given: 257, .. })
+        ));
+        assert!(config.with_shard_limits(ShardLimits::new(1, 255)).is_err());
+        assert_eq!(
+            ShardLayout::from_id(ShardLayout::NonSystematic.id()),
+            Some(ShardLayout::NonSystematic)
+        );
+        assert_eq!(ShardLayout::from_id(2), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_erasure_code_selection() -> Result<(), ReedSolomonError> {
+        let small: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(small.erasure_code(), ErasureCode::Matrix);
+        assert_eq!(small.element_size(), 1);
+
+        let large: EncoderConfig = EncoderConfig::new(100, 28)?;
+        let expected: ErasureCode = if cfg!(feature = "leopard") {
+            ErasureCode::Leopard
+        } else {
+            ErasureCode::Matrix
+        };
+        assert_eq!(large.erasure_code(), expected);
+        assert_eq!(
+            large.with_erasure_code(ErasureCode::Matrix)?.erasure_code(),
+            ErasureCode::Matrix
+        );
+
+        let huge: EncoderConfig = EncoderConfig::with_field(40000, 100, GaloisField::Gf16)?;
+        assert_eq!(huge.erasure_code(), ErasureCode::Matrix);
+        assert!(huge.with_erasure_code(ErasureCode::Leopard).is_err());
+
+        if cfg!(feature = "leopard") {
+            let pinned: EncoderConfig = small.with_erasure_code(ErasureCode::Leopard)?;
+            assert_eq!(pinned.erasure_code(), ErasureCode::Leopard);
+            assert_eq!(pinned.element_size(), 2);
+        } else {
+            assert!(matches!(
+                small.with_erasure_code(ErasureCode::Leopard),
+                Err(ReedSolomonError::ErasureCodeUnavailable(
+                    ErasureCode::Leopard
+                ))
+            ));
+        }
+        assert_eq!(
+            ErasureCode::from_id(ErasureCode::Leopard.id()),
+            Some(ErasureCode::Leopard)
+        );
+        assert_eq!(
+            ErasureCode::from_id(ErasureCode::Replication.id()),
+            Some(ErasureCode::Replication)
+        );
+        assert_eq!(ErasureCode::from_id(3), None);
+        assert!(small.with_erasure_code(ErasureCode::Replication).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_durability() -> Result<(), ReedSolomonError> {
+        let close = |given: f64, expected: f64| (given - expected).abs() <= expected * 1e-9;
+
+        assert!(close(EncoderConfig::new(1, 1)?.durability(0.1), 0.01));
+        assert!(close(
+            EncoderConfig::new(10, 4)?.durability(0.01),
+            1.856894283863548e-7
+        ));
+        assert!(close(
+            EncoderConfig::with_field(200, 56, GaloisField::Gf16)?.durability(0.2),
+            0.2023882727905975
+        ));
+        assert!(close(
+            EncoderConfig::with_field(3000, 1000, GaloisField::Gf16)?.durability(0.2),
+            6.4307677831663786e-15
+        ));
+        assert!(close(
+            EncoderConfig::with_field(20000, 12000, GaloisField::Gf16)?.durability(0.35),
+            5.109033289897844e-21
+        ));
+
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(config.durability(0.0), 0.0);
+        assert_eq!(config.durability(-1.0), 0.0);
+        assert_eq!(config.durability(1.0), 1.0);
+        assert!(EncoderConfig::new(10, 6)?.durability(0.01) < config.durability(0.01));
+        Ok(())
+    }
+
+    #[test]
+    fn test_gf16_allows_more_shards() -> Result<(), ReedSolomonError> {
+        assert!(EncoderConfig::new(400, 100).is_err());
+
+        let config: EncoderConfig = EncoderConfig::with_field(400, 100, GaloisField::Gf16)?;
+        assert_eq!(config.total_shards(), 500);
+        assert_eq!(config.field(), GaloisField::Gf16);
+        assert!(EncoderConfig::with_field(60000, 6000, GaloisField::Gf16).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_polynomial() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        assert_eq!(config.polynomial(), Some(DEFAULT_POLYNOMIAL));
+        assert_eq!(config.with_polynomial(0x187)?.polynomial(), Some(0x187));
+        assert!(matches!(
+            config.with_polynomial(0x11b),
+            Err(ReedSolomonError::InvalidPolynomial(0x11b))
+        ));
+
+        let gf16: EncoderConfig = EncoderConfig::with_field(400, 100, GaloisField::Gf16)?;
+        assert_eq!(gf16.polynomial(), None);
+        assert!(gf16.with_polynomial(0x187).is_err());
+        assert!(config
+            .with_polynomial(0x187)?
+            .with_erasure_code(ErasureCode::Matrix)
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encoding_matrix_is_mds() -> Result<(), ReedSolomonError> {
+        let matrix: Vec<Vec<u8>> = EncoderConfig::new(4, 3)?
+            .encoding_matrix()
+            .ok_or(ReedSolomonError::SingularMatrix)?;
+        assert_eq!(matrix.len(), 7);
+        assert_eq!(matrix[2], vec![0, 0, 1, 0]);
+
+        // Every choice of 4 of the 7 rows must be invertible.
+        let field: Gf8Field = Gf8Field::default();
+        for rows in (0u32..1 << 7).filter(|rows: &u32| rows.count_ones() == 4) {
+            let square: Vec<Vec<u8>> = (0..7)
+                .filter(|row: &usize| rows & (1 << row) != 0)
+                .map(|row: usize| matrix[row].clone())
+                .collect();
+            assert!(field.invert(&square).is_some());
+        }
+
+        let non_systematic: EncoderConfig =
+            EncoderConfig::new(4, 3)?.with_layout(ShardLayout::NonSystematic)?;
+        assert_eq!(
+            non_systematic
+                .encoding_matrix()
+                .map(|m: Vec<Vec<u8>>| m.len()),
+            Some(7)
+        );
+        assert_eq!(
+            EncoderConfig::with_field(4, 3, GaloisField::Gf16)?.encoding_matrix(),
+            None
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip_revalidates() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig =
+            EncoderConfig::with_field(400, 100, GaloisField::Gf16)?.with_block_size(4096)?;
+
+        let json: String = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            json,
+            r#"{"data_shards":400,"parity_shards":100,"field":"gf16","block_size":4096,"interleave_depth":1,"compression":"none"}"#
+        );
+
+        let parsed: EncoderConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.total_shards(), 500);
+        assert_eq!(parsed.block_size(), 4096);
+
+        let defaults: EncoderConfig =
+            serde_json::from_str(r#"{"data_shards":10,"parity_shards":4}"#).unwrap();
+        assert_eq!(defaults.field(), GaloisField::Gf8);
+        assert_eq!(defaults.block_size(), DEFAULT_BLOCK_SIZE);
+        assert_eq!(defaults.layout(), ShardLayout::Systematic);
+
+        let non_systematic: EncoderConfig = config.with_layout(ShardLayout::NonSystematic)?;
+        let json: String = serde_json::to_string(&non_systematic).unwrap();
+        assert!(json.contains(r#""layout":"non_systematic""#));
+        let parsed: EncoderConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.layout(), ShardLayout::NonSystematic);
+
+        assert!(
+            serde_json::from_str::<EncoderConfig>(r#"{"data_shards":0,"parity_shards":4}"#)
+                .is_err()
+        );
+        Ok(())
+    }
+}
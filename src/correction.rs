@@ -0,0 +1,382 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::{EncoderConfig, ErasureCode, GaloisField};
+use crate::error::ReedSolomonError;
+use crate::polynomial::Gf8Field;
+
+impl ReedSolomonCodec {
+    /// Finds and repairs up to `parity_shards / 2` corrupted shard payloads
+    /// in place without being told which ones are bad, and returns the
+    /// indices of the shards it changed.
+    ///
+    /// `shards` holds every stored shard payload, checksums stripped, in
+    /// shard order. Each byte position is corrected on its own, so
+    /// different shards may be wrong at different offsets as long as no
+    /// offset has more than `parity_shards / 2` bad bytes. Lost shards are
+    /// erasures rather than errors; rebuild those with `reconstruct`. If any
+    /// offset cannot be corrected, `shards` is left as it was.
+    pub fn correct_errors(&self, shards: &mut [Vec<u8>]) -> Result<Vec<usize>, ReedSolomonError> {
+        let config: &EncoderConfig = self.config();
+        let total_shards: usize = config.total_shards();
+        if shards.len() != total_shards {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: total_shards,
+                given: shards.len(),
+            });
+        }
+        let shard_len: usize = shards.first().map_or(0, Vec::len);
+        if let Some(index) = shards
+            .iter()
+            .position(|shard: &Vec<u8>| shard.len() != shard_len)
+        {
+            return Err(ReedSolomonError::ShardSizeMismatch {
+                index,
+                given: shards[index].len(),
+                expected: shard_len,
+            });
+        }
+
+        let decoder: SyndromeDecoder = SyndromeDecoder::new(config)?;
+        let mut fixes: Vec<(usize, usize, u8)> = Vec::new();
+        let mut column: Vec<u8> = vec![0u8; total_shards];
+        for offset in 0..shard_len {
+            for (value, shard) in column.iter_mut().zip(shards.iter()) {
+                *value = shard[offset];
+            }
+            for (index, error) in decoder.correct(&column)? {
+                fixes.push((index, offset, error));
+            }
+        }
+
+        let mut corrected: Vec<bool> = vec![false; total_shards];
+        for (index, offset, error) in fixes {
+            shards[index][offset] ^= error;
+            corrected[index] = true;
+        }
+
+        Ok((0..total_shards)
+            .filter(|index: &usize| corrected[*index])
+            .collect())
+    }
+}
+
+/// Syndrome decoding for the matrix code, one byte column at a time.
+///
+/// Stored shard `i` holds the value at point `first_point + i` of a
+/// polynomial of degree below `data_shards`, so the shards form a
+/// Reed-Solomon code in evaluation form. Adding a constant to every point
+/// gives the same code, which keeps zero, a point no error locator can
+/// represent, out of the set. Berlekamp-Massey then finds the error
+/// locator from the syndromes, a Chien search over the points finds the
+/// bad shards and Forney's formula gives the error values.
+struct SyndromeDecoder {
+    field: Gf8Field,
+    /// The shifted evaluation point of each shard.
+    points: Vec<u8>,
+    /// The column multipliers of the parity-check matrix, `1 / prod(x_i - x_j)`.
+    weights: Vec<u8>,
+    redundancy: usize,
+}
+
+impl SyndromeDecoder {
+    fn new(config: &EncoderConfig) -> Result<Self, ReedSolomonError> {
+        if config.field() != GaloisField::Gf8 || config.erasure_code() != ErasureCode::Matrix {
+            return Err(ReedSolomonError::ErrorCorrectionUnsupported);
+        }
+        let field: Gf8Field = Gf8Field::new(
+            config
+                .polynomial()
+                .ok_or(ReedSolomonError::ErrorCorrectionUnsupported)?,
+        )?;
+
+        let total_shards: usize = config.total_shards();
+        let first_point: usize = config.layout().hidden_shards(config.data_shards());
+        let shift: u8 = (0..=255u8)
+            .find(|shift: &u8| {
+                !(first_point..first_point + total_shards).contains(&(*shift as usize))
+            })
+            .ok_or(ReedSolomonError::ErrorCorrectionUnsupported)?;
+        let points: Vec<u8> = (first_point..first_point + total_shards)
+            .map(|point: usize| point as u8 ^ shift)
+            .collect();
+        let weights: Vec<u8> = points
+            .iter()
+            .enumerate()
+            .map(|(i, point): (usize, &u8)| {
+                let product: u8 = points
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _): &(usize, &u8)| *j != i)
+                    .fold(1u8, |product: u8, (_, other): (usize, &u8)| {
+                        field.mul(product, point ^ other)
+                    });
+                field.div(1, product)
+            })
+            .collect();
+
+        Ok(Self {
+            field,
+            points,
+            weights,
+            redundancy: total_shards - config.data_shards(),
+        })
+    }
+
+    /// `S_j = sum_i w_i * r_i * x_i^j` for `j` below the redundancy. All
+    /// zero exactly when the column is a codeword.
+    fn syndromes(&self, column: &[u8]) -> Vec<u8> {
+        let mut syndromes: Vec<u8> = vec![0u8; self.redundancy];
+        for ((value, weight), point) in column.iter().zip(&self.weights).zip(&self.points) {
+            let mut term: u8 = self.field.mul(*value, *weight);
+            for syndrome in syndromes.iter_mut() {
+                *syndrome ^= term;
+                term = self.field.mul(term, *point);
+            }
+        }
+        syndromes
+    }
+
+    /// The `(shard index, error value)` pairs that turn `column` back into
+    /// a codeword.
+    fn correct(&self, column: &[u8]) -> Result<Vec<(usize, u8)>, ReedSolomonError> {
+        let max: usize = self.redundancy / 2;
+        let syndromes: Vec<u8> = self.syndromes(column);
+        if syndromes.iter().all(|syndrome: &u8| *syndrome == 0) {
+            return Ok(Vec::new());
+        }
+
+        let mut repaired: Vec<u8> = column.to_vec();
+        let corrections: Vec<(usize, u8)> = locate_errors(&self.field, &syndromes, &self.points)
+            .filter(|errors: &Vec<(usize, u8)>| errors.len() <= max)
+            .ok_or(ReedSolomonError::TooManyErrors { max })?
+            .into_iter()
+            .map(|(index, weighted): (usize, u8)| {
+                let error: u8 = self.field.div(weighted, self.weights[index]);
+                repaired[index] ^= error;
+                (index, error)
+            })
+            .collect();
+
+        // More errors than can be located can still produce a plausible
+        // locator; only a clean result is trusted.
+        if self
+            .syndromes(&repaired)
+            .iter()
+            .any(|syndrome: &u8| *syndrome != 0)
+        {
+            return Err(ReedSolomonError::TooManyErrors { max });
+        }
+        Ok(corrections)
+    }
+}
+
+/// Solves `S_j = sum_l Y_l * X_l^j` for the unknown locators `X_l`, each
+/// one of `points`, and values `Y_l`. Returns `(index into points, Y_l)`
+/// pairs, or `None` if the syndromes do not fit any set of at most half as
+/// many errors. Callers scale `Y_l` back to the error value and should
+/// check the corrected word, since too many errors can still fit.
+pub(crate) fn locate_errors(
+    field: &Gf8Field,
+    syndromes: &[u8],
+    points: &[u8],
+) -> Option<Vec<(usize, u8)>> {
+    let locator: Vec<u8> = berlekamp_massey(field, syndromes);
+    let errors: usize = locator.len() - 1;
+    if 2 * errors > syndromes.len() {
+        return None;
+    }
+    // Chien search, restricted to the points that are actually in use.
+    let positions: Vec<usize> = (0..points.len())
+        .filter(|i: &usize| points[*i] != 0 && eval(field, &locator, field.div(1, points[*i])) == 0)
+        .collect();
+    if positions.len() != errors {
+        return None;
+    }
+
+    // Forney: Y_l = X_l * Omega(1/X_l) / Lambda'(1/X_l), with Omega =
+    // S * Lambda mod z^len(S). In characteristic 2 the formal derivative
+    // keeps only the odd powers of Lambda.
+    let mut evaluator: Vec<u8> = vec![0u8; syndromes.len()];
+    for (i, coefficient) in locator.iter().enumerate() {
+        for (j, syndrome) in syndromes[..syndromes.len().saturating_sub(i)]
+            .iter()
+            .enumerate()
+        {
+            evaluator[i + j] ^= field.mul(*coefficient, *syndrome);
+        }
+    }
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, coefficient): (usize, &u8)| if i % 2 == 1 { *coefficient } else { 0 })
+        .collect();
+
+    positions
+        .into_iter()
+        .map(|index: usize| {
+            let point: u8 = points[index];
+            let inverse: u8 = field.div(1, point);
+            let denominator: u8 = eval(field, &derivative, inverse);
+            (denominator != 0).then(|| {
+                (
+                    index,
+                    field.div(
+                        field.mul(point, eval(field, &evaluator, inverse)),
+                        denominator,
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+/// The shortest linear recurrence generating the syndromes, as the error
+/// locator `prod(1 - X_l * z)` with the lowest coefficient first.
+fn berlekamp_massey(field: &Gf8Field, syndromes: &[u8]) -> Vec<u8> {
+    let mut locator: Vec<u8> = vec![1];
+    let mut previous: Vec<u8> = vec![1];
+    let mut length: usize = 0;
+    let mut gap: usize = 1;
+    let mut previous_discrepancy: u8 = 1;
+
+    for n in 0..syndromes.len() {
+        let discrepancy: u8 = (1..=length)
+            .filter(|i: &usize| *i < locator.len())
+            .fold(syndromes[n], |sum: u8, i: usize| {
+                sum ^ field.mul(locator[i], syndromes[n - i])
+            });
+        if discrepancy == 0 {
+            gap += 1;
+            continue;
+        }
+
+        let scale: u8 = field.div(discrepancy, previous_discrepancy);
+        let mut next: Vec<u8> = locator.clone();
+        next.resize(next.len().max(previous.len() + gap), 0);
+        for (i, coefficient) in previous.iter().enumerate() {
+            next[i + gap] ^= field.mul(scale, *coefficient);
+        }
+
+        if 2 * length <= n {
+            length = n + 1 - length;
+            previous = core::mem::replace(&mut locator, next);
+            previous_discrepancy = discrepancy;
+            gap = 1;
+        } else {
+            locator = next;
+            gap += 1;
+        }
+    }
+
+    locator.truncate(length + 1);
+    locator
+}
+
+/// Evaluates the polynomial with `coefficients`, lowest first, at `x`.
+pub(crate) fn eval(field: &Gf8Field, coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0u8, |sum: u8, coefficient: &u8| {
+            field.mul(sum, x) ^ coefficient
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ShardLayout;
+    use crate::processor::DataProcessor;
+    use crate::shard::Shard;
+
+    fn payloads(codec: &ReedSolomonCodec, data: &[u8]) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        Ok(codec
+            .encode_shards(data)?
+            .iter()
+            .map(|shard: &Shard| {
+                DataProcessor::verify_checksum(&shard.payload)
+                    .unwrap_or_default()
+                    .to_vec()
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_corrects_up_to_half_the_parity() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?;
+        let original_data: Vec<u8> = (0..5_000u32).map(|i: u32| (i * 7 % 251) as u8).collect();
+        let expected: Vec<Vec<u8>> = payloads(&codec, &original_data)?;
+
+        let mut shards: Vec<Vec<u8>> = expected.clone();
+        assert_eq!(codec.correct_errors(&mut shards)?, Vec::<usize>::new());
+
+        // Shards 3 and 12 are garbage throughout; shard 0 is hit at one
+        // offset, where only one other shard is bad.
+        for (offset, byte) in shards[3].iter_mut().enumerate() {
+            *byte ^= (offset % 255 + 1) as u8;
+        }
+        for (offset, byte) in shards[12].iter_mut().enumerate().skip(1) {
+            *byte = !(*byte ^ offset as u8);
+        }
+        shards[0][0] ^= 0x5a;
+        assert_eq!(codec.correct_errors(&mut shards)?, vec![0, 3, 12]);
+        assert_eq!(shards, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reports_too_many_errors() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(6, 4)?;
+        let mut shards: Vec<Vec<u8>> = payloads(&codec, &[0x42; 3_000])?;
+        for index in [1, 4, 8] {
+            for (offset, byte) in shards[index].iter_mut().enumerate() {
+                *byte ^= (offset as u8).wrapping_mul(index as u8) | 1;
+            }
+        }
+        assert!(matches!(
+            codec.correct_errors(&mut shards),
+            Err(ReedSolomonError::TooManyErrors { max: 2 })
+        ));
+        assert!(codec.correct_errors(&mut shards[1..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failure_leaves_shards_unchanged() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(6, 4)?;
+        let mut shards: Vec<Vec<u8>> = payloads(&codec, &[0x42; 3_000])?;
+        // The first offset alone could be corrected, the last cannot.
+        shards[2][0] ^= 0x10;
+        let last: usize = shards[0].len() - 1;
+        for index in [1, 4, 8] {
+            shards[index][last] ^= 1;
+        }
+        let damaged: Vec<Vec<u8>> = shards.clone();
+
+        assert!(matches!(
+            codec.correct_errors(&mut shards),
+            Err(ReedSolomonError::TooManyErrors { max: 2 })
+        ));
+        assert_eq!(shards, damaged);
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_systematic_with_custom_polynomial() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 3)?
+            .with_layout(ShardLayout::NonSystematic)?
+            .with_polynomial(0x187)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let expected: Vec<Vec<u8>> = payloads(&codec, b"no shard here is plain data")?;
+
+        let mut shards: Vec<Vec<u8>> = expected.clone();
+        shards[5][2] ^= 0xff;
+        shards[6][7] ^= 0x01;
+        assert_eq!(codec.correct_errors(&mut shards)?, vec![5, 6]);
+        assert_eq!(shards, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Direct I/O buffers, file offsets and transfer sizes are multiples of
+/// this. Encoding with `with_shard_alignment(DIRECT_IO_ALIGNMENT)` keeps
+/// whole shards on that boundary.
+pub const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+const DIRECT_IO_BUFFER_SIZE: usize = 1 << 20;
+
+/// A zeroed buffer that starts on a `DIRECT_IO_ALIGNMENT` boundary.
+struct AlignedBuffer {
+    raw: Vec<u8>,
+    offset: usize,
+}
+
+impl AlignedBuffer {
+    fn new(len: usize) -> Self {
+        let raw: Vec<u8> = vec![0u8; len + DIRECT_IO_ALIGNMENT];
+        let offset: usize = raw.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+        Self { raw, offset }
+    }
+
+    fn len(&self) -> usize {
+        self.raw.len() - DIRECT_IO_ALIGNMENT
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.raw[self.offset..self.offset + self.len()]
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        let len: usize = self.len();
+        &mut self.raw[self.offset..self.offset + len]
+    }
+}
+
+/// Opens `path` with `O_DIRECT`, or returns `None` where the platform or
+/// the file system does not support it.
+#[cfg(target_os = "linux")]
+fn open_direct(options: &mut OpenOptions, path: &Path) -> io::Result<Option<File>> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    match options.custom_flags(libc::O_DIRECT).open(path) {
+        Ok(file) => Ok(Some(file)),
+        // tmpfs and some network file systems refuse O_DIRECT.
+        Err(error) if error.raw_os_error() == Some(libc::EINVAL) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_direct(_options: &mut OpenOptions, _path: &Path) -> io::Result<Option<File>> {
+    Ok(None)
+}
+
+/// Reads a file around the page cache in aligned chunks.
+pub(crate) struct DirectReader {
+    file: File,
+    buffer: AlignedBuffer,
+    start: usize,
+    end: usize,
+    eof: bool,
+}
+
+impl DirectReader {
+    /// Falls back to a buffered reader where direct I/O is not available.
+    pub(crate) fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+        match open_direct(OpenOptions::new().read(true), path)? {
+            Some(file) => Ok(Box::new(Self {
+                file,
+                buffer: AlignedBuffer::new(DIRECT_IO_BUFFER_SIZE),
+                start: 0,
+                end: 0,
+                eof: false,
+            })),
+            None => Ok(Box::new(BufReader::new(File::open(path)?))),
+        }
+    }
+}
+
+impl Read for DirectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.start == self.end && !self.eof {
+            // A short read leaves the offset unaligned, which only happens
+            // at the end of the file.
+            let read: usize = self.file.read(self.buffer.as_mut_slice())?;
+            self.eof = read < self.buffer.len();
+            self.start = 0;
+            self.end = read;
+        }
+        let len: usize = buf.len().min(self.end - self.start);
+        buf[..len].copy_from_slice(&self.buffer.as_slice()[self.start..self.start + len]);
+        self.start += len;
+        Ok(len)
+    }
+}
+
+/// Writes a file around the page cache in aligned chunks. The unaligned
+/// tail left on `flush` goes through the page cache.
+pub(crate) struct DirectWriter {
+    file: File,
+    path: PathBuf,
+    direct: bool,
+    buffer: AlignedBuffer,
+    filled: usize,
+}
+
+impl DirectWriter {
+    /// Falls back to buffered writes where direct I/O is not available.
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        let mut options: OpenOptions = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        let (file, direct): (File, bool) = match open_direct(&mut options, path)? {
+            Some(file) => (file, true),
+            None => (File::create(path)?, false),
+        };
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            direct,
+            buffer: AlignedBuffer::new(DIRECT_IO_BUFFER_SIZE),
+            filled: 0,
+        })
+    }
+
+    fn write_buffer(&mut self) -> io::Result<()> {
+        let mut start: usize = 0;
+        if self.direct {
+            start = self.filled / DIRECT_IO_ALIGNMENT * DIRECT_IO_ALIGNMENT;
+            self.file.write_all(&self.buffer.as_slice()[..start])?;
+            if start < self.filled {
+                self.file = OpenOptions::new().append(true).open(&self.path)?;
+                self.direct = false;
+            }
+        }
+        self.file
+            .write_all(&self.buffer.as_slice()[start..self.filled])?;
+        self.filled = 0;
+        Ok(())
+    }
+}
+
+impl Write for DirectWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len: usize = buf.len().min(self.buffer.len() - self.filled);
+        self.buffer.as_mut_slice()[self.filled..self.filled + len].copy_from_slice(&buf[..len]);
+        self.filled += len;
+        if self.filled == self.buffer.len() {
+            self.write_buffer()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_direct_io_roundtrip() -> io::Result<()> {
+        let dir: PathBuf = temp_dir("direct");
+        fs::create_dir_all(&dir)?;
+
+        for len in [
+            0usize,
+            100,
+            DIRECT_IO_ALIGNMENT,
+            DIRECT_IO_BUFFER_SIZE + 12345,
+        ] {
+            let data: Vec<u8> = (0..len).map(|i: usize| (i % 251) as u8).collect();
+            let path: PathBuf = dir.join(format!("file-{}", len));
+            let mut writer: DirectWriter = DirectWriter::create(&path)?;
+            for chunk in data.chunks(7000) {
+                writer.write_all(chunk)?;
+            }
+            writer.flush()?;
+            assert_eq!(fs::read(&path)?, data);
+
+            let mut read: Vec<u8> = Vec::new();
+            DirectReader::open(&path)?.read_to_end(&mut read)?;
+            assert_eq!(read, data, "{}", len);
+        }
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
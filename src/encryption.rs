@@ -0,0 +1,354 @@
+use core::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::Encryption;
+use crate::error::ReedSolomonError;
+use crate::header::{ContainerHeader, NONCE_SIZE};
+use crate::mac::SET_ID_SIZE;
+use crate::report::DecodeReport;
+use crate::shard::Shard;
+
+pub const KEY_SIZE: usize = 32;
+pub const TAG_SIZE: usize = 16;
+/// The set id, shard index and nonce in front of every sealed shard.
+pub const SHARD_HEADER_SIZE: usize = SET_ID_SIZE + 2 + NONCE_SIZE;
+const NONCE_OFFSET: usize = SET_ID_SIZE + 2;
+
+const SHARD_KEY_INFO: &[u8] = b"reed-solomon-codec shard key";
+
+/// What `open_shard` returns: the set id, the index and the stored shard.
+pub type OpenedShard = ([u8; SET_ID_SIZE], u16, Vec<u8>);
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; KEY_SIZE]);
+
+impl EncryptionKey {
+    pub fn new(bytes: [u8; KEY_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn generate() -> Self {
+        let mut bytes: [u8; KEY_SIZE] = [0u8; KEY_SIZE];
+        bytes.copy_from_slice(&XChaCha20Poly1305::generate_key(&mut OsRng));
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_SIZE] {
+        &self.0
+    }
+
+    /// The HKDF-SHA256 sub-key that seals the shard stored at `index` in
+    /// the encoding `set_id`, so no two shards share a key.
+    pub fn shard_key(&self, set_id: &[u8; SET_ID_SIZE], index: u16) -> EncryptionKey {
+        const INFO_SIZE: usize = SHARD_KEY_INFO.len() + SET_ID_SIZE + 2;
+        let mut info: [u8; INFO_SIZE] = [0u8; INFO_SIZE];
+        info[..SHARD_KEY_INFO.len()].copy_from_slice(SHARD_KEY_INFO);
+        info[SHARD_KEY_INFO.len()..INFO_SIZE - 2].copy_from_slice(set_id);
+        BigEndian::write_u16(&mut info[INFO_SIZE - 2..], index);
+
+        let mut bytes: [u8; KEY_SIZE] = [0u8; KEY_SIZE];
+        Hkdf::<Sha256>::new(None, &self.0)
+            .expand(&info, &mut bytes)
+            .expect("KEY_SIZE is a valid HKDF-SHA256 output length");
+        Self(bytes)
+    }
+
+    pub(crate) fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+pub(crate) fn encrypt(
+    key: &EncryptionKey,
+    header: &mut ContainerHeader,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, ReedSolomonError> {
+    let nonce: XNonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    header.encryption = Encryption::XChaCha20Poly1305;
+    header.nonce.copy_from_slice(&nonce);
+
+    key.cipher()
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &header.to_bytes(),
+            },
+        )
+        .map_err(|_| ReedSolomonError::EncryptionFailed)
+}
+
+pub(crate) fn decrypt(
+    key: &EncryptionKey,
+    header: &ContainerHeader,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, ReedSolomonError> {
+    key.cipher()
+        .decrypt(
+            XNonce::from_slice(&header.nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &header.to_bytes(),
+            },
+        )
+        .map_err(|_| ReedSolomonError::AuthenticationFailed)
+}
+
+/// A random id shared by the shards of one encoding.
+pub fn generate_set_id() -> [u8; SET_ID_SIZE] {
+    let mut set_id: [u8; SET_ID_SIZE] = [0u8; SET_ID_SIZE];
+    OsRng.fill_bytes(&mut set_id);
+    set_id
+}
+
+/// Encrypts a stored shard on its own, under the sub-key for its set and
+/// index and a fresh nonce. The sealed shard is the set id, index and
+/// nonce, which are authenticated along with the shard, then the
+/// ciphertext and tag.
+pub fn seal_shard(
+    key: &EncryptionKey,
+    set_id: &[u8; SET_ID_SIZE],
+    shard: &Shard,
+) -> Result<Vec<u8>, ReedSolomonError> {
+    let nonce: XNonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut header: [u8; SHARD_HEADER_SIZE] = [0u8; SHARD_HEADER_SIZE];
+    header[..SET_ID_SIZE].copy_from_slice(set_id);
+    BigEndian::write_u16(&mut header[SET_ID_SIZE..NONCE_OFFSET], shard.index);
+    header[NONCE_OFFSET..].copy_from_slice(&nonce);
+
+    let ciphertext: Vec<u8> = key
+        .shard_key(set_id, shard.index)
+        .cipher()
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &shard.payload,
+                aad: &header,
+            },
+        )
+        .map_err(|_| ReedSolomonError::EncryptionFailed)?;
+
+    let mut sealed: Vec<u8> = Vec::with_capacity(SHARD_HEADER_SIZE + ciphertext.len());
+    sealed.extend_from_slice(&header);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Authenticates and decrypts one shard sealed by `seal_shard`, returning
+/// its set id, its index and the stored shard, checksum included.
+pub fn open_shard(key: &EncryptionKey, sealed: &[u8]) -> Result<OpenedShard, ReedSolomonError> {
+    if sealed.len() < SHARD_HEADER_SIZE + TAG_SIZE {
+        return Err(ReedSolomonError::AuthenticationFailed);
+    }
+
+    let (header, ciphertext) = sealed.split_at(SHARD_HEADER_SIZE);
+    let mut set_id: [u8; SET_ID_SIZE] = [0u8; SET_ID_SIZE];
+    set_id.copy_from_slice(&header[..SET_ID_SIZE]);
+    let index: u16 = BigEndian::read_u16(&header[SET_ID_SIZE..NONCE_OFFSET]);
+    let payload: Vec<u8> = key
+        .shard_key(&set_id, index)
+        .cipher()
+        .decrypt(
+            XNonce::from_slice(&header[NONCE_OFFSET..]),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| ReedSolomonError::AuthenticationFailed)?;
+    Ok((set_id, index, payload))
+}
+
+impl ReedSolomonCodec {
+    /// Encodes like `encode_shards`, then seals every shard separately with
+    /// the codec's key, so each can be authenticated and decrypted without
+    /// the others. The shards share a fresh set id, so they cannot be mixed
+    /// with the shards of another encoding.
+    pub fn encode_shards_sealed(&self, data: &[u8]) -> Result<Vec<Shard>, ReedSolomonError> {
+        let key: &EncryptionKey = self.encryption_key()?;
+        let set_id: [u8; SET_ID_SIZE] = generate_set_id();
+        self.encode_shards(data)?
+            .into_iter()
+            .map(|shard: Shard| {
+                Ok(Shard {
+                    payload: seal_shard(key, &set_id, &shard)?,
+                    ..shard
+                })
+            })
+            .collect()
+    }
+
+    pub fn decode_shards_sealed(&self, shards: Vec<Shard>) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_shards_sealed_with_report(shards)
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    /// Opens each sealed shard and decodes the ones that authenticate.
+    /// Shards that fail, including ones presented under another index or
+    /// sealed for another encoding than most of the others, are reported
+    /// as corrupted.
+    pub fn decode_shards_sealed_with_report(
+        &self,
+        shards: Vec<Shard>,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let key: &EncryptionKey = self.encryption_key()?;
+        let mut rejected: Vec<usize> = Vec::new();
+        let mut opened: Vec<([u8; SET_ID_SIZE], Shard)> = Vec::with_capacity(shards.len());
+        for shard in shards {
+            match open_shard(key, &shard.payload) {
+                Ok((set_id, index, payload)) if index == shard.index => {
+                    opened.push((set_id, Shard { payload, ..shard }))
+                }
+                _ => rejected.push(shard.index as usize),
+            }
+        }
+
+        let set_id: Option<[u8; SET_ID_SIZE]> = opened
+            .iter()
+            .map(|(set_id, _): &([u8; SET_ID_SIZE], Shard)| *set_id)
+            .max_by_key(|candidate: &[u8; SET_ID_SIZE]| {
+                opened
+                    .iter()
+                    .filter(|(set_id, _): &&([u8; SET_ID_SIZE], Shard)| set_id == candidate)
+                    .count()
+            });
+        let mut accepted: Vec<Shard> = Vec::with_capacity(opened.len());
+        for (shard_set_id, shard) in opened {
+            if Some(shard_set_id) == set_id {
+                accepted.push(shard);
+            } else {
+                rejected.push(shard.index as usize);
+            }
+        }
+
+        self.decode_shards_rejecting(accepted, rejected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() -> Result<(), ReedSolomonError> {
+        let key: EncryptionKey = EncryptionKey::generate();
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?;
+        let mut header: ContainerHeader = ContainerHeader::new(&config, 64, 7 + TAG_SIZE);
+
+        let ciphertext: Vec<u8> = encrypt(&key, &mut header, b"payload")?;
+        assert_eq!(ciphertext.len(), 7 + TAG_SIZE);
+        assert_eq!(header.encryption, Encryption::XChaCha20Poly1305);
+        assert_eq!(decrypt(&key, &header, &ciphertext)?, b"payload");
+
+        assert!(decrypt(&EncryptionKey::new([1u8; KEY_SIZE]), &header, &ciphertext).is_err());
+
+        let mut tampered: ContainerHeader = header;
+        tampered.payload_len += 1;
+        assert!(decrypt(&key, &tampered, &ciphertext).is_err());
+        assert_eq!(format!("{:?}", key), "EncryptionKey(..)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sealed_shards_open_individually() -> Result<(), ReedSolomonError> {
+        let key: EncryptionKey = EncryptionKey::generate();
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?.with_encryption_key(key.clone());
+        let original_data: Vec<u8> = (0..1000u32).map(|i: u32| (i % 251) as u8).collect();
+
+        let plain: Vec<Shard> = codec.encode_shards(&original_data)?;
+        let sealed: Vec<Shard> = codec.encode_shards_sealed(&original_data)?;
+        let set_id: [u8; SET_ID_SIZE] = generate_set_id();
+        assert_ne!(key.shard_key(&set_id, 0), key.shard_key(&set_id, 1));
+        assert_ne!(
+            key.shard_key(&set_id, 0),
+            key.shard_key(&generate_set_id(), 0)
+        );
+        assert_eq!(
+            sealed[0].payload[..SET_ID_SIZE],
+            sealed[1].payload[..SET_ID_SIZE]
+        );
+        assert_ne!(
+            sealed[0].payload[NONCE_OFFSET..SHARD_HEADER_SIZE],
+            sealed[1].payload[NONCE_OFFSET..SHARD_HEADER_SIZE]
+        );
+        for (sealed, plain) in sealed.iter().zip(&plain) {
+            assert_eq!(
+                sealed.payload.len(),
+                SHARD_HEADER_SIZE + plain.payload.len() + TAG_SIZE
+            );
+            let (_, index, payload): OpenedShard = open_shard(&key, &sealed.payload)?;
+            assert_eq!((index, payload), (plain.index, plain.payload.clone()));
+        }
+        assert!(open_shard(&EncryptionKey::new([3u8; KEY_SIZE]), &sealed[0].payload).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_sealed_shards_around_damage() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::new(4, 2)?.with_encryption_key(EncryptionKey::generate());
+        let original_data: &[u8] = b"every shard sealed under its own sub-key";
+        let mut sealed: Vec<Shard> = codec.encode_shards_sealed(original_data)?;
+
+        sealed.remove(5);
+        sealed[1].payload[SHARD_HEADER_SIZE] ^= 0x01;
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            codec.decode_shards_sealed_with_report(sealed.clone())?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.corrupted, vec![1]);
+        assert_eq!(report.missing, vec![5]);
+
+        BigEndian::write_u16(&mut sealed[0].payload[SET_ID_SIZE..NONCE_OFFSET], 2);
+        assert!(codec.decode_shards_sealed(sealed).is_err());
+        assert!(matches!(
+            ReedSolomonCodec::new(4, 2)?.encode_shards_sealed(original_data),
+            Err(ReedSolomonError::MissingKey)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sealed_shards_cannot_move_between_encodings() -> Result<(), ReedSolomonError> {
+        let key: EncryptionKey = EncryptionKey::generate();
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?.with_encryption_key(key.clone());
+        let first: Vec<u8> = (0..1000u32).map(|i: u32| (i % 251) as u8).collect();
+        let second: Vec<u8> = (0..1000u32).map(|i: u32| (i % 241) as u8).collect();
+        let mut sealed: Vec<Shard> = codec.encode_shards_sealed(&first)?;
+        let other: Vec<Shard> = codec.encode_shards_sealed(&second)?;
+
+        // Shard 1 of the second encoding opens under the same master key,
+        // but its set id gives it away.
+        let (other_set_id, _, _): OpenedShard = open_shard(&key, &other[1].payload)?;
+        assert_ne!(other_set_id[..], sealed[1].payload[..SET_ID_SIZE]);
+        sealed[1] = other[1].clone();
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            codec.decode_shards_sealed_with_report(sealed.clone())?;
+        assert_eq!(decoded, first);
+        assert_eq!(report.corrupted, vec![1]);
+
+        // Rewriting the set id breaks the shard's authentication instead.
+        let set_id: Vec<u8> = sealed[0].payload[..SET_ID_SIZE].to_vec();
+        sealed[1].payload[..SET_ID_SIZE].copy_from_slice(&set_id);
+        assert!(open_shard(&key, &sealed[1].payload).is_err());
+
+        sealed[0] = other[0].clone();
+        sealed[2] = other[2].clone();
+        sealed[1] = other[1].clone();
+        assert!(codec.decode_shards_sealed(sealed).is_err());
+        Ok(())
+    }
+}
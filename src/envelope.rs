@@ -0,0 +1,185 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::config::EncoderConfig;
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::processor::{crc32c_checksum, CHECKSUM_SIZE};
+use crate::shard::Shard;
+
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// One stored shard as a self-describing CBOR map, for protocols that
+/// already speak CBOR. The payload is a CBOR byte string and the CRC32C
+/// that stored shards end with is carried in its own field, so a receiver
+/// can check a shard and learn how to decode it without any other state.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ShardEnvelope {
+    pub version: u8,
+    pub index: u16,
+    pub config: EncoderConfig,
+    /// CRC32C of `payload`.
+    pub checksum: u32,
+    #[serde(with = "bytes")]
+    pub payload: Vec<u8>,
+}
+
+impl ShardEnvelope {
+    /// Wraps a stored shard, moving its trailing checksum into `checksum`.
+    pub fn new(shard: &Shard, config: &EncoderConfig) -> Result<Self, ReedSolomonError> {
+        if shard.payload.len() < CHECKSUM_SIZE {
+            return Err(ReedSolomonError::Truncated {
+                needed: CHECKSUM_SIZE,
+                given: shard.payload.len(),
+            });
+        }
+        let (payload, checksum) = shard.payload.split_at(shard.payload.len() - CHECKSUM_SIZE);
+
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            index: shard.index,
+            config: *config,
+            checksum: BigEndian::read_u32(checksum),
+            payload: payload.to_vec(),
+        })
+    }
+
+    /// Whether the payload still matches its checksum.
+    pub fn is_intact(&self) -> bool {
+        crc32c_checksum(&self.payload) == self.checksum
+    }
+
+    /// The stored shard, with the checksum appended again. A damaged
+    /// payload is passed through, so the decoder treats it as missing.
+    pub fn into_shard(self) -> Shard {
+        let mut payload: Vec<u8> = self.payload;
+        let mut checksum: [u8; CHECKSUM_SIZE] = [0u8; CHECKSUM_SIZE];
+        BigEndian::write_u32(&mut checksum, self.checksum);
+        payload.extend_from_slice(&checksum);
+        Shard::new(self.index as usize, self.config.data_shards(), payload)
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut encoded: Vec<u8> = Vec::new();
+        ciborium::into_writer(self, &mut encoded).map_err(malformed_cbor)?;
+        Ok(encoded)
+    }
+
+    pub fn from_cbor(encoded: &[u8]) -> Result<Self, ReedSolomonError> {
+        let envelope: Self = ciborium::from_reader(encoded).map_err(malformed_cbor)?;
+        if envelope.version != ENVELOPE_VERSION {
+            return Err(HeaderError::UnsupportedVersion(envelope.version).into());
+        }
+        if envelope.index as usize >= envelope.config.total_shards() {
+            return Err(ReedSolomonError::ShardIndexOutOfRange {
+                index: envelope.index as usize,
+                total: envelope.config.total_shards(),
+            });
+        }
+        Ok(envelope)
+    }
+}
+
+fn malformed_cbor(error: impl fmt::Display) -> ReedSolomonError {
+    HeaderError::MalformedCbor(error.to_string()).into()
+}
+
+/// Serializes a `Vec<u8>` as a byte string rather than a list of numbers.
+mod bytes {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    use serde::de::{SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a byte string")
+        }
+
+        fn visit_bytes<E: serde::de::Error>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(value.to_vec())
+        }
+
+        fn visit_byte_buf<E: serde::de::Error>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(value)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes: Vec<u8> = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(byte) = seq.next_element::<u8>()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+
+    #[test]
+    fn test_envelope_round_trip() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let original_data: Vec<u8> = (0..1000u32).map(|i: u32| (i * 7) as u8).collect();
+        let shards: Vec<Shard> = codec.encode_shards(&original_data)?;
+
+        let mut restored: Vec<Shard> = Vec::new();
+        for shard in shards.iter().skip(2) {
+            let envelope: ShardEnvelope = ShardEnvelope::new(shard, codec.config())?;
+            assert!(envelope.is_intact());
+            let encoded: Vec<u8> = envelope.to_cbor()?;
+            // A byte string costs a few bytes of framing, not one per byte.
+            assert!(encoded.len() < envelope.payload.len() + 200);
+
+            let decoded: ShardEnvelope = ShardEnvelope::from_cbor(&encoded)?;
+            assert_eq!(decoded.config.data_shards(), 4);
+            restored.push(decoded.into_shard());
+        }
+        assert_eq!(restored, shards[2..]);
+        assert_eq!(codec.decode_shards(restored)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_damage_and_bad_input() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(2, 1)?;
+        let shards: Vec<Shard> = codec.encode_shards(b"envelope")?;
+        let mut envelope: ShardEnvelope = ShardEnvelope::new(&shards[0], codec.config())?;
+        envelope.payload[0] ^= 1;
+        assert!(!envelope.is_intact());
+
+        envelope.version = 9;
+        let encoded: Vec<u8> = envelope.to_cbor()?;
+        assert!(matches!(
+            ShardEnvelope::from_cbor(&encoded),
+            Err(ReedSolomonError::InvalidHeader(
+                HeaderError::UnsupportedVersion(9)
+            ))
+        ));
+        assert!(matches!(
+            ShardEnvelope::from_cbor(&encoded[..encoded.len() / 2]),
+            Err(ReedSolomonError::InvalidHeader(HeaderError::MalformedCbor(
+                _
+            )))
+        ));
+        assert!(ShardEnvelope::new(&Shard::new(0, 2, vec![1, 2]), codec.config()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,355 @@
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::io::{self, Error, ErrorKind};
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+use crate::config::{Compression, Encryption, ErasureCode, ShardLayout};
+use crate::shard::ShardKind;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReedSolomonError {
+    #[error("Invalid shard count: {given} is not between {min} and {max}")]
+    InvalidShardCount {
+        given: usize,
+        min: usize,
+        max: usize,
+    },
+
+    #[error("Expected {expected} shards, got {given}")]
+    ShardCountMismatch { expected: usize, given: usize },
+
+    #[error("Not enough shards: need {needed}, got {given}")]
+    TooFewShards { needed: usize, given: usize },
+
+    #[error("Shard index {index} out of range for {total} shards")]
+    ShardIndexOutOfRange { index: usize, total: usize },
+
+    #[error("Duplicate shard index {index}")]
+    DuplicateShard { index: usize },
+
+    #[error("Shard {index} is labelled {kind:?} but belongs to the other kind")]
+    ShardKindMismatch { index: usize, kind: ShardKind },
+
+    #[error("Shard buffer {index} holds {given} bytes, need exactly {expected}")]
+    ShardSizeMismatch {
+        index: usize,
+        given: usize,
+        expected: usize,
+    },
+
+    #[error("Data shard {index} is missing")]
+    MissingDataShard { index: usize },
+
+    #[error("Invalid data size: {given} is not between {min} and {max}")]
+    InvalidDataSize {
+        given: usize,
+        min: usize,
+        max: usize,
+    },
+
+    #[error("Expected {expected} bytes, got {given}")]
+    LengthMismatch { expected: usize, given: usize },
+
+    #[error("Data length ({given}) not divisible by total shards ({total_shards})")]
+    IndivisibleLength { given: usize, total_shards: usize },
+
+    #[error("Output buffer holds {given} bytes, need {needed}")]
+    BufferTooSmall { given: usize, needed: usize },
+
+    #[error("Input is truncated: need {needed} bytes, got {given}")]
+    Truncated { needed: usize, given: usize },
+
+    #[error("Size overflows usize")]
+    SizeOverflow,
+
+    #[cfg(feature = "par2")]
+    #[error("PAR2 slice size must be a positive multiple of 4, got {0}")]
+    InvalidSliceSize(u64),
+
+    #[cfg(feature = "fountain")]
+    #[error("RaptorQ symbol size must be a positive multiple of 8, got {0}")]
+    InvalidSymbolSize(u16),
+
+    #[error("Field polynomial {0:#x} is not a primitive degree-8 polynomial usable with this configuration")]
+    InvalidPolynomial(u16),
+
+    #[error("Shard alignment must be a power of two, got {0}")]
+    InvalidAlignment(usize),
+
+    #[error("Codec error: {error}")]
+    CodecError {
+        #[cfg_attr(feature = "std", source)]
+        error: reed_solomon_erasure::Error,
+    },
+
+    #[error("Encoding error: {error}")]
+    EncodingError {
+        #[cfg_attr(feature = "std", source)]
+        error: reed_solomon_erasure::Error,
+    },
+
+    #[error("Decoding error: {error}")]
+    DecodingError {
+        #[cfg_attr(feature = "std", source)]
+        error: reed_solomon_erasure::Error,
+    },
+
+    #[error("Decode matrix is singular")]
+    SingularMatrix,
+
+    #[error("Invalid size prefix")]
+    InvalidSizePrefix,
+
+    #[error("Payload digest mismatch: recovered data differs from the original")]
+    DigestMismatch,
+
+    #[error("Too many corrupted shards to locate: at most {max} can be corrected")]
+    TooManyErrors { max: usize },
+
+    #[error("Error correction requires the GF(2^8) matrix code")]
+    ErrorCorrectionUnsupported,
+
+    #[error("Operation is not supported by the {0:?} shard layout")]
+    UnsupportedLayout(ShardLayout),
+
+    #[error("Encoded shards failed verification")]
+    VerificationFailed,
+
+    #[error("{0:?} compression requires the `compression` feature")]
+    CompressionUnavailable(Compression),
+
+    #[error("{0:?} encryption requires the `encryption` feature")]
+    EncryptionUnavailable(Encryption),
+
+    #[error("{0:?} erasure code requires the `leopard` feature")]
+    ErasureCodeUnavailable(ErasureCode),
+
+    #[cfg(feature = "leopard")]
+    #[error("Leopard error: {0}")]
+    LeopardError(#[source] reed_solomon_simd::Error),
+
+    #[cfg(feature = "remote")]
+    #[error("Object store error: {0}")]
+    ObjectStore(#[source] s3::error::S3Error),
+
+    #[cfg(feature = "http")]
+    #[error("HTTP error: {0}")]
+    Http(#[source] Box<ureq::Error>),
+
+    #[cfg(feature = "std")]
+    #[error("Compression failed: {0}")]
+    CompressionFailed(#[source] io::Error),
+
+    #[cfg(feature = "std")]
+    #[error("Decompression failed: {0}")]
+    DecompressionFailed(#[source] io::Error),
+
+    #[error("Encryption failed")]
+    EncryptionFailed,
+
+    #[error("Authentication failed: wrong key or tampered container")]
+    AuthenticationFailed,
+
+    #[error("Container is encrypted; supply a key with `with_encryption_key`")]
+    MissingKey,
+
+    #[error("Container is not encrypted but the codec requires encryption")]
+    NotEncrypted,
+
+    #[error("Shards are authenticated; supply a key with `with_mac_key`")]
+    MissingMacKey,
+
+    #[error("Invalid header: {0}")]
+    InvalidHeader(#[from] HeaderError),
+
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(#[from] ManifestError),
+
+    #[error("Invalid armored text: {0}")]
+    InvalidArmor(#[from] ArmorError),
+
+    #[cfg(feature = "std")]
+    #[error("Invalid file name: {}", .0.display())]
+    InvalidFileName(PathBuf),
+
+    #[cfg(feature = "std")]
+    #[error("Shard set already uses {data_shards} data + {parity_shards} parity shards")]
+    UnchangedShardCounts {
+        data_shards: usize,
+        parity_shards: usize,
+    },
+
+    #[cfg(feature = "std")]
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HeaderError {
+    #[error("Header requires {needed} bytes, got {given}")]
+    Truncated { needed: usize, given: usize },
+
+    #[error("Missing magic bytes")]
+    MissingMagic,
+
+    #[error("Header checksum mismatch")]
+    ChecksumMismatch,
+
+    #[error("Unsupported format version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Unsupported field GF(2^{0})")]
+    UnsupportedField(u8),
+
+    #[error("Unsupported compression {0}")]
+    UnsupportedCompression(u8),
+
+    #[error("Unsupported encryption {0}")]
+    UnsupportedEncryption(u8),
+
+    #[error("Unsupported digest {0}")]
+    UnsupportedDigest(u8),
+
+    #[error("Unsupported erasure code {0}")]
+    UnsupportedErasureCode(u8),
+
+    #[error("Group contains no blocks")]
+    EmptyGroup,
+
+    #[error("Headers in one group or share set use different shard configurations")]
+    Inconsistent,
+
+    #[error("Padding {padding} must be less than {max}")]
+    InvalidPadding { padding: usize, max: usize },
+
+    #[cfg(feature = "fountain")]
+    #[error("Invalid RaptorQ transmission information")]
+    InvalidTransmissionInfo,
+
+    #[cfg(feature = "serde")]
+    #[error("Malformed CBOR envelope: {0}")]
+    MalformedCbor(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ManifestError {
+    #[error("Malformed line: {0}")]
+    MalformedLine(String),
+
+    #[error("Invalid value for {0}")]
+    InvalidValue(String),
+
+    #[error("Unknown key: {0}")]
+    UnknownKey(String),
+
+    #[error("Missing {0}")]
+    MissingKey(&'static str),
+
+    #[error("Unsupported manifest version {0}")]
+    UnsupportedVersion(u64),
+
+    #[error("Unsupported field GF(2^{0})")]
+    UnsupportedField(u64),
+
+    #[error("Block size must be greater than 0")]
+    ZeroBlockSize,
+
+    #[error("No shard set with a manifest found")]
+    NoShardSet,
+
+    #[error("Several shard sets found: {0}")]
+    AmbiguousShardSet(String),
+
+    #[error("Shard set holds a sparse file and must be decoded with its sparse map")]
+    SparseFile,
+
+    #[error("Sparse map is missing")]
+    MissingSparseMap,
+
+    #[error("Sparse map does not match the manifest")]
+    SparseMapMismatch,
+
+    #[cfg(feature = "json")]
+    #[error("Malformed JSON manifest: {0}")]
+    MalformedJson(String),
+
+    #[cfg(feature = "protobuf")]
+    #[error("Malformed protobuf message: {0}")]
+    MalformedProtobuf(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ArmorError {
+    #[error("No armored block found")]
+    NoBlock,
+
+    #[error("Armored block has no END line")]
+    Unterminated,
+
+    #[error("Malformed header line: {0}")]
+    MalformedHeader(String),
+
+    #[error("Missing header {0}")]
+    MissingHeader(&'static str),
+
+    #[error("Invalid value for header {0}")]
+    InvalidValue(String),
+
+    #[error("Invalid base64 on line {line}")]
+    InvalidBase64 { line: usize },
+
+    #[error("Checksum mismatch: the text was damaged or mistyped")]
+    ChecksumMismatch,
+}
+
+#[cfg(feature = "std")]
+impl From<ReedSolomonError> for io::Error {
+    fn from(error: ReedSolomonError) -> Self {
+        let kind = match error {
+            ReedSolomonError::Io(error) => return error,
+            ReedSolomonError::InvalidShardCount { .. }
+            | ReedSolomonError::InvalidDataSize { .. }
+            | ReedSolomonError::InvalidAlignment(_)
+            | ReedSolomonError::BufferTooSmall { .. }
+            | ReedSolomonError::ShardSizeMismatch { .. }
+            | ReedSolomonError::InvalidFileName(_)
+            | ReedSolomonError::UnchangedShardCounts { .. } => ErrorKind::InvalidInput,
+            ReedSolomonError::InvalidHeader(_)
+            | ReedSolomonError::InvalidManifest(_)
+            | ReedSolomonError::InvalidArmor(_) => ErrorKind::InvalidData,
+            ReedSolomonError::Truncated { .. } => ErrorKind::UnexpectedEof,
+            _ => ErrorKind::Other,
+        };
+        Error::new(kind, error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Without std the backend error is not reported as the source.
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_source_is_preserved() {
+        use std::error::Error as _;
+
+        let error: ReedSolomonError = ReedSolomonError::DecodingError {
+            error: reed_solomon_erasure::Error::TooFewShardsPresent,
+        };
+        assert!(error.source().is_some());
+
+        let error: ReedSolomonError = HeaderError::ChecksumMismatch.into();
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_nested_errors_convert() {
+        let error: ReedSolomonError = HeaderError::ChecksumMismatch.into();
+        assert!(matches!(
+            error,
+            ReedSolomonError::InvalidHeader(HeaderError::ChecksumMismatch)
+        ));
+    }
+}
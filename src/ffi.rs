@@ -0,0 +1,252 @@
+use std::ptr;
+use std::slice;
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+
+pub struct RsCodec(ReedSolomonCodec);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RsStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidShardCount = 2,
+    InvalidDataSize = 3,
+    CodecError = 4,
+    EncodingError = 5,
+    DecodingError = 6,
+    InvalidHeader = 7,
+    InvalidManifest = 8,
+    Io = 9,
+}
+
+impl From<&ReedSolomonError> for RsStatus {
+    fn from(error: &ReedSolomonError) -> Self {
+        match error {
+            ReedSolomonError::InvalidShardCount { .. }
+            | ReedSolomonError::ShardCountMismatch { .. }
+            | ReedSolomonError::TooFewShards { .. }
+            | ReedSolomonError::ShardIndexOutOfRange { .. } => RsStatus::InvalidShardCount,
+            #[cfg(feature = "std")]
+            ReedSolomonError::UnchangedShardCounts { .. } => RsStatus::InvalidShardCount,
+            ReedSolomonError::InvalidDataSize { .. }
+            | ReedSolomonError::LengthMismatch { .. }
+            | ReedSolomonError::IndivisibleLength { .. }
+            | ReedSolomonError::BufferTooSmall { .. }
+            | ReedSolomonError::ShardSizeMismatch { .. }
+            | ReedSolomonError::Truncated { .. }
+            | ReedSolomonError::InvalidAlignment(_)
+            | ReedSolomonError::SizeOverflow => RsStatus::InvalidDataSize,
+            #[cfg(feature = "par2")]
+            ReedSolomonError::InvalidSliceSize(_) => RsStatus::InvalidDataSize,
+            #[cfg(feature = "fountain")]
+            ReedSolomonError::InvalidSymbolSize(_) => RsStatus::InvalidDataSize,
+            ReedSolomonError::CodecError { .. }
+            | ReedSolomonError::InvalidPolynomial(_)
+            | ReedSolomonError::CompressionUnavailable(_)
+            | ReedSolomonError::EncryptionUnavailable(_)
+            | ReedSolomonError::ErasureCodeUnavailable(_)
+            | ReedSolomonError::ErrorCorrectionUnsupported
+            | ReedSolomonError::UnsupportedLayout(_) => RsStatus::CodecError,
+            #[cfg(feature = "leopard")]
+            ReedSolomonError::LeopardError(_) => RsStatus::CodecError,
+            ReedSolomonError::EncodingError { .. }
+            | ReedSolomonError::CompressionFailed(_)
+            | ReedSolomonError::EncryptionFailed
+            | ReedSolomonError::VerificationFailed => RsStatus::EncodingError,
+            ReedSolomonError::DecodingError { .. }
+            | ReedSolomonError::DuplicateShard { .. }
+            | ReedSolomonError::ShardKindMismatch { .. }
+            | ReedSolomonError::MissingDataShard { .. }
+            | ReedSolomonError::SingularMatrix
+            | ReedSolomonError::InvalidSizePrefix
+            | ReedSolomonError::DigestMismatch
+            | ReedSolomonError::TooManyErrors { .. }
+            | ReedSolomonError::DecompressionFailed(_)
+            | ReedSolomonError::AuthenticationFailed
+            | ReedSolomonError::MissingKey
+            | ReedSolomonError::NotEncrypted
+            | ReedSolomonError::MissingMacKey => RsStatus::DecodingError,
+            ReedSolomonError::InvalidHeader(_) | ReedSolomonError::InvalidArmor(_) => {
+                RsStatus::InvalidHeader
+            }
+            ReedSolomonError::InvalidManifest(_) => RsStatus::InvalidManifest,
+            ReedSolomonError::InvalidFileName(_) | ReedSolomonError::Io(_) => RsStatus::Io,
+            #[cfg(feature = "remote")]
+            ReedSolomonError::ObjectStore(_) => RsStatus::Io,
+            #[cfg(feature = "http")]
+            ReedSolomonError::Http(_) => RsStatus::Io,
+        }
+    }
+}
+
+/// Creates a codec, returning null if the shard counts are invalid.
+/// Release it with `rs_codec_free`.
+#[no_mangle]
+pub extern "C" fn rs_codec_new(data_shards: usize, parity_shards: usize) -> *mut RsCodec {
+    match ReedSolomonCodec::new(data_shards, parity_shards) {
+        Ok(codec) => Box::into_raw(Box::new(RsCodec(codec))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `codec` must be null or a pointer returned by `rs_codec_new` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rs_codec_free(codec: *mut RsCodec) {
+    if !codec.is_null() {
+        drop(Box::from_raw(codec));
+    }
+}
+
+/// Encodes `data_len` bytes into a container. On success `*out`/`*out_len`
+/// receive a buffer that must be released with `rs_free`.
+///
+/// # Safety
+///
+/// `codec` must come from `rs_codec_new`, `data` must be valid for reads of
+/// `data_len` bytes, and `out`/`out_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rs_encode(
+    codec: *const RsCodec,
+    data: *const u8,
+    data_len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> RsStatus {
+    call(
+        codec,
+        data,
+        data_len,
+        out,
+        out_len,
+        ReedSolomonCodec::encode,
+    )
+}
+
+/// Decodes a container produced by `rs_encode`. On success `*out`/`*out_len`
+/// receive a buffer that must be released with `rs_free`.
+///
+/// # Safety
+///
+/// Same requirements as `rs_encode`.
+#[no_mangle]
+pub unsafe extern "C" fn rs_decode(
+    codec: *const RsCodec,
+    data: *const u8,
+    data_len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> RsStatus {
+    call(
+        codec,
+        data,
+        data_len,
+        out,
+        out_len,
+        ReedSolomonCodec::decode,
+    )
+}
+
+/// # Safety
+///
+/// `buffer` must be null or a buffer returned by `rs_encode`/`rs_decode`
+/// together with its exact `len`, and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn rs_free(buffer: *mut u8, len: usize) {
+    if !buffer.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, len)));
+    }
+}
+
+unsafe fn call(
+    codec: *const RsCodec,
+    data: *const u8,
+    data_len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+    operation: fn(&ReedSolomonCodec, &[u8]) -> Result<Vec<u8>, ReedSolomonError>,
+) -> RsStatus {
+    if codec.is_null() || out.is_null() || out_len.is_null() || (data.is_null() && data_len > 0) {
+        return RsStatus::NullPointer;
+    }
+
+    let input: &[u8] = if data_len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, data_len)
+    };
+
+    match operation(&(*codec).0, input) {
+        Ok(output) => {
+            let output: Box<[u8]> = output.into_boxed_slice();
+            *out_len = output.len();
+            *out = Box::into_raw(output) as *mut u8;
+            RsStatus::Ok
+        }
+        Err(e) => RsStatus::from(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        let original_data: &[u8] = b"called from C";
+
+        unsafe {
+            let codec: *mut RsCodec = rs_codec_new(4, 2);
+            assert!(!codec.is_null());
+
+            let mut encoded: *mut u8 = ptr::null_mut();
+            let mut encoded_len: usize = 0;
+            let status: RsStatus = rs_encode(
+                codec,
+                original_data.as_ptr(),
+                original_data.len(),
+                &mut encoded,
+                &mut encoded_len,
+            );
+            assert_eq!(status, RsStatus::Ok);
+
+            let mut decoded: *mut u8 = ptr::null_mut();
+            let mut decoded_len: usize = 0;
+            let status: RsStatus =
+                rs_decode(codec, encoded, encoded_len, &mut decoded, &mut decoded_len);
+            assert_eq!(status, RsStatus::Ok);
+            assert_eq!(slice::from_raw_parts(decoded, decoded_len), original_data);
+
+            rs_free(encoded, encoded_len);
+            rs_free(decoded, decoded_len);
+            rs_codec_free(codec);
+        }
+    }
+
+    #[test]
+    fn test_ffi_reports_errors() {
+        assert!(rs_codec_new(0, 2).is_null());
+
+        unsafe {
+            let codec: *mut RsCodec = rs_codec_new(4, 2);
+            let mut out: *mut u8 = ptr::null_mut();
+            let mut out_len: usize = 0;
+
+            assert_eq!(
+                rs_decode(codec, b"garbage".as_ptr(), 7, &mut out, &mut out_len),
+                RsStatus::InvalidHeader
+            );
+            assert_eq!(
+                rs_encode(codec, ptr::null(), 4, &mut out, &mut out_len),
+                RsStatus::NullPointer
+            );
+            assert!(out.is_null());
+
+            rs_codec_free(codec);
+        }
+    }
+}
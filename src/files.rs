@@ -0,0 +1,1278 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::bundle::BUNDLE_FILE_KEY;
+use crate::codec::ReedSolomonCodec;
+use crate::config::{EncoderConfig, ErasureCode, GaloisField, ShardLayout};
+use crate::error::{ManifestError, ReedSolomonError};
+#[cfg(feature = "json")]
+use crate::json_manifest::{json_manifest_path, JsonManifest};
+use crate::placement::PlacementPolicy;
+use crate::platform::write_replacing;
+use crate::polynomial::DEFAULT_POLYNOMIAL;
+use crate::processor::{DataProcessor, CHECKSUM_SIZE, LEGACY_PREFIX_SIZE, PREFIX_SIZE};
+use crate::shard::Shard;
+use crate::sparse::SparseDigest;
+#[cfg(feature = "json")]
+use crate::store::repair_shards;
+use crate::store::{
+    decode_from_shard_store, repair_shard_store, FileStore, FileWriter, ShardWriter,
+};
+use crate::stream::read_full;
+
+pub const MANIFEST_VERSION: u32 = 2;
+pub const LEGACY_MANIFEST_VERSION: u32 = 1;
+pub const MANIFEST_EXTENSION: &str = "manifest";
+pub const SHARD_EXTENSION: &str = "rssh";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "ShardManifestFields")
+)]
+pub struct ShardManifest {
+    pub version: u32,
+    pub field: GaloisField,
+    pub polynomial: Option<u16>,
+    pub layout: ShardLayout,
+    pub erasure_code: ErasureCode,
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    pub block_size: u64,
+    pub payload_len: u64,
+    /// Counts the times the set has been written under this basename. The
+    /// shard file names carry it, so shards left from an earlier
+    /// generation are never read as part of this one.
+    pub generation: u64,
+    /// Shards are zero-padded up to a multiple of this many bytes.
+    pub shard_alignment: usize,
+    /// Set when the payload is the data regions of a sparse file, which
+    /// only `decode_sparse_from_files` restores, with the map it needs.
+    pub sparse: Option<SparseDigest>,
+}
+
+/// The fields of a deserialized `ShardManifest`, checked like a parsed one
+/// before they are used.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ShardManifestFields {
+    version: u32,
+    field: GaloisField,
+    #[serde(default = "default_polynomial")]
+    polynomial: Option<u16>,
+    #[serde(default)]
+    layout: ShardLayout,
+    #[serde(default)]
+    erasure_code: ErasureCode,
+    data_shards: usize,
+    parity_shards: usize,
+    block_size: u64,
+    payload_len: u64,
+    #[serde(default)]
+    generation: u64,
+    #[serde(default = "default_shard_alignment")]
+    shard_alignment: usize,
+    #[serde(default)]
+    sparse: Option<SparseDigest>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ShardManifestFields> for ShardManifest {
+    type Error = ReedSolomonError;
+
+    fn try_from(fields: ShardManifestFields) -> Result<Self, Self::Error> {
+        Self {
+            version: fields.version,
+            field: fields.field,
+            polynomial: fields.polynomial,
+            layout: fields.layout,
+            erasure_code: fields.erasure_code,
+            data_shards: fields.data_shards,
+            parity_shards: fields.parity_shards,
+            block_size: fields.block_size,
+            payload_len: fields.payload_len,
+            generation: fields.generation,
+            shard_alignment: fields.shard_alignment,
+            sparse: fields.sparse,
+        }
+        .validate()
+    }
+}
+
+impl ShardManifest {
+    pub fn config(&self) -> Result<EncoderConfig, ReedSolomonError> {
+        let config: EncoderConfig =
+            EncoderConfig::with_field(self.data_shards, self.parity_shards, self.field)?;
+        let config: EncoderConfig = match self.polynomial {
+            Some(polynomial) => config.with_polynomial(polynomial)?,
+            None => config,
+        };
+        config
+            .with_layout(self.layout)?
+            .with_erasure_code(self.erasure_code)?
+            .with_shard_alignment(self.shard_alignment)
+    }
+
+    pub fn block_count(&self) -> u64 {
+        self.payload_len.div_ceil(self.block_size)
+    }
+
+    pub fn block_len(&self, block: u64) -> usize {
+        (self.payload_len - block * self.block_size).min(self.block_size) as usize
+    }
+
+    pub fn prefix_size(&self) -> usize {
+        if self.version == LEGACY_MANIFEST_VERSION {
+            LEGACY_PREFIX_SIZE
+        } else {
+            PREFIX_SIZE
+        }
+    }
+
+    pub fn shard_size(&self, block: u64) -> usize {
+        (DataProcessor::shard_size(
+            self.block_len(block),
+            self.prefix_size(),
+            self.data_shards,
+            self.field
+                .element_size()
+                .max(self.erasure_code.element_size()),
+        ) + CHECKSUM_SIZE)
+            .next_multiple_of(self.shard_alignment)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text: String = format!(
+            "version={}\ndata_shards={}\nparity_shards={}\nblock_size={}\npayload_len={}\n",
+            self.version, self.data_shards, self.parity_shards, self.block_size, self.payload_len
+        );
+        if self.field != GaloisField::Gf8 {
+            text.push_str(&format!("field={}\n", self.field.bits()));
+        }
+        if let Some(polynomial) = self
+            .polynomial
+            .filter(|polynomial: &u16| *polynomial != DEFAULT_POLYNOMIAL)
+        {
+            text.push_str(&format!("polynomial={}\n", polynomial));
+        }
+        if self.layout != ShardLayout::Systematic {
+            text.push_str(&format!("layout={}\n", self.layout.id()));
+        }
+        if self.erasure_code != ErasureCode::Matrix {
+            text.push_str(&format!("erasure_code={}\n", self.erasure_code.id()));
+        }
+        if self.generation != 0 {
+            text.push_str(&format!("generation={}\n", self.generation));
+        }
+        if self.shard_alignment != 1 {
+            text.push_str(&format!("shard_alignment={}\n", self.shard_alignment));
+        }
+        if let Some(sparse) = self.sparse {
+            text.push_str(&format!(
+                "sparse_len={}\nsparse_map={}\n",
+                sparse.file_len, sparse.map_checksum
+            ));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReedSolomonError> {
+        let mut version: Option<u64> = None;
+        let mut field: Option<u64> = None;
+        let mut polynomial: Option<u64> = None;
+        let mut layout: Option<u64> = None;
+        let mut erasure_code: Option<u64> = None;
+        let mut data_shards: Option<u64> = None;
+        let mut parity_shards: Option<u64> = None;
+        let mut block_size: Option<u64> = None;
+        let mut payload_len: Option<u64> = None;
+        let mut generation: Option<u64> = None;
+        let mut shard_alignment: Option<u64> = None;
+        let mut sparse_len: Option<u64> = None;
+        let mut sparse_map: Option<u64> = None;
+
+        for line in text
+            .lines()
+            .map(str::trim)
+            .filter(|line: &&str| !line.is_empty())
+        {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ManifestError::MalformedLine(line.to_string()))?;
+            // Bundled files are read by `BundleIndex::parse`.
+            if key.trim() == BUNDLE_FILE_KEY {
+                continue;
+            }
+            let value: u64 = value
+                .trim()
+                .parse()
+                .map_err(|_| ManifestError::InvalidValue(key.trim().to_string()))?;
+
+            let slot: &mut Option<u64> = match key.trim() {
+                "version" => &mut version,
+                "field" => &mut field,
+                "polynomial" => &mut polynomial,
+                "layout" => &mut layout,
+                "erasure_code" => &mut erasure_code,
+                "data_shards" => &mut data_shards,
+                "parity_shards" => &mut parity_shards,
+                "block_size" => &mut block_size,
+                "payload_len" => &mut payload_len,
+                "generation" => &mut generation,
+                "shard_alignment" => &mut shard_alignment,
+                "sparse_len" => &mut sparse_len,
+                "sparse_map" => &mut sparse_map,
+                other => return Err(ManifestError::UnknownKey(other.to_string()).into()),
+            };
+            *slot = Some(value);
+        }
+
+        let required =
+            |value: Option<u64>, name: &'static str| value.ok_or(ManifestError::MissingKey(name));
+
+        let version: u64 = required(version, "version")?;
+        if !(LEGACY_MANIFEST_VERSION as u64..=MANIFEST_VERSION as u64).contains(&version) {
+            return Err(ManifestError::UnsupportedVersion(version).into());
+        }
+
+        let field: GaloisField = match field {
+            None => GaloisField::Gf8,
+            Some(bits) => u8::try_from(bits)
+                .ok()
+                .and_then(GaloisField::from_bits)
+                .ok_or(ManifestError::UnsupportedField(bits))?,
+        };
+
+        let polynomial: Option<u16> = match (field, polynomial) {
+            (GaloisField::Gf8, None) => Some(DEFAULT_POLYNOMIAL),
+            (GaloisField::Gf8, Some(polynomial)) => Some(
+                u16::try_from(polynomial)
+                    .map_err(|_| ManifestError::InvalidValue("polynomial".to_string()))?,
+            ),
+            (GaloisField::Gf16, None) => None,
+            (GaloisField::Gf16, Some(_)) => {
+                return Err(ManifestError::InvalidValue("polynomial".to_string()).into())
+            }
+        };
+
+        let layout: ShardLayout = match layout {
+            None => ShardLayout::Systematic,
+            Some(id) => u8::try_from(id)
+                .ok()
+                .and_then(ShardLayout::from_id)
+                .ok_or_else(|| ManifestError::InvalidValue("layout".to_string()))?,
+        };
+
+        let erasure_code: ErasureCode = match erasure_code {
+            None => ErasureCode::Matrix,
+            Some(id) => u8::try_from(id)
+                .ok()
+                .and_then(ErasureCode::from_id)
+                .ok_or_else(|| ManifestError::InvalidValue("erasure_code".to_string()))?,
+        };
+
+        let sparse: Option<SparseDigest> = match (sparse_len, sparse_map) {
+            (None, None) => None,
+            (file_len, map_checksum) => Some(SparseDigest {
+                file_len: required(file_len, "sparse_len")?,
+                map_checksum: u32::try_from(required(map_checksum, "sparse_map")?)
+                    .map_err(|_| ManifestError::InvalidValue("sparse_map".to_string()))?,
+            }),
+        };
+
+        let manifest: ShardManifest = Self {
+            version: version as u32,
+            field,
+            polynomial,
+            layout,
+            erasure_code,
+            data_shards: required(data_shards, "data_shards")? as usize,
+            parity_shards: required(parity_shards, "parity_shards")? as usize,
+            block_size: required(block_size, "block_size")?,
+            payload_len: required(payload_len, "payload_len")?,
+            generation: generation.unwrap_or(0),
+            shard_alignment: usize::try_from(shard_alignment.unwrap_or(1))
+                .map_err(|_| ManifestError::InvalidValue("shard_alignment".to_string()))?,
+            sparse,
+        };
+
+        manifest.validate()
+    }
+
+    /// Rejects what the block and shard size arithmetic cannot handle: an
+    /// unknown version, a zero block size, or a configuration the codec
+    /// would refuse, such as an alignment that is not a power of two.
+    fn validate(self) -> Result<Self, ReedSolomonError> {
+        if !(LEGACY_MANIFEST_VERSION..=MANIFEST_VERSION).contains(&self.version) {
+            return Err(ManifestError::UnsupportedVersion(self.version as u64).into());
+        }
+        if self.block_size == 0 {
+            return Err(ManifestError::ZeroBlockSize.into());
+        }
+        self.config()?;
+        Ok(self)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReedSolomonError> {
+        write_replacing(path, self.to_text().as_bytes())?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ReedSolomonError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn shard_path(&self, dir: &Path, basename: &str, index: usize) -> PathBuf {
+        dir.join(
+            ShardFileName {
+                basename: basename.to_string(),
+                data_shards: self.data_shards,
+                parity_shards: self.parity_shards,
+                generation: self.generation,
+                index,
+            }
+            .to_string(),
+        )
+    }
+}
+
+/// A shard file name that describes itself: `archive.tar.k10m4.s03.rssh`
+/// is shard 3 of `archive.tar`, encoded with 10 data and 4 parity shards.
+/// Sets written again under the same basename add their generation, as
+/// in `archive.tar.k10m4.g2.s03.rssh`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShardFileName {
+    pub basename: String,
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    pub generation: u64,
+    pub index: usize,
+}
+
+impl ShardFileName {
+    pub fn parse(name: &str) -> Option<Self> {
+        let name: &str = name.strip_suffix(SHARD_EXTENSION)?.strip_suffix('.')?;
+        let (name, index) = name.rsplit_once('.')?;
+        let (rest, segment) = name.rsplit_once('.')?;
+        let (name, generation): (&str, u64) = match segment.strip_prefix('g') {
+            Some(digits) => (
+                rest,
+                parse_count(digits).filter(|g: &usize| *g != 0)? as u64,
+            ),
+            None => (name, 0),
+        };
+        let (basename, counts) = name.rsplit_once('.')?;
+        let (data_shards, parity_shards) = counts.strip_prefix('k')?.split_once('m')?;
+
+        let parsed: ShardFileName = Self {
+            basename: basename.to_string(),
+            data_shards: parse_count(data_shards)?,
+            parity_shards: parse_count(parity_shards)?,
+            generation,
+            index: parse_count(index.strip_prefix('s')?)?,
+        };
+        (!basename.is_empty() && parsed.index < parsed.data_shards + parsed.parity_shards)
+            .then_some(parsed)
+    }
+}
+
+impl fmt::Display for ShardFileName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.k{}m{}",
+            self.basename, self.data_shards, self.parity_shards
+        )?;
+        if self.generation != 0 {
+            write!(f, ".g{}", self.generation)?;
+        }
+        write!(f, ".s{:02}.{}", self.index, SHARD_EXTENSION)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_polynomial() -> Option<u16> {
+    Some(DEFAULT_POLYNOMIAL)
+}
+
+#[cfg(feature = "serde")]
+fn default_shard_alignment() -> usize {
+    1
+}
+
+fn parse_count(digits: &str) -> Option<usize> {
+    if digits.is_empty() || !digits.bytes().all(|b: u8| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// The `basename.shard.NNN` name used before shard file names carried the
+/// shard counts. Such files are still found when reading.
+pub fn legacy_shard_path(dir: &Path, basename: &str, index: usize) -> PathBuf {
+    dir.join(format!("{}.shard.{:03}", basename, index))
+}
+
+pub fn manifest_path(dir: &Path, basename: &str) -> PathBuf {
+    dir.join(format!("{}.{}", basename, MANIFEST_EXTENSION))
+}
+
+/// Where each shard of the set lives, falling back to the legacy name for
+/// shards of the first generation that only exist under it.
+pub fn shard_paths(dir: &Path, basename: &str, manifest: &ShardManifest) -> Vec<PathBuf> {
+    (0..manifest.data_shards + manifest.parity_shards)
+        .map(|i: usize| {
+            let path: PathBuf = manifest.shard_path(dir, basename, i);
+            let legacy: PathBuf = legacy_shard_path(dir, basename, i);
+            if manifest.generation == 0 && !path.exists() && legacy.exists() {
+                legacy
+            } else {
+                path
+            }
+        })
+        .collect()
+}
+
+/// A set of shard files found by `find_shard_sets`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardSet {
+    pub basename: String,
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    pub generation: u64,
+    /// `None` when no manifest for the set was found, in which case it
+    /// cannot be decoded.
+    pub manifest: Option<ShardManifest>,
+    pub paths: Vec<Option<PathBuf>>,
+    /// Whether these are leftover shards of an earlier generation or shard
+    /// counts: they have no manifest, but their basename does.
+    pub stale: bool,
+}
+
+impl ShardSet {
+    pub fn present(&self) -> Vec<usize> {
+        (0..self.paths.len())
+            .filter(|i: &usize| self.paths[*i].is_some())
+            .collect()
+    }
+
+    pub fn missing(&self) -> Vec<usize> {
+        (0..self.paths.len())
+            .filter(|i: &usize| self.paths[*i].is_none())
+            .collect()
+    }
+}
+
+/// Scans `dir` for shard sets: every manifest together with its shard
+/// files, plus any self-describing shard files whose manifest is gone.
+/// Sets are sorted by basename.
+pub fn find_shard_sets(dir: &Path) -> Result<Vec<ShardSet>, ReedSolomonError> {
+    let mut sets: BTreeMap<(String, usize, usize, u64), ShardSet> = BTreeMap::new();
+    let mut names: Vec<String> = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        if let Some(name) = entry?.file_name().to_str() {
+            names.push(name.to_string());
+        }
+    }
+
+    for name in &names {
+        let Some(basename) = name.strip_suffix(&format!(".{}", MANIFEST_EXTENSION)) else {
+            continue;
+        };
+        let manifest: ShardManifest = ShardManifest::read(&dir.join(name))?;
+        let paths: Vec<Option<PathBuf>> = shard_paths(dir, basename, &manifest)
+            .into_iter()
+            .map(|path: PathBuf| path.exists().then_some(path))
+            .collect();
+        sets.insert(
+            (
+                basename.to_string(),
+                manifest.data_shards,
+                manifest.parity_shards,
+                manifest.generation,
+            ),
+            ShardSet {
+                basename: basename.to_string(),
+                data_shards: manifest.data_shards,
+                parity_shards: manifest.parity_shards,
+                generation: manifest.generation,
+                manifest: Some(manifest),
+                paths,
+                stale: false,
+            },
+        );
+    }
+
+    for name in &names {
+        let Some(shard) = ShardFileName::parse(name) else {
+            continue;
+        };
+        let set: &mut ShardSet = sets
+            .entry((
+                shard.basename.clone(),
+                shard.data_shards,
+                shard.parity_shards,
+                shard.generation,
+            ))
+            .or_insert_with(|| ShardSet {
+                basename: shard.basename.clone(),
+                data_shards: shard.data_shards,
+                parity_shards: shard.parity_shards,
+                generation: shard.generation,
+                manifest: None,
+                paths: vec![None; shard.data_shards + shard.parity_shards],
+                stale: false,
+            });
+        set.paths[shard.index] = Some(dir.join(name));
+    }
+
+    let mut sets: Vec<ShardSet> = sets.into_values().collect();
+    let current: Vec<String> = sets
+        .iter()
+        .filter(|set: &&ShardSet| set.manifest.is_some())
+        .map(|set: &ShardSet| set.basename.clone())
+        .collect();
+    for set in sets.iter_mut() {
+        set.stale = set.manifest.is_none() && current.contains(&set.basename);
+    }
+    Ok(sets)
+}
+
+/// Decodes the only shard set in `dir` that has a manifest, whatever
+/// its basename.
+pub fn decode_from_dir<W: Write>(dir: &Path, writer: W) -> Result<u64, ReedSolomonError> {
+    let mut sets: Vec<(String, ShardManifest)> = find_shard_sets(dir)?
+        .into_iter()
+        .filter_map(|set: ShardSet| {
+            set.manifest
+                .map(|manifest: ShardManifest| (set.basename, manifest))
+        })
+        .collect();
+    if sets.len() > 1 {
+        let basenames: Vec<&str> = sets
+            .iter()
+            .map(|(basename, _): &(String, ShardManifest)| basename.as_str())
+            .collect();
+        return Err(ManifestError::AmbiguousShardSet(basenames.join(", ")).into());
+    }
+    let (basename, manifest): (String, ShardManifest) =
+        sets.pop().ok_or(ManifestError::NoShardSet)?;
+
+    decode_shard_files(
+        ReedSolomonCodec::with_config(manifest.config()?)?,
+        &manifest,
+        &shard_paths(dir, &basename, &manifest),
+        writer,
+    )
+}
+
+impl ReedSolomonCodec {
+    pub(crate) fn shard_manifest(&self, payload_len: u64) -> ShardManifest {
+        ShardManifest {
+            version: MANIFEST_VERSION,
+            field: self.config().field(),
+            polynomial: self.config().polynomial(),
+            layout: self.config().layout(),
+            erasure_code: self.config().erasure_code(),
+            data_shards: self.config().data_shards(),
+            parity_shards: self.config().parity_shards(),
+            block_size: self.config().block_size() as u64,
+            payload_len,
+            generation: 0,
+            shard_alignment: self.config().shard_alignment(),
+            sparse: None,
+        }
+    }
+
+    pub fn encode_to_files<R: Read>(
+        &self,
+        reader: R,
+        dir: &Path,
+        basename: &str,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        self.encode_to_file_store(reader, &FileStore::new(dir), basename)
+    }
+
+    /// Encodes like `encode_to_files` into the directory of `store`, with
+    /// the I/O options it was built with.
+    pub fn encode_to_file_store<R: Read>(
+        &self,
+        reader: R,
+        store: &FileStore,
+        basename: &str,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        self.encode_to_file_store_with(reader, store, basename, None)
+    }
+
+    /// Encodes like `encode_to_file_store`, recording in the manifests the
+    /// sparse map the payload was read through.
+    pub(crate) fn encode_to_file_store_with<R: Read>(
+        &self,
+        reader: R,
+        store: &FileStore,
+        basename: &str,
+        sparse: Option<SparseDigest>,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        let manifest: ShardManifest =
+            self.encode_to_shard_store_with(reader, store, basename, sparse)?;
+        #[cfg(feature = "json")]
+        JsonManifest::describe(self.config(), &manifest, store.dir(), basename)?
+            .write(&json_manifest_path(store.dir(), basename))?;
+
+        Ok(manifest)
+    }
+
+    /// Encodes like `encode_to_files`, but writes each shard file to the
+    /// directory `policy` picks for it from `targets`, and the manifest to
+    /// every target, so any surviving target can start a decode.
+    pub fn encode_to_placed_files<R: Read>(
+        &self,
+        reader: R,
+        targets: &[PathBuf],
+        basename: &str,
+        policy: &dyn PlacementPolicy,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        for target in targets {
+            fs::create_dir_all(target)?;
+        }
+        let previous: Option<ShardManifest> = targets.iter().find_map(|target: &PathBuf| {
+            ShardManifest::read(&manifest_path(target, basename)).ok()
+        });
+        let generation: u64 = previous.map_or(0, |previous: ShardManifest| previous.generation + 1);
+        let naming: ShardManifest = ShardManifest {
+            generation,
+            ..self.shard_manifest(0)
+        };
+        let writers: Vec<Box<dyn ShardWriter>> =
+            placed_shard_paths(targets, basename, &naming, policy)?
+                .into_iter()
+                .map(
+                    |path: PathBuf| Ok(Box::new(FileWriter::create(path)?) as Box<dyn ShardWriter>),
+                )
+                .collect::<Result<_, ReedSolomonError>>()?;
+        let payload_len: u64 = self.write_shards(reader, writers)?;
+
+        let manifest: ShardManifest = ShardManifest {
+            generation,
+            ..self.shard_manifest(payload_len)
+        };
+        for target in targets {
+            manifest.write(&manifest_path(target, basename))?;
+        }
+        if let Some(previous) = previous {
+            for path in placed_shard_paths(targets, basename, &previous, policy)? {
+                match fs::remove_file(&path) {
+                    Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                        return Err(error.into())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Streams `reader` into one writer per shard and commits them all once
+    /// the last block is written, returning the payload length.
+    pub(crate) fn write_shards<R: Read>(
+        &self,
+        mut reader: R,
+        mut writers: Vec<Box<dyn ShardWriter + '_>>,
+    ) -> Result<u64, ReedSolomonError> {
+        let block_size: usize = self.config().block_size();
+
+        let mut block: Vec<u8> = vec![0u8; block_size];
+        let mut payload_len: u64 = 0;
+
+        loop {
+            let filled: usize = read_full(&mut reader, &mut block)?;
+            if filled == 0 {
+                break;
+            }
+
+            let shards: Vec<Shard> = self.encode_shards(&block[..filled])?;
+            self.throttle(self.shard_len(filled) * shards.len());
+            for (writer, shard) in writers.iter_mut().zip(&shards) {
+                writer.write_all(&shard.payload)?;
+            }
+            payload_len += filled as u64;
+
+            if filled < block_size {
+                break;
+            }
+        }
+
+        for writer in writers {
+            writer.commit()?;
+        }
+        Ok(payload_len)
+    }
+}
+
+pub fn decode_from_files<W: Write>(
+    dir: &Path,
+    basename: &str,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    decode_from_shard_store(&FileStore::new(dir), basename, writer)
+}
+
+/// Where each shard of a set written by `encode_to_placed_files` lives.
+pub fn placed_shard_paths(
+    targets: &[PathBuf],
+    basename: &str,
+    manifest: &ShardManifest,
+    policy: &dyn PlacementPolicy,
+) -> Result<Vec<PathBuf>, ReedSolomonError> {
+    let total_shards: usize = manifest.data_shards + manifest.parity_shards;
+    policy
+        .plan(total_shards)
+        .into_iter()
+        .enumerate()
+        .map(|(index, target): (usize, usize)| {
+            let dir: &PathBuf =
+                targets
+                    .get(target)
+                    .ok_or(ReedSolomonError::ShardIndexOutOfRange {
+                        index: target,
+                        total: targets.len(),
+                    })?;
+            Ok(manifest.shard_path(dir, basename, index))
+        })
+        .collect()
+}
+
+/// Decodes a set written by `encode_to_placed_files`, reading the manifest
+/// from the first target that still has one.
+pub fn decode_from_placed_files<W: Write>(
+    targets: &[PathBuf],
+    basename: &str,
+    policy: &dyn PlacementPolicy,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let manifest: ShardManifest = targets
+        .iter()
+        .find_map(|target: &PathBuf| ShardManifest::read(&manifest_path(target, basename)).ok())
+        .ok_or(ManifestError::NoShardSet)?;
+    decode_shard_files(
+        ReedSolomonCodec::with_config(manifest.config()?)?,
+        &manifest,
+        &placed_shard_paths(targets, basename, &manifest, policy)?,
+        writer,
+    )
+}
+
+pub(crate) fn decode_shard_files<W: Write>(
+    codec: ReedSolomonCodec,
+    manifest: &ShardManifest,
+    paths: &[PathBuf],
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    if manifest.sparse.is_some() {
+        return Err(ManifestError::SparseFile.into());
+    }
+    let readers: Vec<Option<Box<dyn Read>>> = paths
+        .iter()
+        .map(|path: &PathBuf| {
+            File::open(path)
+                .ok()
+                .map(|file: File| Box::new(BufReader::new(file)) as Box<dyn Read>)
+        })
+        .collect();
+    decode_shard_readers(ShardFileReader::new(codec, manifest, readers), writer)
+}
+
+pub(crate) fn decode_shard_readers<W: Write>(
+    mut reader: ShardFileReader,
+    mut writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let mut written: u64 = 0;
+    while let Some(decoded) = reader.next_block()? {
+        writer.write_all(&decoded)?;
+        written += decoded.len() as u64;
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Reads the payload of a shard set back, decoding one block at a time.
+pub(crate) struct ShardFileReader<'a> {
+    codec: ReedSolomonCodec,
+    manifest: ShardManifest,
+    readers: Vec<Option<Box<dyn Read + 'a>>>,
+    block: u64,
+    decoded: Vec<u8>,
+    position: usize,
+}
+
+impl<'a> ShardFileReader<'a> {
+    /// Takes one reader per shard, `None` for shards that are missing.
+    pub(crate) fn new(
+        codec: ReedSolomonCodec,
+        manifest: &ShardManifest,
+        readers: Vec<Option<Box<dyn Read + 'a>>>,
+    ) -> Self {
+        Self {
+            codec: codec.with_prefix_size(manifest.prefix_size()),
+            manifest: *manifest,
+            readers,
+            block: 0,
+            decoded: Vec::new(),
+            position: 0,
+        }
+    }
+
+    fn next_block(&mut self) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        if self.block == self.manifest.block_count() {
+            return Ok(None);
+        }
+
+        let shard_size: usize = self.manifest.shard_size(self.block);
+        let mut shards: Vec<Option<Vec<u8>>> = Vec::with_capacity(self.readers.len());
+        for reader in self.readers.iter_mut() {
+            let shard: Option<Vec<u8>> = match reader {
+                Some(file) => {
+                    let mut shard: Vec<u8> = vec![0u8; shard_size];
+                    let filled: usize = read_full(file, &mut shard)?;
+                    (filled == shard_size).then_some(shard)
+                }
+                None => None,
+            };
+            shards.push(shard);
+        }
+
+        let decoded: Vec<u8> = self.codec.decode_positional(shards)?;
+        self.codec.throttle(decoded.len());
+        self.block += 1;
+        Ok(Some(decoded))
+    }
+}
+
+impl Read for ShardFileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() {
+            match self.next_block()? {
+                Some(decoded) => {
+                    self.decoded = decoded;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let len: usize = buf.len().min(self.decoded.len() - self.position);
+        buf[..len].copy_from_slice(&self.decoded[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl ReedSolomonCodec {
+    /// Re-encodes the shard set `basename` in `dir` with this codec's
+    /// configuration, streaming one block at a time so only a block of the
+    /// old and of the new encoding is held in memory. The new shard files
+    /// are written next to the old ones, the manifest is replaced once they
+    /// are complete, and only then are the old shard files removed, so a
+    /// failure part way leaves the old set readable. The new set is the
+    /// next generation, so its shard files never share a name with the old
+    /// ones. The shard counts must change.
+    pub fn reshard_files(
+        &self,
+        dir: &Path,
+        basename: &str,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        let resharded: ShardManifest = self.reshard_shard_store(&FileStore::new(dir), basename)?;
+        #[cfg(feature = "json")]
+        JsonManifest::describe(self.config(), &resharded, dir, basename)?
+            .write(&json_manifest_path(dir, basename))?;
+        Ok(resharded)
+    }
+}
+
+pub fn repair_files(dir: &Path, basename: &str) -> Result<Vec<usize>, ReedSolomonError> {
+    repair_shard_store(&FileStore::new(dir), basename)
+}
+
+#[cfg(feature = "json")]
+pub(crate) fn repair_shard_files(
+    codec: &ReedSolomonCodec,
+    manifest: &ShardManifest,
+    paths: &[PathBuf],
+) -> Result<Vec<usize>, ReedSolomonError> {
+    repair_shards(
+        codec,
+        manifest,
+        |index: usize| match File::open(&paths[index]) {
+            Ok(file) => Ok(Some(Box::new(BufReader::new(file)) as Box<dyn Read>)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        },
+        |index: usize| {
+            Ok(Box::new(FileWriter::create(paths[index].clone())?) as Box<dyn ShardWriter>)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::placement::RoundRobin;
+    use crate::test_util::temp_dir;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_manifest_roundtrip() -> Result<(), ReedSolomonError> {
+        let manifest: ShardManifest = ShardManifest {
+            version: MANIFEST_VERSION,
+            field: GaloisField::Gf8,
+            polynomial: Some(DEFAULT_POLYNOMIAL),
+            layout: ShardLayout::Systematic,
+            erasure_code: ErasureCode::Matrix,
+            data_shards: 10,
+            parity_shards: 4,
+            block_size: 4096,
+            payload_len: 10_000,
+            generation: 0,
+            shard_alignment: 1,
+            sparse: None,
+        };
+
+        assert_eq!(ShardManifest::parse(&manifest.to_text())?, manifest);
+        assert_eq!(manifest.block_count(), 3);
+        assert_eq!(manifest.block_len(2), 10_000 - 2 * 4096);
+        assert!(ShardManifest::parse("version=1\ndata_shards=10\n").is_err());
+
+        let legacy: ShardManifest = ShardManifest::parse(
+            "version=1\ndata_shards=10\nparity_shards=4\nblock_size=4096\npayload_len=10000\n",
+        )?;
+        assert_eq!(legacy.prefix_size(), LEGACY_PREFIX_SIZE);
+        assert_eq!(manifest.prefix_size(), PREFIX_SIZE);
+        assert_eq!((legacy.shard_size(0), manifest.shard_size(0)), (414, 415));
+        assert!(
+            ShardManifest::parse(&manifest.to_text().replace("version=2", "version=3")).is_err()
+        );
+
+        let hidden: ShardManifest = ShardManifest {
+            layout: ShardLayout::NonSystematic,
+            ..manifest
+        };
+        assert!(hidden.to_text().contains("layout=1"));
+        assert_eq!(ShardManifest::parse(&hidden.to_text())?, hidden);
+        assert!(ShardManifest::parse(&format!("{}layout=7\n", manifest.to_text())).is_err());
+        assert!(ShardManifest::parse(&format!("{}erasure_code=9\n", manifest.to_text())).is_err());
+
+        let custom: ShardManifest = ShardManifest {
+            polynomial: Some(0x187),
+            ..manifest
+        };
+        assert!(custom.to_text().contains("polynomial=391"));
+        assert_eq!(ShardManifest::parse(&custom.to_text())?, custom);
+        assert_eq!(custom.config()?.polynomial(), Some(0x187));
+        assert!(ShardManifest::parse(&format!("{}polynomial=283\n", manifest.to_text())).is_err());
+
+        let rewritten: ShardManifest = ShardManifest {
+            generation: 3,
+            ..manifest
+        };
+        assert!(!manifest.to_text().contains("generation"));
+        assert_eq!(ShardManifest::parse(&rewritten.to_text())?, rewritten);
+
+        let aligned: ShardManifest = ShardManifest {
+            shard_alignment: 512,
+            ..manifest
+        };
+        assert_eq!(ShardManifest::parse(&aligned.to_text())?, aligned);
+        assert_eq!(aligned.shard_size(0), 512);
+        assert_eq!(aligned.config()?.shard_alignment(), 512);
+        assert!(
+            ShardManifest::parse(&format!("{}shard_alignment=100\n", manifest.to_text())).is_err()
+        );
+
+        let sparse: ShardManifest = ShardManifest {
+            sparse: Some(SparseDigest {
+                file_len: 1 << 30,
+                map_checksum: 0xdead_beef,
+            }),
+            ..manifest
+        };
+        assert!(!manifest.to_text().contains("sparse"));
+        assert_eq!(ShardManifest::parse(&sparse.to_text())?, sparse);
+        assert!(ShardManifest::parse(&format!("{}sparse_len=100\n", manifest.to_text())).is_err());
+        assert!(
+            ShardManifest::parse(&sparse.to_text().replace("sparse_map=", "sparse_map=1")).is_err()
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_deserialized_manifest_is_validated() -> Result<(), ReedSolomonError> {
+        use crate::json_manifest::malformed_json;
+
+        let manifest: ShardManifest = ShardManifest {
+            version: MANIFEST_VERSION,
+            field: GaloisField::Gf8,
+            polynomial: Some(DEFAULT_POLYNOMIAL),
+            layout: ShardLayout::Systematic,
+            erasure_code: ErasureCode::Matrix,
+            data_shards: 4,
+            parity_shards: 2,
+            block_size: 4096,
+            payload_len: 10_000,
+            generation: 1,
+            shard_alignment: 64,
+            sparse: None,
+        };
+        let json: String = serde_json::to_string(&manifest).map_err(malformed_json)?;
+        assert_eq!(
+            serde_json::from_str::<ShardManifest>(&json).map_err(malformed_json)?,
+            manifest
+        );
+
+        // Each of these would otherwise divide by zero or overflow when
+        // sizing blocks and shards.
+        for (from, to) in [
+            ("\"block_size\":4096", "\"block_size\":0"),
+            ("\"shard_alignment\":64", "\"shard_alignment\":0"),
+            ("\"shard_alignment\":64", "\"shard_alignment\":100"),
+            ("\"data_shards\":4", "\"data_shards\":0"),
+            ("\"version\":2", "\"version\":9"),
+        ] {
+            assert!(json.contains(from));
+            assert!(serde_json::from_str::<ShardManifest>(&json.replace(from, to)).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_files_roundtrip_with_missing_shards() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("files-roundtrip");
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 253) as u8).collect();
+
+        let manifest: ShardManifest =
+            codec.encode_to_files(Cursor::new(&original_data), &dir, "data")?;
+        assert_eq!(manifest.payload_len, original_data.len() as u64);
+
+        fs::remove_file(manifest.shard_path(&dir, "data", 1))?;
+        fs::write(manifest.shard_path(&dir, "data", 4), b"truncated")?;
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_files(&dir, "data", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair_rewrites_damaged_shards() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("files-repair");
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 251) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_files(Cursor::new(&original_data), &dir, "data")?;
+
+        let originals: Vec<Vec<u8>> = (0..6)
+            .map(|i: usize| fs::read(manifest.shard_path(&dir, "data", i)))
+            .collect::<io::Result<_>>()?;
+        assert_eq!(repair_files(&dir, "data")?, Vec::<usize>::new());
+
+        fs::remove_file(manifest.shard_path(&dir, "data", 0))?;
+        let mut corrupted: Vec<u8> = originals[3].clone();
+        corrupted[100] ^= 0xff;
+        fs::write(manifest.shard_path(&dir, "data", 3), corrupted)?;
+        fs::write(manifest.shard_path(&dir, "data", 5), &originals[5][..500])?;
+
+        assert_eq!(repair_files(&dir, "data")?, vec![0, 3, 5]);
+        for (i, original) in originals.iter().enumerate() {
+            assert_eq!(&fs::read(manifest.shard_path(&dir, "data", i))?, original);
+        }
+
+        fs::remove_file(manifest.shard_path(&dir, "data", 1))?;
+        fs::remove_file(manifest.shard_path(&dir, "data", 2))?;
+        fs::remove_file(manifest.shard_path(&dir, "data", 4))?;
+        assert!(repair_files(&dir, "data").is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_placed_files_survive_a_lost_target() -> Result<(), ReedSolomonError> {
+        let root: PathBuf = temp_dir("files-placed");
+        let targets: Vec<PathBuf> = (0..3)
+            .map(|i: usize| root.join(format!("disk{}", i)))
+            .collect();
+        let policy: RoundRobin = RoundRobin::new(3);
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 233) as u8).collect();
+
+        let manifest: ShardManifest =
+            codec.encode_to_placed_files(Cursor::new(&original_data), &targets, "data", &policy)?;
+        assert!(manifest.shard_path(&targets[1], "data", 4).exists());
+        assert_eq!(policy.tolerated_failures(6, 2), 1);
+
+        fs::remove_dir_all(&targets[0])?;
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_placed_files(&targets, "data", &policy, &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_reshard_files() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("files-reshard");
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 241) as u8).collect();
+        let old: ShardManifest =
+            codec.encode_to_files(Cursor::new(&original_data), &dir, "data")?;
+        fs::remove_file(old.shard_path(&dir, "data", 2))?;
+
+        assert!(matches!(
+            codec.reshard_files(&dir, "data"),
+            Err(ReedSolomonError::UnchangedShardCounts { .. })
+        ));
+
+        let config: EncoderConfig = EncoderConfig::new(6, 3)?.with_block_size(2048)?;
+        let new: ShardManifest =
+            ReedSolomonCodec::with_config(config)?.reshard_files(&dir, "data")?;
+        assert_eq!((new.data_shards, new.parity_shards), (6, 3));
+        assert_eq!(ShardManifest::read(&manifest_path(&dir, "data"))?, new);
+        assert!((0..6).all(|i: usize| !old.shard_path(&dir, "data", i).exists()));
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_files(&dir, "data", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_starts_a_new_generation() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("files-generation");
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let first: ShardManifest =
+            codec.encode_to_files(Cursor::new(&[1u8; 3000]), &dir, "data")?;
+        let stale: Vec<u8> = fs::read(first.shard_path(&dir, "data", 0))?;
+
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 239) as u8).collect();
+        let second: ShardManifest =
+            codec.encode_to_files(Cursor::new(&original_data), &dir, "data")?;
+        assert_eq!((first.generation, second.generation), (0, 1));
+        assert!((0..6).all(|i: usize| !first.shard_path(&dir, "data", i).exists()));
+
+        fs::remove_file(second.shard_path(&dir, "data", 0))?;
+        fs::write(first.shard_path(&dir, "data", 0), stale)?;
+        let sets: Vec<ShardSet> = find_shard_sets(&dir)?;
+        assert_eq!(sets.len(), 2);
+        assert!(sets[0].stale && sets[0].generation == 0);
+        assert!(!sets[1].stale && sets[1].manifest == Some(second));
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_dir(&dir, &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_shard_file_names() {
+        let name: ShardFileName = ShardFileName {
+            basename: "archive.tar".to_string(),
+            data_shards: 10,
+            parity_shards: 4,
+            generation: 0,
+            index: 3,
+        };
+        assert_eq!(name.to_string(), "archive.tar.k10m4.s03.rssh");
+        assert_eq!(
+            ShardFileName::parse("archive.tar.k10m4.s03.rssh"),
+            Some(name.clone())
+        );
+        let rewritten: ShardFileName = ShardFileName {
+            generation: 2,
+            ..name
+        };
+        assert_eq!(rewritten.to_string(), "archive.tar.k10m4.g2.s03.rssh");
+        assert_eq!(
+            ShardFileName::parse("archive.tar.k10m4.g2.s03.rssh"),
+            Some(rewritten)
+        );
+        assert_eq!(
+            ShardFileName::parse("big.k200m100.s299.rssh").map(|name: ShardFileName| name.index),
+            Some(299)
+        );
+
+        for invalid in [
+            "archive.tar.k10m4.s14.rssh",
+            "archive.tar.k10m4.s03",
+            "archive.tar.k10m4.s+3.rssh",
+            "archive.tar.k10.s03.rssh",
+            "archive.tar.k10m4.g0.s03.rssh",
+            "archive.tar.k10m4.g.s03.rssh",
+            ".k10m4.s03.rssh",
+            "archive.tar.shard.003",
+        ] {
+            assert_eq!(ShardFileName::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_discovers_shard_sets() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("files-discover");
+        let config: EncoderConfig =
+            EncoderConfig::with_field(3, 2, GaloisField::Gf16)?.with_block_size(512)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..2000u32).map(|i: u32| (i % 247) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_files(Cursor::new(&original_data), &dir, "photos")?;
+
+        fs::remove_file(manifest.shard_path(&dir, "photos", 0))?;
+        fs::rename(
+            manifest.shard_path(&dir, "photos", 4),
+            legacy_shard_path(&dir, "photos", 4),
+        )?;
+        fs::write(dir.join("notes.k2m1.s01.rssh"), b"orphan")?;
+
+        let sets: Vec<ShardSet> = find_shard_sets(&dir)?;
+        assert_eq!(sets.len(), 2);
+        assert_eq!(
+            (sets[0].basename.as_str(), sets[0].manifest),
+            ("notes", None)
+        );
+        assert_eq!(sets[0].present(), vec![1]);
+        assert_eq!(sets[1].manifest, Some(manifest));
+        assert_eq!(sets[1].missing(), vec![0]);
+        assert_eq!(sets[1].paths[4], Some(legacy_shard_path(&dir, "photos", 4)));
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_dir(&dir, &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        codec.encode_to_files(Cursor::new(b"second set"), &dir, "other")?;
+        assert!(matches!(
+            decode_from_dir(&dir, Vec::new()),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::AmbiguousShardSet(_)
+            ))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use raptorq::{
+    Decoder, Encoder, EncodingPacket, ObjectTransmissionInformation, SourceBlockEncoder,
+};
+
+use crate::config::DATA_SIZE_LIMITS;
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::processor::{DataProcessor, CHECKSUM_SIZE};
+
+pub const FOUNTAIN_HEADER_SIZE: usize = 12;
+pub const DEFAULT_SYMBOL_SIZE: u16 = 1024;
+pub const MAX_TRANSFER_LENGTH: u64 = 946_270_874_880;
+pub const MAX_REPAIR_PACKETS: u32 = (1 << 24) - MAX_SOURCE_SYMBOLS;
+
+const MAX_SOURCE_SYMBOLS: u32 = 56_403;
+
+const SYMBOL_ALIGNMENT: u16 = 8;
+const PAYLOAD_ID_SIZE: usize = 4;
+
+pub struct FountainEncoder {
+    encoder: Encoder,
+}
+
+impl FountainEncoder {
+    pub fn new(data: &[u8], symbol_size: u16) -> Result<Self, ReedSolomonError> {
+        if symbol_size == 0 || !symbol_size.is_multiple_of(SYMBOL_ALIGNMENT) {
+            return Err(ReedSolomonError::InvalidSymbolSize(symbol_size));
+        }
+        DATA_SIZE_LIMITS.check(data.len())?;
+        if data.len() as u64 > MAX_TRANSFER_LENGTH {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: data.len(),
+                min: DATA_SIZE_LIMITS.min,
+                max: MAX_TRANSFER_LENGTH as usize,
+            });
+        }
+
+        Ok(Self {
+            encoder: Encoder::with_defaults(data, symbol_size),
+        })
+    }
+
+    pub fn header(&self) -> [u8; FOUNTAIN_HEADER_SIZE] {
+        self.encoder.get_config().serialize()
+    }
+
+    pub fn source_packets(&self) -> Vec<Vec<u8>> {
+        self.encoder
+            .get_block_encoders()
+            .iter()
+            .flat_map(SourceBlockEncoder::source_packets)
+            .map(seal_packet)
+            .collect()
+    }
+
+    /// Repair packets `start..start + count` of every source block. Each
+    /// call can continue where the last one stopped, so a sender can keep
+    /// producing fresh repair packets until the receiver has enough.
+    pub fn repair_packets(&self, start: u32, count: u32) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        let end: u64 = start as u64 + count as u64;
+        if end > MAX_REPAIR_PACKETS as u64 {
+            return Err(ReedSolomonError::ShardIndexOutOfRange {
+                index: end as usize - 1,
+                total: MAX_REPAIR_PACKETS as usize,
+            });
+        }
+
+        Ok(self
+            .encoder
+            .get_block_encoders()
+            .iter()
+            .flat_map(|block: &SourceBlockEncoder| block.repair_packets(start, count))
+            .map(seal_packet)
+            .collect())
+    }
+}
+
+pub struct FountainDecoder {
+    decoder: Decoder,
+    symbol_size: usize,
+    source_symbols: usize,
+    accepted: usize,
+    decoded: Option<Vec<u8>>,
+}
+
+impl FountainDecoder {
+    pub fn new(header: &[u8]) -> Result<Self, ReedSolomonError> {
+        let header: &[u8; FOUNTAIN_HEADER_SIZE] = header
+            .get(..FOUNTAIN_HEADER_SIZE)
+            .and_then(|header: &[u8]| header.try_into().ok())
+            .ok_or(HeaderError::Truncated {
+                needed: FOUNTAIN_HEADER_SIZE,
+                given: header.len(),
+            })?;
+
+        let config: ObjectTransmissionInformation =
+            ObjectTransmissionInformation::deserialize(header);
+        let alignment: u16 = config.symbol_alignment() as u16;
+        if !(1..=MAX_TRANSFER_LENGTH).contains(&config.transfer_length())
+            || config.source_blocks() == 0
+            || config.sub_blocks() == 0
+            || alignment == 0
+            || config.symbol_size() == 0
+            || !config.symbol_size().is_multiple_of(alignment)
+        {
+            return Err(HeaderError::InvalidTransmissionInfo.into());
+        }
+
+        let symbol_size: usize = config.symbol_size() as usize;
+        Ok(Self {
+            decoder: Decoder::new(config),
+            symbol_size,
+            source_symbols: usize::try_from(config.transfer_length().div_ceil(symbol_size as u64))
+                .map_err(|_| ReedSolomonError::SizeOverflow)?,
+            accepted: 0,
+            decoded: None,
+        })
+    }
+
+    /// Feeds one packet and reports whether the data is complete. Packets
+    /// that fail their checksum or have the wrong size are dropped, as a
+    /// lost packet would be.
+    pub fn push(&mut self, packet: &[u8]) -> bool {
+        if self.decoded.is_some() {
+            return true;
+        }
+
+        let Some(payload) = DataProcessor::verify_checksum(packet) else {
+            return false;
+        };
+        if payload.len() != PAYLOAD_ID_SIZE + self.symbol_size {
+            return false;
+        }
+
+        self.accepted += 1;
+        self.decoded = self.decoder.decode(EncodingPacket::deserialize(payload));
+        self.decoded.is_some()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.decoded.is_some()
+    }
+
+    pub fn accepted_packets(&self) -> usize {
+        self.accepted
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decoded.ok_or(ReedSolomonError::TooFewShards {
+            needed: self.source_symbols,
+            given: self.accepted,
+        })
+    }
+}
+
+pub fn decode_packets<'a, I: IntoIterator<Item = &'a [u8]>>(
+    header: &[u8],
+    packets: I,
+) -> Result<Vec<u8>, ReedSolomonError> {
+    let mut decoder: FountainDecoder = FountainDecoder::new(header)?;
+    for packet in packets {
+        if decoder.push(packet) {
+            break;
+        }
+    }
+    decoder.finish()
+}
+
+fn seal_packet(packet: EncodingPacket) -> Vec<u8> {
+    let mut sealed: Vec<u8> = packet.serialize();
+    sealed.reserve_exact(CHECKSUM_SIZE);
+    DataProcessor::append_checksum(sealed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_packets_replace_lost_source_packets() -> Result<(), ReedSolomonError> {
+        let original_data: Vec<u8> = (0..10_000u32).map(|i: u32| (i % 251) as u8).collect();
+        let encoder: FountainEncoder = FountainEncoder::new(&original_data, 512)?;
+        let header: [u8; FOUNTAIN_HEADER_SIZE] = encoder.header();
+
+        let source: Vec<Vec<u8>> = encoder.source_packets();
+        let repair: Vec<Vec<u8>> = encoder.repair_packets(0, source.len() as u32)?;
+        let mut more: Vec<Vec<u8>> = encoder.repair_packets(source.len() as u32, 4)?;
+        more[0][10] ^= 0xff;
+
+        let received: Vec<&[u8]> = source
+            .iter()
+            .step_by(2)
+            .chain(&more)
+            .chain(&repair)
+            .map(Vec::as_slice)
+            .collect();
+        assert_eq!(decode_packets(&header, received)?, original_data);
+
+        let mut decoder: FountainDecoder = FountainDecoder::new(&header)?;
+        assert!(!decoder.push(&source[0]));
+        assert!(!decoder.push(&more[0]));
+        assert_eq!(decoder.accepted_packets(), 1);
+        assert!(matches!(
+            decoder.finish(),
+            Err(ReedSolomonError::TooFewShards { given: 1, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_invalid_parameters() {
+        assert!(FountainEncoder::new(b"data", 0).is_err());
+        assert!(FountainEncoder::new(b"data", 100).is_err());
+        assert!(FountainEncoder::new(b"", 64).is_err());
+        assert!(FountainEncoder::new(b"data", 64)
+            .and_then(|encoder: FountainEncoder| encoder.repair_packets(MAX_REPAIR_PACKETS, 1))
+            .is_err());
+        assert!(FountainDecoder::new(&[0u8; FOUNTAIN_HEADER_SIZE - 1]).is_err());
+        assert!(FountainDecoder::new(&[0u8; FOUNTAIN_HEADER_SIZE]).is_err());
+    }
+}
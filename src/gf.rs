@@ -0,0 +1,133 @@
+use crate::polynomial::DEFAULT_POLYNOMIAL;
+
+/// Powers of the generator 2, repeated once so a sum of two logarithms can
+/// index it without reducing modulo 255.
+pub const EXP_TABLE: [u8; 510] = exp_table();
+
+/// Discrete logarithms to base 2. `LOG_TABLE[0]` is unused and left at 0.
+pub const LOG_TABLE: [u8; 256] = log_table();
+
+const fn exp_table() -> [u8; 510] {
+    let mut exp: [u8; 510] = [0u8; 510];
+    let mut value: u16 = 1;
+    let mut i: usize = 0;
+    while i < 255 {
+        exp[i] = value as u8;
+        exp[i + 255] = value as u8;
+        value <<= 1;
+        if value & 0x100 != 0 {
+            value ^= DEFAULT_POLYNOMIAL;
+        }
+        i += 1;
+    }
+    exp
+}
+
+const fn log_table() -> [u8; 256] {
+    let mut log: [u8; 256] = [0u8; 256];
+    let mut i: usize = 0;
+    while i < 255 {
+        log[EXP_TABLE[i] as usize] = i as u8;
+        i += 1;
+    }
+    log
+}
+
+/// Addition and subtraction are both XOR.
+pub fn add(a: u8, b: u8) -> u8 {
+    a ^ b
+}
+
+pub fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    EXP_TABLE[LOG_TABLE[a as usize] as usize + LOG_TABLE[b as usize] as usize]
+}
+
+/// Panics if `b` is zero.
+pub fn div(a: u8, b: u8) -> u8 {
+    assert!(b != 0, "division by zero in GF(2^8)");
+    if a == 0 {
+        return 0;
+    }
+    EXP_TABLE[LOG_TABLE[a as usize] as usize + 255 - LOG_TABLE[b as usize] as usize]
+}
+
+/// Panics if `a` is zero.
+pub fn inv(a: u8) -> u8 {
+    div(1, a)
+}
+
+/// The generator raised to `power`.
+pub fn exp(power: usize) -> u8 {
+    EXP_TABLE[power % 255]
+}
+
+/// The power of the generator that gives `a`, or `None` for zero.
+pub fn log(a: u8) -> Option<u8> {
+    (a != 0).then(|| LOG_TABLE[a as usize])
+}
+
+/// `base` raised to `power`, with `0^0 = 1`.
+pub fn pow(base: u8, power: usize) -> u8 {
+    if power == 0 {
+        return 1;
+    }
+    if base == 0 {
+        return 0;
+    }
+    exp(LOG_TABLE[base as usize] as usize * power)
+}
+
+/// Evaluates the polynomial whose coefficient of `x^i` is
+/// `coefficients[i]` at `x`, by Horner's rule.
+pub fn eval(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0u8, |sum: u8, coefficient: &u8| mul(sum, x) ^ coefficient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reed_solomon_erasure::galois_8;
+
+    #[test]
+    fn test_matches_backend_exhaustively() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(add(a, b), galois_8::add(a, b));
+                assert_eq!(mul(a, b), galois_8::mul(a, b));
+                if b != 0 {
+                    assert_eq!(div(a, b), galois_8::div(a, b));
+                }
+            }
+            for power in 0..300 {
+                assert_eq!(pow(a, power), galois_8::exp(a, power));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tables_and_inverses() {
+        assert_eq!(log(0), None);
+        for a in 1..=255u8 {
+            assert_eq!(exp(log(a).unwrap_or_default() as usize), a);
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+        assert_eq!(exp(255), 1);
+        assert_eq!(exp(8), (DEFAULT_POLYNOMIAL & 0xff) as u8);
+    }
+
+    #[test]
+    fn test_eval() {
+        let coefficients: [u8; 4] = [7, 0, 3, 1];
+        for x in 0..=255u8 {
+            let expected: u8 = 7 ^ mul(3, mul(x, x)) ^ pow(x, 3);
+            assert_eq!(eval(&coefficients, x), expected);
+        }
+        assert_eq!(eval(&[], 5), 0);
+    }
+}
@@ -0,0 +1,404 @@
+use alloc::vec::Vec;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::config::{
+    Compression, EncoderConfig, Encryption, ErasureCode, GaloisField, ShardLayout,
+};
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::processor::{crc32c_checksum, CHECKSUM_SIZE};
+
+pub const MAGIC: [u8; 4] = *b"RSCF";
+pub const FORMAT_VERSION: u8 = 1;
+pub const HEADER_SIZE: usize = 92;
+pub const NONCE_SIZE: usize = 24;
+pub const DIGEST_SIZE: usize = 32;
+
+const DIGEST_BLAKE3: u8 = 1;
+
+const FIELD_BITS_MASK: u8 = 0x1f;
+const NON_SYSTEMATIC_FLAG: u8 = 0x20;
+const COMPRESSION_SHIFT: u32 = 6;
+const ENCRYPTION_MASK: u8 = 0x0f;
+const ERASURE_CODE_SHIFT: u32 = 4;
+
+pub const GROUP_MAGIC: [u8; 4] = *b"RSIG";
+pub const GROUP_VERSION: u8 = 1;
+pub const GROUP_HEADER_SIZE: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContainerHeader {
+    pub version: u8,
+    pub field: GaloisField,
+    /// The GF(2^8) polynomial; `None` for GF(2^16).
+    pub polynomial: Option<u16>,
+    pub compression: Compression,
+    pub layout: ShardLayout,
+    pub erasure_code: ErasureCode,
+    pub data_shards: u16,
+    pub parity_shards: u16,
+    pub shard_size: u64,
+    pub payload_len: u64,
+    pub encryption: Encryption,
+    pub nonce: [u8; NONCE_SIZE],
+    pub digest: Option<[u8; DIGEST_SIZE]>,
+}
+
+impl ContainerHeader {
+    pub fn new(config: &EncoderConfig, shard_size: usize, payload_len: usize) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            field: config.field(),
+            polynomial: config.polynomial(),
+            compression: Compression::None,
+            layout: config.layout(),
+            erasure_code: config.erasure_code(),
+            data_shards: config.data_shards() as u16,
+            parity_shards: config.parity_shards() as u16,
+            shard_size: shard_size as u64,
+            payload_len: payload_len as u64,
+            encryption: Encryption::None,
+            nonce: [0u8; NONCE_SIZE],
+            digest: None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        HEADER_SIZE
+    }
+
+    pub fn peek_size(data: &[u8]) -> Result<usize, ReedSolomonError> {
+        if data.len() <= 4 {
+            return Err(HeaderError::Truncated {
+                needed: 5,
+                given: data.len(),
+            }
+            .into());
+        }
+
+        if data[..4] != MAGIC {
+            return Err(HeaderError::MissingMagic.into());
+        }
+
+        match data[4] {
+            FORMAT_VERSION => Ok(HEADER_SIZE),
+            version => Err(HeaderError::UnsupportedVersion(version).into()),
+        }
+    }
+
+    /// Zero bytes after the payload in the data shards, or `None` when the
+    /// shards are too small to hold it. The data shards carry no length
+    /// prefix: `payload_len` is exact.
+    pub fn padding(&self) -> Option<u64> {
+        let capacity: u64 = self
+            .shard_size
+            .checked_sub(CHECKSUM_SIZE as u64)?
+            .checked_mul(self.data_shards as u64)?;
+        capacity.checked_sub(self.payload_len)
+    }
+
+    pub fn config(&self) -> Result<EncoderConfig, ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::with_field(
+            self.data_shards as usize,
+            self.parity_shards as usize,
+            self.field,
+        )?;
+        let config: EncoderConfig = match self.polynomial {
+            Some(polynomial) => config.with_polynomial(polynomial)?,
+            None => config,
+        };
+        config
+            .with_layout(self.layout)?
+            .with_erasure_code(self.erasure_code)
+    }
+
+    pub fn matches(&self, config: &EncoderConfig) -> bool {
+        self.field == config.field()
+            && self.polynomial == config.polynomial()
+            && self.layout == config.layout()
+            && self.erasure_code == config.erasure_code()
+            && self.data_shards as usize == config.data_shards()
+            && self.parity_shards as usize == config.parity_shards()
+    }
+
+    pub fn body_len(&self) -> Option<usize> {
+        let total_shards: u64 = self.data_shards as u64 + self.parity_shards as u64;
+        usize::try_from(self.shard_size.checked_mul(total_shards)?).ok()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = alloc::vec![0u8; self.size()];
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4] = self.version;
+        bytes[5] = self.field.bits() | (self.compression.id() << COMPRESSION_SHIFT);
+        if self.layout == ShardLayout::NonSystematic {
+            bytes[5] |= NON_SYSTEMATIC_FLAG;
+        }
+        BigEndian::write_u16(&mut bytes[6..8], self.data_shards);
+        BigEndian::write_u16(&mut bytes[8..10], self.parity_shards);
+        BigEndian::write_u64(&mut bytes[10..18], self.shard_size);
+        BigEndian::write_u64(&mut bytes[18..26], self.payload_len);
+        bytes[26] = self.encryption.id() | (self.erasure_code.id() << ERASURE_CODE_SHIFT);
+        if let Some(digest) = &self.digest {
+            bytes[27] = DIGEST_BLAKE3;
+            bytes[52..52 + DIGEST_SIZE].copy_from_slice(digest);
+        }
+        bytes[28..28 + NONCE_SIZE].copy_from_slice(&self.nonce);
+        BigEndian::write_u16(&mut bytes[84..86], self.polynomial.unwrap_or(0));
+
+        let (fields, checksum) = bytes.split_at_mut(self.size() - 4);
+        BigEndian::write_u32(checksum, crc32c_checksum(fields));
+        bytes
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, ReedSolomonError> {
+        let size: usize = Self::peek_size(data)?;
+        if data.len() < size {
+            return Err(HeaderError::Truncated {
+                needed: size,
+                given: data.len(),
+            }
+            .into());
+        }
+
+        if crc32c_checksum(&data[..size - 4]) != BigEndian::read_u32(&data[size - 4..size]) {
+            return Err(HeaderError::ChecksumMismatch.into());
+        }
+
+        let version: u8 = data[4];
+        let id: u8 = data[26] & ENCRYPTION_MASK;
+        let encryption: Encryption =
+            Encryption::from_id(id).ok_or(HeaderError::UnsupportedEncryption(id))?;
+        let id: u8 = data[26] >> ERASURE_CODE_SHIFT;
+        let erasure_code: ErasureCode =
+            ErasureCode::from_id(id).ok_or(HeaderError::UnsupportedErasureCode(id))?;
+        let mut nonce: [u8; NONCE_SIZE] = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&data[28..28 + NONCE_SIZE]);
+        let digest: Option<[u8; DIGEST_SIZE]> = match data[27] {
+            0 => None,
+            DIGEST_BLAKE3 => {
+                let mut digest: [u8; DIGEST_SIZE] = [0u8; DIGEST_SIZE];
+                digest.copy_from_slice(&data[52..52 + DIGEST_SIZE]);
+                Some(digest)
+            }
+            id => return Err(HeaderError::UnsupportedDigest(id).into()),
+        };
+
+        let compression: Compression = Compression::from_id(data[5] >> COMPRESSION_SHIFT).ok_or(
+            HeaderError::UnsupportedCompression(data[5] >> COMPRESSION_SHIFT),
+        )?;
+
+        let layout: ShardLayout = if data[5] & NON_SYSTEMATIC_FLAG != 0 {
+            ShardLayout::NonSystematic
+        } else {
+            ShardLayout::Systematic
+        };
+
+        let field: GaloisField = match data[5] & FIELD_BITS_MASK {
+            0 => GaloisField::Gf8,
+            bits => GaloisField::from_bits(bits).ok_or(HeaderError::UnsupportedField(bits))?,
+        };
+        let polynomial: Option<u16> = match field {
+            GaloisField::Gf8 => Some(BigEndian::read_u16(&data[84..86])),
+            GaloisField::Gf16 => None,
+        };
+
+        Ok(Self {
+            version,
+            field,
+            polynomial,
+            compression,
+            layout,
+            erasure_code,
+            data_shards: BigEndian::read_u16(&data[6..8]),
+            parity_shards: BigEndian::read_u16(&data[8..10]),
+            shard_size: BigEndian::read_u64(&data[10..18]),
+            payload_len: BigEndian::read_u64(&data[18..26]),
+            encryption,
+            nonce,
+            digest,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupHeader {
+    pub version: u8,
+    pub block_count: u16,
+}
+
+impl GroupHeader {
+    pub fn new(block_count: usize) -> Self {
+        Self {
+            version: GROUP_VERSION,
+            block_count: block_count as u16,
+        }
+    }
+
+    pub fn headers_len(&self, header_size: usize) -> usize {
+        self.block_count as usize * header_size
+    }
+
+    pub fn to_bytes(&self) -> [u8; GROUP_HEADER_SIZE] {
+        let mut bytes: [u8; GROUP_HEADER_SIZE] = [0u8; GROUP_HEADER_SIZE];
+        bytes[..4].copy_from_slice(&GROUP_MAGIC);
+        bytes[4] = self.version;
+        BigEndian::write_u16(&mut bytes[6..8], self.block_count);
+
+        let checksum: u32 = crc32c_checksum(&bytes[..8]);
+        BigEndian::write_u32(&mut bytes[8..12], checksum);
+        bytes
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, ReedSolomonError> {
+        if data.len() < GROUP_HEADER_SIZE {
+            return Err(HeaderError::Truncated {
+                needed: GROUP_HEADER_SIZE,
+                given: data.len(),
+            }
+            .into());
+        }
+
+        if data[..4] != GROUP_MAGIC {
+            return Err(HeaderError::MissingMagic.into());
+        }
+
+        if crc32c_checksum(&data[..8]) != BigEndian::read_u32(&data[8..12]) {
+            return Err(HeaderError::ChecksumMismatch.into());
+        }
+
+        let version: u8 = data[4];
+        if version != GROUP_VERSION {
+            return Err(HeaderError::UnsupportedVersion(version).into());
+        }
+
+        let block_count: u16 = BigEndian::read_u16(&data[6..8]);
+        if block_count == 0 {
+            return Err(HeaderError::EmptyGroup.into());
+        }
+
+        Ok(Self {
+            version,
+            block_count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polynomial::DEFAULT_POLYNOMIAL;
+
+    #[test]
+    fn test_header_roundtrip() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        let header: ContainerHeader = ContainerHeader::new(&config, 128, 1000);
+
+        let parsed: ContainerHeader = ContainerHeader::read(&header.to_bytes())?;
+
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.polynomial, Some(DEFAULT_POLYNOMIAL));
+        assert_eq!(parsed.padding(), Some(124 * 10 - 1000));
+        assert!(parsed.matches(&config));
+        assert_eq!(parsed.body_len(), Some(128 * 14));
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_rejects_bad_input() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        let bytes: Vec<u8> = ContainerHeader::new(&config, 128, 1000).to_bytes();
+
+        assert!(ContainerHeader::read(&bytes[..HEADER_SIZE - 1]).is_err());
+
+        let mut bad_magic: Vec<u8> = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(ContainerHeader::read(&bad_magic).is_err());
+
+        let mut compressed: ContainerHeader = ContainerHeader::new(&config, 128, 1000);
+        compressed.compression = Compression::Zstd;
+        assert_eq!(ContainerHeader::read(&compressed.to_bytes())?, compressed);
+
+        let non_systematic: EncoderConfig = config.with_layout(ShardLayout::NonSystematic)?;
+        let mut hidden: ContainerHeader = ContainerHeader::new(&non_systematic, 128, 1000);
+        hidden.compression = Compression::Zstd;
+        let parsed: ContainerHeader = ContainerHeader::read(&hidden.to_bytes())?;
+        assert_eq!(parsed, hidden);
+        assert!(parsed.matches(&non_systematic));
+        assert!(!parsed.matches(&config));
+
+        let mut leopard: ContainerHeader = ContainerHeader::new(&config, 128, 1000);
+        leopard.erasure_code = ErasureCode::Leopard;
+        leopard.encryption = Encryption::XChaCha20Poly1305;
+        let parsed: ContainerHeader = ContainerHeader::read(&leopard.to_bytes())?;
+        assert_eq!(parsed, leopard);
+        assert!(!parsed.matches(&config));
+
+        let mut bad_field: Vec<u8> = bytes;
+        bad_field[7] ^= 0x01;
+        assert!(ContainerHeader::read(&bad_field).is_err());
+
+        let custom: EncoderConfig = config.with_polynomial(0x187)?;
+        let parsed: ContainerHeader =
+            ContainerHeader::read(&ContainerHeader::new(&custom, 128, 1000).to_bytes())?;
+        assert_eq!(parsed.polynomial, Some(0x187));
+        assert!(parsed.matches(&custom));
+        assert!(!parsed.matches(&config));
+        assert_eq!(parsed.config()?.polynomial(), Some(0x187));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extended_header_roundtrip() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        let mut header: ContainerHeader = ContainerHeader::new(&config, 128, 1000);
+        header.encryption = Encryption::XChaCha20Poly1305;
+        header.nonce = [7u8; NONCE_SIZE];
+        header.digest = Some([9u8; DIGEST_SIZE]);
+
+        let bytes: Vec<u8> = header.to_bytes();
+        assert_eq!(bytes.len(), HEADER_SIZE);
+        assert_eq!(ContainerHeader::peek_size(&bytes)?, HEADER_SIZE);
+        assert_eq!(ContainerHeader::read(&bytes)?, header);
+        assert!(ContainerHeader::read(&bytes[..HEADER_SIZE - 1]).is_err());
+
+        let mut bad_digest: Vec<u8> = bytes;
+        bad_digest[60] ^= 0x01;
+        assert!(ContainerHeader::read(&bad_digest).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_rejects_unknown_versions() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(10, 4)?;
+        let mut header: ContainerHeader = ContainerHeader::new(&config, 128, 1000);
+        header.version = FORMAT_VERSION + 1;
+        assert!(matches!(
+            ContainerHeader::read(&header.to_bytes()),
+            Err(ReedSolomonError::InvalidHeader(
+                HeaderError::UnsupportedVersion(2)
+            ))
+        ));
+
+        header.version = FORMAT_VERSION;
+        header.shard_size = 100;
+        assert_eq!(header.padding(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_header_roundtrip() -> Result<(), ReedSolomonError> {
+        let header: GroupHeader = GroupHeader::new(16);
+        let bytes: [u8; GROUP_HEADER_SIZE] = header.to_bytes();
+
+        assert_eq!(GroupHeader::read(&bytes)?, header);
+        assert_eq!(header.headers_len(HEADER_SIZE), 16 * HEADER_SIZE);
+
+        let mut corrupt: [u8; GROUP_HEADER_SIZE] = bytes;
+        corrupt[7] ^= 0x01;
+        assert!(GroupHeader::read(&corrupt).is_err());
+        assert!(GroupHeader::read(&GroupHeader::new(0).to_bytes()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use alloc::vec::Vec;
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::EncoderConfig;
+use crate::error::ReedSolomonError;
+use crate::shard::Shard;
+
+/// Two nested codes: the outer one spreads the payload across groups, such
+/// as storage nodes, and the inner one spreads each group's share across
+/// that group's shards, such as the disks of a node. Up to the outer parity
+/// of whole groups can be lost, and within every other group up to the
+/// inner parity of shards.
+#[derive(Debug, Clone, Copy)]
+pub struct HierarchicalConfig {
+    outer: EncoderConfig,
+    inner: EncoderConfig,
+}
+
+impl HierarchicalConfig {
+    pub fn new(
+        data_groups: usize,
+        parity_groups: usize,
+        data_shards: usize,
+        parity_shards: usize,
+    ) -> Result<Self, ReedSolomonError> {
+        Ok(Self::with_configs(
+            EncoderConfig::new(data_groups, parity_groups)?,
+            EncoderConfig::new(data_shards, parity_shards)?,
+        ))
+    }
+
+    /// Nests any two configurations, for instance a non-systematic outer
+    /// code so no single group holds readable data.
+    pub fn with_configs(outer: EncoderConfig, inner: EncoderConfig) -> Self {
+        Self { outer, inner }
+    }
+
+    /// The code across groups.
+    pub fn outer(&self) -> &EncoderConfig {
+        &self.outer
+    }
+
+    /// The code within each group.
+    pub fn inner(&self) -> &EncoderConfig {
+        &self.inner
+    }
+
+    pub fn total_groups(&self) -> usize {
+        self.outer.total_shards()
+    }
+
+    pub fn total_shards(&self) -> usize {
+        self.outer.total_shards() * self.inner.total_shards()
+    }
+
+    /// Stored bytes per payload byte, ignoring checksums and padding.
+    pub fn overhead(&self) -> f64 {
+        (self.outer.total_shards() as f64 / self.outer.data_shards() as f64)
+            * (self.inner.total_shards() as f64 / self.inner.data_shards() as f64)
+    }
+}
+
+#[derive(Debug)]
+pub struct HierarchicalCodec {
+    config: HierarchicalConfig,
+    outer: ReedSolomonCodec,
+    inner: ReedSolomonCodec,
+}
+
+impl HierarchicalCodec {
+    pub fn new(config: HierarchicalConfig) -> Result<Self, ReedSolomonError> {
+        Ok(Self {
+            config,
+            outer: ReedSolomonCodec::with_config(config.outer)?,
+            inner: ReedSolomonCodec::with_config(config.inner)?,
+        })
+    }
+
+    pub fn config(&self) -> &HierarchicalConfig {
+        &self.config
+    }
+
+    /// Encodes `data` into one set of inner shards per group, indexed by
+    /// group and then by shard.
+    pub fn encode_shards(&self, data: &[u8]) -> Result<Vec<Vec<Shard>>, ReedSolomonError> {
+        self.outer
+            .encode_shards(data)?
+            .iter()
+            .map(|group: &Shard| self.inner.encode_shards(&group.payload))
+            .collect()
+    }
+
+    /// Decodes the surviving shards of every group, with an empty set for
+    /// a group that was lost entirely. A group whose own shards cannot be
+    /// decoded counts as lost.
+    pub fn decode_shards(&self, groups: Vec<Vec<Shard>>) -> Result<Vec<u8>, ReedSolomonError> {
+        if groups.len() != self.config.total_groups() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.config.total_groups(),
+                given: groups.len(),
+            });
+        }
+
+        let outer: &EncoderConfig = self.config.outer();
+        let stored_data_groups: usize =
+            outer.data_shards() - outer.layout().hidden_shards(outer.data_shards());
+        let recovered: Vec<Shard> = groups
+            .into_iter()
+            .enumerate()
+            .filter(|(_, shards): &(usize, Vec<Shard>)| !shards.is_empty())
+            .filter_map(|(index, shards): (usize, Vec<Shard>)| {
+                let payload: Vec<u8> = self.inner.decode_shards(shards).ok()?;
+                Some(Shard::new(index, stored_data_groups, payload))
+            })
+            .collect();
+        self.outer.decode_shards(recovered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ShardLayout;
+
+    #[test]
+    fn test_config() -> Result<(), ReedSolomonError> {
+        let config: HierarchicalConfig = HierarchicalConfig::new(4, 2, 6, 2)?;
+        assert_eq!(config.total_groups(), 6);
+        assert_eq!(config.total_shards(), 48);
+        assert!((config.overhead() - 2.0).abs() < 1e-9);
+        assert!(HierarchicalConfig::new(4, 2, 0, 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_survives_lost_groups_and_shards() -> Result<(), ReedSolomonError> {
+        let codec: HierarchicalCodec =
+            HierarchicalCodec::new(HierarchicalConfig::new(4, 2, 6, 2)?)?;
+        let original_data: Vec<u8> = (0..10_000u32).map(|i: u32| (i % 239) as u8).collect();
+        let mut groups: Vec<Vec<Shard>> = codec.encode_shards(&original_data)?;
+        assert_eq!(groups.len(), 6);
+        assert!(groups.iter().all(|group: &Vec<Shard>| group.len() == 8));
+
+        // Two whole nodes and two disks in every other node are gone. A
+        // third lost disk in group 5 then costs that node as well.
+        groups[1].clear();
+        groups[4].clear();
+        for group in groups.iter_mut() {
+            group.retain(|shard: &Shard| shard.index != 0 && shard.index != 7);
+        }
+        assert_eq!(codec.decode_shards(groups.clone())?, original_data);
+
+        groups[5].retain(|shard: &Shard| shard.index != 3);
+        assert!(codec.decode_shards(groups).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_systematic_outer_code() -> Result<(), ReedSolomonError> {
+        let config: HierarchicalConfig = HierarchicalConfig::with_configs(
+            EncoderConfig::new(3, 2)?.with_layout(ShardLayout::NonSystematic)?,
+            EncoderConfig::new(4, 1)?,
+        );
+        let codec: HierarchicalCodec = HierarchicalCodec::new(config)?;
+        let mut groups: Vec<Vec<Shard>> = codec.encode_shards(b"no node holds plain data")?;
+        groups[0].clear();
+        groups[3].clear();
+        assert_eq!(codec.decode_shards(groups)?, b"no node holds plain data");
+        Ok(())
+    }
+}
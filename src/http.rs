@@ -0,0 +1,297 @@
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::files::{ShardFileName, ShardManifest, MANIFEST_EXTENSION};
+use crate::processor::DataProcessor;
+
+/// Read access to shard files and manifests, whole or by byte range.
+pub trait RangeSource {
+    /// `None` when nothing is stored under `key`.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError>;
+
+    /// Bytes `range` of the object under `key`, cut short if the object
+    /// ends first.
+    fn get_range(&self, key: &str, range: Range<u64>) -> Result<Option<Vec<u8>>, ReedSolomonError>;
+}
+
+/// Shard files and manifests served over HTTP(S) from one base URL, as a
+/// plain web server or a public bucket would serve the output of
+/// `encode_to_files`.
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpSource {
+    pub fn new(base_url: &str) -> Self {
+        Self::with_agent(base_url, ureq::Agent::new())
+    }
+
+    /// Uses `agent` for every request, for custom timeouts, proxies or TLS.
+    pub fn with_agent(base_url: &str, agent: ureq::Agent) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent,
+        }
+    }
+
+    pub fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+}
+
+impl RangeSource for HttpSource {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        match self.agent.get(&self.url(key)).call() {
+            Ok(response) => {
+                let mut body: Vec<u8> = Vec::new();
+                response.into_reader().read_to_end(&mut body)?;
+                Ok(Some(body))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(error) => Err(ReedSolomonError::Http(Box::new(error))),
+        }
+    }
+
+    fn get_range(&self, key: &str, range: Range<u64>) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        if range.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+        let response: ureq::Response = match self
+            .agent
+            .get(&self.url(key))
+            .set("Range", &format!("bytes={}-{}", range.start, range.end - 1))
+            .call()
+        {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(ureq::Error::Status(416, _)) => return Ok(Some(Vec::new())),
+            Err(error) => return Err(ReedSolomonError::Http(Box::new(error))),
+        };
+
+        // A server that ignores `Range` answers 200 with the whole object.
+        let skip: u64 = if response.status() == 206 {
+            0
+        } else {
+            range.start
+        };
+        let mut reader: Box<dyn Read + Send + Sync> = response.into_reader();
+        io::copy(&mut (&mut reader).take(skip), &mut io::sink())?;
+        let mut body: Vec<u8> = Vec::new();
+        reader
+            .take(range.end - range.start)
+            .read_to_end(&mut body)?;
+        Ok(Some(body))
+    }
+}
+
+/// Decodes payload bytes `range` of the shard set `basename`, fetching
+/// only the manifest and, from each shard file used, the bytes of the
+/// blocks that overlap `range`. Shard files are tried in index order until
+/// `data_shards` of them pass their checksums. The end of `range` is
+/// clamped to the payload length. Returns the number of bytes written.
+pub fn decode_range<S: RangeSource + ?Sized, W: Write>(
+    source: &S,
+    basename: &str,
+    range: Range<u64>,
+    mut writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let key: String = format!("{}.{}", basename, MANIFEST_EXTENSION);
+    let text: Vec<u8> = source
+        .get(&key)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, key))?;
+    let manifest: ShardManifest = ShardManifest::parse(&String::from_utf8_lossy(&text))?;
+    let range: Range<u64> = range.start..range.end.min(manifest.payload_len);
+    if range.is_empty() {
+        return Ok(0);
+    }
+    let codec: ReedSolomonCodec =
+        ReedSolomonCodec::with_config(manifest.config()?)?.with_prefix_size(manifest.prefix_size());
+
+    let blocks: Range<u64> =
+        range.start / manifest.block_size..(range.end - 1) / manifest.block_size + 1;
+    let full_shard_size: u64 = manifest.shard_size(0) as u64;
+    let shard_range: Range<u64> = blocks.start * full_shard_size
+        ..(blocks.end - 1) * full_shard_size + manifest.shard_size(blocks.end - 1) as u64;
+
+    let total_shards: usize = manifest.data_shards + manifest.parity_shards;
+    let mut pieces: Vec<Option<Vec<u8>>> = vec![None; total_shards];
+    let mut intact: usize = 0;
+    for (index, piece) in pieces.iter_mut().enumerate() {
+        if intact == manifest.data_shards {
+            break;
+        }
+        let name: String = ShardFileName {
+            basename: basename.to_string(),
+            data_shards: manifest.data_shards,
+            parity_shards: manifest.parity_shards,
+            generation: manifest.generation,
+            index,
+        }
+        .to_string();
+        let Some(bytes) = source.get_range(&name, shard_range.clone())? else {
+            continue;
+        };
+        if is_intact(&bytes, &manifest, blocks.clone()) {
+            intact += 1;
+        }
+        *piece = Some(bytes);
+    }
+
+    let mut offset: usize = 0;
+    let mut written: u64 = 0;
+    for block in blocks {
+        let shard_size: usize = manifest.shard_size(block);
+        let shards: Vec<Option<Vec<u8>>> = pieces
+            .iter()
+            .map(|piece: &Option<Vec<u8>>| {
+                piece
+                    .as_deref()
+                    .and_then(|piece: &[u8]| piece.get(offset..offset + shard_size))
+                    .map(<[u8]>::to_vec)
+            })
+            .collect();
+        offset += shard_size;
+
+        let decoded: Vec<u8> = codec.decode_positional(shards)?;
+        let block_start: u64 = block * manifest.block_size;
+        let start: usize = range.start.saturating_sub(block_start) as usize;
+        let end: usize = ((range.end - block_start) as usize).min(decoded.len());
+        writer.write_all(&decoded[start..end])?;
+        written += (end - start) as u64;
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Decodes the whole payload, fetching `data_shards` shard files when
+/// they are intact.
+pub fn decode_all<S: RangeSource + ?Sized, W: Write>(
+    source: &S,
+    basename: &str,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    decode_range(source, basename, 0..u64::MAX, writer)
+}
+
+fn is_intact(piece: &[u8], manifest: &ShardManifest, blocks: Range<u64>) -> bool {
+    let mut rest: &[u8] = piece;
+    for block in blocks {
+        let shard_size: usize = manifest.shard_size(block);
+        if rest.len() < shard_size || DataProcessor::verify_checksum(&rest[..shard_size]).is_none()
+        {
+            return false;
+        }
+        rest = &rest[shard_size..];
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::test_util::temp_dir;
+
+    /// Serves the files of a directory and counts the bytes handed out.
+    struct DirSource {
+        dir: PathBuf,
+        fetched: RefCell<HashMap<String, u64>>,
+    }
+
+    impl DirSource {
+        fn new(dir: &Path) -> Self {
+            Self {
+                dir: dir.to_path_buf(),
+                fetched: RefCell::new(HashMap::new()),
+            }
+        }
+
+        fn read(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+            match fs::read(self.dir.join(key)) {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(error) => Err(error.into()),
+            }
+        }
+    }
+
+    impl RangeSource for DirSource {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+            self.read(key)
+        }
+
+        fn get_range(
+            &self,
+            key: &str,
+            range: Range<u64>,
+        ) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+            let Some(bytes) = self.read(key)? else {
+                return Ok(None);
+            };
+            let start: usize = (range.start as usize).min(bytes.len());
+            let end: usize = (range.end as usize).min(bytes.len());
+            *self
+                .fetched
+                .borrow_mut()
+                .entry(key.to_string())
+                .or_default() += (end - start) as u64;
+            Ok(Some(bytes[start..end].to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_decode_range_fetches_only_overlapping_blocks() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("http-range");
+        fs::create_dir_all(&dir)?;
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1000)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..50_000u32).map(|i: u32| (i % 253) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_files(original_data.as_slice(), &dir, "archive")?;
+
+        let source: DirSource = DirSource::new(&dir);
+        let mut decoded: Vec<u8> = Vec::new();
+        assert_eq!(
+            decode_range(&source, "archive", 12_345..14_321, &mut decoded)?,
+            1976
+        );
+        assert_eq!(decoded, original_data[12_345..14_321]);
+        // Three blocks from each of the four data shards.
+        let fetched: HashMap<String, u64> = source.fetched.take();
+        assert_eq!(fetched.len(), 4);
+        assert!(fetched
+            .values()
+            .all(|bytes: &u64| *bytes == 3 * manifest.shard_size(0) as u64));
+
+        fs::remove_file(manifest.shard_path(&dir, "archive", 1))?;
+        let shard_3: PathBuf = manifest.shard_path(&dir, "archive", 3);
+        let mut bytes: Vec<u8> = fs::read(&shard_3)?;
+        bytes[manifest.shard_size(0) * 49 + 10] ^= 0xff;
+        fs::write(&shard_3, bytes)?;
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_range(&source, "archive", 49_500..60_000, &mut decoded)?;
+        assert_eq!(decoded, original_data[49_500..]);
+        assert_eq!(source.fetched.take().len(), 5);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_all(&source, "archive", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(
+            decode_range(&source, "archive", 60_000..70_000, Vec::new())?,
+            0
+        );
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::EncoderConfig;
+use crate::error::{ManifestError, ReedSolomonError};
+use crate::files::{
+    decode_shard_files, repair_shard_files, ShardFileName, ShardManifest, MANIFEST_VERSION,
+};
+use crate::platform::write_replacing;
+use crate::sparse::SparseDigest;
+use crate::verify::{verify_shard_files, VerifyReport};
+
+pub const JSON_MANIFEST_VERSION: u32 = 1;
+pub const JSON_MANIFEST_EXTENSION: &str = "manifest.json";
+
+/// Everything needed to read a set of shard files back: the full
+/// `EncoderConfig` plus the name, size and BLAKE3 hash of every shard file.
+/// Shard file names are resolved relative to the manifest's directory.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsonManifest {
+    pub version: u32,
+    pub config: EncoderConfig,
+    pub payload_len: u64,
+    /// The generation of the set, as in `ShardManifest`. Self-describing
+    /// shard file names must carry the same one.
+    #[serde(default)]
+    pub generation: u64,
+    /// The sparse map the payload was read through, as in `ShardManifest`.
+    #[serde(default)]
+    pub sparse: Option<SparseDigest>,
+    pub shards: Vec<ShardFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ShardFile {
+    pub index: usize,
+    pub file: String,
+    pub size: u64,
+    pub blake3: String,
+}
+
+impl JsonManifest {
+    /// Describes the shard files `encode_to_files` wrote next to `manifest`.
+    /// The erasure code is pinned, so reading them back does not depend on
+    /// which features the decoding build has enabled.
+    pub fn describe(
+        config: &EncoderConfig,
+        manifest: &ShardManifest,
+        dir: &Path,
+        basename: &str,
+    ) -> Result<Self, ReedSolomonError> {
+        let config: EncoderConfig = config.with_erasure_code(manifest.erasure_code)?;
+        let shards: Vec<ShardFile> = (0..config.total_shards())
+            .map(|index: usize| {
+                let path: PathBuf = manifest.shard_path(dir, basename, index);
+                let (size, blake3): (u64, String) = hash_file(&path)?;
+                let file: String = path
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .map(str::to_string)
+                    .ok_or_else(|| ReedSolomonError::InvalidFileName(path.clone()))?;
+                Ok(ShardFile {
+                    index,
+                    file,
+                    size,
+                    blake3,
+                })
+            })
+            .collect::<Result<_, ReedSolomonError>>()?;
+
+        Ok(Self {
+            version: JSON_MANIFEST_VERSION,
+            config,
+            payload_len: manifest.payload_len,
+            generation: manifest.generation,
+            sparse: manifest.sparse,
+            shards,
+        })
+    }
+
+    pub fn shard_manifest(&self) -> ShardManifest {
+        ShardManifest {
+            version: MANIFEST_VERSION,
+            field: self.config.field(),
+            polynomial: self.config.polynomial(),
+            layout: self.config.layout(),
+            erasure_code: self.config.erasure_code(),
+            data_shards: self.config.data_shards(),
+            parity_shards: self.config.parity_shards(),
+            block_size: self.config.block_size() as u64,
+            payload_len: self.payload_len,
+            generation: self.generation,
+            shard_alignment: self.config.shard_alignment(),
+            sparse: self.sparse,
+        }
+    }
+
+    pub fn paths(&self, dir: &Path) -> Vec<PathBuf> {
+        self.shards
+            .iter()
+            .map(|shard: &ShardFile| dir.join(&shard.file))
+            .collect()
+    }
+
+    /// Indices of the shard files in `dir` that are missing or no longer
+    /// match their recorded size and hash.
+    pub fn damaged_shards(&self, dir: &Path) -> Result<Vec<usize>, ReedSolomonError> {
+        let mut damaged: Vec<usize> = Vec::new();
+        for (shard, path) in self.shards.iter().zip(self.paths(dir)) {
+            let intact: bool = match hash_file(&path) {
+                Ok((size, blake3)) => size == shard.size && blake3 == shard.blake3,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => false,
+                Err(error) => return Err(error.into()),
+            };
+            if !intact {
+                damaged.push(shard.index);
+            }
+        }
+        Ok(damaged)
+    }
+
+    pub fn to_json(&self) -> Result<String, ReedSolomonError> {
+        serde_json::to_string_pretty(self).map_err(malformed_json)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReedSolomonError> {
+        let manifest: Self = serde_json::from_str(text).map_err(malformed_json)?;
+        if manifest.version != JSON_MANIFEST_VERSION {
+            return Err(ManifestError::UnsupportedVersion(manifest.version as u64).into());
+        }
+        if manifest.shards.len() != manifest.config.total_shards() {
+            return Err(ManifestError::InvalidValue("shards".to_string()).into());
+        }
+
+        for (position, shard) in manifest.shards.iter().enumerate() {
+            if shard.index != position {
+                return Err(ManifestError::InvalidValue("index".to_string()).into());
+            }
+            if !is_plain_file_name(&shard.file) || !manifest.names_own_shard(shard) {
+                return Err(ManifestError::InvalidValue("file".to_string()).into());
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Whether a self-describing shard file name agrees with the manifest
+    /// on the shard counts, the index and the generation, so a shard left
+    /// from another generation is never read as part of this one. Other
+    /// names, such as legacy ones, say nothing to disagree with.
+    fn names_own_shard(&self, shard: &ShardFile) -> bool {
+        ShardFileName::parse(&shard.file).is_none_or(|name: ShardFileName| {
+            name.data_shards == self.config.data_shards()
+                && name.parity_shards == self.config.parity_shards()
+                && name.index == shard.index
+                && name.generation == self.generation
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReedSolomonError> {
+        write_replacing(path, self.to_json()?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ReedSolomonError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+pub fn json_manifest_path(dir: &Path, basename: &str) -> PathBuf {
+    dir.join(format!("{}.{}", basename, JSON_MANIFEST_EXTENSION))
+}
+
+pub fn decode_from_json_manifest<W: Write>(
+    path: &Path,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let manifest: JsonManifest = JsonManifest::read(path)?;
+    decode_shard_files(
+        ReedSolomonCodec::with_config(manifest.config)?,
+        &manifest.shard_manifest(),
+        &manifest.paths(manifest_dir(path)),
+        writer,
+    )
+}
+
+pub fn repair_from_json_manifest(path: &Path) -> Result<Vec<usize>, ReedSolomonError> {
+    let manifest: JsonManifest = JsonManifest::read(path)?;
+    repair_shard_files(
+        &ReedSolomonCodec::with_config(manifest.config)?,
+        &manifest.shard_manifest(),
+        &manifest.paths(manifest_dir(path)),
+    )
+}
+
+pub fn verify_from_json_manifest(path: &Path) -> Result<VerifyReport, ReedSolomonError> {
+    let manifest: JsonManifest = JsonManifest::read(path)?;
+    verify_shard_files(
+        &ReedSolomonCodec::with_config(manifest.config)?,
+        &manifest.shard_manifest(),
+        &manifest.paths(manifest_dir(path)),
+    )
+}
+
+fn manifest_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new("."))
+}
+
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+fn hash_file(path: &Path) -> io::Result<(u64, String)> {
+    let mut file: File = File::open(path)?;
+    let mut hasher: blake3::Hasher = blake3::Hasher::new();
+    let mut buffer: Vec<u8> = vec![0u8; 64 * 1024];
+    let mut size: u64 = 0;
+    loop {
+        let read: usize = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, hasher.finalize().to_hex().to_string()))
+}
+
+pub(crate) fn malformed_json(error: serde_json::Error) -> ReedSolomonError {
+    ManifestError::MalformedJson(error.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GaloisField;
+    use crate::test_util::temp_dir;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_json_manifest_decodes_and_repairs() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("json-manifest");
+        let config: EncoderConfig =
+            EncoderConfig::with_field(4, 2, GaloisField::Gf16)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 241) as u8).collect();
+        let shards: ShardManifest =
+            codec.encode_to_files(Cursor::new(&original_data), &dir, "data")?;
+
+        let path: PathBuf = json_manifest_path(&dir, "data");
+        let manifest: JsonManifest = JsonManifest::read(&path)?;
+        assert_eq!(manifest.config.field(), GaloisField::Gf16);
+        assert_eq!(manifest.shards[5].file, "data.k4m2.s05.rssh");
+        assert_eq!(manifest.damaged_shards(&dir)?, Vec::<usize>::new());
+
+        let original: Vec<u8> = fs::read(shards.shard_path(&dir, "data", 2))?;
+        let mut corrupted: Vec<u8> = original.clone();
+        corrupted[10] ^= 0xff;
+        fs::write(shards.shard_path(&dir, "data", 2), corrupted)?;
+        fs::remove_file(shards.shard_path(&dir, "data", 4))?;
+        assert_eq!(manifest.damaged_shards(&dir)?, vec![2, 4]);
+        assert_eq!(verify_from_json_manifest(&path)?.damaged_shards, vec![2, 4]);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_json_manifest(&path, &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        assert_eq!(repair_from_json_manifest(&path)?, vec![2, 4]);
+        assert_eq!(fs::read(shards.shard_path(&dir, "data", 2))?, original);
+        assert_eq!(manifest.damaged_shards(&dir)?, Vec::<usize>::new());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_manifests() -> Result<(), ReedSolomonError> {
+        let shard = |index: usize, file: &str| ShardFile {
+            index,
+            file: file.to_string(),
+            size: 0,
+            blake3: String::new(),
+        };
+        let mut manifest: JsonManifest = JsonManifest {
+            version: JSON_MANIFEST_VERSION,
+            config: EncoderConfig::new(1, 1)?,
+            payload_len: 1,
+            generation: 0,
+            sparse: None,
+            shards: vec![shard(0, "a.shard.000"), shard(1, "a.shard.001")],
+        };
+        assert!(JsonManifest::parse(&manifest.to_json()?).is_ok());
+
+        manifest.shards[1] = shard(1, "../a.shard.001");
+        assert!(JsonManifest::parse(&manifest.to_json()?).is_err());
+        manifest.shards[1] = shard(0, "a.shard.001");
+        assert!(JsonManifest::parse(&manifest.to_json()?).is_err());
+        manifest.shards.pop();
+        assert!(JsonManifest::parse(&manifest.to_json()?).is_err());
+        assert!(JsonManifest::parse("{").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_shards_of_another_generation() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("json-manifest-generation");
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        codec.encode_to_files(Cursor::new(&[1u8; 3000]), &dir, "data")?;
+        let first: String = fs::read_to_string(json_manifest_path(&dir, "data"))?;
+        let second: ShardManifest =
+            codec.encode_to_files(Cursor::new(&[2u8; 3000]), &dir, "data")?;
+
+        let path: PathBuf = json_manifest_path(&dir, "data");
+        let manifest: JsonManifest = JsonManifest::read(&path)?;
+        assert_eq!(manifest.generation, 1);
+        assert_eq!(manifest.shard_manifest(), second);
+        assert_eq!(manifest.shards[0].file, "data.k4m2.g1.s00.rssh");
+
+        // Pointing the new manifest at a shard of the first generation.
+        let mut stale: JsonManifest = manifest.clone();
+        stale.shards[0].file = "data.k4m2.s00.rssh".to_string();
+        assert!(matches!(
+            JsonManifest::parse(&stale.to_json()?),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::InvalidValue(_)
+            ))
+        ));
+        // A manifest of the first generation carries none, so it names the
+        // old shards consistently.
+        assert_eq!(JsonManifest::parse(&first)?.generation, 0);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use crate::backend::ErasureBackend;
+use crate::config::{ErasureCode, LEOPARD_SHARD_LIMITS};
+use crate::error::ReedSolomonError;
+
+/// Leopard-RS through `reed-solomon-simd`: an FFT-based code over GF(2^16)
+/// whose cost grows as O(n log n) in the shard count rather than with the
+/// size of a matrix, using whichever SIMD kernels the CPU offers. Shards
+/// must hold an even number of bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct LeopardBackend {
+    data_shards: usize,
+    parity_shards: usize,
+}
+
+impl LeopardBackend {
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, ReedSolomonError> {
+        if !ErasureCode::Leopard.supports(data_shards, parity_shards) {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: data_shards.max(parity_shards),
+                min: LEOPARD_SHARD_LIMITS.min,
+                max: LEOPARD_SHARD_LIMITS.max,
+            });
+        }
+
+        Ok(Self {
+            data_shards,
+            parity_shards,
+        })
+    }
+
+    fn total_shards(&self) -> usize {
+        self.data_shards + self.parity_shards
+    }
+
+    fn check_shards<I: Iterator<Item = Option<usize>>>(
+        &self,
+        lens: I,
+    ) -> Result<(), ReedSolomonError> {
+        let lens: Vec<Option<usize>> = lens.collect();
+        if lens.len() != self.total_shards() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.total_shards(),
+                given: lens.len(),
+            });
+        }
+
+        let Some(expected) = lens.iter().flatten().next().copied() else {
+            return Ok(());
+        };
+        for (index, len) in lens.into_iter().enumerate() {
+            match len {
+                Some(given) if given != expected || !given.is_multiple_of(2) => {
+                    return Err(ReedSolomonError::ShardSizeMismatch {
+                        index,
+                        given,
+                        expected: expected.next_multiple_of(2),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn recovery_shards<T: AsRef<[u8]>, I: IntoIterator<Item = T>>(
+        &self,
+        data: I,
+    ) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        reed_solomon_simd::encode(self.data_shards, self.parity_shards, data)
+            .map_err(ReedSolomonError::LeopardError)
+    }
+}
+
+impl ErasureBackend for LeopardBackend {
+    fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    fn parity_shards(&self) -> usize {
+        self.parity_shards
+    }
+
+    fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+        self.check_shards(shards.iter().map(|shard: &&mut [u8]| Some(shard.len())))?;
+
+        let (data, parity) = shards.split_at_mut(self.data_shards);
+        let recovery: Vec<Vec<u8>> =
+            self.recovery_shards(data.iter().map(|shard: &&mut [u8]| &**shard))?;
+        for (shard, recovered) in parity.iter_mut().zip(recovery) {
+            shard.copy_from_slice(&recovered);
+        }
+        Ok(())
+    }
+
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        self.check_shards(
+            shards
+                .iter()
+                .map(|shard: &Option<Vec<u8>>| shard.as_ref().map(Vec::len)),
+        )?;
+
+        let present: usize = shards.iter().flatten().count();
+        if present < self.data_shards {
+            return Err(ReedSolomonError::TooFewShards {
+                needed: self.data_shards,
+                given: present,
+            });
+        }
+
+        let (data, parity) = shards.split_at_mut(self.data_shards);
+        if data.iter().any(Option::is_none) {
+            let restored: HashMap<usize, Vec<u8>> = reed_solomon_simd::decode(
+                self.data_shards,
+                self.parity_shards,
+                data.iter().enumerate().filter_map(present_shard),
+                parity.iter().enumerate().filter_map(present_shard),
+            )
+            .map_err(ReedSolomonError::LeopardError)?;
+
+            for (index, shard) in restored {
+                data[index] = Some(shard);
+            }
+        }
+
+        if !data_only && parity.iter().any(Option::is_none) {
+            let recovery: Vec<Vec<u8>> = self.recovery_shards(data.iter().flatten())?;
+            for (slot, recovered) in parity.iter_mut().zip(recovery) {
+                if slot.is_none() {
+                    *slot = Some(recovered);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+        self.check_shards(shards.iter().map(|shard: &&[u8]| Some(shard.len())))?;
+
+        let (data, parity) = shards.split_at(self.data_shards);
+        let recovery: Vec<Vec<u8>> = self.recovery_shards(data)?;
+        Ok(parity
+            .iter()
+            .zip(&recovery)
+            .all(|(shard, recovered): (&&[u8], &Vec<u8>)| *shard == recovered.as_slice()))
+    }
+}
+
+fn present_shard((index, shard): (usize, &Option<Vec<u8>>)) -> Option<(usize, &[u8])> {
+    shard.as_deref().map(|shard: &[u8]| (index, shard))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+    use crate::config::{EncoderConfig, ShardLayout};
+    use crate::header::{ContainerHeader, HEADER_SIZE};
+
+    #[test]
+    fn test_backend_roundtrip() -> Result<(), ReedSolomonError> {
+        let backend: LeopardBackend = LeopardBackend::new(4, 3)?;
+        let mut buffers: Vec<Vec<u8>> = (0..7u8).map(|i: u8| vec![i + 1; 8]).collect();
+        let mut shards: Vec<&mut [u8]> = buffers.iter_mut().map(Vec::as_mut_slice).collect();
+        backend.encode_shards(&mut shards)?;
+
+        let slices: Vec<&[u8]> = buffers.iter().map(Vec::as_slice).collect();
+        assert!(backend.verify(&slices)?);
+
+        let mut received: Vec<Option<Vec<u8>>> = buffers.iter().cloned().map(Some).collect();
+        received[0] = None;
+        received[2] = None;
+        received[5] = None;
+        backend.reconstruct(&mut received, true)?;
+        assert_eq!(received[0].as_deref(), Some(buffers[0].as_slice()));
+        assert_eq!(received[5], None);
+        backend.reconstruct(&mut received, false)?;
+        assert_eq!(
+            received,
+            buffers.iter().cloned().map(Some).collect::<Vec<_>>()
+        );
+
+        received[0] = None;
+        received[1] = None;
+        received[2] = None;
+        received[3] = None;
+        assert!(matches!(
+            backend.reconstruct(&mut received, true),
+            Err(ReedSolomonError::TooFewShards {
+                needed: 4,
+                given: 3
+            })
+        ));
+
+        let mut odd: Vec<Vec<u8>> = vec![vec![0u8; 7]; 7];
+        let mut shards: Vec<&mut [u8]> = odd.iter_mut().map(Vec::as_mut_slice).collect();
+        assert!(matches!(
+            backend.encode_shards(&mut shards),
+            Err(ReedSolomonError::ShardSizeMismatch { index: 0, .. })
+        ));
+        assert!(LeopardBackend::new(40000, 4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_configs_use_leopard() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(160, 40)?;
+        assert_eq!(config.erasure_code(), ErasureCode::Leopard);
+
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5001u32).map(|i: u32| (i % 249) as u8).collect();
+        let encoded: Vec<u8> = codec.encode(&original_data)?;
+        let header: ContainerHeader = ContainerHeader::read(&encoded)?;
+        assert_eq!(header.erasure_code, ErasureCode::Leopard);
+
+        let shard_len: usize = header.shard_size as usize;
+        let mut damaged: Vec<u8> = encoded;
+        for shard in [0, 7, 99, 150, 199] {
+            damaged[HEADER_SIZE + shard * shard_len] ^= 0xff;
+        }
+        assert_eq!(codec.decode(&damaged)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_leopard_with_hidden_data_shards() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(5, 3)?
+            .with_layout(ShardLayout::NonSystematic)?
+            .with_erasure_code(ErasureCode::Leopard)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+
+        let encoded: Vec<u8> = codec.encode(b"parity all the way down")?;
+        assert_eq!(codec.decode(&encoded)?, b"parity all the way down");
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod archive;
+pub mod armor;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod backend;
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod bundle;
+#[cfg(feature = "std")]
+pub mod cas;
+pub mod ccsds;
+#[cfg(feature = "chunking")]
+pub mod chunking;
+pub mod codec;
+pub mod collector;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod config;
+pub mod correction;
+#[cfg(feature = "std")]
+pub mod direct;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "serde")]
+pub mod envelope;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod files;
+#[cfg(feature = "fountain")]
+pub mod fountain;
+pub mod gf;
+pub mod header;
+pub mod hierarchical;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "json")]
+pub mod json_manifest;
+#[cfg(feature = "leopard")]
+pub mod leopard;
+#[cfg(feature = "std")]
+pub mod log;
+pub mod lrc;
+pub mod mac;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod packet;
+#[cfg(feature = "par2")]
+pub mod par2;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod placement;
+#[cfg(feature = "std")]
+pub mod plan;
+#[cfg(feature = "std")]
+pub mod platform;
+pub mod polynomial;
+#[cfg(feature = "std")]
+pub mod pool;
+pub mod presets;
+pub mod processor;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(any(feature = "remote", feature = "net"))]
+pub mod remote;
+pub mod replication;
+pub mod report;
+pub mod sans_io;
+#[cfg(feature = "json")]
+pub mod scrub;
+pub mod shard;
+#[cfg(feature = "std")]
+pub mod simd;
+#[cfg(feature = "std")]
+pub mod sparse;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod test_util;
+#[cfg(feature = "encryption")]
+pub mod threshold;
+#[cfg(feature = "std")]
+pub mod throttle;
+#[cfg(feature = "std")]
+pub mod trailer;
+#[cfg(feature = "std")]
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod watch;
+#[cfg(feature = "zfec")]
+pub mod zfec;
+
+pub use backend::ErasureBackend;
+pub use batch::EncodedBatch;
+#[cfg(feature = "std")]
+pub use bundle::{BundleEntry, BundleIndex};
+#[cfg(feature = "std")]
+pub use cas::ContentStore;
+pub use ccsds::{CcsdsCode, SymbolBasis};
+#[cfg(feature = "chunking")]
+pub use chunking::{ChunkRecipe, ChunkReport, FastCdc};
+pub use codec::{EncodeCheck, ReedSolomonCodec};
+pub use collector::ShardCollector;
+pub use config::{
+    Compression, DataSizeLimits, EncoderConfig, EncoderConfigBuilder, Encryption, ErasureCode,
+    GaloisField, ShardLayout, ShardLimits, AUTO_BLOCK_COUNT, DATA_SIZE_LIMITS, DEFAULT_BLOCK_SIZE,
+    GF16_SHARD_LIMITS, LEOPARD_SHARD_LIMITS, LEOPARD_SHARD_THRESHOLD, MAX_AUTO_SHARD_SIZE,
+    MAX_INTERLEAVE_DEPTH, MIN_AUTO_SHARD_SIZE, SHARD_LIMITS,
+};
+#[cfg(feature = "std")]
+pub use direct::DIRECT_IO_ALIGNMENT;
+#[cfg(feature = "encryption")]
+pub use encryption::EncryptionKey;
+#[cfg(feature = "serde")]
+pub use envelope::ShardEnvelope;
+pub use error::{ArmorError, HeaderError, ManifestError, ReedSolomonError};
+#[cfg(feature = "std")]
+pub use files::ShardManifest;
+pub use header::ContainerHeader;
+pub use hierarchical::{HierarchicalCodec, HierarchicalConfig};
+#[cfg(feature = "http")]
+pub use http::{HttpSource, RangeSource};
+#[cfg(feature = "json")]
+pub use json_manifest::JsonManifest;
+#[cfg(feature = "std")]
+pub use log::LogEncoder;
+pub use lrc::LrcCodec;
+pub use mac::MacKey;
+#[cfg(feature = "std")]
+pub use metrics::{Metrics, MetricsSnapshot};
+#[cfg(feature = "net")]
+pub use net::{ShardClient, ShardCluster, ShardServer};
+pub use placement::{PlacementPolicy, RoundRobin};
+#[cfg(feature = "std")]
+pub use plan::EncodePlan;
+pub use polynomial::{Gf8Field, PolynomialBackend, DEFAULT_POLYNOMIAL};
+#[cfg(feature = "std")]
+pub use pool::{BufferPool, PoolStats};
+pub use presets::{BlockCode, Preset};
+pub use processor::DataProcessor;
+#[cfg(feature = "std")]
+pub use progress::{ProgressCounter, ProgressSink, ProgressUpdate};
+#[cfg(any(feature = "remote", feature = "net"))]
+pub use remote::ObjectStore;
+#[cfg(feature = "remote")]
+pub use remote::S3Store;
+pub use replication::{HybridPolicy, ReplicationBackend};
+pub use report::DecodeReport;
+pub use sans_io::{BlockShard, Decoder, Encoder};
+#[cfg(feature = "json")]
+pub use scrub::{ScrubItem, ScrubReport, ScrubStatus, Scrubber};
+pub use shard::{Shard, ShardKind};
+#[cfg(feature = "std")]
+pub use simd::{SimdBackend, SimdLevel};
+#[cfg(feature = "std")]
+pub use sparse::SparseMap;
+#[cfg(feature = "std")]
+pub use store::{FileStore, MemoryStore, ShardId, ShardStore, ShardWriter};
+#[cfg(feature = "std")]
+pub use throttle::{RateLimiter, Throttled};
+#[cfg(feature = "std")]
+pub use trailer::{Trailer, TrailerEntry};
+#[cfg(feature = "std")]
+pub use verify::VerifyReport;
+#[cfg(feature = "std")]
+pub use watch::DropFolder;
@@ -0,0 +1,494 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::EncoderConfig;
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::processor::{DataProcessor, CHECKSUM_SIZE};
+use crate::report::DecodeReport;
+
+pub const RECORD_MAGIC: [u8; 4] = *b"RSLR";
+pub const PARITY_MAGIC: [u8; 4] = *b"RSLP";
+/// Magic, sequence number and record length.
+pub const RECORD_HEADER_SIZE: usize = 16;
+/// Magic, first sequence number, record count, shard counts and shard size.
+pub const PARITY_HEADER_SIZE: usize = 24;
+
+/// Appends records to a write-ahead log as they come, each in its own
+/// checksummed frame, and after every `data_shards` records a parity frame
+/// computed over them. Nothing written is ever rewritten: a group's
+/// records are the data shards of its code, padded to the longest one,
+/// and only the parity is new.
+#[derive(Debug)]
+pub struct LogEncoder<W: Write> {
+    codec: ReedSolomonCodec,
+    writer: W,
+    next_sequence: u64,
+    group: Vec<Vec<u8>>,
+}
+
+impl<W: Write> LogEncoder<W> {
+    pub fn new(codec: ReedSolomonCodec, writer: W) -> Self {
+        Self {
+            codec,
+            writer,
+            next_sequence: 0,
+            group: Vec::new(),
+        }
+    }
+
+    /// Numbers records from `sequence`, to keep appending to an existing
+    /// log after reopening it.
+    pub fn with_next_sequence(mut self, sequence: u64) -> Self {
+        self.next_sequence = sequence;
+        self
+    }
+
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// Records appended since the last parity frame.
+    pub fn pending_records(&self) -> usize {
+        self.group.len()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Writes `record` and, when it completes a group, the group's parity
+    /// frame. Returns the record's sequence number.
+    pub fn append(&mut self, record: &[u8]) -> Result<u64, ReedSolomonError> {
+        let len: u32 =
+            u32::try_from(record.len()).map_err(|_| ReedSolomonError::InvalidDataSize {
+                given: record.len(),
+                min: 0,
+                max: u32::MAX as usize,
+            })?;
+
+        let sequence: u64 = self.next_sequence;
+        let mut frame: Vec<u8> =
+            Vec::with_capacity(RECORD_HEADER_SIZE + record.len() + CHECKSUM_SIZE);
+        frame.extend_from_slice(&RECORD_MAGIC);
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        frame.extend_from_slice(&len.to_be_bytes());
+        frame.extend_from_slice(record);
+        self.writer
+            .write_all(&DataProcessor::append_checksum(frame))?;
+
+        self.next_sequence += 1;
+        self.group.push(record.to_vec());
+        if self.group.len() == self.codec.config().data_shards() {
+            self.write_parity()?;
+        }
+        Ok(sequence)
+    }
+
+    /// Flushes the writer without closing the current group.
+    pub fn flush(&mut self) -> Result<(), ReedSolomonError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the parity of a short final group, flushes and returns the
+    /// writer. The missing records of a short group count as empty.
+    pub fn finish(mut self) -> Result<W, ReedSolomonError> {
+        if !self.group.is_empty() {
+            self.write_parity()?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_parity(&mut self) -> Result<(), ReedSolomonError> {
+        let config: &EncoderConfig = self.codec.config();
+        let element_size: usize = config.element_size();
+        let longest: usize = self.group.iter().map(Vec::len).max().unwrap_or(0);
+        let shard_size: usize = longest.max(1).next_multiple_of(element_size);
+
+        let data: Vec<Vec<u8>> = (0..config.data_shards())
+            .map(|index: usize| {
+                let mut shard: Vec<u8> = self.group.get(index).cloned().unwrap_or_default();
+                shard.resize(shard_size, 0);
+                DataProcessor::append_checksum(shard)
+            })
+            .collect();
+        let parity: Vec<Vec<u8>> = self.codec.regenerate_parity(&data)?;
+
+        let first: u64 = self.next_sequence - self.group.len() as u64;
+        let mut frame: Vec<u8> = vec![0u8; PARITY_HEADER_SIZE];
+        frame[..4].copy_from_slice(&PARITY_MAGIC);
+        BigEndian::write_u64(&mut frame[4..12], first);
+        BigEndian::write_u16(&mut frame[12..14], self.group.len() as u16);
+        BigEndian::write_u16(&mut frame[14..16], config.data_shards() as u16);
+        BigEndian::write_u16(&mut frame[16..18], config.parity_shards() as u16);
+        BigEndian::write_u32(&mut frame[20..24], shard_size as u32);
+        for record in &self.group {
+            frame.extend_from_slice(&(record.len() as u32).to_be_bytes());
+        }
+        let mut frame: Vec<u8> = DataProcessor::append_checksum(frame);
+        for shard in &parity {
+            frame.extend_from_slice(shard);
+        }
+
+        self.writer.write_all(&frame)?;
+        self.group.clear();
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ParityFrame<'a> {
+    first: u64,
+    lengths: Vec<usize>,
+    shard_size: usize,
+    parity: Vec<&'a [u8]>,
+}
+
+impl<'a> ParityFrame<'a> {
+    /// Parses the parity frame at the start of `data` and returns it with
+    /// its length.
+    fn read(data: &'a [u8], codec: &ReedSolomonCodec) -> Option<(Self, usize)> {
+        let header: &[u8] = data.get(..PARITY_HEADER_SIZE)?;
+        let count: usize = BigEndian::read_u16(&header[12..14]) as usize;
+        let data_shards: usize = BigEndian::read_u16(&header[14..16]) as usize;
+        let parity_shards: usize = BigEndian::read_u16(&header[16..18]) as usize;
+        let shard_size: usize = BigEndian::read_u32(&header[20..24]) as usize;
+        if data_shards != codec.config().data_shards()
+            || parity_shards != codec.config().parity_shards()
+            || count > data_shards
+        {
+            return None;
+        }
+        let header_len: usize = PARITY_HEADER_SIZE + 4 * count + CHECKSUM_SIZE;
+        DataProcessor::verify_checksum(data.get(..header_len)?)?;
+
+        let len: usize = header_len + parity_shards * (shard_size + CHECKSUM_SIZE);
+        let frame: &'a [u8] = data.get(..len)?;
+        Some((
+            Self {
+                first: BigEndian::read_u64(&header[4..12]),
+                lengths: frame[PARITY_HEADER_SIZE..header_len - CHECKSUM_SIZE]
+                    .chunks_exact(4)
+                    .map(|len: &[u8]| BigEndian::read_u32(len) as usize)
+                    .collect(),
+                shard_size,
+                parity: frame[header_len..]
+                    .chunks_exact(shard_size + CHECKSUM_SIZE)
+                    .collect(),
+            },
+            len,
+        ))
+    }
+}
+
+/// A record's sequence number and payload.
+type Record<'a> = (u64, &'a [u8]);
+
+/// Parses the record frame at the start of `data` and returns its
+/// sequence number, payload and length, or only the length when the frame
+/// is damaged but the next one starts where it says it ends.
+fn read_record(data: &[u8]) -> Option<(Option<Record<'_>>, usize)> {
+    let header: &[u8] = data.get(..RECORD_HEADER_SIZE)?;
+    let len: usize = (BigEndian::read_u32(&header[12..16]) as usize)
+        .checked_add(RECORD_HEADER_SIZE + CHECKSUM_SIZE)?;
+    let frame: &[u8] = data.get(..len)?;
+    match DataProcessor::verify_checksum(frame) {
+        Some(body) => Some((
+            Some((
+                BigEndian::read_u64(&header[4..12]),
+                &body[RECORD_HEADER_SIZE..],
+            )),
+            len,
+        )),
+        None if len == data.len() || is_frame(&data[len..]) => Some((None, len)),
+        None => None,
+    }
+}
+
+fn is_frame(data: &[u8]) -> bool {
+    data.starts_with(&RECORD_MAGIC) || data.starts_with(&PARITY_MAGIC)
+}
+
+impl ReedSolomonCodec {
+    pub fn decode_log(&self, data: &[u8]) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        self.decode_log_with_report(data)
+            .map(|(records, _): (Vec<Vec<u8>>, DecodeReport)| records)
+    }
+
+    /// Reads back the records of a log written by `LogEncoder`, in
+    /// sequence order, rebuilding lost or damaged ones from their group's
+    /// parity. The report lists records by sequence number. A damaged
+    /// record after the last parity frame cannot be rebuilt and is an
+    /// error; records missing from the end of the log go unnoticed.
+    pub fn decode_log_with_report(
+        &self,
+        data: &[u8],
+    ) -> Result<(Vec<Vec<u8>>, DecodeReport), ReedSolomonError> {
+        let mut records: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+        let mut groups: Vec<ParityFrame<'_>> = Vec::new();
+        // Where the damage starts while the bytes after the last intact
+        // frame are damaged.
+        let mut damaged_tail: Option<usize> = None;
+
+        let mut offset: usize = 0;
+        while offset < data.len() {
+            let rest: &[u8] = &data[offset..];
+            let frame: Option<usize> = if rest.starts_with(&RECORD_MAGIC) {
+                read_record(rest).map(|(record, len): (Option<Record<'_>>, usize)| {
+                    match record {
+                        Some((sequence, payload)) => {
+                            records.insert(sequence, payload.to_vec());
+                            damaged_tail = None;
+                        }
+                        None => {
+                            damaged_tail.get_or_insert(offset);
+                        }
+                    }
+                    len
+                })
+            } else if rest.starts_with(&PARITY_MAGIC) {
+                ParityFrame::read(rest, self).map(|(group, len): (ParityFrame<'_>, usize)| {
+                    groups.push(group);
+                    damaged_tail = None;
+                    len
+                })
+            } else {
+                None
+            };
+
+            match frame {
+                Some(len) => offset += len,
+                // Lost sync: resume at the next frame magic.
+                None => {
+                    damaged_tail.get_or_insert(offset);
+                    offset += (1..rest.len())
+                        .find(|start: &usize| is_frame(&rest[*start..]))
+                        .unwrap_or(rest.len());
+                }
+            }
+        }
+
+        let mut report: DecodeReport = DecodeReport::default();
+        for group in &groups {
+            self.rebuild_group(group, &mut records, &mut report)?;
+        }
+
+        let first: Option<u64> = records
+            .keys()
+            .next()
+            .copied()
+            .into_iter()
+            .chain(groups.iter().map(|group: &ParityFrame<'_>| group.first))
+            .min();
+        let Some(first) = first else {
+            if let Some(intact) = damaged_tail {
+                return Err(ReedSolomonError::Truncated {
+                    needed: data.len(),
+                    given: intact,
+                });
+            }
+            return Ok((Vec::new(), report));
+        };
+
+        let mut decoded: Vec<Vec<u8>> = Vec::with_capacity(records.len());
+        for (expected, (sequence, record)) in (first..).zip(records) {
+            if sequence != expected {
+                return Err(ReedSolomonError::MissingDataShard {
+                    index: expected as usize,
+                });
+            }
+            decoded.push(record);
+        }
+        if damaged_tail.is_some() {
+            return Err(ReedSolomonError::MissingDataShard {
+                index: (first + decoded.len() as u64) as usize,
+            });
+        }
+
+        report.parity_used = !report.reconstructed.is_empty();
+        Ok((decoded, report))
+    }
+
+    fn rebuild_group(
+        &self,
+        group: &ParityFrame<'_>,
+        records: &mut BTreeMap<u64, Vec<u8>>,
+        report: &mut DecodeReport,
+    ) -> Result<(), ReedSolomonError> {
+        let data_shards: usize = self.config().data_shards();
+        let mut shards: Vec<Option<Vec<u8>>> = (0..data_shards)
+            .map(|index: usize| {
+                let Some(len) = group.lengths.get(index) else {
+                    return Some(vec![0u8; group.shard_size]);
+                };
+                records
+                    .get(&(group.first + index as u64))
+                    .filter(|record: &&Vec<u8>| record.len() == *len && *len <= group.shard_size)
+                    .map(|record: &Vec<u8>| {
+                        let mut shard: Vec<u8> = record.clone();
+                        shard.resize(group.shard_size, 0);
+                        shard
+                    })
+            })
+            .collect();
+        let lost: Vec<usize> = (0..group.lengths.len())
+            .filter(|index: &usize| shards[*index].is_none())
+            .collect();
+        if lost.is_empty() {
+            return Ok(());
+        }
+
+        shards.extend(
+            group
+                .parity
+                .iter()
+                .map(|shard: &&[u8]| DataProcessor::verify_checksum(shard).map(<[u8]>::to_vec)),
+        );
+        let present: usize = shards.iter().flatten().count();
+        if present < data_shards {
+            return Err(ReedSolomonError::TooFewShards {
+                needed: data_shards,
+                given: present,
+            });
+        }
+        self.reconstruct_data(&mut shards)?;
+
+        for index in lost {
+            let sequence: u64 = group.first + index as u64;
+            let mut record: Vec<u8> = shards[index].take().ok_or(HeaderError::Inconsistent)?;
+            record.truncate(group.lengths[index]);
+            records.insert(sequence, record);
+            report.missing.push(sequence as usize);
+            report.reconstructed.push(sequence as usize);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(count: usize) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|i: usize| {
+                (0..(i * 7) % 40 + 1)
+                    .map(|j: usize| (i + j) as u8)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn record_offset(log: &[u8], sequence: u64) -> usize {
+        log.windows(12)
+            .position(|window: &[u8]| {
+                window[..4] == RECORD_MAGIC && window[4..] == sequence.to_be_bytes()
+            })
+            .unwrap_or(log.len())
+    }
+
+    fn write_log(
+        codec: &ReedSolomonCodec,
+        records: &[Vec<u8>],
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut encoder: LogEncoder<Vec<u8>> =
+            LogEncoder::new(ReedSolomonCodec::with_config(*codec.config())?, Vec::new());
+        for (sequence, record) in records.iter().enumerate() {
+            assert_eq!(encoder.append(record)?, sequence as u64);
+        }
+        encoder.finish()
+    }
+
+    #[test]
+    fn test_log_appends_without_rewriting() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let records: Vec<Vec<u8>> = records(10);
+        let mut encoder: LogEncoder<Vec<u8>> =
+            LogEncoder::new(ReedSolomonCodec::new(4, 2)?, Vec::new());
+
+        let mut previous: Vec<u8> = Vec::new();
+        for record in &records {
+            encoder.append(record)?;
+            assert!(encoder.get_ref().starts_with(&previous));
+            previous = encoder.get_ref().clone();
+        }
+        assert_eq!(encoder.pending_records(), 2);
+        let log: Vec<u8> = encoder.finish()?;
+        assert!(log.starts_with(&previous));
+
+        let (decoded, report): (Vec<Vec<u8>>, DecodeReport) = codec.decode_log_with_report(&log)?;
+        assert_eq!(decoded, records);
+        assert!(report.is_clean());
+        assert!(codec.decode_log(&[])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_rebuilds_damaged_records() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let records: Vec<Vec<u8>> = records(11);
+        let log: Vec<u8> = write_log(&codec, &records)?;
+
+        let frame_len = |record: &Vec<u8>| RECORD_HEADER_SIZE + record.len() + CHECKSUM_SIZE;
+        let mut damaged: Vec<u8> = log.clone();
+        damaged[RECORD_HEADER_SIZE] ^= 0x01;
+        let second: usize = frame_len(&records[0]);
+        for byte in &mut damaged[second..second + RECORD_HEADER_SIZE] {
+            *byte = 0xff;
+        }
+        let ninth: usize = record_offset(&log, 9);
+        damaged[ninth + RECORD_HEADER_SIZE] ^= 0x01;
+
+        let (decoded, report): (Vec<Vec<u8>>, DecodeReport) =
+            codec.decode_log_with_report(&damaged)?;
+        assert_eq!(decoded, records);
+        assert_eq!(report.reconstructed, vec![0, 1, 9]);
+        assert!(report.parity_used);
+
+        let mut lost: Vec<u8> = log.clone();
+        for byte in &mut lost[..record_offset(&log, 2) + RECORD_HEADER_SIZE] {
+            *byte = 0;
+        }
+        assert!(matches!(
+            codec.decode_log(&lost),
+            Err(ReedSolomonError::TooFewShards {
+                needed: 4,
+                given: 3
+            })
+        ));
+
+        let mut encoder: LogEncoder<Vec<u8>> =
+            LogEncoder::new(ReedSolomonCodec::new(4, 2)?, Vec::new());
+        encoder.append(&records[0])?;
+        let mut torn: Vec<u8> = encoder.get_ref().clone();
+        torn[RECORD_HEADER_SIZE] ^= 0x01;
+        let torn_len: usize = torn.len();
+        assert!(matches!(
+            codec.decode_log(&torn),
+            Err(ReedSolomonError::Truncated { needed, given: 0 }) if needed == torn_len
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_resumes_after_reopening() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 1)?;
+        let records: Vec<Vec<u8>> = records(8);
+        let mut log: Vec<u8> = write_log(&codec, &records[..5])?;
+
+        let mut encoder: LogEncoder<&mut Vec<u8>> =
+            LogEncoder::new(ReedSolomonCodec::new(3, 1)?, &mut log).with_next_sequence(5);
+        for record in &records[5..] {
+            encoder.append(record)?;
+        }
+        assert_eq!(encoder.next_sequence(), 8);
+        encoder.finish()?;
+
+        assert_eq!(codec.decode_log(&log)?, records);
+        Ok(())
+    }
+}
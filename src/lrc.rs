@@ -0,0 +1,314 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::SHARD_LIMITS;
+use crate::error::ReedSolomonError;
+use crate::processor::{DataProcessor, PREFIX_SIZE};
+use crate::shard::Shard;
+
+/// A Local Reconstruction Code: the data shards are split into groups that
+/// each get one XOR parity shard, and Reed-Solomon parity over all data
+/// shards covers larger losses. A single lost data or local parity shard is
+/// rebuilt from its group alone instead of from `data_shards` shards.
+///
+/// Shards are indexed data first, then one local parity per group, then
+/// the global parity.
+#[derive(Debug)]
+pub struct LrcCodec {
+    local_groups: usize,
+    global: ReedSolomonCodec,
+}
+
+impl LrcCodec {
+    pub fn new(
+        data_shards: usize,
+        local_groups: usize,
+        global_parity: usize,
+    ) -> Result<Self, ReedSolomonError> {
+        if !(1..=data_shards).contains(&local_groups) {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: local_groups,
+                min: 1,
+                max: data_shards.max(1),
+            });
+        }
+        let total_shards: usize = data_shards + local_groups + global_parity;
+        if total_shards > SHARD_LIMITS.max {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: total_shards,
+                min: SHARD_LIMITS.min,
+                max: SHARD_LIMITS.max,
+            });
+        }
+
+        Ok(Self {
+            local_groups,
+            global: ReedSolomonCodec::new(data_shards, global_parity)?,
+        })
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.global.config().data_shards()
+    }
+
+    pub fn local_groups(&self) -> usize {
+        self.local_groups
+    }
+
+    pub fn global_parity(&self) -> usize {
+        self.global.config().parity_shards()
+    }
+
+    pub fn total_shards(&self) -> usize {
+        self.data_shards() + self.local_groups + self.global_parity()
+    }
+
+    /// The local group a data or local parity shard belongs to, or `None`
+    /// for global parity. Groups differ in size by at most one shard.
+    pub fn group_of(&self, index: usize) -> Option<usize> {
+        let data_shards: usize = self.data_shards();
+        if index < data_shards {
+            Some(index * self.local_groups / data_shards)
+        } else {
+            (index < data_shards + self.local_groups).then(|| index - data_shards)
+        }
+    }
+
+    /// The data shards and the local parity shard of `group`.
+    pub fn group_members(&self, group: usize) -> Vec<usize> {
+        (0..self.data_shards())
+            .filter(|index: &usize| self.group_of(*index) == Some(group))
+            .chain((group < self.local_groups).then(|| self.data_shards() + group))
+            .collect()
+    }
+
+    /// The shards to read to rebuild shard `index` locally, or `None` for
+    /// global parity, which needs `data_shards` shards.
+    pub fn local_repair_set(&self, index: usize) -> Option<Vec<usize>> {
+        let group: usize = self.group_of(index)?;
+        Some(
+            self.group_members(group)
+                .into_iter()
+                .filter(|member: &usize| *member != index)
+                .collect(),
+        )
+    }
+
+    pub fn encode_shards(&self, data: &[u8]) -> Result<Vec<Shard>, ReedSolomonError> {
+        let mut shards: Vec<Shard> = self.global.encode_shards(data)?;
+        let data_shards: usize = self.data_shards();
+        let global: Vec<Shard> = shards.split_off(data_shards);
+
+        for group in 0..self.local_groups {
+            let members: Vec<&[u8]> = self
+                .group_members(group)
+                .into_iter()
+                .filter(|index: &usize| *index < data_shards)
+                .filter_map(|index: usize| DataProcessor::verify_checksum(&shards[index].payload))
+                .collect();
+            let parity: Vec<u8> = xor(&members);
+            shards.push(Shard::new(
+                data_shards + group,
+                data_shards,
+                DataProcessor::append_checksum(parity),
+            ));
+        }
+
+        shards.extend(
+            global
+                .into_iter()
+                .enumerate()
+                .map(|(i, shard): (usize, Shard)| {
+                    Shard::new(
+                        data_shards + self.local_groups + i,
+                        data_shards,
+                        shard.payload,
+                    )
+                }),
+        );
+        Ok(shards)
+    }
+
+    /// Rebuilds every missing shard payload in place. Each group missing a
+    /// single shard is repaired from its own members; only if data or
+    /// global parity is still missing after that is the global code used.
+    pub fn reconstruct(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), ReedSolomonError> {
+        if shards.len() != self.total_shards() {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: self.total_shards(),
+                given: shards.len(),
+            });
+        }
+        let Some(shard_len) = shards.iter().flatten().map(Vec::len).next() else {
+            return Err(ReedSolomonError::TooFewShards {
+                needed: self.data_shards(),
+                given: 0,
+            });
+        };
+        if let Some(index) = shards.iter().position(|slot: &Option<Vec<u8>>| {
+            slot.as_ref()
+                .is_some_and(|shard: &Vec<u8>| shard.len() != shard_len)
+        }) {
+            return Err(ReedSolomonError::ShardSizeMismatch {
+                index,
+                given: shards[index].as_ref().map_or(0, Vec::len),
+                expected: shard_len,
+            });
+        }
+
+        for group in 0..self.local_groups {
+            self.repair_group(shards, group);
+        }
+
+        let data_shards: usize = self.data_shards();
+        let global_start: usize = data_shards + self.local_groups;
+        if shards[..data_shards]
+            .iter()
+            .chain(&shards[global_start..])
+            .any(Option::is_none)
+        {
+            let (local, global) = shards.split_at_mut(global_start);
+            let mut coded: Vec<Option<Vec<u8>>> = local[..data_shards]
+                .iter_mut()
+                .chain(global.iter_mut())
+                .map(Option::take)
+                .collect();
+            let result: Result<(), ReedSolomonError> = self.global.reconstruct(&mut coded);
+
+            for (slot, shard) in local[..data_shards]
+                .iter_mut()
+                .chain(global.iter_mut())
+                .zip(coded)
+            {
+                *slot = shard;
+            }
+            result?;
+
+            for group in 0..self.local_groups {
+                self.repair_group(shards, group);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes shards made by `encode_shards`, treating any that fail their
+    /// checksum as missing.
+    pub fn decode_shards(&self, shards: Vec<Shard>) -> Result<Vec<u8>, ReedSolomonError> {
+        let total_shards: usize = self.total_shards();
+        let mut slots: Vec<Option<Vec<u8>>> = vec![None; total_shards];
+        let mut seen: Vec<bool> = vec![false; total_shards];
+        for shard in shards {
+            let index: usize = shard.index as usize;
+            if index >= total_shards {
+                return Err(ReedSolomonError::ShardIndexOutOfRange {
+                    index,
+                    total: total_shards,
+                });
+            }
+            if seen[index] {
+                return Err(ReedSolomonError::DuplicateShard { index });
+            }
+            seen[index] = true;
+            slots[index] = DataProcessor::verify_checksum(&shard.payload).map(<[u8]>::to_vec);
+        }
+
+        self.reconstruct(&mut slots)?;
+        let data: Vec<&[u8]> = slots[..self.data_shards()]
+            .iter()
+            .map(|slot: &Option<Vec<u8>>| slot.as_deref().unwrap_or_default())
+            .collect();
+        DataProcessor::extract_from_shards(&data, PREFIX_SIZE)
+    }
+
+    /// XORs the rest of `group` into its one missing member, if exactly one
+    /// is missing.
+    fn repair_group(&self, shards: &mut [Option<Vec<u8>>], group: usize) {
+        let members: Vec<usize> = self.group_members(group);
+        let missing: Vec<usize> = members
+            .iter()
+            .copied()
+            .filter(|index: &usize| shards[*index].is_none())
+            .collect();
+        if let [index] = missing[..] {
+            let present: Vec<&[u8]> = members
+                .iter()
+                .filter_map(|member: &usize| shards[*member].as_deref())
+                .collect();
+            shards[index] = Some(xor(&present));
+        }
+    }
+}
+
+fn xor(shards: &[&[u8]]) -> Vec<u8> {
+    let mut sum: Vec<u8> = vec![0u8; shards.first().map_or(0, |shard: &&[u8]| shard.len())];
+    for shard in shards {
+        for (sum, byte) in sum.iter_mut().zip(*shard) {
+            *sum ^= byte;
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payloads(shards: &[Shard]) -> Vec<Option<Vec<u8>>> {
+        shards
+            .iter()
+            .map(|shard: &Shard| DataProcessor::verify_checksum(&shard.payload).map(<[u8]>::to_vec))
+            .collect()
+    }
+
+    #[test]
+    fn test_groups_and_repair_sets() -> Result<(), ReedSolomonError> {
+        let codec: LrcCodec = LrcCodec::new(12, 2, 2)?;
+        assert_eq!(codec.total_shards(), 16);
+        assert_eq!(codec.group_members(0), vec![0, 1, 2, 3, 4, 5, 12]);
+        assert_eq!(codec.group_members(1), vec![6, 7, 8, 9, 10, 11, 13]);
+        assert_eq!(codec.local_repair_set(7), Some(vec![6, 8, 9, 10, 11, 13]));
+        assert_eq!(codec.local_repair_set(14), None);
+
+        let uneven: LrcCodec = LrcCodec::new(5, 2, 1)?;
+        assert_eq!(uneven.group_members(0), vec![0, 1, 2, 5]);
+        assert_eq!(uneven.group_members(1), vec![3, 4, 6]);
+
+        assert!(LrcCodec::new(4, 0, 2).is_err());
+        assert!(LrcCodec::new(4, 5, 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_loss_is_repaired_locally() -> Result<(), ReedSolomonError> {
+        let codec: LrcCodec = LrcCodec::new(6, 2, 2)?;
+        let shards: Vec<Shard> = codec.encode_shards(b"a lost shard only costs its own group")?;
+        let expected: Vec<Option<Vec<u8>>> = payloads(&shards);
+
+        // With every global parity also gone, only the local parity can
+        // bring back shard 4.
+        let mut damaged: Vec<Option<Vec<u8>>> = expected.clone();
+        damaged[4] = None;
+        damaged[8] = None;
+        damaged[9] = None;
+        codec.reconstruct(&mut damaged)?;
+        assert_eq!(damaged, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_falls_back_to_global_parity() -> Result<(), ReedSolomonError> {
+        let codec: LrcCodec = LrcCodec::new(6, 2, 2)?;
+        let original_data: &[u8] = b"two losses in one group need the global parity";
+        let mut shards: Vec<Shard> = codec.encode_shards(original_data)?;
+        assert_eq!(shards.len(), 10);
+
+        shards.retain(|shard: &Shard| shard.index != 0 && shard.index != 1 && shard.index != 6);
+        shards[2].payload[0] ^= 0xff;
+        assert_eq!(codec.decode_shards(shards.clone())?, original_data);
+
+        shards.retain(|shard: &Shard| shard.index != 8);
+        assert!(codec.decode_shards(shards).is_err());
+        Ok(())
+    }
+}
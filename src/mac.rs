@@ -0,0 +1,232 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::report::DecodeReport;
+use crate::shard::Shard;
+
+pub const MAC_KEY_SIZE: usize = 32;
+pub const MAC_SIZE: usize = 32;
+/// Bytes of the set id stored in front of each tag.
+pub const SET_ID_SIZE: usize = 16;
+
+/// The secret for keyed BLAKE3 shard tags. Unlike the per-shard CRC32C,
+/// which anyone can recompute, a tag can only be forged with the key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MacKey([u8; MAC_KEY_SIZE]);
+
+impl MacKey {
+    pub fn new(bytes: [u8; MAC_KEY_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; MAC_KEY_SIZE] {
+        &self.0
+    }
+
+    /// Identifies one encoding of `data`: a keyed hash of its length and
+    /// contents, so it reveals nothing about the data without the key.
+    pub fn set_id(&self, data: &[u8]) -> [u8; SET_ID_SIZE] {
+        let mut hasher: blake3::Hasher = blake3::Hasher::new_keyed(&self.0);
+        hasher.update(b"set");
+        hasher.update(&(data.len() as u64).to_be_bytes());
+        hasher.update(data);
+        let mut set_id: [u8; SET_ID_SIZE] = [0u8; SET_ID_SIZE];
+        set_id.copy_from_slice(&hasher.finalize().as_bytes()[..SET_ID_SIZE]);
+        set_id
+    }
+
+    fn tag(&self, set_id: &[u8; SET_ID_SIZE], index: u16, payload: &[u8]) -> blake3::Hash {
+        let mut hasher: blake3::Hasher = blake3::Hasher::new_keyed(&self.0);
+        hasher.update(b"tag");
+        hasher.update(set_id);
+        hasher.update(&index.to_be_bytes());
+        hasher.update(payload);
+        hasher.finalize()
+    }
+}
+
+impl fmt::Debug for MacKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MacKey(..)")
+    }
+}
+
+/// Appends the set id and a keyed BLAKE3 tag over it and the shard's
+/// index and payload, so a shard can neither be altered, passed off under
+/// another index, nor moved into another encoding.
+pub fn tag_shard(key: &MacKey, set_id: &[u8; SET_ID_SIZE], shard: &mut Shard) {
+    let tag: blake3::Hash = key.tag(set_id, shard.index, &shard.payload);
+    shard.payload.extend_from_slice(set_id);
+    shard.payload.extend_from_slice(tag.as_bytes());
+}
+
+/// Returns the set id and payload of a shard tagged by `tag_shard`, without
+/// the trailer, or `None` if the tag does not match. The comparison is
+/// constant-time.
+pub fn verify_shard<'a>(key: &MacKey, shard: &'a Shard) -> Option<([u8; SET_ID_SIZE], &'a [u8])> {
+    let split: usize = shard.payload.len().checked_sub(SET_ID_SIZE + MAC_SIZE)?;
+    let (payload, trailer) = shard.payload.split_at(split);
+    let mut set_id: [u8; SET_ID_SIZE] = [0u8; SET_ID_SIZE];
+    set_id.copy_from_slice(&trailer[..SET_ID_SIZE]);
+    let mut expected: [u8; MAC_SIZE] = [0u8; MAC_SIZE];
+    expected.copy_from_slice(&trailer[SET_ID_SIZE..]);
+    (key.tag(&set_id, shard.index, payload) == blake3::Hash::from(expected))
+        .then_some((set_id, payload))
+}
+
+impl ReedSolomonCodec {
+    /// Encodes like `encode_shards`, then tags every shard with the codec's
+    /// MAC key.
+    pub fn encode_shards_authenticated(&self, data: &[u8]) -> Result<Vec<Shard>, ReedSolomonError> {
+        let key: &MacKey = self.mac_key()?;
+        let set_id: [u8; SET_ID_SIZE] = key.set_id(data);
+        let mut shards: Vec<Shard> = self.encode_shards(data)?;
+        for shard in &mut shards {
+            tag_shard(key, &set_id, shard);
+        }
+        Ok(shards)
+    }
+
+    pub fn decode_shards_authenticated(
+        &self,
+        shards: Vec<Shard>,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_shards_authenticated_with_report(shards)
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    /// Checks each shard's tag and decodes the ones that pass. A shard with
+    /// a bad tag, or tagged for another encoding than most of the others,
+    /// is treated as an erasure and reported as corrupted. The decoded data
+    /// must match the set id the shards carry.
+    pub fn decode_shards_authenticated_with_report(
+        &self,
+        shards: Vec<Shard>,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let key: &MacKey = self.mac_key()?;
+        let mut rejected: Vec<usize> = Vec::new();
+        let mut verified: Vec<([u8; SET_ID_SIZE], Shard)> = Vec::with_capacity(shards.len());
+        for mut shard in shards {
+            match verify_shard(key, &shard) {
+                Some((set_id, payload)) => {
+                    let len: usize = payload.len();
+                    shard.payload.truncate(len);
+                    verified.push((set_id, shard));
+                }
+                None => rejected.push(shard.index as usize),
+            }
+        }
+
+        let set_id: Option<[u8; SET_ID_SIZE]> = verified
+            .iter()
+            .map(|(set_id, _): &([u8; SET_ID_SIZE], Shard)| *set_id)
+            .max_by_key(|candidate: &[u8; SET_ID_SIZE]| {
+                verified
+                    .iter()
+                    .filter(|(set_id, _): &&([u8; SET_ID_SIZE], Shard)| set_id == candidate)
+                    .count()
+            });
+        let mut accepted: Vec<Shard> = Vec::with_capacity(verified.len());
+        for (shard_set_id, shard) in verified {
+            if Some(shard_set_id) == set_id {
+                accepted.push(shard);
+            } else {
+                rejected.push(shard.index as usize);
+            }
+        }
+
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            self.decode_shards_rejecting(accepted, rejected)?;
+        if Some(key.set_id(&decoded)) != set_id {
+            return Err(ReedSolomonError::DigestMismatch);
+        }
+        Ok((decoded, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tagged_shards_verify() -> Result<(), ReedSolomonError> {
+        let key: MacKey = MacKey::new([7u8; MAC_KEY_SIZE]);
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?.with_mac_key(key.clone());
+        let original_data: &[u8] = b"tagged so a storage node cannot swap shard contents";
+
+        let plain: Vec<Shard> = codec.encode_shards(original_data)?;
+        let mut tagged: Vec<Shard> = codec.encode_shards_authenticated(original_data)?;
+        let set_id: [u8; SET_ID_SIZE] = key.set_id(original_data);
+        for (tagged, plain) in tagged.iter().zip(&plain) {
+            assert_eq!(
+                verify_shard(&key, tagged),
+                Some((set_id, &plain.payload[..]))
+            );
+        }
+        assert_eq!(
+            verify_shard(&MacKey::new([8u8; MAC_KEY_SIZE]), &tagged[0]),
+            None
+        );
+
+        tagged[0].index = 1;
+        assert_eq!(verify_shard(&key, &tagged[0]), None);
+        assert_eq!(format!("{:?}", key), "MacKey(..)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_treats_bad_tags_as_erasures() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::new(4, 2)?.with_mac_key(MacKey::new([1u8; MAC_KEY_SIZE]));
+        let original_data: Vec<u8> = (0..1000u32).map(|i: u32| (i % 251) as u8).collect();
+        let mut tagged: Vec<Shard> = codec.encode_shards_authenticated(&original_data)?;
+
+        // A substituted shard with a valid CRC32C still fails its tag.
+        let forged: Vec<Shard> = codec.encode_shards(&[0u8; 1000])?;
+        tagged[2].payload[..forged[2].payload.len()].copy_from_slice(&forged[2].payload);
+        tagged.remove(4);
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            codec.decode_shards_authenticated_with_report(tagged.clone())?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.corrupted, vec![2]);
+        assert_eq!(report.missing, vec![4]);
+        assert_eq!(report.reconstructed, vec![2]);
+
+        tagged[0].payload.truncate(SET_ID_SIZE + MAC_SIZE - 1);
+        assert!(codec.decode_shards_authenticated(tagged).is_err());
+        assert!(matches!(
+            ReedSolomonCodec::new(4, 2)?.encode_shards_authenticated(&original_data),
+            Err(ReedSolomonError::MissingMacKey)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shards_cannot_move_between_encodings() -> Result<(), ReedSolomonError> {
+        let key: MacKey = MacKey::new([3u8; MAC_KEY_SIZE]);
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?.with_mac_key(key.clone());
+        let first: Vec<u8> = (0..1000u32).map(|i: u32| (i % 251) as u8).collect();
+        let second: Vec<u8> = (0..1000u32).map(|i: u32| (i % 241) as u8).collect();
+        let mut shards: Vec<Shard> = codec.encode_shards_authenticated(&first)?;
+        let other: Vec<Shard> = codec.encode_shards_authenticated(&second)?;
+        assert_ne!(key.set_id(&first), key.set_id(&second));
+
+        // Shard 1 of the second object carries a valid tag, but for another
+        // set, so it is rejected rather than spliced into the first.
+        assert!(verify_shard(&key, &other[1]).is_some());
+        shards[1] = other[1].clone();
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            codec.decode_shards_authenticated_with_report(shards.clone())?;
+        assert_eq!(decoded, first);
+        assert_eq!(report.corrupted, vec![1]);
+
+        // Split three and three, neither set has enough shards, and the
+        // decode fails instead of returning a mix of the two.
+        shards[0] = other[0].clone();
+        shards[2] = other[2].clone();
+        assert!(codec.decode_shards_authenticated(shards).is_err());
+        Ok(())
+    }
+}
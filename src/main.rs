@@ -1,286 +1,1758 @@
-use byteorder::{BigEndian, ByteOrder};
-use reed_solomon_erasure::galois_8::ReedSolomon;
-use std::io::{self, Error, ErrorKind};
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
 
-const SHARD_LIMITS: ShardLimits = ShardLimits::new(1, 256);
-const DATA_SIZE_LIMITS: DataSizeLimits = DataSizeLimits::new(1, 1 << 32);
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use reed_solomon_codec::archive::{ArchiveReader, ArchiveSummary, ArchiveWriter};
+use reed_solomon_codec::armor::{ArmoredBlock, DATA_LABEL};
+use reed_solomon_codec::bundle::{decode_bundle, decode_bundle_file, BundleReader};
+#[cfg(feature = "chunking")]
+use reed_solomon_codec::chunking::decode_chunked;
+use reed_solomon_codec::files::{
+    decode_from_dir, find_shard_sets, manifest_path, ShardManifest, ShardSet, MANIFEST_EXTENSION,
+};
+use reed_solomon_codec::header::{DIGEST_SIZE, GROUP_HEADER_SIZE, GROUP_MAGIC, HEADER_SIZE};
+use reed_solomon_codec::json_manifest::{
+    decode_from_json_manifest, repair_from_json_manifest, verify_from_json_manifest,
+    JSON_MANIFEST_EXTENSION,
+};
+#[cfg(feature = "par2")]
+use reed_solomon_codec::par2;
+use reed_solomon_codec::platform::{long_path, portable_file_name};
+use reed_solomon_codec::scrub;
+use reed_solomon_codec::sparse::{decode_sparse_from_files, sparse_map_path};
+#[cfg(feature = "chunking")]
+use reed_solomon_codec::store::RECIPE_EXTENSION;
+use reed_solomon_codec::store::{decode_from_shard_store, read_manifest, repair_shard_store};
+use reed_solomon_codec::stream::leading_header;
+use reed_solomon_codec::trailer::{Trailer, TRAILER_FOOTER_SIZE, TRAILER_MAGIC};
+use reed_solomon_codec::verify::verify_files;
+use reed_solomon_codec::watch::WatchEvent;
+#[cfg(feature = "zfec")]
+use reed_solomon_codec::zfec;
+use reed_solomon_codec::{
+    ArmorError, BundleIndex, Compression, ContainerHeader, DropFolder, EncodePlan, EncoderConfig,
+    FileStore, GaloisField, HybridPolicy, JsonManifest, ManifestError, Preset, ProgressCounter,
+    ProgressSink, ProgressUpdate, RateLimiter, ReedSolomonCodec, ReedSolomonError, ScrubReport,
+    Scrubber, ShardId, ShardLayout, ShardServer, SparseMap, Throttled, VerifyReport,
+    DEFAULT_BLOCK_SIZE,
+};
+#[cfg(feature = "chunking")]
+use reed_solomon_codec::{ChunkReport, FastCdc};
 
-#[derive(Debug, Clone, Copy)]
-pub struct ShardLimits {
-    pub min: usize,
-    pub max: usize,
+/// Stands for stdin as an input and stdout as an output.
+const STDIO: &str = "-";
+
+#[derive(Debug, Parser)]
+#[command(name = "rs", version, about = "Reed-Solomon erasure coding for files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Print progress and an estimated time remaining to stderr
+    #[arg(long, global = true)]
+    progress: bool,
+
+    /// Limit encoding and decoding to about BYTES per second, so background
+    /// jobs leave disk and network bandwidth for other traffic
+    #[arg(long, global = true, value_name = "BYTES")]
+    rate_limit: Option<u64>,
+
+    /// Read and write shard files with O_DIRECT where the platform allows
+    /// it, so large jobs do not evict everything else from the page cache
+    #[arg(long, global = true)]
+    direct_io: bool,
 }
 
-impl ShardLimits {
-    const fn new(min: usize, max: usize) -> Self {
-        Self { min, max }
-    }
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Encode a file into a Reed-Solomon container
+    Encode(EncodeArgs),
+
+    /// Decode a container, a shard manifest or a directory of shard files
+    /// back into the original file. A container can be read from stdin and
+    /// any input decoded to stdout by passing `-`. A set encoded from
+    /// several files is unpacked into the OUTPUT directory
+    Decode {
+        input: PathBuf,
+        output: PathBuf,
+
+        /// Decode only the file NAME of a set encoded from several files
+        #[arg(long, value_name = "NAME")]
+        file: Option<String>,
+    },
+
+    /// Pack every file under a directory into one stream and encode it,
+    /// taking the same options as `encode`
+    EncodeDir(EncodeArgs),
+
+    /// Decode the output of `encode-dir` and unpack its files into the
+    /// OUTPUT directory
+    DecodeDir { input: PathBuf, output: PathBuf },
+
+    /// Check the shard files listed by a manifest (`.manifest` or
+    /// `.manifest.json`) and rewrite any that are missing, truncated or fail
+    /// their checksums
+    Repair { manifest: PathBuf },
+
+    /// Re-encode the shard files listed by a manifest with new shard counts,
+    /// one block at a time, then rewrite the manifest and remove the old
+    /// shard files. Other settings are kept
+    Reshard {
+        manifest: PathBuf,
+
+        /// New number of data shards
+        #[arg(long, value_name = "COUNT")]
+        to_data: usize,
+
+        /// New number of parity shards
+        #[arg(long, value_name = "COUNT")]
+        to_parity: usize,
+    },
+
+    /// Check the checksums and parity of a container, a shard manifest or
+    /// every shard set in a directory without decoding anything. Exits
+    /// with status 2 when something is damaged
+    Verify { input: PathBuf },
+
+    /// Verify every shard set in ROOT and the directories below it, repair
+    /// the damaged shards of sets that can still be decoded, correct blocks
+    /// whose parity disagrees with their data, and write a JSON report of
+    /// healthy, degraded and lost sets. Exits with status 2
+    /// when a set is left degraded or lost
+    Scrub {
+        root: PathBuf,
+
+        /// Only check, without rewriting any shard
+        #[arg(long)]
+        no_repair: bool,
+
+        /// File to write the JSON report to, or `-` for stdout
+        #[arg(long, value_name = "FILE", default_value = STDIO)]
+        report: PathBuf,
+
+        /// Keep running and scrub each set again every SECONDS, appending a
+        /// one-line JSON report to FILE for every pass that checked something
+        #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+    },
+
+    /// Describe a container, a shard manifest or every shard set in a
+    /// directory: configuration, sizes, checksums and whether it can still
+    /// be recovered
+    Info { input: PathBuf },
+
+    /// Turn files, such as shard files, into base64 text with a checksum
+    /// that can be pasted into an email or printed as a paper backup
+    Armor {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Text file to write, or `-` for stdout
+        #[arg(long, short, value_name = "FILE", default_value = STDIO)]
+        output: PathBuf,
+    },
+
+    /// Restore every file in armored text, read from a file or `-` for
+    /// stdin, into the OUTPUT directory
+    Dearmor { input: PathBuf, output: PathBuf },
+
+    /// Store shard objects sent over TCP in the DIR directory and serve
+    /// them back, so this process can act as one node of a `ShardCluster`
+    Serve {
+        dir: PathBuf,
+
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:7878")]
+        listen: String,
+    },
+
+    /// Encode every file in DIR into its own shard set in the --out
+    /// directory, then keep running and encode each file again as it is
+    /// written or moved into DIR
+    Watch {
+        dir: PathBuf,
+
+        /// Directory to write the shard sets to
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+
+        /// Number of data shards
+        #[arg(long = "data", value_name = "COUNT", default_value_t = 10)]
+        data_shards: usize,
+
+        /// Number of parity shards
+        #[arg(long = "parity", value_name = "COUNT", default_value_t = 4)]
+        parity_shards: usize,
+    },
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct DataSizeLimits {
-    pub min: usize,
-    pub max: usize,
+#[derive(Debug, Args)]
+struct EncodeArgs {
+    /// Number of data shards
+    #[arg(
+        long = "data",
+        value_name = "COUNT",
+        required_unless_present = "preset"
+    )]
+    data_shards: Option<usize>,
+
+    /// Number of parity shards
+    #[arg(
+        long = "parity",
+        value_name = "COUNT",
+        required_unless_present = "preset"
+    )]
+    parity_shards: Option<usize>,
+
+    /// Take the shard counts and field polynomial from a standard code:
+    /// dvb-t, cd-c1, cd-c2, ccsds, qr-1l, qr-1m, qr-1q or qr-1h
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = parse_preset,
+        conflicts_with_all = ["data_shards", "parity_shards", "gf16", "polynomial"]
+    )]
+    preset: Option<Preset>,
+
+    /// Use GF(2^16), allowing up to 65536 shards in total
+    #[arg(long)]
+    gf16: bool,
+
+    /// Build GF(2^8) on this primitive polynomial, such as 0x187, instead
+    /// of 0x11d, so the shards match those of other Reed-Solomon tools
+    #[arg(long, value_name = "POLY", value_parser = parse_polynomial, conflicts_with = "gf16")]
+    polynomial: Option<u16>,
+
+    /// Size in bytes of each independently encoded block. By default it is
+    /// picked from the input size and shard counts, or 1 MiB for stdin
+    #[arg(long, value_name = "BYTES")]
+    block_size: Option<usize>,
+
+    /// Zero-pad every shard of a block, checksum included, to a multiple of
+    /// BYTES, such as 4096 for shard files read with O_DIRECT
+    #[arg(long, value_name = "BYTES", default_value_t = 1)]
+    align: usize,
+
+    /// Number of consecutive blocks whose shards are interleaved on output,
+    /// so a long burst of corruption only costs each block a few shards
+    #[arg(long, value_name = "DEPTH", default_value_t = 1)]
+    interleave: usize,
+
+    /// Compress each block with zstd before encoding
+    #[arg(long)]
+    compress: bool,
+
+    /// Store only parity shards, so no shard holds the input verbatim and
+    /// --data shards are needed to read anything back
+    #[arg(long)]
+    non_systematic: bool,
+
+    /// Store blocks smaller than BYTES as --parity plus one full copies
+    /// instead of erasure coding them, which reads tiny files back faster
+    #[arg(long, value_name = "BYTES", conflicts_with = "split")]
+    replicate_below: Option<usize>,
+
+    /// Append a trailer with a copy of every block header, so the
+    /// container still decodes when its beginning is damaged
+    #[arg(long, conflicts_with = "split")]
+    trailer: bool,
+
+    /// Write each shard to its own file in the OUTPUT directory
+    #[arg(long)]
+    split: bool,
+
+    /// With --split, cut the input into content-defined chunks and encode
+    /// each into its own shard set, skipping chunks already in OUTPUT, so
+    /// re-encoding a changed file only writes the chunks around the change
+    #[cfg(feature = "chunking")]
+    #[arg(long, requires = "split")]
+    chunked: bool,
+
+    /// Also write PAR2 recovery files with COUNT recovery slices next to
+    /// the output, so it can be verified and repaired with par2 tooling
+    #[cfg(feature = "par2")]
+    #[arg(long, value_name = "COUNT")]
+    par2: Option<usize>,
+
+    /// Write zfec-compatible share files (`<name>.NN_MM.fec`) to the
+    /// OUTPUT directory instead of a container, using --data as k and
+    /// --data plus --parity as m
+    #[cfg(feature = "zfec")]
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "split",
+            "gf16",
+            "polynomial",
+            "compress",
+            "non_systematic",
+            "replicate_below",
+            "trailer",
+        ]
+    )]
+    zfec: bool,
+
+    /// Report the shard sizes, block count and overhead the encoding would
+    /// produce without writing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Basename for the shard or share files, needed with --split when the
+    /// input is stdin
+    #[arg(long, value_name = "NAME")]
+    name: Option<String>,
+
+    /// File to encode, or `-` to stream stdin. With --split and --name,
+    /// several files are encoded back to back into one shard set whose
+    /// manifest records where each of them starts
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Container to write, or `-` for stdout; a directory with --split
+    output: PathBuf,
 }
 
-impl DataSizeLimits {
-    const fn new(min: usize, max: usize) -> Self {
-        Self { min, max }
+impl EncodeArgs {
+    fn with_native_paths(self) -> Self {
+        Self {
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input: &PathBuf| native_path(input))
+                .collect(),
+            output: native_path(&self.output),
+            ..self
+        }
+    }
+
+    /// Exits with a usage error when several inputs are given where they
+    /// cannot be bundled into one shard set.
+    fn checked_inputs(self, directory: bool) -> Self {
+        #[cfg(feature = "chunking")]
+        let chunked: bool = self.chunked;
+        #[cfg(not(feature = "chunking"))]
+        let chunked: bool = false;
+        if self.inputs.len() > 1
+            && (directory
+                || chunked
+                || !self.split
+                || self.name.is_none()
+                || self.inputs.iter().any(|input: &PathBuf| is_stdio(input)))
+        {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "several inputs are only encoded by `encode --split --name NAME`, \
+                     and cannot include stdin",
+                )
+                .exit();
+        }
+        self
+    }
+
+    fn input(&self) -> &Path {
+        &self.inputs[0]
     }
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum ReedSolomonError {
-    #[error("Invalid shard count: {0}")]
-    InvalidShardCount(String),
+fn main() -> ExitCode {
+    let cli: Cli = Cli::parse();
+    let limiter: Option<Arc<RateLimiter>> = cli
+        .rate_limit
+        .map(|bytes_per_second: u64| Arc::new(RateLimiter::new(bytes_per_second)));
+    let limiter: Option<&Arc<RateLimiter>> = limiter.as_ref();
 
-    #[error("Invalid data size: {0}")]
-    InvalidDataSize(String),
+    let result: Result<(), ReedSolomonError> = match cli.command {
+        Command::Encode(args) => encode(
+            &args.with_native_paths().checked_inputs(false),
+            cli.progress,
+            limiter,
+            cli.direct_io,
+            false,
+        ),
+        Command::Decode {
+            input,
+            output,
+            file,
+        } => decode(
+            &native_path(&input),
+            &native_path(&output),
+            file.as_deref(),
+            cli.progress,
+            limiter,
+            cli.direct_io,
+        ),
+        Command::EncodeDir(args) => encode(
+            &args.with_native_paths().checked_inputs(true),
+            cli.progress,
+            limiter,
+            cli.direct_io,
+            true,
+        ),
+        Command::DecodeDir { input, output } => decode_dir(
+            &native_path(&input),
+            &native_path(&output),
+            cli.progress,
+            limiter,
+            cli.direct_io,
+        ),
+        Command::Repair { manifest } => repair(&native_path(&manifest)),
+        Command::Reshard {
+            manifest,
+            to_data,
+            to_parity,
+        } => reshard(
+            &native_path(&manifest),
+            to_data,
+            to_parity,
+            cli.progress,
+            limiter,
+        ),
+        Command::Verify { input } => match verify(&native_path(&input)) {
+            Ok(true) => Ok(()),
+            Ok(false) => return ExitCode::from(2),
+            Err(e) => Err(e),
+        },
+        Command::Scrub {
+            root,
+            no_repair,
+            report,
+            interval: Some(seconds),
+        } => scrub_every(
+            &native_path(&root),
+            !no_repair,
+            &report,
+            Duration::from_secs(seconds),
+        ),
+        Command::Scrub {
+            root,
+            no_repair,
+            report,
+            interval: None,
+        } => match scrub(&native_path(&root), !no_repair, &report) {
+            Ok(true) => Ok(()),
+            Ok(false) => return ExitCode::from(2),
+            Err(e) => Err(e),
+        },
+        Command::Info { input } => info(&native_path(&input)),
+        Command::Armor { inputs, output } => armor(&inputs, &output),
+        Command::Dearmor { input, output } => dearmor(&input, &output),
+        Command::Serve { dir, listen } => serve(&dir, &listen),
+        Command::Watch {
+            dir,
+            out,
+            data_shards,
+            parity_shards,
+        } => watch(
+            &native_path(&dir),
+            &native_path(&out),
+            data_shards,
+            parity_shards,
+            cli.direct_io,
+        ),
+    };
 
-    #[error("Codec error: {0}")]
-    CodecError(String),
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    #[error("Encoding error: {0}")]
-    EncodingError(String),
+struct ProgressLine {
+    counter: ProgressCounter,
+}
 
-    #[error("Decoding error: {0}")]
-    DecodingError(String),
+impl ProgressLine {
+    fn attach(
+        codec: ReedSolomonCodec,
+        enabled: bool,
+        total_bytes: Option<u64>,
+    ) -> ReedSolomonCodec {
+        if !enabled {
+            return codec;
+        }
+        codec.with_progress(Arc::new(Self {
+            counter: ProgressCounter::new(total_bytes),
+        }))
+    }
 }
 
-impl From<ReedSolomonError> for io::Error {
-    fn from(error: ReedSolomonError) -> Self {
-        let kind = match error {
-            ReedSolomonError::InvalidShardCount(_) | ReedSolomonError::InvalidDataSize(_) => {
-                ErrorKind::InvalidInput
-            }
-            _ => ErrorKind::Other,
-        };
-        Error::new(kind, error.to_string())
+impl ProgressSink for ProgressLine {
+    fn report(&self, update: ProgressUpdate) {
+        self.counter.report(update);
+
+        let mut line: String = format!(
+            "{} bytes, {} shards",
+            self.counter.bytes(),
+            self.counter.shards()
+        );
+        if let (Some(fraction), Some(eta)) = (self.counter.fraction(), self.counter.eta()) {
+            line.push_str(&format!(
+                " ({:.1}%, {}s left)",
+                fraction * 100.0,
+                eta.as_secs()
+            ));
+        }
+        eprint!("\r{}", line);
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct EncoderConfig {
-    data_shards: usize,
-    parity_shards: usize,
-    total_shards: usize,
+/// Applies the rate limit to a codec's own reads and writes.
+fn rate_limited(codec: ReedSolomonCodec, limiter: Option<&Arc<RateLimiter>>) -> ReedSolomonCodec {
+    match limiter {
+        Some(limiter) => codec.with_rate_limit(Arc::clone(limiter)),
+        None => codec,
+    }
 }
 
-impl EncoderConfig {
-    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, ReedSolomonError> {
-        if !Self::is_valid_shard_count(data_shards) {
-            return Err(ReedSolomonError::InvalidShardCount(format!(
-                "Data shards must be between {} and {}",
-                SHARD_LIMITS.min, SHARD_LIMITS.max
-            )));
+/// Applies the rate limit to output written by codecs the library builds
+/// itself, such as when decoding shard files.
+fn throttled<'a, W: Write + 'a>(
+    writer: W,
+    limiter: Option<&Arc<RateLimiter>>,
+) -> Box<dyn Write + 'a> {
+    match limiter {
+        Some(limiter) => Box::new(Throttled::new(writer, Arc::clone(limiter))),
+        None => Box::new(writer),
+    }
+}
+
+fn encode(
+    args: &EncodeArgs,
+    progress: bool,
+    limiter: Option<&Arc<RateLimiter>>,
+    direct_io: bool,
+    directory: bool,
+) -> Result<(), ReedSolomonError> {
+    let field: GaloisField = if args.gf16 {
+        GaloisField::Gf16
+    } else {
+        GaloisField::Gf8
+    };
+    let compression: Compression = if args.compress {
+        Compression::Zstd
+    } else {
+        Compression::None
+    };
+    let layout: ShardLayout = if args.non_systematic {
+        ShardLayout::NonSystematic
+    } else {
+        ShardLayout::Systematic
+    };
+    let config: EncoderConfig = match (args.preset, args.data_shards, args.parity_shards) {
+        (Some(preset), _, _) => preset.config()?,
+        (None, Some(data_shards), Some(parity_shards)) => {
+            EncoderConfig::with_field(data_shards, parity_shards, field)?
+        }
+        _ => unreachable!("clap requires --data and --parity without --preset"),
+    };
+    let config: EncoderConfig = match args.polynomial {
+        Some(polynomial) => config.with_polynomial(polynomial)?,
+        None => config,
+    };
+    let config: EncoderConfig = config
+        .with_interleave_depth(args.interleave)?
+        .with_shard_alignment(args.align)?
+        .with_compression(compression)?
+        .with_layout(layout)?;
+    let (input, output): (&Path, &Path) = (args.input(), &args.output);
+    #[cfg(feature = "par2")]
+    if args.par2.is_some() && is_stdio(output) {
+        return Err(ReedSolomonError::InvalidFileName(output.to_path_buf()));
+    }
+
+    let bundle: Option<BundleIndex> = match args.inputs.len() {
+        1 => None,
+        _ => Some(BundleIndex::scan(&args.inputs)?),
+    };
+    let (reader, input_len): (Box<dyn Read>, Option<u64>) = if let Some(bundle) = &bundle {
+        (
+            Box::new(BundleReader::new(&args.inputs, bundle)),
+            Some(bundle.payload_len()),
+        )
+    } else if directory {
+        let archive: ArchiveReader = ArchiveReader::new(input)?;
+        let input_len: u64 = archive.total_len();
+        (Box::new(archive), Some(input_len))
+    } else if is_stdio(input) {
+        (Box::new(io::stdin().lock()), None)
+    } else {
+        (
+            Box::new(BufReader::new(File::open(input)?)),
+            Some(input.metadata()?.len()),
+        )
+    };
+    let config: EncoderConfig = match (args.block_size, input_len) {
+        (Some(block_size), _) => config.with_block_size(block_size)?,
+        (None, Some(input_len)) => config.with_auto_block_size(input_len)?,
+        (None, None) => config.with_block_size(DEFAULT_BLOCK_SIZE)?,
+    };
+    let codec: ReedSolomonCodec = match args.replicate_below {
+        Some(threshold) => ReedSolomonCodec::with_config(config)?
+            .with_hybrid_policy(HybridPolicy::matching(&config, threshold)?),
+        None => ReedSolomonCodec::with_config(config)?,
+    };
+    let codec: ReedSolomonCodec =
+        rate_limited(ProgressLine::attach(codec, progress, input_len), limiter);
+
+    if args.dry_run {
+        let input_len: u64 =
+            input_len.ok_or_else(|| ReedSolomonError::InvalidFileName(input.to_path_buf()))?;
+        let plan: EncodePlan = if args.split {
+            codec.plan_files(input_len)
+        } else {
+            codec.plan(input_len)?
+        };
+        print_plan(input, &plan);
+        return Ok(());
+    }
+
+    #[cfg(feature = "zfec")]
+    if args.zfec {
+        let basename: String = output_basename(args)?;
+        let reader: Box<dyn Read> = match limiter {
+            Some(limiter) => Box::new(Throttled::new(reader, Arc::clone(limiter))),
+            None => reader,
+        };
+        zfec::encode_to_zfec_files(
+            reader,
+            output,
+            &basename,
+            config.data_shards(),
+            config.total_shards(),
+        )?;
+
+        println!(
+            "Encoded {} into {} zfec shares in {}",
+            input.display(),
+            config.total_shards(),
+            output.display()
+        );
+        return Ok(());
+    }
+
+    #[cfg(feature = "chunking")]
+    if args.chunked {
+        let basename: String = output_basename(args)?;
+        let report: ChunkReport = codec.encode_chunked(
+            reader,
+            &FileStore::new(output),
+            &basename,
+            &FastCdc::default(),
+        )?;
+        if progress {
+            eprintln!();
         }
 
-        if !Self::is_valid_shard_count(parity_shards) {
-            return Err(ReedSolomonError::InvalidShardCount(format!(
-                "Parity shards must be between {} and {}",
-                SHARD_LIMITS.min, SHARD_LIMITS.max
-            )));
+        println!(
+            "Encoded {} into {} chunks in {} ({} already stored)",
+            input.display(),
+            report.recipe.chunks.len(),
+            output.display(),
+            report.reused
+        );
+        return Ok(());
+    }
+
+    if args.split {
+        let basename: String = output_basename(args)?;
+        let store: FileStore = file_store(output, direct_io);
+        let holes: u64 = if let Some(bundle) = &bundle {
+            let manifest: ShardManifest = codec.encode_to_file_store(reader, &store, &basename)?;
+            bundle.write(&store, &basename, &manifest)?;
+            0
+        } else if directory || is_stdio(input) {
+            codec.encode_to_file_store(reader, &store, &basename)?;
+            0
+        } else {
+            let (_, map): (ShardManifest, SparseMap) =
+                codec.encode_sparse_file_to_file_store(input, &store, &basename)?;
+            map.hole_len()
+        };
+        #[cfg(feature = "par2")]
+        if let Some(recovery_slices) = args.par2 {
+            par2::write_par2_for_shards(output, &basename, recovery_slices)?;
+        }
+        if progress {
+            eprintln!();
         }
 
-        let total_shards = data_shards + parity_shards;
-        if total_shards > SHARD_LIMITS.max {
-            return Err(ReedSolomonError::InvalidShardCount(format!(
-                "Total shards ({}) exceeds maximum allowed ({})",
-                total_shards, SHARD_LIMITS.max
-            )));
+        let source: String = match &bundle {
+            Some(bundle) => format!("{} files", bundle.entries.len()),
+            None => input.display().to_string(),
+        };
+        println!(
+            "Encoded {} into {} shard files in {} ({} byte blocks)",
+            source,
+            config.total_shards(),
+            output.display(),
+            config.block_size()
+        );
+        if holes > 0 {
+            println!("Skipped {} bytes of holes", holes);
         }
+        return Ok(());
+    }
 
-        Ok(Self {
-            data_shards,
-            parity_shards,
-            total_shards,
-        })
+    #[cfg(feature = "mmap")]
+    let written: u64 = if args.trailer {
+        codec.encode_stream_with_trailer(reader, create_output(output)?)?
+    } else if directory || is_stdio(input) || is_stdio(output) {
+        codec.encode_stream(reader, create_output(output)?)?
+    } else {
+        codec.encode_file(input, output)?
+    };
+    #[cfg(not(feature = "mmap"))]
+    let written: u64 = if args.trailer {
+        codec.encode_stream_with_trailer(reader, create_output(output)?)?
+    } else {
+        codec.encode_stream(reader, create_output(output)?)?
+    };
+    if progress {
+        eprintln!();
+    }
+    #[cfg(feature = "par2")]
+    if let Some(recovery_slices) = args.par2 {
+        let dir: &Path = output.parent().unwrap_or(Path::new("."));
+        let slice_size: u64 = par2::slice_size_for(written, par2::DEFAULT_SOURCE_SLICES);
+        par2::write_par2(
+            &[output.to_path_buf()],
+            dir,
+            &file_basename(output)?,
+            slice_size,
+            recovery_slices,
+        )?;
+    }
+
+    summary(
+        output,
+        format!(
+            "Encoded {} into {} ({} bytes, {} data + {} parity shards, {} byte blocks)",
+            input.display(),
+            output.display(),
+            written,
+            config.data_shards(),
+            config.parity_shards(),
+            config.block_size()
+        ),
+    );
+    Ok(())
+}
+
+fn decode(
+    input: &Path,
+    output: &Path,
+    file: Option<&str>,
+    progress: bool,
+    limiter: Option<&Arc<RateLimiter>>,
+    direct_io: bool,
+) -> Result<(), ReedSolomonError> {
+    if let Some((dir, basename)) = bundle_shard_set(input)? {
+        let store: FileStore = file_store(&dir, direct_io);
+        return decode_bundle_set(&store, &basename, input, output, file, limiter);
+    }
+    if let Some(name) = file {
+        return Err(ReedSolomonError::InvalidFileName(PathBuf::from(name)));
     }
 
-    fn is_valid_shard_count(count: usize) -> bool {
-        (SHARD_LIMITS.min..=SHARD_LIMITS.max).contains(&count)
+    let sparse: Option<(PathBuf, String)> = sparse_shard_set(input)?;
+    #[cfg(feature = "mmap")]
+    let written: u64 = if let Some((dir, basename)) = sparse {
+        decode_sparse(&dir, &basename, output)?
+    } else if is_shard_set(input) || is_stdio(input) || is_stdio(output) || has_trailer(input)? {
+        decode_into(input, create_output(output)?, progress, limiter, direct_io)?
+    } else {
+        rate_limited(open_container(input, progress)?.0, limiter).decode_file(input, output)?
+    };
+    #[cfg(not(feature = "mmap"))]
+    let written: u64 = match sparse {
+        Some((dir, basename)) => decode_sparse(&dir, &basename, output)?,
+        None => decode_into(input, create_output(output)?, progress, limiter, direct_io)?,
+    };
+    if progress {
+        eprintln!();
     }
+
+    summary(
+        output,
+        format!(
+            "Decoded {} into {} ({} bytes)",
+            input.display(),
+            output.display(),
+            written
+        ),
+    );
+    Ok(())
 }
 
-#[derive(Debug)]
-pub struct ReedSolomonCodec {
-    codec: ReedSolomon,
-    config: EncoderConfig,
+fn decode_dir(
+    input: &Path,
+    output: &Path,
+    progress: bool,
+    limiter: Option<&Arc<RateLimiter>>,
+    direct_io: bool,
+) -> Result<(), ReedSolomonError> {
+    let mut unpacker: ArchiveWriter = ArchiveWriter::new(output)?;
+    decode_into(input, &mut unpacker, progress, limiter, direct_io)?;
+    let summary: ArchiveSummary = unpacker.finish()?;
+    if progress {
+        eprintln!();
+    }
+
+    println!(
+        "Extracted {} files and {} directories ({} bytes) from {} into {}",
+        summary.files,
+        summary.directories,
+        summary.bytes,
+        input.display(),
+        output.display()
+    );
+    Ok(())
 }
 
-impl ReedSolomonCodec {
-    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, ReedSolomonError> {
-        let config: EncoderConfig = EncoderConfig::new(data_shards, parity_shards)?;
+/// Decodes a container, shard manifest, directory of shard files or zfec
+/// share set, whichever `input` names.
+fn decode_into<W: Write>(
+    input: &Path,
+    writer: W,
+    progress: bool,
+    limiter: Option<&Arc<RateLimiter>>,
+    direct_io: bool,
+) -> Result<u64, ReedSolomonError> {
+    if input.is_dir() {
+        for set in find_shard_sets(input)? {
+            report_shard_set(&set);
+        }
+        return decode_from_dir(input, throttled(writer, limiter));
+    }
 
-        let codec: reed_solomon_erasure::ReedSolomon<reed_solomon_erasure::galois_8::Field> =
-            ReedSolomon::new(config.data_shards, config.parity_shards).map_err(
-                |e: reed_solomon_erasure::Error| ReedSolomonError::CodecError(e.to_string()),
-            )?;
+    if is_json_manifest(input) {
+        return decode_from_json_manifest(input, throttled(writer, limiter));
+    }
 
-        Ok(Self { codec, config })
+    if input.extension().and_then(OsStr::to_str) == Some(MANIFEST_EXTENSION) {
+        let dir: &Path = input.parent().unwrap_or(Path::new("."));
+        let basename: String = file_basename(&input.with_extension(""))?;
+        return decode_from_shard_store(
+            &file_store(dir, direct_io),
+            &basename,
+            throttled(writer, limiter),
+        );
     }
 
-    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
-        let encoded_data: Vec<u8> = DataProcessor::prepare_data(data)?;
-        let mut shards: Vec<Vec<u8>> = DataProcessor::split_into_shards(
-            &encoded_data,
-            self.config.data_shards,
-            self.config.total_shards,
-        )?;
+    #[cfg(feature = "chunking")]
+    if input.extension().and_then(OsStr::to_str) == Some(RECIPE_EXTENSION) {
+        let dir: &Path = input.parent().unwrap_or(Path::new("."));
+        let basename: String = file_basename(&input.with_extension(""))?;
+        return decode_chunked(&FileStore::new(dir), &basename, throttled(writer, limiter));
+    }
 
-        let mut shard_refs: Vec<&mut [u8]> = shards
-            .iter_mut()
-            .map(|shard: &mut Vec<u8>| shard.as_mut_slice())
-            .collect();
+    #[cfg(feature = "zfec")]
+    if input.extension().and_then(OsStr::to_str) == Some(zfec::ZFEC_EXTENSION) {
+        let shares: Vec<PathBuf> = zfec::find_zfec_shares(input)?;
+        return zfec::decode_from_zfec_files(&shares, throttled(writer, limiter));
+    }
+
+    if has_trailer(input)? {
+        return decode_with_trailer(input, throttled(writer, limiter), progress);
+    }
+
+    let (codec, prefix, reader): OpenedContainer = open_container(input, progress)?;
+    rate_limited(codec, limiter).decode_stream(Cursor::new(prefix).chain(reader), writer)
+}
 
-        self.codec
-            .encode(&mut shard_refs)
-            .map_err(|e: reed_solomon_erasure::Error| {
-                ReedSolomonError::EncodingError(e.to_string())
-            })?;
+fn file_store(dir: &Path, direct_io: bool) -> FileStore {
+    if direct_io {
+        FileStore::new(dir).with_direct_io()
+    } else {
+        FileStore::new(dir)
+    }
+}
 
-        Ok(shards.into_iter().flatten().collect())
+#[cfg(feature = "mmap")]
+fn is_shard_set(input: &Path) -> bool {
+    let extension: Option<&str> = input.extension().and_then(OsStr::to_str);
+    #[cfg(feature = "chunking")]
+    if extension == Some(RECIPE_EXTENSION) {
+        return true;
     }
+    #[cfg(feature = "zfec")]
+    if extension == Some(zfec::ZFEC_EXTENSION) {
+        return true;
+    }
+    input.is_dir() || is_json_manifest(input) || extension == Some(MANIFEST_EXTENSION)
+}
+
+fn report_shard_set(set: &ShardSet) {
+    let missing: Vec<usize> = set.missing();
+    let mut line: String = format!(
+        "Found {} ({} data + {} parity shards): {} of {} present",
+        set.basename,
+        set.data_shards,
+        set.parity_shards,
+        set.present().len(),
+        set.paths.len()
+    );
+    if !missing.is_empty() {
+        line.push_str(&format!(", missing {:?}", missing));
+    }
+    if set.stale {
+        line.push_str(", stale generation, ignored");
+    } else if set.manifest.is_none() {
+        line.push_str(", no manifest");
+    }
+    eprintln!("{}", line);
+}
+
+/// A container's codec, the bytes read to find it and the rest of the input.
+type OpenedContainer = (ReedSolomonCodec, Vec<u8>, Box<dyn Read>);
+
+/// Decodes a container written with `--trailer` one block at a time,
+/// taking the headers of any damaged block from the trailer.
+fn decode_with_trailer<W: Write>(
+    input: &Path,
+    writer: W,
+    progress: bool,
+) -> Result<u64, ReedSolomonError> {
+    let mut file: File = File::open(input)?;
+    let header: ContainerHeader = match Trailer::read_from(&mut file) {
+        Ok((trailer, _)) if !trailer.entries.is_empty() => {
+            leading_header(&trailer.entries[0].headers)?
+        }
+        _ => {
+            let mut prefix: Vec<u8> = Vec::new();
+            file.rewind()?;
+            (&mut file)
+                .take((GROUP_HEADER_SIZE + HEADER_SIZE) as u64)
+                .read_to_end(&mut prefix)?;
+            leading_header(&prefix)?
+        }
+    };
+    let codec: ReedSolomonCodec =
+        ProgressLine::attach(ReedSolomonCodec::from_header(&header)?, progress, None);
+
+    codec.decode_file_with_trailer(BufReader::new(file), writer)
+}
+
+/// The directory and basename of the shard set `input` names: its
+/// manifest, or a directory that holds one set with a manifest.
+fn manifest_shard_set(input: &Path) -> Result<Option<(PathBuf, String)>, ReedSolomonError> {
+    let (dir, basename): (PathBuf, String) =
+        if input.extension().and_then(OsStr::to_str) == Some(MANIFEST_EXTENSION) {
+            (
+                input.parent().unwrap_or(Path::new(".")).to_path_buf(),
+                file_basename(&input.with_extension(""))?,
+            )
+        } else if is_json_manifest(input) {
+            (
+                input.parent().unwrap_or(Path::new(".")).to_path_buf(),
+                file_basename(&input.with_extension("").with_extension(""))?,
+            )
+        } else if input.is_dir() {
+            let mut sets: Vec<ShardSet> = find_shard_sets(input)?
+                .into_iter()
+                .filter(|set: &ShardSet| set.manifest.is_some())
+                .collect();
+            match (sets.pop(), sets.is_empty()) {
+                (Some(set), true) => (input.to_path_buf(), set.basename),
+                _ => return Ok(None),
+            }
+        } else {
+            return Ok(None);
+        };
+    Ok(Some((dir, basename)))
+}
 
-    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
-        let shares: Vec<Vec<u8>> =
-            DataProcessor::validate_and_split_shares(data, self.config.total_shards)?;
-        let shard_size: usize = shares[0].len();
+/// The shard set `input` names, when its manifest records a sparse file or
+/// a sparse map lies next to it, so that a map that is missing or does
+/// not match fails the decode.
+fn sparse_shard_set(input: &Path) -> Result<Option<(PathBuf, String)>, ReedSolomonError> {
+    let Some((dir, basename)) = manifest_shard_set(input)? else {
+        return Ok(None);
+    };
+    let sparse: bool = sparse_map_path(&dir, &basename).exists()
+        || read_manifest(&FileStore::new(&dir), &basename)
+            .is_ok_and(|manifest: ShardManifest| manifest.sparse.is_some());
+    Ok(sparse.then_some((dir, basename)))
+}
 
-        let mut decode_buffer: Vec<u8> = vec![0u8; shard_size * self.config.data_shards];
-        let mut decode_shards: Vec<_> = decode_buffer.chunks_mut(shard_size).collect();
+/// The shard set `input` names when its manifest lists several files.
+fn bundle_shard_set(input: &Path) -> Result<Option<(PathBuf, String)>, ReedSolomonError> {
+    Ok(
+        manifest_shard_set(input)?.filter(|(dir, basename): &(PathBuf, String)| {
+            BundleIndex::read(&FileStore::new(dir), basename)
+                .is_ok_and(|index: BundleIndex| !index.is_empty())
+        }),
+    )
+}
 
-        for (i, share) in shares.iter().take(self.config.data_shards).enumerate() {
-            decode_shards[i].copy_from_slice(share);
+/// Decodes the file `name` of a bundled set into `output`, or without a
+/// name unpacks every file into the `output` directory.
+fn decode_bundle_set(
+    store: &FileStore,
+    basename: &str,
+    input: &Path,
+    output: &Path,
+    file: Option<&str>,
+    limiter: Option<&Arc<RateLimiter>>,
+) -> Result<(), ReedSolomonError> {
+    let Some(name) = file else {
+        if is_stdio(output) {
+            return Err(ReedSolomonError::InvalidFileName(output.to_path_buf()));
         }
+        let index: BundleIndex = decode_bundle(store, basename, output)?;
+        println!(
+            "Unpacked {} files ({} bytes) from {} into {}",
+            index.entries.len(),
+            index.payload_len(),
+            input.display(),
+            output.display()
+        );
+        return Ok(());
+    };
 
-        DataProcessor::extract_original_data(&decode_buffer)
+    let written: u64 = decode_bundle_file(
+        store,
+        basename,
+        name,
+        throttled(create_output(output)?, limiter),
+    )?;
+    summary(
+        output,
+        format!(
+            "Decoded {} from {} into {} ({} bytes)",
+            name,
+            input.display(),
+            output.display(),
+            written
+        ),
+    );
+    Ok(())
+}
+
+/// Restores a sparse file, which needs a seekable output to leave its
+/// holes unwritten.
+fn decode_sparse(dir: &Path, basename: &str, output: &Path) -> Result<u64, ReedSolomonError> {
+    if is_stdio(output) {
+        return Err(ReedSolomonError::InvalidFileName(output.to_path_buf()));
     }
+    decode_sparse_from_files(dir, basename, &File::create(output)?)
 }
 
-struct DataProcessor;
+fn has_trailer(input: &Path) -> io::Result<bool> {
+    if is_stdio(input) || !input.is_file() {
+        return Ok(false);
+    }
 
-impl DataProcessor {
-    fn prepare_data(data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
-        Self::validate_data_size(data)?;
+    let mut file: File = File::open(input)?;
+    if file.metadata()?.len() < TRAILER_FOOTER_SIZE as u64 {
+        return Ok(false);
+    }
+    file.seek(SeekFrom::End(-(TRAILER_FOOTER_SIZE as i64)))?;
+    let mut footer: [u8; TRAILER_FOOTER_SIZE] = [0u8; TRAILER_FOOTER_SIZE];
+    file.read_exact(&mut footer)?;
+    Ok(footer[TRAILER_FOOTER_SIZE - TRAILER_MAGIC.len()..] == TRAILER_MAGIC)
+}
+
+/// Reads the leading header of a container, from a file or stdin, and
+/// returns a codec for it.
+fn open_container(input: &Path, progress: bool) -> Result<OpenedContainer, ReedSolomonError> {
+    let mut reader: Box<dyn Read> = open_input(input)?;
+    let mut prefix: Vec<u8> = Vec::new();
+    (&mut reader)
+        .take((GROUP_HEADER_SIZE + HEADER_SIZE) as u64)
+        .read_to_end(&mut prefix)?;
 
-        let mut buffer: Vec<u8> = Vec::with_capacity(data.len() + 4);
-        let mut size_prefix: [u8; 4] = [0u8; 4];
-        BigEndian::write_u32(&mut size_prefix, data.len() as u32);
+    let header: ContainerHeader = leading_header(&prefix)?;
+    let codec: ReedSolomonCodec =
+        ProgressLine::attach(ReedSolomonCodec::from_header(&header)?, progress, None);
+    Ok((codec, prefix, reader))
+}
 
-        buffer.extend_from_slice(&size_prefix);
-        buffer.extend_from_slice(data);
+fn repair(manifest: &Path) -> Result<(), ReedSolomonError> {
+    let dir: &Path = manifest.parent().unwrap_or(Path::new("."));
+    let (repaired, paths): (Vec<usize>, Vec<PathBuf>) = if is_json_manifest(manifest) {
+        let paths: Vec<PathBuf> = JsonManifest::read(manifest)?.paths(dir);
+        (repair_from_json_manifest(manifest)?, paths)
+    } else {
+        let basename: String = file_basename(&manifest.with_extension(""))?;
+        let store: FileStore = FileStore::new(dir);
+        let shard_manifest: ShardManifest = read_manifest(&store, &basename)?;
+        let paths: Vec<PathBuf> = (0..shard_manifest.data_shards + shard_manifest.parity_shards)
+            .map(|i: usize| store.path(&ShardId::shard(&basename, &shard_manifest, i)))
+            .collect();
+        (repair_shard_store(&store, &basename)?, paths)
+    };
 
-        Ok(buffer)
+    if repaired.is_empty() {
+        println!("All shard files of {} are intact", manifest.display());
+        return Ok(());
     }
 
-    fn validate_data_size(data: &[u8]) -> Result<(), ReedSolomonError> {
-        if !(DATA_SIZE_LIMITS.min..=DATA_SIZE_LIMITS.max).contains(&data.len()) {
-            return Err(ReedSolomonError::InvalidDataSize(format!(
-                "Data size must be between {} and {}",
-                DATA_SIZE_LIMITS.min, DATA_SIZE_LIMITS.max
-            )));
-        }
-        Ok(())
+    for index in &repaired {
+        println!("Repaired {}", paths[*index].display());
+    }
+    println!("Repaired {} of {} shard files", repaired.len(), paths.len());
+    Ok(())
+}
+
+fn armor(inputs: &[PathBuf], output: &Path) -> Result<(), ReedSolomonError> {
+    let mut text: String = String::new();
+    for input in inputs {
+        let block: ArmoredBlock = ArmoredBlock::new(DATA_LABEL, fs::read(input)?)
+            .with_header("Name", file_basename(input)?);
+        text.push_str(&block.to_string());
     }
 
-    fn split_into_shards(
-        data: &[u8],
-        data_shards: usize,
-        total_shards: usize,
-    ) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
-        let shard_size: usize = (data.len() + data_shards - 1) / data_shards;
-        let mut shards: Vec<Vec<u8>> = vec![vec![0u8; shard_size]; total_shards];
+    if is_stdio(output) {
+        io::stdout().lock().write_all(text.as_bytes())?;
+    } else {
+        fs::write(output, &text)?;
+        println!("Armored {} files into {}", inputs.len(), output.display());
+    }
+    Ok(())
+}
+
+fn dearmor(input: &Path, output: &Path) -> Result<(), ReedSolomonError> {
+    let text: String = if is_stdio(input) {
+        let mut text: String = String::new();
+        io::stdin().lock().read_to_string(&mut text)?;
+        text
+    } else {
+        fs::read_to_string(input)?
+    };
+    let blocks: Vec<ArmoredBlock> = ArmoredBlock::parse_all(&text)?;
 
-        for (i, chunk) in data.chunks(shard_size).enumerate().take(data_shards) {
-            shards[i][..chunk.len()].copy_from_slice(chunk);
+    fs::create_dir_all(output)?;
+    for block in &blocks {
+        let name: &str = block
+            .header("Name")
+            .ok_or(ArmorError::MissingHeader("Name"))?;
+        // Only a plain file name, so the text cannot write outside OUTPUT.
+        if Path::new(name).file_name() != Some(OsStr::new(name)) {
+            return Err(ReedSolomonError::InvalidFileName(PathBuf::from(name)));
         }
+        let path: PathBuf = output.join(name);
+        fs::write(&path, &block.data)?;
+        println!("Restored {}", path.display());
+    }
+    Ok(())
+}
+
+fn serve(dir: &Path, listen: &str) -> Result<(), ReedSolomonError> {
+    let server: ShardServer = ShardServer::bind(listen, dir)?;
+    println!(
+        "Serving shards from {} on {}",
+        dir.display(),
+        server.local_addr()?
+    );
+    server.serve()
+}
+
+fn watch(
+    dir: &Path,
+    out: &Path,
+    data_shards: usize,
+    parity_shards: usize,
+    direct_io: bool,
+) -> Result<(), ReedSolomonError> {
+    let config: EncoderConfig = EncoderConfig::new(data_shards, parity_shards)?;
+    let mut folder: DropFolder = DropFolder::new(config, dir, file_store(out, direct_io))?;
+    println!("Watching {} for files to encode", dir.display());
+    folder.run(&mut |(name, result): WatchEvent| match result {
+        Ok(manifest) => println!(
+            "Encoded {} ({} bytes, generation {})",
+            name, manifest.payload_len, manifest.generation
+        ),
+        Err(e) => eprintln!("error: {}: {}", name, e),
+    })
+}
+
+fn reshard(
+    manifest: &Path,
+    data_shards: usize,
+    parity_shards: usize,
+    progress: bool,
+    limiter: Option<&Arc<RateLimiter>>,
+) -> Result<(), ReedSolomonError> {
+    let dir: &Path = manifest.parent().unwrap_or(Path::new("."));
+    let name: String = file_basename(manifest)?;
+    let basename: &str = name
+        .strip_suffix(&format!(".{}", JSON_MANIFEST_EXTENSION))
+        .or_else(|| name.strip_suffix(&format!(".{}", MANIFEST_EXTENSION)))
+        .ok_or_else(|| ReedSolomonError::InvalidFileName(manifest.to_path_buf()))?;
 
-        Ok(shards)
+    let old: ShardManifest = ShardManifest::read(&manifest_path(dir, basename))?;
+    let config: EncoderConfig = ShardManifest {
+        data_shards,
+        parity_shards,
+        ..old
+    }
+    .config()?
+    .with_block_size(old.block_size as usize)?;
+    let codec: ReedSolomonCodec = rate_limited(
+        ProgressLine::attach(
+            ReedSolomonCodec::with_config(config)?,
+            progress,
+            Some(old.payload_len),
+        ),
+        limiter,
+    );
+    let new: ShardManifest = codec.reshard_files(dir, basename)?;
+    if progress {
+        eprintln!();
     }
 
-    fn validate_and_split_shares(
-        data: &[u8],
-        total_shards: usize,
-    ) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
-        if data.is_empty() {
-            return Err(ReedSolomonError::InvalidDataSize("Empty data".to_string()));
+    println!(
+        "Resharded {} from {} data + {} parity to {} data + {} parity shards",
+        basename, old.data_shards, old.parity_shards, new.data_shards, new.parity_shards
+    );
+    Ok(())
+}
+
+fn verify(input: &Path) -> Result<bool, ReedSolomonError> {
+    if input.is_dir() {
+        let sets: Vec<ShardSet> = find_shard_sets(input)?;
+        if sets.is_empty() {
+            return Err(ManifestError::NoShardSet.into());
         }
 
-        if data.len() % total_shards != 0 {
-            return Err(ReedSolomonError::InvalidDataSize(format!(
-                "Data length ({}) not divisible by total shards ({})",
-                data.len(),
-                total_shards
-            )));
+        let mut clean: bool = true;
+        for set in sets {
+            if set.stale {
+                println!("{}: stale shards of an earlier generation", set.basename);
+                continue;
+            }
+            if set.manifest.is_none() {
+                println!("{}: no manifest, cannot verify", set.basename);
+                clean = false;
+                continue;
+            }
+            let report: VerifyReport = verify_files(input, &set.basename)?;
+            clean &= print_verify_report(&set.basename, &report);
         }
+        return Ok(clean);
+    }
+
+    let report: VerifyReport = if is_json_manifest(input) {
+        verify_from_json_manifest(input)?
+    } else if input.extension().and_then(OsStr::to_str) == Some(MANIFEST_EXTENSION) {
+        let dir: &Path = input.parent().unwrap_or(Path::new("."));
+        verify_files(dir, &file_basename(&input.with_extension(""))?)?
+    } else {
+        let (codec, prefix, reader): OpenedContainer = open_container(input, false)?;
+        codec.verify_stream(Cursor::new(prefix).chain(reader))?
+    };
+    Ok(print_verify_report(&input.display().to_string(), &report))
+}
+
+/// Writes the JSON report and returns whether every set ended up healthy.
+fn scrub(root: &Path, repair: bool, output: &Path) -> Result<bool, ReedSolomonError> {
+    let report: ScrubReport = scrub::scrub(root, repair)?;
+    write_scrub_report(&report, create_output(output)?, report.to_json()?)?;
+    Ok(report.is_healthy())
+}
+
+/// Runs until an error, appending the report of every pass to the report
+/// file as one line of JSON, so earlier passes are kept.
+fn scrub_every(
+    root: &Path,
+    repair: bool,
+    output: &Path,
+    interval: Duration,
+) -> Result<(), ReedSolomonError> {
+    let mut scrubber: Scrubber = Scrubber::new(root, repair, interval);
+    eprintln!(
+        "Scrubbing {} every {} seconds",
+        root.display(),
+        interval.as_secs()
+    );
+    scrubber.run(&mut |report: ScrubReport| {
+        write_scrub_report(&report, append_output(output)?, report.to_json_line()?)
+    })
+}
+
+fn write_scrub_report(
+    report: &ScrubReport,
+    mut writer: Box<dyn Write>,
+    json: String,
+) -> Result<(), ReedSolomonError> {
+    writeln!(writer, "{}", json)?;
+    writer.flush()?;
+    eprintln!(
+        "{} healthy, {} degraded, {} lost",
+        report.healthy, report.degraded, report.lost
+    );
+    Ok(())
+}
+
+/// Prints one line per checked container or shard set and returns whether
+/// it was clean.
+fn print_verify_report(name: &str, report: &VerifyReport) -> bool {
+    println!("{}: {}", name, verify_status(report));
+    report.is_clean()
+}
 
-        let share_size: usize = data.len() / total_shards;
-        Ok((0..total_shards)
-            .map(|i: usize| data[i * share_size..(i + 1) * share_size].to_vec())
-            .collect())
+fn verify_status(report: &VerifyReport) -> String {
+    if report.is_clean() {
+        return format!("all {} blocks intact", report.blocks);
     }
 
-    fn extract_original_data(decoded: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
-        if decoded.len() < 4 {
-            return Err(ReedSolomonError::DecodingError(
-                "Data too short".to_string(),
-            ));
+    let mut line: String = format!("{} blocks", report.blocks);
+    if !report.damaged_shards.is_empty() {
+        line.push_str(&format!(", damaged shards {:?}", report.damaged_shards));
+    }
+    if !report.inconsistent_blocks.is_empty() {
+        line.push_str(&format!(
+            ", parity mismatch in blocks {:?}",
+            report.inconsistent_blocks
+        ));
+    }
+    if report.is_recoverable() {
+        line.push_str(", still recoverable");
+    } else {
+        line.push_str(&format!(", unrecoverable blocks {:?}", report.lost_blocks));
+    }
+    line
+}
+
+fn info(input: &Path) -> Result<(), ReedSolomonError> {
+    if input.is_dir() {
+        let sets: Vec<ShardSet> = find_shard_sets(input)?;
+        if sets.is_empty() {
+            return Err(ManifestError::NoShardSet.into());
         }
 
-        let original_size: usize = BigEndian::read_u32(&decoded[..4]) as usize;
-        if original_size > decoded.len() - 4 {
-            return Err(ReedSolomonError::DecodingError(
-                "Invalid size prefix".to_string(),
-            ));
+        for set in sets {
+            match &set.manifest {
+                Some(manifest) => {
+                    let report: VerifyReport = verify_files(input, &set.basename)?;
+                    print_manifest_info(&set.basename, manifest, &report);
+                }
+                None => {
+                    println!("{}", set.basename);
+                    println!(
+                        "  shards        {} data + {} parity, {} present",
+                        set.data_shards,
+                        set.parity_shards,
+                        set.present().len()
+                    );
+                    if set.stale {
+                        println!(
+                            "  status        stale generation {}, ignored",
+                            set.generation
+                        );
+                    } else {
+                        println!("  status        no manifest, cannot be decoded");
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    if is_json_manifest(input) {
+        let manifest: JsonManifest = JsonManifest::read(input)?;
+        let dir: &Path = input.parent().unwrap_or(Path::new("."));
+        let report: VerifyReport = verify_from_json_manifest(input)?;
+        print_manifest_info(
+            &input.display().to_string(),
+            &manifest.shard_manifest(),
+            &report,
+        );
+
+        let changed: Vec<usize> = manifest.damaged_shards(dir)?;
+        for shard in &manifest.shards {
+            let note: &str = if changed.contains(&shard.index) {
+                "  (changed)"
+            } else {
+                ""
+            };
+            println!(
+                "  shard {:>5}   {} ({} bytes, blake3 {}){}",
+                shard.index, shard.file, shard.size, shard.blake3, note
+            );
+        }
+        return Ok(());
+    }
+
+    if input.extension().and_then(OsStr::to_str) == Some(MANIFEST_EXTENSION) {
+        let dir: &Path = input.parent().unwrap_or(Path::new("."));
+        let basename: String = file_basename(&input.with_extension(""))?;
+        let manifest: ShardManifest = ShardManifest::read(input)?;
+        let report: VerifyReport = verify_files(dir, &basename)?;
+        print_manifest_info(&input.display().to_string(), &manifest, &report);
+        for entry in BundleIndex::read(&FileStore::new(dir), &basename)?.entries {
+            println!(
+                "  file          {} ({} bytes at offset {})",
+                entry.name, entry.len, entry.offset
+            );
         }
+        return Ok(());
+    }
+
+    let (codec, prefix, reader): OpenedContainer = open_container(input, false)?;
+    let header: ContainerHeader = leading_header(&prefix)?;
+    let interleaved: bool = prefix.starts_with(&GROUP_MAGIC);
+    let report: VerifyReport = codec.verify_stream(Cursor::new(prefix).chain(reader))?;
+
+    println!("{}", input.display());
+    println!("  format        version {}", header.version);
+    println!(
+        "  shards        {} data + {} parity",
+        header.data_shards, header.parity_shards
+    );
+    println!("  field         {:?}", header.field);
+    if let Some(polynomial) = header.polynomial {
+        println!("  polynomial    {:#x}", polynomial);
+    }
+    println!("  layout        {:?}", header.layout);
+    println!("  erasure code  {:?}", header.erasure_code);
+    println!("  compression   {:?}", header.compression);
+    println!("  encryption    {:?}", header.encryption);
+    println!("  interleaved   {}", if interleaved { "yes" } else { "no" });
+    println!(
+        "  payload       {} bytes stored in {} blocks",
+        report.payload_len, report.blocks
+    );
+    if !is_stdio(input) {
+        println!("  file size     {} bytes", input.metadata()?.len());
+    }
+    println!(
+        "  first block   {} bytes per shard, {} bytes of padding, payload digest {}",
+        header.shard_size,
+        header
+            .padding()
+            .map_or_else(|| "invalid".to_string(), |padding: u64| padding.to_string()),
+        header.digest.map_or_else(
+            || "none".to_string(),
+            |digest: [u8; DIGEST_SIZE]| hex(&digest)
+        )
+    );
+    println!("  status        {}", verify_status(&report));
+    Ok(())
+}
+
+fn print_manifest_info(name: &str, manifest: &ShardManifest, report: &VerifyReport) {
+    let shard_file_len: u64 = (0..manifest.block_count())
+        .map(|block: u64| manifest.shard_size(block) as u64)
+        .sum();
+
+    println!("{}", name);
+    println!("  manifest      version {}", manifest.version);
+    if manifest.generation != 0 {
+        println!("  generation    {}", manifest.generation);
+    }
+    println!(
+        "  shards        {} data + {} parity",
+        manifest.data_shards, manifest.parity_shards
+    );
+    println!("  field         {:?}", manifest.field);
+    if let Some(polynomial) = manifest.polynomial {
+        println!("  polynomial    {:#x}", polynomial);
+    }
+    println!("  layout        {:?}", manifest.layout);
+    println!("  erasure code  {:?}", manifest.erasure_code);
+    println!(
+        "  payload       {} bytes in {} blocks of up to {} bytes",
+        manifest.payload_len,
+        manifest.block_count(),
+        manifest.block_size
+    );
+    println!("  shard files   {} bytes each", shard_file_len);
+    if manifest.shard_alignment != 1 {
+        println!("  alignment     {} bytes", manifest.shard_alignment);
+    }
+    println!("  status        {}", verify_status(report));
+}
+
+fn print_plan(input: &Path, plan: &EncodePlan) {
+    println!("{}", input.display());
+    println!(
+        "  payload       {} bytes in {} blocks",
+        plan.input_len, plan.block_count
+    );
+    println!(
+        "  shards        {} of {} bytes each",
+        plan.total_shards, plan.shard_len
+    );
+    println!("  encoded       {} bytes", plan.encoded_len);
+    println!(
+        "  overhead      {} bytes ({:.1}%)",
+        plan.overhead(),
+        plan.overhead() as f64 * 100.0 / plan.input_len.max(1) as f64
+    );
+}
+
+/// Accepts `0x187` or `391`.
+fn parse_polynomial(value: &str) -> Result<u16, String> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(digits) => u16::from_str_radix(digits, 16),
+        None => value.parse(),
+    }
+    .map_err(|error: std::num::ParseIntError| error.to_string())
+}
+
+fn parse_preset(value: &str) -> Result<Preset, String> {
+    Preset::from_name(value).ok_or_else(|| {
+        let names: Vec<&str> = Preset::ALL.iter().map(Preset::name).collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte: &u8| format!("{:02x}", byte))
+        .collect()
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO
+}
+
+/// `path` in a form Windows opens whatever its length; stdin and stdout
+/// stay as they are.
+fn native_path(path: &Path) -> PathBuf {
+    if is_stdio(path) {
+        return path.to_path_buf();
+    }
+    long_path(path)
+}
+
+fn open_input(path: &Path) -> io::Result<Box<dyn Read>> {
+    if is_stdio(path) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    Ok(Box::new(BufReader::new(File::open(path)?)))
+}
+
+fn create_output(path: &Path) -> io::Result<Box<dyn Write>> {
+    if is_stdio(path) {
+        return Ok(Box::new(BufWriter::new(io::stdout().lock())));
+    }
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
 
-        Ok(decoded[4..4 + original_size].to_vec())
+/// Like `create_output`, but adds to the end of an existing file.
+fn append_output(path: &Path) -> io::Result<Box<dyn Write>> {
+    if is_stdio(path) {
+        return create_output(path);
     }
+    let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Prints the closing summary of a command, on stderr when stdout carries
+/// the output itself.
+fn summary(output: &Path, message: String) {
+    if is_stdio(output) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// The basename for shard files: `--name` if given, otherwise the input's
+/// file name, which stdin does not have. Names Windows cannot create, such
+/// as `CON`, are made portable so the shard set can be copied anywhere.
+fn output_basename(args: &EncodeArgs) -> Result<String, ReedSolomonError> {
+    let name: String = match &args.name {
+        Some(name) => name.clone(),
+        None if is_stdio(args.input()) => {
+            return Err(ReedSolomonError::InvalidFileName(
+                args.input().to_path_buf(),
+            ))
+        }
+        None => file_basename(args.input())?,
+    };
+    Ok(portable_file_name(&name))
+}
+
+fn is_json_manifest(path: &Path) -> bool {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .is_some_and(|name: &str| name.ends_with(&format!(".{}", JSON_MANIFEST_EXTENSION)))
+}
+
+fn file_basename(path: &Path) -> Result<String, ReedSolomonError> {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .map(str::to_string)
+        .ok_or_else(|| ReedSolomonError::InvalidFileName(path.to_path_buf()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The library's `test_util::temp_dir`, which the binary cannot reach.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!(
+            "reed-solomon-codec-cli-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Runs `command` on `paths` with `flags` the way `main` does.
+    fn run(command: &str, paths: &[&Path], flags: &[&str]) -> Result<(), ReedSolomonError> {
+        let args: Vec<&OsStr> = [OsStr::new("rs"), OsStr::new(command)]
+            .into_iter()
+            .chain(paths.iter().map(|path: &&Path| path.as_os_str()))
+            .chain(flags.iter().map(OsStr::new))
+            .collect();
+        let cli: Cli = Cli::try_parse_from(args).expect("the arguments should parse");
+        match cli.command {
+            Command::Encode(args) => encode(&args.with_native_paths(), false, None, false, false),
+            Command::Decode {
+                input,
+                output,
+                file,
+            } => decode(&input, &output, file.as_deref(), false, None, false),
+            Command::Info { input } => info(&input),
+            command => panic!("not run by these tests: {:?}", command),
+        }
+    }
+
     #[test]
-    fn test_encode_decode_roundtrip() -> Result<(), ReedSolomonError> {
-        let codec: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?;
-        let original_data = b"Hello, World!".to_vec();
+    fn test_encode_and_decode_commands_roundtrip() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("roundtrip");
+        fs::create_dir_all(&dir)?;
+        let original_data: Vec<u8> = (0..20_000u32).map(|i: u32| (i % 251) as u8).collect();
+        let input: PathBuf = dir.join("input.bin");
+        fs::write(&input, &original_data)?;
+        let counts: [&str; 4] = ["--data", "4", "--parity", "2"];
+
+        let container: PathBuf = dir.join("input.rsc");
+        let decoded: PathBuf = dir.join("decoded.bin");
+        run("encode", &[&input, &container], &counts)?;
+        run("decode", &[&container, &decoded], &[])?;
+        assert_eq!(fs::read(&decoded)?, original_data);
 
-        let encoded: Vec<u8> = codec.encode(&original_data)?;
-        let decoded: Vec<u8> = codec.decode(&encoded)?;
+        // Split into shard files, two of which are then lost.
+        let shards: PathBuf = dir.join("shards");
+        run(
+            "encode",
+            &[&input, &shards],
+            &[&counts[..], &["--split"]].concat(),
+        )?;
+        fs::remove_file(shards.join("input.bin.k4m2.s00.rssh"))?;
+        fs::remove_file(shards.join("input.bin.k4m2.s05.rssh"))?;
+        fs::remove_file(&decoded)?;
+        run("decode", &[&shards, &decoded], &[])?;
+        assert_eq!(fs::read(&decoded)?, original_data);
 
-        assert_eq!(original_data, decoded);
+        assert!(run("decode", &[&input, &decoded], &[]).is_err());
+        fs::remove_dir_all(&dir)?;
         Ok(())
     }
 
     #[test]
-    fn test_invalid_config() {
-        assert!(ReedSolomonCodec::new(0, 1).is_err());
-        assert!(ReedSolomonCodec::new(1, SHARD_LIMITS.max + 1).is_err());
-        assert!(ReedSolomonCodec::new(SHARD_LIMITS.max / 2, SHARD_LIMITS.max / 2 + 1).is_err());
-    }
+    fn test_info_describes_containers_and_shard_sets() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("info");
+        let shards: PathBuf = dir.join("shards");
+        fs::create_dir_all(&shards)?;
+        let input: PathBuf = dir.join("input.bin");
+        fs::write(&input, vec![3u8; 10_000])?;
+        let counts: [&str; 4] = ["--data", "4", "--parity", "2"];
 
-    #[test]
-    fn test_invalid_data_size() {
-        let codec: ReedSolomonCodec = ReedSolomonCodec::new(10, 4).unwrap();
-        let too_large: Vec<u8> = vec![0u8; DATA_SIZE_LIMITS.max + 1];
-        assert!(codec.encode(&too_large).is_err());
+        let container: PathBuf = dir.join("input.rsc");
+        run("encode", &[&input, &container], &counts)?;
+        run(
+            "encode",
+            &[&input, &shards],
+            &[&counts[..], &["--split"]].concat(),
+        )?;
+        fs::remove_file(shards.join("input.bin.k4m2.s03.rssh"))?;
+        for described in [
+            &container,
+            &shards,
+            &shards.join("input.bin.manifest"),
+            &shards.join("input.bin.manifest.json"),
+        ] {
+            run("info", &[described], &[])?;
+        }
+
+        let report: VerifyReport = verify_files(&shards, "input.bin")?;
+        assert_eq!(report.damaged_shards, vec![3]);
+        assert!(verify_status(&report).ends_with("damaged shards [3], still recoverable"));
+        let lost: VerifyReport = VerifyReport {
+            lost_blocks: vec![0],
+            ..report
+        };
+        assert!(verify_status(&lost).ends_with("unrecoverable blocks [0]"));
+
+        assert!(run("info", &[&input], &[]).is_err());
+        let empty: PathBuf = dir.join("empty");
+        fs::create_dir_all(&empty)?;
+        assert!(matches!(
+            run("info", &[&empty], &[]),
+            Err(ReedSolomonError::InvalidManifest(ManifestError::NoShardSet))
+        ));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
     }
-}
 
-fn main() -> Result<(), ReedSolomonError> {
-    let codec: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?;
-    let original_data: Vec<u8> = b"Hello, World!".to_vec();
+    #[test]
+    fn test_dash_stands_for_stdin_and_stdout() -> Result<(), ReedSolomonError> {
+        let dash: &Path = Path::new(STDIO);
+        assert!(is_stdio(dash));
+        assert_eq!(native_path(dash), dash);
+        assert!(!is_stdio(Path::new("./-")));
 
-    let encoded: Vec<u8> = codec.encode(&original_data)?;
-    let decoded: Vec<u8> = codec.decode(&encoded)?;
+        // Stdin has no file name to name the shard files after.
+        let parse = |flags: &[&str]| -> EncodeArgs {
+            let args: Vec<&str> = ["rs", "encode", STDIO, "shards", "--split"]
+                .into_iter()
+                .chain(["--data", "4", "--parity", "2"])
+                .chain(flags.iter().copied())
+                .collect();
+            match Cli::try_parse_from(args)
+                .expect("the arguments should parse")
+                .command
+            {
+                Command::Encode(args) => args,
+                command => panic!("parsed as {:?}", command),
+            }
+        };
+        assert!(matches!(
+            output_basename(&parse(&[])),
+            Err(ReedSolomonError::InvalidFileName(_))
+        ));
+        assert_eq!(output_basename(&parse(&["--name", "backup"]))?, "backup");
 
-    println!("Original: {}", String::from_utf8_lossy(&original_data));
-    println!("Encoded: {:?}", encoded);
-    println!("Decoded: {}", String::from_utf8_lossy(&decoded));
+        // What `decode INPUT -` writes to stdout.
+        let dir: PathBuf = temp_dir("stdio");
+        fs::create_dir_all(&dir)?;
+        let original_data: Vec<u8> = (0..9000u32).map(|i: u32| (i % 241) as u8).collect();
+        let input: PathBuf = dir.join("input.bin");
+        fs::write(&input, &original_data)?;
+        let container: PathBuf = dir.join("input.rsc");
+        run(
+            "encode",
+            &[&input, &container],
+            &["--data", "4", "--parity", "2"],
+        )?;
+        let mut stdout: Vec<u8> = Vec::new();
+        let written: u64 = decode_into(&container, &mut stdout, false, None, false)?;
+        assert_eq!((written, stdout), (9000, original_data));
 
-    assert_eq!(original_data, decoded);
-    Ok(())
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub encodes: u64,
+    pub decodes: u64,
+    pub bytes_encoded: u64,
+    pub bytes_decoded: u64,
+    pub encode_time: Duration,
+    pub decode_time: Duration,
+    pub reconstructions: u64,
+    pub shards_reconstructed: u64,
+}
+
+impl MetricsSnapshot {
+    pub fn encode_mb_per_sec(&self) -> f64 {
+        mb_per_sec(self.bytes_encoded, self.encode_time)
+    }
+
+    pub fn decode_mb_per_sec(&self) -> f64 {
+        mb_per_sec(self.bytes_decoded, self.decode_time)
+    }
+}
+
+fn mb_per_sec(bytes: u64, time: Duration) -> f64 {
+    if time.is_zero() {
+        return 0.0;
+    }
+    bytes as f64 / 1_000_000.0 / time.as_secs_f64()
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    encodes: AtomicU64,
+    decodes: AtomicU64,
+    bytes_encoded: AtomicU64,
+    bytes_decoded: AtomicU64,
+    encode_nanos: AtomicU64,
+    decode_nanos: AtomicU64,
+    reconstructions: AtomicU64,
+    shards_reconstructed: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_encode(&self, bytes: usize, time: Duration) {
+        self.encodes.fetch_add(1, Ordering::Relaxed);
+        self.bytes_encoded
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        self.encode_nanos
+            .fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_decode(&self, bytes: usize, time: Duration) {
+        self.decodes.fetch_add(1, Ordering::Relaxed);
+        self.bytes_decoded
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        self.decode_nanos
+            .fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_reconstruction(&self, shards: usize) {
+        self.reconstructions.fetch_add(1, Ordering::Relaxed);
+        self.shards_reconstructed
+            .fetch_add(shards as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            encodes: self.encodes.load(Ordering::Relaxed),
+            decodes: self.decodes.load(Ordering::Relaxed),
+            bytes_encoded: self.bytes_encoded.load(Ordering::Relaxed),
+            bytes_decoded: self.bytes_decoded.load(Ordering::Relaxed),
+            encode_time: Duration::from_nanos(self.encode_nanos.load(Ordering::Relaxed)),
+            decode_time: Duration::from_nanos(self.decode_nanos.load(Ordering::Relaxed)),
+            reconstructions: self.reconstructions.load(Ordering::Relaxed),
+            shards_reconstructed: self.shards_reconstructed.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        for counter in [
+            &self.encodes,
+            &self.decodes,
+            &self.bytes_encoded,
+            &self.bytes_decoded,
+            &self.encode_nanos,
+            &self.decode_nanos,
+            &self.reconstructions,
+            &self.shards_reconstructed,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_accumulates_and_resets() {
+        let metrics: Metrics = Metrics::new();
+
+        metrics.record_encode(2_000_000, Duration::from_secs(1));
+        metrics.record_encode(2_000_000, Duration::from_secs(1));
+        metrics.record_decode(500_000, Duration::from_millis(250));
+        metrics.record_reconstruction(3);
+
+        let snapshot: MetricsSnapshot = metrics.snapshot();
+        assert_eq!(snapshot.encodes, 2);
+        assert_eq!(snapshot.bytes_encoded, 4_000_000);
+        assert_eq!(snapshot.encode_mb_per_sec(), 2.0);
+        assert_eq!(snapshot.decode_mb_per_sec(), 2.0);
+        assert_eq!(snapshot.reconstructions, 1);
+        assert_eq!(snapshot.shards_reconstructed, 3);
+
+        metrics.reset();
+        assert_eq!(metrics.snapshot(), MetricsSnapshot::default());
+        assert_eq!(MetricsSnapshot::default().encode_mb_per_sec(), 0.0);
+    }
+}
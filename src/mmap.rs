@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::panic;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, ScopedJoinHandle};
+
+use memmap2::Mmap;
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::stream::split_blocks;
+
+/// Groups each pipeline stage may run ahead of the next.
+const PIPELINE_DEPTH: usize = 2;
+const PAGE_SIZE: usize = 4096;
+
+impl ReedSolomonCodec {
+    /// Encodes in three overlapping stages: one thread faults the next
+    /// groups of the mapping in from disk, the calling thread encodes, and
+    /// a third thread writes the encoded groups out. Bounded channels keep
+    /// at most `PIPELINE_DEPTH` groups queued between stages.
+    pub fn encode_file(&self, input: &Path, output: &Path) -> Result<u64, ReedSolomonError> {
+        let source: Option<Mmap> = map_file(input)?;
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(output)?);
+        let group_size: usize = self.group_size()?;
+        let data: &[u8] = source.as_deref().unwrap_or_default();
+
+        thread::scope(|scope: &thread::Scope<'_, '_>| {
+            let (read_tx, read_rx): (SyncSender<&[u8]>, Receiver<&[u8]>) =
+                mpsc::sync_channel(PIPELINE_DEPTH);
+            let (encoded_tx, encoded_rx): (SyncSender<Vec<u8>>, Receiver<Vec<u8>>) =
+                mpsc::sync_channel(PIPELINE_DEPTH);
+
+            scope.spawn(move || {
+                for group in data.chunks(group_size) {
+                    prefault(group);
+                    if read_tx.send(group).is_err() {
+                        break;
+                    }
+                }
+            });
+            let writing: ScopedJoinHandle<'_, Result<u64, ReedSolomonError>> =
+                scope.spawn(move || {
+                    let mut written: u64 = 0;
+                    for encoded in encoded_rx {
+                        self.throttle(encoded.len());
+                        writer.write_all(&encoded)?;
+                        written += encoded.len() as u64;
+                        self.pool().give(encoded);
+                    }
+                    writer.flush()?;
+                    Ok(written)
+                });
+
+            let mut encoding: Result<(), ReedSolomonError> = Ok(());
+            for group in read_rx.iter() {
+                let mut encoded: Vec<u8> = self.pool().take(0);
+                if let Err(error) = self.encode_unit_into(group, &mut encoded) {
+                    encoding = Err(error);
+                    break;
+                }
+                // A closed channel means the writer failed; its error is
+                // returned below.
+                if encoded_tx.send(encoded).is_err() {
+                    break;
+                }
+            }
+            drop(read_rx);
+            drop(encoded_tx);
+
+            let written: u64 = writing
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload))?;
+            encoding.map(|()| written)
+        })
+    }
+
+    pub fn decode_file(&self, input: &Path, output: &Path) -> Result<u64, ReedSolomonError> {
+        let source: Option<Mmap> = map_file(input)?;
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(output)?);
+        let mut written: u64 = 0;
+
+        for unit in split_blocks(source.as_deref().unwrap_or_default())? {
+            let decoded: Vec<u8> = self.decode_unit(unit)?;
+            self.throttle(decoded.len());
+            writer.write_all(&decoded)?;
+            written += decoded.len() as u64;
+            self.pool().give(decoded);
+        }
+
+        writer.flush()?;
+        Ok(written)
+    }
+}
+
+/// Reads one byte per page so the disk reads for `data` happen on the
+/// calling thread rather than when the encoder first touches it.
+fn prefault(data: &[u8]) {
+    let folded: u8 = data
+        .iter()
+        .step_by(PAGE_SIZE)
+        .fold(0u8, |folded: u8, byte: &u8| folded ^ byte);
+    std::hint::black_box(folded);
+}
+
+fn map_file(path: &Path) -> io::Result<Option<Mmap>> {
+    let file: File = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+
+    // SAFETY: the mapping is only read, and it is dropped before returning to
+    // the caller. Like any mmap, it assumes no other process truncates or
+    // rewrites the file while it is being encoded or decoded.
+    unsafe { Mmap::map(&file) }.map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::test_util::temp_dir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_file_roundtrip_matches_stream_format() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..10_000u32).map(|i: u32| (i % 239) as u8).collect();
+
+        let dir: PathBuf = temp_dir("mmap");
+        fs::create_dir_all(&dir)?;
+        let (input, encoded, decoded): (PathBuf, PathBuf, PathBuf) =
+            (dir.join("input"), dir.join("encoded"), dir.join("decoded"));
+        fs::write(&input, &original_data)?;
+
+        let written: u64 = codec.encode_file(&input, &encoded)?;
+        let container: Vec<u8> = fs::read(&encoded)?;
+        assert_eq!(written, container.len() as u64);
+        assert_eq!(container, codec.encode_blocks(&original_data)?);
+
+        assert_eq!(codec.decode_file(&encoded, &decoded)?, 10_000);
+        assert_eq!(fs::read(&decoded)?, original_data);
+
+        fs::write(&input, b"")?;
+        assert_eq!(codec.encode_file(&input, &encoded)?, 0);
+        assert_eq!(codec.decode_file(&encoded, &decoded)?, 0);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipelined_encode_keeps_group_order() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(3, 2)?
+            .with_block_size(512)?
+            .with_interleave_depth(2)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..50_000u32).map(|i: u32| (i % 233) as u8).collect();
+
+        let dir: PathBuf = temp_dir("mmap-order");
+        fs::create_dir_all(&dir)?;
+        let (input, encoded): (PathBuf, PathBuf) = (dir.join("input"), dir.join("encoded"));
+        fs::write(&input, &original_data)?;
+
+        codec.encode_file(&input, &encoded)?;
+        assert_eq!(fs::read(&encoded)?, codec.encode_blocks(&original_data)?);
+        assert!(codec
+            .encode_file(&input, &dir.join("missing").join("out"))
+            .is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
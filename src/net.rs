@@ -0,0 +1,357 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::ReedSolomonError;
+use crate::platform::write_replacing;
+use crate::remote::{parse_shard_key, ObjectStore};
+
+/// The largest object a server accepts, so a bad length cannot make it
+/// allocate without bound.
+pub const MAX_OBJECT_SIZE: u64 = 1 << 32;
+
+const OP_PUT: u8 = 1;
+const OP_GET: u8 = 2;
+
+const STATUS_OK: u8 = 0;
+const STATUS_NOT_FOUND: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keeps shard objects in a directory and serves them over TCP, so a few
+/// processes on one or more hosts can act as storage nodes.
+///
+/// Each request is an opcode, a key of up to 65535 bytes with a 2-byte
+/// length, and for puts the object with an 8-byte length, all big-endian.
+/// Each response is a status byte, followed by the object for a successful
+/// get or a message for an error. Keys are stored as file names, so they
+/// cannot contain path separators. Connections may carry any number of
+/// requests and are handled on their own threads.
+#[derive(Debug)]
+pub struct ShardServer {
+    listener: TcpListener,
+    dir: PathBuf,
+}
+
+impl ShardServer {
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        dir: impl Into<PathBuf>,
+    ) -> Result<Self, ReedSolomonError> {
+        let dir: PathBuf = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            dir,
+        })
+    }
+
+    /// The address the server listens on, with the port filled in when
+    /// bound to port 0.
+    pub fn local_addr(&self) -> Result<SocketAddr, ReedSolomonError> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Accepts connections until the listener fails. Errors on a single
+    /// connection only close that connection.
+    pub fn serve(&self) -> Result<(), ReedSolomonError> {
+        for stream in self.listener.incoming() {
+            let stream: TcpStream = stream?;
+            let dir: PathBuf = self.dir.clone();
+            thread::spawn(move || handle_connection(stream, &dir));
+        }
+        Ok(())
+    }
+}
+
+fn handle_connection(stream: TcpStream, dir: &Path) -> io::Result<()> {
+    let mut reader: BufReader<&TcpStream> = BufReader::new(&stream);
+    let mut writer: BufWriter<&TcpStream> = BufWriter::new(&stream);
+    loop {
+        let mut op: [u8; 1] = [0u8; 1];
+        if reader.read(&mut op)? == 0 {
+            return Ok(());
+        }
+        let key: String = read_string(&mut reader)?;
+        let path: Option<PathBuf> = is_plain_name(&key).then(|| dir.join(&key));
+
+        match (op[0], path) {
+            (OP_PUT, path) => {
+                let data: Vec<u8> = read_object(&mut reader)?;
+                match path.map(|path: PathBuf| store_object(&path, &data)) {
+                    Some(Ok(())) => writer.write_all(&[STATUS_OK])?,
+                    Some(Err(error)) => write_error(&mut writer, &error.to_string())?,
+                    None => write_error(&mut writer, &format!("invalid key {:?}", key))?,
+                }
+            }
+            (OP_GET, Some(path)) => match fs::read(&path) {
+                Ok(data) => {
+                    writer.write_all(&[STATUS_OK])?;
+                    writer.write_all(&(data.len() as u64).to_be_bytes())?;
+                    writer.write_all(&data)?;
+                }
+                Err(error) if error.kind() == ErrorKind::NotFound => {
+                    writer.write_all(&[STATUS_NOT_FOUND])?
+                }
+                Err(error) => write_error(&mut writer, &error.to_string())?,
+            },
+            (OP_GET, None) => write_error(&mut writer, &format!("invalid key {:?}", key))?,
+            (op, _) => {
+                write_error(&mut writer, &format!("unknown request {}", op))?;
+                writer.flush()?;
+                return Ok(());
+            }
+        }
+        writer.flush()?;
+    }
+}
+
+/// Writes through a temporary file, so a reader never sees half an object.
+fn store_object(path: &Path, data: &[u8]) -> io::Result<()> {
+    write_replacing(path, data)
+}
+
+fn is_plain_name(key: &str) -> bool {
+    !key.starts_with('.') && Path::new(key).file_name() == Some(OsStr::new(key))
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let mut len: [u8; 2] = [0u8; 2];
+    reader.read_exact(&mut len)?;
+    let mut bytes: Vec<u8> = vec![0u8; u16::from_be_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|error| io::Error::new(ErrorKind::InvalidData, error))
+}
+
+fn read_object(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len: [u8; 8] = [0u8; 8];
+    reader.read_exact(&mut len)?;
+    let len: u64 = u64::from_be_bytes(len);
+    if len > MAX_OBJECT_SIZE {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("object of {} bytes is too large", len),
+        ));
+    }
+    let mut data: Vec<u8> = Vec::new();
+    reader.take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+fn write_error(writer: &mut impl Write, message: &str) -> io::Result<()> {
+    let message: &[u8] = &message.as_bytes()[..message.len().min(u16::MAX as usize)];
+    writer.write_all(&[STATUS_ERROR])?;
+    writer.write_all(&(message.len() as u16).to_be_bytes())?;
+    writer.write_all(message)
+}
+
+fn write_key(writer: &mut impl Write, key: &str) -> Result<(), ReedSolomonError> {
+    let len: u16 = u16::try_from(key.len()).map_err(|_| ReedSolomonError::InvalidDataSize {
+        given: key.len(),
+        min: 0,
+        max: u16::MAX as usize,
+    })?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(key.as_bytes())?;
+    Ok(())
+}
+
+/// One storage node, reached with a new connection per request.
+#[derive(Debug, Clone)]
+pub struct ShardClient {
+    addr: SocketAddr,
+}
+
+impl ShardClient {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn request(
+        &self,
+        op: u8,
+        key: &str,
+        data: Option<&[u8]>,
+    ) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        let stream: TcpStream = TcpStream::connect_timeout(&self.addr, CONNECT_TIMEOUT)?;
+        let mut writer: BufWriter<&TcpStream> = BufWriter::new(&stream);
+        writer.write_all(&[op])?;
+        write_key(&mut writer, key)?;
+        if let Some(data) = data {
+            writer.write_all(&(data.len() as u64).to_be_bytes())?;
+            writer.write_all(data)?;
+        }
+        writer.flush()?;
+
+        let mut reader: BufReader<&TcpStream> = BufReader::new(&stream);
+        let mut status: [u8; 1] = [0u8; 1];
+        reader.read_exact(&mut status)?;
+        match status[0] {
+            STATUS_OK if op == OP_GET => Ok(Some(read_object(&mut reader)?)),
+            STATUS_OK => Ok(None),
+            STATUS_NOT_FOUND => Ok(None),
+            _ => {
+                let message: String = read_string(&mut reader)?;
+                Err(io::Error::other(format!("{}: {}", self.addr, message)).into())
+            }
+        }
+    }
+}
+
+impl ObjectStore for ShardClient {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), ReedSolomonError> {
+        self.request(OP_PUT, key, Some(data))?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        self.request(OP_GET, key, None)
+    }
+}
+
+/// Several storage nodes used as one store: shard `i` lives on node
+/// `i % nodes`, and every other object, such as the manifest, on all of
+/// them. A node that cannot be reached reads as missing shards, so
+/// `decode_from_store` recovers from the rest as long as enough shards
+/// survive.
+#[derive(Debug, Clone)]
+pub struct ShardCluster {
+    nodes: Vec<ShardClient>,
+}
+
+impl ShardCluster {
+    pub fn new(nodes: Vec<ShardClient>) -> Result<Self, ReedSolomonError> {
+        if nodes.is_empty() {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: 0,
+                min: 1,
+                max: usize::MAX,
+            });
+        }
+        Ok(Self { nodes })
+    }
+
+    pub fn nodes(&self) -> &[ShardClient] {
+        &self.nodes
+    }
+
+    /// The node holding shard `index`.
+    pub fn node_for(&self, index: usize) -> &ShardClient {
+        &self.nodes[index % self.nodes.len()]
+    }
+}
+
+impl ObjectStore for ShardCluster {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), ReedSolomonError> {
+        match parse_shard_key(key) {
+            Some(index) => self.node_for(index).put(key, data),
+            None => self
+                .nodes
+                .iter()
+                .try_for_each(|node: &ShardClient| node.put(key, data)),
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        if let Some(index) = parse_shard_key(key) {
+            return match self.node_for(index).get(key) {
+                Err(ReedSolomonError::Io(_)) => Ok(None),
+                result => result,
+            };
+        }
+
+        // Only fail when no node could be asked at all.
+        let mut first_error: Option<ReedSolomonError> = None;
+        let mut answered: bool = false;
+        for node in &self.nodes {
+            match node.get(key) {
+                Ok(Some(data)) => return Ok(Some(data)),
+                Ok(None) => answered = true,
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+        match first_error {
+            Some(error) if !answered => Err(error),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+    use crate::remote::decode_from_store;
+    use crate::test_util::temp_dir;
+
+    fn start_server(dir: &Path) -> Result<ShardClient, ReedSolomonError> {
+        let server: ShardServer = ShardServer::bind("127.0.0.1:0", dir)?;
+        let client: ShardClient = ShardClient::new(server.local_addr()?);
+        thread::spawn(move || server.serve());
+        Ok(client)
+    }
+
+    #[test]
+    fn test_put_and_get() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("net-put-get");
+        let client: ShardClient = start_server(&dir)?;
+
+        client.put("data.shard.000", b"shard bytes")?;
+        assert_eq!(client.get("data.shard.000")?, Some(b"shard bytes".to_vec()));
+        assert_eq!(client.get("data.shard.001")?, None);
+        assert_eq!(fs::read(dir.join("data.shard.000"))?, b"shard bytes");
+        assert!(client.put("../escape", b"no").is_err());
+        assert!(client.get("a/b").is_err());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovers_with_a_node_down() -> Result<(), ReedSolomonError> {
+        let dirs: Vec<PathBuf> = (0..3)
+            .map(|node: usize| temp_dir(&format!("net-node-{}", node)))
+            .collect();
+        let mut nodes: Vec<ShardClient> = dirs
+            .iter()
+            .map(|dir: &PathBuf| start_server(dir))
+            .collect::<Result<_, ReedSolomonError>>()?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let original_data: Vec<u8> = (0..20_000u32).map(|i: u32| (i % 251) as u8).collect();
+        codec.encode_to_store(
+            Cursor::new(&original_data),
+            &ShardCluster::new(nodes.clone())?,
+            "backup",
+        )?;
+
+        // Point the last node at a port nothing listens on.
+        let closed: SocketAddr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        nodes[2] = ShardClient::new(closed);
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_store(&ShardCluster::new(nodes)?, "backup", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+        for dir in &dirs {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::{EncoderConfig, ErasureCode, GaloisField, ShardLayout};
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::polynomial::DEFAULT_POLYNOMIAL;
+use crate::processor::{crc32c_checksum, DataProcessor, CHECKSUM_SIZE, PREFIX_SIZE};
+use crate::shard::Shard;
+
+pub const PACKET_VERSION: u8 = 1;
+pub const PACKET_HEADER_SIZE: usize = 20;
+/// Fits a UDP datagram on any path without fragmentation.
+pub const DEFAULT_MTU: usize = 1200;
+pub const DEFAULT_WINDOW: usize = 64;
+
+const FIELD_BITS_MASK: u8 = 0x1f;
+const NON_SYSTEMATIC_FLAG: u8 = 0x20;
+const ERASURE_CODE_SHIFT: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketHeader {
+    pub stream_id: u32,
+    pub block: u32,
+    pub index: u16,
+    pub data_shards: u16,
+    pub parity_shards: u16,
+    pub field: GaloisField,
+    pub layout: ShardLayout,
+    pub erasure_code: ErasureCode,
+}
+
+impl PacketHeader {
+    pub fn config(&self) -> Result<EncoderConfig, ReedSolomonError> {
+        EncoderConfig::with_field(
+            self.data_shards as usize,
+            self.parity_shards as usize,
+            self.field,
+        )?
+        .with_layout(self.layout)?
+        .with_erasure_code(self.erasure_code)
+    }
+
+    /// Whether both headers describe shards of the same code.
+    pub fn same_code(&self, other: &PacketHeader) -> bool {
+        self.data_shards == other.data_shards
+            && self.parity_shards == other.parity_shards
+            && self.field == other.field
+            && self.layout == other.layout
+            && self.erasure_code == other.erasure_code
+    }
+
+    pub fn to_bytes(&self) -> [u8; PACKET_HEADER_SIZE] {
+        let mut bytes: [u8; PACKET_HEADER_SIZE] = [0u8; PACKET_HEADER_SIZE];
+        bytes[0] = PACKET_VERSION;
+        bytes[1] = self.field.bits() | (self.erasure_code.id() << ERASURE_CODE_SHIFT);
+        if self.layout == ShardLayout::NonSystematic {
+            bytes[1] |= NON_SYSTEMATIC_FLAG;
+        }
+        BigEndian::write_u32(&mut bytes[2..6], self.stream_id);
+        BigEndian::write_u32(&mut bytes[6..10], self.block);
+        BigEndian::write_u16(&mut bytes[10..12], self.index);
+        BigEndian::write_u16(&mut bytes[12..14], self.data_shards);
+        BigEndian::write_u16(&mut bytes[14..16], self.parity_shards);
+
+        let (fields, checksum) = bytes.split_at_mut(PACKET_HEADER_SIZE - CHECKSUM_SIZE);
+        BigEndian::write_u32(checksum, crc32c_checksum(fields));
+        bytes
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, ReedSolomonError> {
+        if data.len() < PACKET_HEADER_SIZE {
+            return Err(HeaderError::Truncated {
+                needed: PACKET_HEADER_SIZE,
+                given: data.len(),
+            }
+            .into());
+        }
+
+        let (fields, checksum) =
+            data[..PACKET_HEADER_SIZE].split_at(PACKET_HEADER_SIZE - CHECKSUM_SIZE);
+        if crc32c_checksum(fields) != BigEndian::read_u32(checksum) {
+            return Err(HeaderError::ChecksumMismatch.into());
+        }
+        if data[0] != PACKET_VERSION {
+            return Err(HeaderError::UnsupportedVersion(data[0]).into());
+        }
+
+        let bits: u8 = data[1] & FIELD_BITS_MASK;
+        let field: GaloisField =
+            GaloisField::from_bits(bits).ok_or(HeaderError::UnsupportedField(bits))?;
+        let code: u8 = data[1] >> ERASURE_CODE_SHIFT;
+        let erasure_code: ErasureCode =
+            ErasureCode::from_id(code).ok_or(HeaderError::UnsupportedErasureCode(code))?;
+        let layout: ShardLayout = if data[1] & NON_SYSTEMATIC_FLAG != 0 {
+            ShardLayout::NonSystematic
+        } else {
+            ShardLayout::Systematic
+        };
+
+        Ok(Self {
+            stream_id: BigEndian::read_u32(&data[2..6]),
+            block: BigEndian::read_u32(&data[6..10]),
+            index: BigEndian::read_u16(&data[10..12]),
+            data_shards: BigEndian::read_u16(&data[12..14]),
+            parity_shards: BigEndian::read_u16(&data[14..16]),
+            field,
+            layout,
+            erasure_code,
+        })
+    }
+}
+
+/// Splits a stream into blocks small enough that every shard, with its
+/// packet header, fits in one datagram of `mtu` bytes.
+#[derive(Debug)]
+pub struct PacketEncoder {
+    codec: ReedSolomonCodec,
+    stream_id: u32,
+    block_capacity: usize,
+    next_block: u32,
+}
+
+impl PacketEncoder {
+    pub fn new(
+        config: EncoderConfig,
+        stream_id: u32,
+        mtu: usize,
+    ) -> Result<Self, ReedSolomonError> {
+        // The packet header has no room for the polynomial, so receivers
+        // always assume the default one.
+        if let Some(polynomial) = config
+            .polynomial()
+            .filter(|polynomial: &u16| *polynomial != DEFAULT_POLYNOMIAL)
+        {
+            return Err(ReedSolomonError::InvalidPolynomial(polynomial));
+        }
+
+        let element_size: usize = config.element_size();
+        let shard_size: usize =
+            mtu.saturating_sub(PACKET_HEADER_SIZE + CHECKSUM_SIZE) / element_size * element_size;
+        let block_capacity: usize = (shard_size * config.data_shards()).saturating_sub(PREFIX_SIZE);
+        if block_capacity == 0 {
+            let min_shard_size: usize =
+                (PREFIX_SIZE / config.data_shards() + 1).next_multiple_of(element_size);
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: mtu,
+                min: PACKET_HEADER_SIZE + CHECKSUM_SIZE + min_shard_size,
+                max: usize::MAX,
+            });
+        }
+
+        Ok(Self {
+            codec: ReedSolomonCodec::with_config(config)?,
+            stream_id,
+            block_capacity,
+            next_block: 0,
+        })
+    }
+
+    /// Largest block whose shards still fit in the MTU.
+    pub fn block_capacity(&self) -> usize {
+        self.block_capacity
+    }
+
+    pub fn next_block(&self) -> u32 {
+        self.next_block
+    }
+
+    /// Encodes `data` as the next block and returns one packet per shard.
+    pub fn encode_block(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        if data.is_empty() || data.len() > self.block_capacity {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: data.len(),
+                min: 1,
+                max: self.block_capacity,
+            });
+        }
+
+        let config: &EncoderConfig = self.codec.config();
+        let mut header: PacketHeader = PacketHeader {
+            stream_id: self.stream_id,
+            block: self.next_block,
+            index: 0,
+            data_shards: config.data_shards() as u16,
+            parity_shards: config.parity_shards() as u16,
+            field: config.field(),
+            layout: config.layout(),
+            erasure_code: config.erasure_code(),
+        };
+        let packets: Vec<Vec<u8>> = self
+            .codec
+            .encode_iter(data)?
+            .map(|shard: Shard| {
+                header.index = shard.index;
+                let mut packet: Vec<u8> =
+                    Vec::with_capacity(PACKET_HEADER_SIZE + shard.payload.len());
+                packet.extend_from_slice(&header.to_bytes());
+                packet.extend_from_slice(&shard.payload);
+                packet
+            })
+            .collect();
+
+        self.next_block = self.next_block.wrapping_add(1);
+        Ok(packets)
+    }
+
+    /// Encodes `data` as as many blocks as it takes, in order.
+    pub fn encode(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        let mut packets: Vec<Vec<u8>> = Vec::new();
+        for block in data.chunks(self.block_capacity) {
+            packets.extend(self.encode_block(block)?);
+        }
+        Ok(packets)
+    }
+}
+
+#[derive(Debug)]
+struct PendingBlock {
+    header: PacketHeader,
+    shards: Vec<Option<Vec<u8>>>,
+    intact: usize,
+}
+
+/// Reassembles blocks from packets that arrive out of order, duplicated or
+/// not at all. Each block is decoded as soon as `data_shards` of its
+/// packets are in; packets that are damaged, belong to another stream or
+/// arrive after their block was decoded are dropped.
+#[derive(Debug)]
+pub struct PacketReceiver {
+    stream_id: u32,
+    window: usize,
+    pending: BTreeMap<u32, PendingBlock>,
+    done: BTreeSet<u32>,
+    codec: Option<(PacketHeader, ReedSolomonCodec)>,
+    dropped: usize,
+}
+
+impl PacketReceiver {
+    pub fn new(stream_id: u32) -> Self {
+        Self {
+            stream_id,
+            window: DEFAULT_WINDOW,
+            pending: BTreeMap::new(),
+            done: BTreeSet::new(),
+            codec: None,
+            dropped: 0,
+        }
+    }
+
+    /// Number of incomplete blocks kept before the oldest is given up, and
+    /// of decoded blocks remembered so their late packets are ignored.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    pub fn pending_blocks(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn dropped_packets(&self) -> usize {
+        self.dropped
+    }
+
+    /// Feeds one packet and returns the block it completed, if any.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<(u32, Vec<u8>)>, ReedSolomonError> {
+        match self.accept(packet) {
+            Some(block) => self.decode_block(block).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn accept(&mut self, packet: &[u8]) -> Option<PendingBlock> {
+        let Some(header) = self.admit(packet) else {
+            self.dropped += 1;
+            return None;
+        };
+
+        let total_shards: usize = header.data_shards as usize + header.parity_shards as usize;
+        let block: &mut PendingBlock =
+            self.pending
+                .entry(header.block)
+                .or_insert_with(|| PendingBlock {
+                    header,
+                    shards: vec![None; total_shards],
+                    intact: 0,
+                });
+
+        let shard: &[u8] = &packet[PACKET_HEADER_SIZE..];
+        let shard_len: Option<usize> = block.shards.iter().flatten().map(Vec::len).next();
+        let index: usize = header.index as usize;
+        if !block.header.same_code(&header)
+            || index >= block.shards.len()
+            || block.shards[index].is_some()
+            || shard_len.is_some_and(|shard_len: usize| shard_len != shard.len())
+        {
+            self.dropped += 1;
+            return None;
+        }
+
+        block.shards[index] = Some(shard.to_vec());
+        block.intact += 1;
+        if block.intact < header.data_shards as usize {
+            while self.pending.len() > self.window {
+                self.pending.pop_first();
+            }
+            return None;
+        }
+
+        self.done.insert(header.block);
+        while self.done.len() > self.window {
+            self.done.pop_first();
+        }
+        self.pending.remove(&header.block)
+    }
+
+    fn admit(&self, packet: &[u8]) -> Option<PacketHeader> {
+        let header: PacketHeader = PacketHeader::read(packet).ok()?;
+        if header.stream_id != self.stream_id
+            || self.done.contains(&header.block)
+            || DataProcessor::verify_checksum(&packet[PACKET_HEADER_SIZE..]).is_none()
+            || header.config().is_err()
+        {
+            return None;
+        }
+        Some(header)
+    }
+
+    fn decode_block(&mut self, block: PendingBlock) -> Result<(u32, Vec<u8>), ReedSolomonError> {
+        let cached: Option<ReedSolomonCodec> = self
+            .codec
+            .take()
+            .filter(|(header, _): &(PacketHeader, ReedSolomonCodec)| {
+                header.same_code(&block.header)
+            })
+            .map(|(_, codec): (PacketHeader, ReedSolomonCodec)| codec);
+        let codec: ReedSolomonCodec = match cached {
+            Some(codec) => codec,
+            None => ReedSolomonCodec::with_config(block.header.config()?)?,
+        };
+
+        let decoded: Result<Vec<u8>, ReedSolomonError> = codec.decode_positional(block.shards);
+        self.codec = Some((block.header, codec));
+        Ok((block.header.block, decoded?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_roundtrip() -> Result<(), ReedSolomonError> {
+        let header: PacketHeader = PacketHeader {
+            stream_id: 0xdead_beef,
+            block: 7,
+            index: 300,
+            data_shards: 200,
+            parity_shards: 120,
+            field: GaloisField::Gf16,
+            layout: ShardLayout::NonSystematic,
+            erasure_code: ErasureCode::Matrix,
+        };
+        let bytes: [u8; PACKET_HEADER_SIZE] = header.to_bytes();
+        assert_eq!(PacketHeader::read(&bytes)?, header);
+
+        let mut damaged: [u8; PACKET_HEADER_SIZE] = bytes;
+        damaged[3] ^= 0x01;
+        assert!(PacketHeader::read(&damaged).is_err());
+        assert!(PacketHeader::read(&bytes[..PACKET_HEADER_SIZE - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_packets_fit_the_mtu() -> Result<(), ReedSolomonError> {
+        let mut encoder: PacketEncoder = PacketEncoder::new(EncoderConfig::new(8, 4)?, 1, 512)?;
+        let data: Vec<u8> = vec![0x42; encoder.block_capacity()];
+        let packets: Vec<Vec<u8>> = encoder.encode_block(&data)?;
+
+        assert_eq!(packets.len(), 12);
+        assert!(packets.iter().all(|packet: &Vec<u8>| packet.len() == 512));
+        assert!(encoder.encode_block(&[0x42; 4000]).is_err());
+        assert!(PacketEncoder::new(EncoderConfig::new(1, 1)?, 1, PACKET_HEADER_SIZE + 8).is_err());
+        assert!(matches!(
+            PacketEncoder::new(EncoderConfig::new(8, 4)?.with_polynomial(0x187)?, 1, 512),
+            Err(ReedSolomonError::InvalidPolynomial(0x187))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_receiver_survives_loss_reordering_and_duplicates() -> Result<(), ReedSolomonError> {
+        let mut encoder: PacketEncoder = PacketEncoder::new(EncoderConfig::new(4, 2)?, 9, 128)?;
+        let data: Vec<u8> = (0..1000u32).map(|i: u32| (i % 239) as u8).collect();
+        let packets: Vec<Vec<u8>> = encoder.encode(&data)?;
+        let blocks: usize = data.len().div_ceil(encoder.block_capacity());
+        assert_eq!(packets.len(), blocks * 6);
+
+        let mut other: PacketEncoder = PacketEncoder::new(EncoderConfig::new(4, 2)?, 10, 128)?;
+        let mut received: Vec<Vec<u8>> = other.encode_block(b"another stream")?;
+        for (i, packet) in packets.iter().enumerate().rev() {
+            match i % 6 {
+                1 => continue,
+                3 => {
+                    let mut damaged: Vec<u8> = packet.clone();
+                    damaged[PACKET_HEADER_SIZE] ^= 0xff;
+                    received.push(damaged);
+                }
+                _ => {
+                    received.push(packet.clone());
+                    received.push(packet.clone());
+                }
+            }
+        }
+
+        let mut receiver: PacketReceiver = PacketReceiver::new(9);
+        let mut decoded: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+        for packet in &received {
+            if let Some((block, bytes)) = receiver.push(packet)? {
+                assert!(decoded.insert(block, bytes).is_none());
+            }
+        }
+
+        assert_eq!(decoded.len(), blocks);
+        assert_eq!(decoded.into_values().collect::<Vec<_>>().concat(), data);
+        assert_eq!(receiver.pending_blocks(), 0);
+        assert!(receiver.dropped_packets() > blocks * 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_gives_up_on_stale_blocks() -> Result<(), ReedSolomonError> {
+        let mut encoder: PacketEncoder = PacketEncoder::new(EncoderConfig::new(2, 1)?, 1, 64)?;
+        let mut receiver: PacketReceiver = PacketReceiver::new(1).with_window(2);
+
+        for _ in 0..4 {
+            let packets: Vec<Vec<u8>> = encoder.encode_block(b"one")?;
+            assert_eq!(receiver.push(&packets[0])?, None);
+        }
+        assert_eq!(receiver.pending_blocks(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,424 @@
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use byteorder::{ByteOrder, LittleEndian};
+use md5::{Digest, Md5};
+
+use crate::error::ReedSolomonError;
+use crate::files::{manifest_path, shard_paths, ShardManifest};
+#[cfg(feature = "json")]
+use crate::json_manifest::json_manifest_path;
+use crate::stream::read_full;
+
+pub const PAR2_EXTENSION: &str = "par2";
+pub const MAX_SOURCE_SLICES: usize = 32768;
+pub const DEFAULT_SOURCE_SLICES: usize = 2000;
+
+const PACKET_MAGIC: [u8; 8] = *b"PAR2\0PKT";
+const PACKET_HEADER_SIZE: usize = 64;
+const MAIN_TYPE: [u8; 16] = *b"PAR 2.0\0Main\0\0\0\0";
+const FILE_DESC_TYPE: [u8; 16] = *b"PAR 2.0\0FileDesc";
+const IFSC_TYPE: [u8; 16] = *b"PAR 2.0\0IFSC\0\0\0\0";
+const RECOVERY_TYPE: [u8; 16] = *b"PAR 2.0\0RecvSlic";
+const CREATOR_TYPE: [u8; 16] = *b"PAR 2.0\0Creator\0";
+const HASH_16K: usize = 16 * 1024;
+
+const GF_POLYNOMIAL: u32 = 0x1100b;
+const GF_LIMIT: usize = 65535;
+
+type Md5Hash = [u8; 16];
+
+struct Gf16Tables {
+    log: Vec<u16>,
+    antilog: Vec<u16>,
+}
+
+impl Gf16Tables {
+    fn new() -> Self {
+        let mut log: Vec<u16> = vec![0u16; GF_LIMIT + 1];
+        let mut antilog: Vec<u16> = vec![0u16; GF_LIMIT];
+        let mut value: u32 = 1;
+        for (exponent, entry) in antilog.iter_mut().enumerate() {
+            *entry = value as u16;
+            log[value as usize] = exponent as u16;
+            value <<= 1;
+            if value & 0x10000 != 0 {
+                value ^= GF_POLYNOMIAL;
+            }
+        }
+        Self { log, antilog }
+    }
+
+    fn multiply_into(&self, out: &mut [u8], input: &[u8], log_factor: usize) {
+        for (out, input) in out.chunks_exact_mut(2).zip(input.chunks_exact(2)) {
+            let word: u16 = LittleEndian::read_u16(input);
+            if word == 0 {
+                continue;
+            }
+
+            let product: u16 =
+                self.antilog[(self.log[word as usize] as usize + log_factor) % GF_LIMIT];
+            let acc: u16 = LittleEndian::read_u16(out) ^ product;
+            LittleEndian::write_u16(out, acc);
+        }
+    }
+}
+
+struct SourceFile {
+    path: PathBuf,
+    name: String,
+    len: u64,
+    hash_16k: Md5Hash,
+    id: Md5Hash,
+}
+
+impl SourceFile {
+    fn open(path: &Path) -> Result<Self, ReedSolomonError> {
+        let name: String = path
+            .file_name()
+            .and_then(|name: &std::ffi::OsStr| name.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| ReedSolomonError::InvalidFileName(path.to_path_buf()))?;
+        let len: u64 = fs::metadata(path)?.len();
+        if len == 0 {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: 0,
+                min: 1,
+                max: usize::MAX,
+            });
+        }
+
+        let mut head: Vec<u8> = Vec::with_capacity(HASH_16K);
+        File::open(path)?
+            .take(HASH_16K as u64)
+            .read_to_end(&mut head)?;
+        let hash_16k: Md5Hash = Md5::digest(&head).into();
+
+        let mut hasher: Md5 = Md5::new();
+        hasher.update(hash_16k);
+        hasher.update(len.to_le_bytes());
+        hasher.update(name.as_bytes());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            name,
+            len,
+            hash_16k,
+            id: hasher.finalize().into(),
+        })
+    }
+
+    fn slice_count(&self, slice_size: u64) -> u64 {
+        self.len.div_ceil(slice_size)
+    }
+}
+
+fn compare_ids(a: &Md5Hash, b: &Md5Hash) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+fn packet(set_id: &Md5Hash, kind: &[u8; 16], body: &[u8]) -> Vec<u8> {
+    let mut packet: Vec<u8> = Vec::with_capacity(PACKET_HEADER_SIZE + body.len());
+    packet.extend_from_slice(&PACKET_MAGIC);
+    packet.extend_from_slice(&((PACKET_HEADER_SIZE + body.len()) as u64).to_le_bytes());
+    packet.extend_from_slice(&[0u8; 16]);
+    packet.extend_from_slice(set_id);
+    packet.extend_from_slice(kind);
+    packet.extend_from_slice(body);
+
+    let hash: Md5Hash = Md5::digest(&packet[32..]).into();
+    packet[16..32].copy_from_slice(&hash);
+    packet
+}
+
+fn pad_to_word(bytes: &mut Vec<u8>) {
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+}
+
+pub fn slice_size_for(total_len: u64, source_slices: usize) -> u64 {
+    total_len
+        .div_ceil(source_slices.clamp(1, MAX_SOURCE_SLICES) as u64)
+        .next_multiple_of(4)
+        .max(4)
+}
+
+pub fn par2_index_path(dir: &Path, basename: &str) -> PathBuf {
+    dir.join(format!("{}.{}", basename, PAR2_EXTENSION))
+}
+
+pub fn par2_volume_path(dir: &Path, basename: &str, recovery_slices: usize) -> PathBuf {
+    dir.join(format!(
+        "{}.vol0+{}.{}",
+        basename, recovery_slices, PAR2_EXTENSION
+    ))
+}
+
+pub fn write_par2(
+    files: &[PathBuf],
+    dir: &Path,
+    basename: &str,
+    slice_size: u64,
+    recovery_slices: usize,
+) -> Result<Vec<PathBuf>, ReedSolomonError> {
+    if slice_size == 0 || !slice_size.is_multiple_of(4) {
+        return Err(ReedSolomonError::InvalidSliceSize(slice_size));
+    }
+
+    if !(1..=MAX_SOURCE_SLICES).contains(&recovery_slices) {
+        return Err(ReedSolomonError::InvalidShardCount {
+            given: recovery_slices,
+            min: 1,
+            max: MAX_SOURCE_SLICES,
+        });
+    }
+
+    let mut sources: Vec<SourceFile> = files
+        .iter()
+        .map(|path: &PathBuf| SourceFile::open(path))
+        .collect::<Result<_, _>>()?;
+    sources.sort_by(|a: &SourceFile, b: &SourceFile| compare_ids(&a.id, &b.id));
+    sources.dedup_by(|a: &mut SourceFile, b: &mut SourceFile| a.id == b.id);
+
+    let source_slices: u64 = sources
+        .iter()
+        .map(|source: &SourceFile| source.slice_count(slice_size))
+        .sum();
+    if source_slices > MAX_SOURCE_SLICES as u64 {
+        return Err(ReedSolomonError::InvalidShardCount {
+            given: source_slices as usize,
+            min: 1,
+            max: MAX_SOURCE_SLICES,
+        });
+    }
+
+    let mut main_body: Vec<u8> = Vec::new();
+    main_body.extend_from_slice(&slice_size.to_le_bytes());
+    main_body.extend_from_slice(&(sources.len() as u32).to_le_bytes());
+    for source in &sources {
+        main_body.extend_from_slice(&source.id);
+    }
+    let set_id: Md5Hash = Md5::digest(&main_body).into();
+
+    let tables: Gf16Tables = Gf16Tables::new();
+    let slice_len: usize = slice_size as usize;
+    let mut recovery: Vec<Vec<u8>> = vec![vec![0u8; slice_len]; recovery_slices];
+    let mut critical: Vec<u8> = packet(&set_id, &MAIN_TYPE, &main_body);
+    let mut slice: Vec<u8> = vec![0u8; slice_len];
+    let mut log_base: usize = 0;
+
+    for source in &sources {
+        let mut reader: BufReader<File> = BufReader::new(File::open(&source.path)?);
+        let mut file_hash: Md5 = Md5::new();
+        let mut checksums: Vec<u8> = source.id.to_vec();
+        let mut remaining: u64 = source.len;
+
+        while remaining > 0 {
+            let filled: usize = read_full(&mut reader, &mut slice)?;
+            if filled == 0 || (filled < slice_len && filled as u64 != remaining) {
+                return Err(ReedSolomonError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{} changed while it was being read", source.path.display()),
+                )));
+            }
+            slice[filled..].fill(0);
+            remaining -= filled as u64;
+
+            file_hash.update(&slice[..filled]);
+            let slice_hash: Md5Hash = Md5::digest(&slice).into();
+            checksums.extend_from_slice(&slice_hash);
+            checksums.extend_from_slice(&crc32fast::hash(&slice).to_le_bytes());
+
+            while gcd(GF_LIMIT, log_base) != 1 {
+                log_base += 1;
+            }
+            for (exponent, output) in recovery.iter_mut().enumerate() {
+                tables.multiply_into(output, &slice, (log_base * exponent) % GF_LIMIT);
+            }
+            log_base += 1;
+        }
+
+        let file_hash: Md5Hash = file_hash.finalize().into();
+        let mut desc_body: Vec<u8> = source.id.to_vec();
+        desc_body.extend_from_slice(&file_hash);
+        desc_body.extend_from_slice(&source.hash_16k);
+        desc_body.extend_from_slice(&source.len.to_le_bytes());
+        desc_body.extend_from_slice(source.name.as_bytes());
+        pad_to_word(&mut desc_body);
+
+        critical.extend_from_slice(&packet(&set_id, &FILE_DESC_TYPE, &desc_body));
+        critical.extend_from_slice(&packet(&set_id, &IFSC_TYPE, &checksums));
+    }
+
+    let mut creator_body: Vec<u8> = format!(
+        "Created by reed-solomon-codec version {}",
+        env!("CARGO_PKG_VERSION")
+    )
+    .into_bytes();
+    pad_to_word(&mut creator_body);
+    critical.extend_from_slice(&packet(&set_id, &CREATOR_TYPE, &creator_body));
+
+    fs::create_dir_all(dir)?;
+    let index: PathBuf = par2_index_path(dir, basename);
+    fs::write(&index, &critical)?;
+
+    let volume: PathBuf = par2_volume_path(dir, basename, recovery_slices);
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(&volume)?);
+    for (exponent, data) in recovery.iter().enumerate() {
+        let mut body: Vec<u8> = Vec::with_capacity(4 + data.len());
+        body.extend_from_slice(&(exponent as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        writer.write_all(&packet(&set_id, &RECOVERY_TYPE, &body))?;
+    }
+    writer.write_all(&critical)?;
+    writer.flush()?;
+
+    Ok(vec![index, volume])
+}
+
+pub fn write_par2_for_shards(
+    dir: &Path,
+    basename: &str,
+    recovery_slices: usize,
+) -> Result<Vec<PathBuf>, ReedSolomonError> {
+    let manifest: ShardManifest = ShardManifest::read(&manifest_path(dir, basename))?;
+    let mut files: Vec<PathBuf> = shard_paths(dir, basename, &manifest);
+    files.push(manifest_path(dir, basename));
+    #[cfg(feature = "json")]
+    files.extend(Some(json_manifest_path(dir, basename)).filter(|path: &PathBuf| path.exists()));
+
+    let total_len: u64 = files
+        .iter()
+        .map(|path: &PathBuf| fs::metadata(path).map(|metadata: fs::Metadata| metadata.len()))
+        .sum::<Result<u64, _>>()?;
+    let slice_size: u64 = slice_size_for(total_len, DEFAULT_SOURCE_SLICES);
+
+    write_par2(&files, dir, basename, slice_size, recovery_slices)
+}
+
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    fn packets(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+        let mut packets: Vec<(&[u8], &[u8])> = Vec::new();
+        let mut offset: usize = 0;
+        while offset < data.len() {
+            assert_eq!(data[offset..offset + 8], PACKET_MAGIC);
+            let len: usize = LittleEndian::read_u64(&data[offset + 8..offset + 16]) as usize;
+            let packet: &[u8] = &data[offset..offset + len];
+            let hash: Md5Hash = Md5::digest(&packet[32..]).into();
+            assert_eq!(packet[16..32], hash);
+            packets.push((&packet[48..64], &packet[PACKET_HEADER_SIZE..]));
+            offset += len;
+        }
+        packets
+    }
+
+    #[test]
+    fn test_gf16_tables() {
+        let tables: Gf16Tables = Gf16Tables::new();
+        assert_eq!(tables.antilog[0], 1);
+        assert_eq!(tables.antilog[16], 0x100b);
+        assert_eq!(tables.log[2], 1);
+    }
+
+    #[test]
+    fn test_par2_recovery_rebuilds_missing_slice() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("par2-recovery");
+        fs::create_dir_all(&dir)?;
+        let first: Vec<u8> = (0..100u32).map(|i: u32| (i * 7 % 256) as u8).collect();
+        let second: Vec<u8> = (0..30u32).map(|i: u32| (i * 13 % 256) as u8).collect();
+        fs::write(dir.join("first.bin"), &first)?;
+        fs::write(dir.join("second.bin"), &second)?;
+
+        let files: Vec<PathBuf> = vec![dir.join("first.bin"), dir.join("second.bin")];
+        let written: Vec<PathBuf> = write_par2(&files, &dir, "set", 32, 2)?;
+        assert_eq!(written.len(), 2);
+
+        let index: Vec<u8> = fs::read(&written[0])?;
+        let kinds: Vec<&[u8]> = packets(&index)
+            .into_iter()
+            .map(|(kind, _): (&[u8], &[u8])| kind)
+            .collect();
+        let count = |expected: [u8; 16]| {
+            kinds
+                .iter()
+                .filter(|kind: &&&[u8]| **kind == expected)
+                .count()
+        };
+        assert_eq!(count(MAIN_TYPE), 1);
+        assert_eq!(count(FILE_DESC_TYPE), 2);
+        assert_eq!(count(IFSC_TYPE), 2);
+        assert_eq!(count(CREATOR_TYPE), 1);
+
+        let volume: Vec<u8> = fs::read(&written[1])?;
+        let recovery: Vec<&[u8]> = packets(&volume)
+            .into_iter()
+            .filter(|(kind, _): &(&[u8], &[u8])| *kind == RECOVERY_TYPE)
+            .map(|(_, body): (&[u8], &[u8])| &body[4..])
+            .collect();
+        assert_eq!(recovery.len(), 2);
+
+        let mut sources: Vec<SourceFile> = files
+            .iter()
+            .map(|path: &PathBuf| SourceFile::open(path))
+            .collect::<Result<_, _>>()?;
+        sources.sort_by(|a: &SourceFile, b: &SourceFile| compare_ids(&a.id, &b.id));
+        let mut slices: Vec<Vec<u8>> = Vec::new();
+        for source in &sources {
+            for chunk in fs::read(&source.path)?.chunks(32) {
+                let mut slice: Vec<u8> = chunk.to_vec();
+                slice.resize(32, 0);
+                slices.push(slice);
+            }
+        }
+
+        let parity: Vec<u8> = slices
+            .iter()
+            .fold(vec![0u8; 32], |acc: Vec<u8>, slice: &Vec<u8>| {
+                acc.iter()
+                    .zip(slice)
+                    .map(|(a, b): (&u8, &u8)| a ^ b)
+                    .collect()
+            });
+        assert_eq!(recovery[0], parity.as_slice());
+
+        let tables: Gf16Tables = Gf16Tables::new();
+        let log_bases: Vec<usize> = (1..)
+            .filter(|log_base: &usize| gcd(GF_LIMIT, *log_base) == 1)
+            .take(slices.len())
+            .collect();
+        let missing: usize = 2;
+        let mut rebuilt: Vec<u8> = recovery[1].to_vec();
+        for (i, slice) in slices
+            .iter()
+            .enumerate()
+            .filter(|(i, _): &(usize, &Vec<u8>)| *i != missing)
+        {
+            tables.multiply_into(&mut rebuilt, slice, log_bases[i]);
+        }
+        let mut recovered: Vec<u8> = vec![0u8; 32];
+        tables.multiply_into(&mut recovered, &rebuilt, GF_LIMIT - log_bases[missing]);
+        assert_eq!(recovered, slices[missing]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_slice_size_for() {
+        assert_eq!(slice_size_for(10, 2000), 4);
+        assert_eq!(slice_size_for(1 << 20, 1000), 1052);
+        assert!(write_par2(&[], Path::new("."), "x", 6, 1).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use rayon::prelude::*;
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::stream::split_blocks;
+
+impl ReedSolomonCodec {
+    pub fn encode_parallel(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        let blocks: Vec<Vec<u8>> = data
+            .par_chunks(self.group_size()?)
+            .map(|group: &[u8]| {
+                let mut encoded: Vec<u8> = Vec::new();
+                self.encode_unit_into(group, &mut encoded)?;
+                Ok(encoded)
+            })
+            .collect::<Result<_, ReedSolomonError>>()?;
+
+        Ok(blocks.concat())
+    }
+
+    pub fn decode_parallel(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        let blocks: Vec<Vec<u8>> = split_blocks(data)?
+            .par_iter()
+            .map(|block: &&[u8]| self.decode_unit(block))
+            .collect::<Result<_, _>>()?;
+
+        Ok(blocks.concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+
+    #[test]
+    fn test_parallel_matches_stream_format() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..10_000u32).map(|i: u32| (i % 241) as u8).collect();
+
+        let parallel: Vec<u8> = codec.encode_parallel(&original_data)?;
+        let sequential: Vec<u8> = codec.encode_blocks(&original_data)?;
+
+        assert_eq!(parallel, sequential);
+        assert_eq!(codec.decode_parallel(&parallel)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_interleaved_matches_stream_format() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?
+            .with_block_size(512)?
+            .with_interleave_depth(4)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 239) as u8).collect();
+
+        let parallel: Vec<u8> = codec.encode_parallel(&original_data)?;
+
+        assert_eq!(parallel, codec.encode_blocks(&original_data)?);
+        assert_eq!(codec.decode_parallel(&parallel)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_parallel_rejects_truncated_input() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let encoded: Vec<u8> = codec.encode_parallel(&[7u8; 3000])?;
+
+        assert!(codec
+            .decode_parallel(&encoded[..encoded.len() - 1])
+            .is_err());
+        Ok(())
+    }
+}
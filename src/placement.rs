@@ -0,0 +1,110 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Decides which storage target (a disk, host or zone) each shard goes to,
+/// so shards that must fail independently land in different failure
+/// domains.
+pub trait PlacementPolicy: fmt::Debug + Send + Sync {
+    /// How many targets shards are spread over.
+    fn targets(&self) -> usize;
+
+    /// The target, below `targets()`, for shard `index` of `total_shards`.
+    fn target(&self, index: usize, total_shards: usize) -> usize;
+
+    /// The target of every shard, in shard order.
+    fn plan(&self, total_shards: usize) -> Vec<usize> {
+        (0..total_shards)
+            .map(|index: usize| self.target(index, total_shards))
+            .collect()
+    }
+
+    /// How many targets can fail at once, whichever they are, without
+    /// losing more than `parity_shards` shards.
+    fn tolerated_failures(&self, total_shards: usize, parity_shards: usize) -> usize {
+        let mut per_target: Vec<usize> = vec![0; self.targets()];
+        for target in self.plan(total_shards) {
+            per_target[target] += 1;
+        }
+        per_target.sort_unstable_by(|a: &usize, b: &usize| b.cmp(a));
+
+        let mut lost: usize = 0;
+        per_target
+            .iter()
+            .take_while(|shards: &&usize| {
+                lost += **shards;
+                lost <= parity_shards
+            })
+            .count()
+    }
+}
+
+/// Deals shards out to the targets in turn: shard `i` goes to target
+/// `i % targets`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundRobin {
+    targets: usize,
+}
+
+impl RoundRobin {
+    /// Panics if `targets` is zero.
+    pub fn new(targets: usize) -> Self {
+        assert!(
+            targets > 0,
+            "round-robin placement needs at least one target"
+        );
+        Self { targets }
+    }
+}
+
+impl PlacementPolicy for RoundRobin {
+    fn targets(&self) -> usize {
+        self.targets
+    }
+
+    fn target(&self, index: usize, _total_shards: usize) -> usize {
+        index % self.targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps the first `data` shards on target 0, as a deliberately bad
+    /// policy.
+    #[derive(Debug)]
+    struct DataTogether {
+        data: usize,
+    }
+
+    impl PlacementPolicy for DataTogether {
+        fn targets(&self) -> usize {
+            2
+        }
+
+        fn target(&self, index: usize, _total_shards: usize) -> usize {
+            usize::from(index >= self.data)
+        }
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let policy: RoundRobin = RoundRobin::new(4);
+        assert_eq!(policy.plan(6), vec![0, 1, 2, 3, 0, 1]);
+
+        // 14 shards over 4 targets puts at most 4 on one target.
+        assert_eq!(policy.tolerated_failures(14, 4), 1);
+        assert_eq!(policy.tolerated_failures(14, 7), 1);
+        assert_eq!(policy.tolerated_failures(14, 8), 2);
+        assert_eq!(RoundRobin::new(14).tolerated_failures(14, 4), 4);
+    }
+
+    #[test]
+    fn test_tolerated_failures_of_custom_policy() {
+        let policy: DataTogether = DataTogether { data: 10 };
+        assert_eq!(policy.plan(12)[9..], [0, 1, 1]);
+        assert_eq!(policy.tolerated_failures(12, 2), 0);
+        assert_eq!(policy.tolerated_failures(12, 10), 1);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::files::ShardManifest;
+use crate::header::{GROUP_HEADER_SIZE, HEADER_SIZE};
+
+/// What encoding a payload of a given length would write, worked out from
+/// the configuration alone, so configurations can be compared before any
+/// disk space is spent. Compressed blocks are counted as if compression
+/// saved nothing, which makes the sizes an upper bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodePlan {
+    pub input_len: u64,
+    pub block_count: u64,
+    pub total_shards: usize,
+    /// Bytes of each shard across every block.
+    pub shard_len: u64,
+    /// Bytes written in total, headers included.
+    pub encoded_len: u64,
+}
+
+impl EncodePlan {
+    /// Bytes written beyond the payload itself: parity, padding,
+    /// checksums and headers.
+    pub fn overhead(&self) -> u64 {
+        self.encoded_len.saturating_sub(self.input_len)
+    }
+
+    fn repeated(self, count: u64) -> Self {
+        Self {
+            input_len: self.input_len * count,
+            block_count: self.block_count * count,
+            total_shards: self.total_shards,
+            shard_len: self.shard_len * count,
+            encoded_len: self.encoded_len * count,
+        }
+    }
+
+    fn and(self, other: Self) -> Self {
+        Self {
+            input_len: self.input_len + other.input_len,
+            block_count: self.block_count + other.block_count,
+            total_shards: self.total_shards,
+            shard_len: self.shard_len + other.shard_len,
+            encoded_len: self.encoded_len + other.encoded_len,
+        }
+    }
+}
+
+impl ReedSolomonCodec {
+    /// Plans the container `encode_stream` writes for `input_len` bytes.
+    pub fn plan(&self, input_len: u64) -> Result<EncodePlan, ReedSolomonError> {
+        let group_size: u64 = self.group_size()? as u64;
+        let full: EncodePlan = self.plan_unit(group_size as usize)?;
+        let plan: EncodePlan = full.repeated(input_len / group_size);
+        match (input_len % group_size) as usize {
+            0 => Ok(plan),
+            rest => Ok(plan.and(self.plan_unit(rest)?)),
+        }
+    }
+
+    /// Plans the shard files `encode_to_files` writes for `input_len`
+    /// bytes. Only the shard files are counted, not the manifests.
+    pub fn plan_files(&self, input_len: u64) -> EncodePlan {
+        let manifest: ShardManifest = self.shard_manifest(input_len);
+        let block_count: u64 = manifest.block_count();
+        let shard_len: u64 = match block_count {
+            0 => 0,
+            _ => {
+                (block_count - 1) * manifest.shard_size(0) as u64
+                    + manifest.shard_size(block_count - 1) as u64
+            }
+        };
+        let total_shards: usize = manifest.data_shards + manifest.parity_shards;
+        EncodePlan {
+            input_len,
+            block_count,
+            total_shards,
+            shard_len,
+            encoded_len: shard_len * total_shards as u64,
+        }
+    }
+
+    /// Plans one unit of `encode_unit_into`: a single container, or a
+    /// group of interleaved blocks behind a group header.
+    fn plan_unit(&self, unit_len: usize) -> Result<EncodePlan, ReedSolomonError> {
+        if let Some(replica) = self.replica_codec(unit_len)? {
+            return replica.plan_unit(unit_len);
+        }
+
+        let block_size: usize = self.config().block_size();
+        let block: EncodePlan = self.plan_block(block_size);
+        let plan: EncodePlan = block.repeated((unit_len / block_size) as u64);
+        let plan: EncodePlan = match unit_len % block_size {
+            0 => plan,
+            rest => plan.and(self.plan_block(rest)),
+        };
+        if plan.block_count > 1 {
+            return Ok(EncodePlan {
+                encoded_len: plan.encoded_len + GROUP_HEADER_SIZE as u64,
+                ..plan
+            });
+        }
+        Ok(plan)
+    }
+
+    fn plan_block(&self, block_len: usize) -> EncodePlan {
+        let total_shards: usize = self.config().total_shards();
+        let encoded_len: usize = self.coded_len(block_len);
+        EncodePlan {
+            input_len: block_len as u64,
+            block_count: 1,
+            total_shards,
+            shard_len: ((encoded_len - HEADER_SIZE) / total_shards) as u64,
+            encoded_len: encoded_len as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::replication::HybridPolicy;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_plan_matches_encoded_stream() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?
+            .with_block_size(1000)?
+            .with_interleave_depth(3)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        for input_len in [0usize, 1, 999, 1000, 2500, 3000, 7001] {
+            let data: Vec<u8> = (0..input_len).map(|i: usize| (i % 251) as u8).collect();
+            let plan: EncodePlan = codec.plan(input_len as u64)?;
+            assert_eq!(
+                plan.encoded_len,
+                codec.encode_blocks(&data)?.len() as u64,
+                "{}",
+                input_len
+            );
+            assert_eq!(plan.block_count, (input_len as u64).div_ceil(1000));
+            assert_eq!(plan.overhead(), plan.encoded_len - input_len as u64);
+        }
+
+        let hybrid: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?
+            .with_hybrid_policy(HybridPolicy::matching(&config, 100)?);
+        let plan: EncodePlan = hybrid.plan(50)?;
+        assert_eq!(
+            plan.encoded_len,
+            hybrid.encode_blocks(&[7u8; 50])?.len() as u64
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_files_matches_shard_files() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("plan-files");
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 251) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_files(original_data.as_slice(), &dir, "data")?;
+
+        let plan: EncodePlan = codec.plan_files(original_data.len() as u64);
+        assert_eq!((plan.block_count, plan.total_shards), (5, 6));
+        for index in 0..6 {
+            let path: PathBuf = manifest.shard_path(&dir, "data", index);
+            assert_eq!(fs::metadata(path)?.len(), plan.shard_len);
+        }
+        assert_eq!(plan.encoded_len, 6 * plan.shard_len);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
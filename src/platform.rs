@@ -0,0 +1,322 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Absolute paths at least this long need the `\\?\` form on Windows. It
+/// is below MAX_PATH because `CreateDirectoryW` keeps room for a file name.
+const WINDOWS_PATH_LIMIT: usize = 248;
+
+/// How often `replace_file` retries a rename Windows refuses.
+const RENAME_ATTEMPTS: u32 = 5;
+
+/// Device names Windows reserves in every directory, whatever the extension.
+/// The superscript digits count as ports too.
+const RESERVED_NAMES: [&str; 30] = [
+    "CON",
+    "PRN",
+    "AUX",
+    "NUL",
+    "COM0",
+    "COM1",
+    "COM2",
+    "COM3",
+    "COM4",
+    "COM5",
+    "COM6",
+    "COM7",
+    "COM8",
+    "COM9",
+    "COM\u{b9}",
+    "COM\u{b2}",
+    "COM\u{b3}",
+    "LPT0",
+    "LPT1",
+    "LPT2",
+    "LPT3",
+    "LPT4",
+    "LPT5",
+    "LPT6",
+    "LPT7",
+    "LPT8",
+    "LPT9",
+    "LPT\u{b9}",
+    "LPT\u{b2}",
+    "LPT\u{b3}",
+];
+
+/// Whether Windows reads `name` as a device, as it does `nul` or `COM1.txt`.
+pub fn is_reserved_name(name: &str) -> bool {
+    let stem: &str = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved: &&str| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// `name` made into a file name every platform accepts, so shard sets can
+/// be copied between them: characters Windows rejects become `_`, trailing
+/// dots and spaces are dropped and a reserved stem gets a `_` appended.
+pub fn portable_file_name(name: &str) -> String {
+    let mut portable: String = name
+        .chars()
+        .map(|c: char| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string();
+    if portable.is_empty() {
+        return "_".to_string();
+    }
+    if is_reserved_name(&portable) {
+        let stem_len: usize = portable.find('.').unwrap_or(portable.len());
+        portable.insert(stem_len, '_');
+    }
+    portable
+}
+
+/// `path` in the `\\?\` form on Windows when it is too long for the
+/// classic API; other paths, and every path elsewhere, come back as is.
+pub fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+    std::path::absolute(path)
+        .ok()
+        .and_then(|absolute: PathBuf| windows_long_path(&absolute))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// What `long_path` makes of the absolute Windows path `absolute`: its
+/// `\\?\` form when it is too long for the classic API, or `None` when it
+/// is short enough as is.
+fn windows_long_path(absolute: &Path) -> Option<PathBuf> {
+    if absolute.as_os_str().len() < WINDOWS_PATH_LIMIT {
+        return None;
+    }
+    Some(
+        absolute
+            .to_str()
+            .and_then(extended_length_path)
+            .map_or_else(|| absolute.to_path_buf(), PathBuf::from),
+    )
+}
+
+/// The `\\?\` form of an absolute Windows path, or `None` for a path that
+/// has no such form.
+fn extended_length_path(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") {
+        return Some(path.to_string());
+    }
+    if path.starts_with(r"\\.\") {
+        return None;
+    }
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", share));
+    }
+    match path.as_bytes() {
+        [drive, b':', b'\\', ..] if drive.is_ascii_alphabetic() => Some(format!(r"\\?\{}", path)),
+        _ => None,
+    }
+}
+
+/// Renames `from` over `to` in one step. Windows refuses while another
+/// process, typically a virus scanner or indexer, briefly holds `to`
+/// open, so there the rename is retried for a moment before giving up.
+pub fn replace_file(from: &Path, to: &Path) -> io::Result<()> {
+    let mut attempt: u32 = 0;
+    loop {
+        match fs::rename(from, to) {
+            Err(error)
+                if cfg!(windows)
+                    && error.kind() == io::ErrorKind::PermissionDenied
+                    && attempt < RENAME_ATTEMPTS =>
+            {
+                attempt += 1;
+                thread::sleep(Duration::from_millis(10 << attempt));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Writes `data` next to `path` and renames it into place, so a reader
+/// sees the old file or the new one and never half of either. The data
+/// and the directory are flushed to disk before the rename, and the
+/// directory again after it, so a crash cannot leave the new name
+/// pointing at a file whose contents never reached the disk.
+pub fn write_replacing(path: &Path, data: &[u8]) -> io::Result<()> {
+    let partial: PathBuf = partial_path(path);
+    let written: io::Result<()> = File::create(&partial)
+        .and_then(|mut file: File| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|()| sync_parent_dir(&partial));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(error);
+    }
+    replace_file(&partial, path)?;
+    sync_parent_dir(path)
+}
+
+/// Flushes the entries of the directory holding `path` to disk.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let dir: &Path = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+/// Windows cannot open a directory to flush it; `sync_all` on the file
+/// already covers its metadata there.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Where a file is written before it is renamed to `path`.
+pub(crate) fn partial_path(path: &Path) -> PathBuf {
+    let mut partial: std::ffi::OsString = path.as_os_str().to_os_string();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_reserved_names_are_avoided() {
+        for name in [
+            "CON",
+            "nul",
+            "Aux.txt",
+            "com1.tar.gz",
+            "LPT9",
+            "prn .log",
+            "COM0",
+            "lpt0.txt",
+            "COM\u{b9}",
+            "com\u{b2}.log",
+            "LPT\u{b3}",
+        ] {
+            assert!(is_reserved_name(name), "{}", name);
+            assert!(!is_reserved_name(&portable_file_name(name)), "{}", name);
+        }
+        for name in [
+            "CONSOLE",
+            "COM10",
+            "COM\u{2074}",
+            "nullable.txt",
+            "my.con",
+            "backup",
+        ] {
+            assert!(!is_reserved_name(name), "{}", name);
+            assert_eq!(portable_file_name(name), name);
+        }
+
+        assert_eq!(portable_file_name("con.txt"), "con_.txt");
+        assert_eq!(portable_file_name("a:b|c?.txt"), "a_b_c_.txt");
+        assert_eq!(portable_file_name("notes. . "), "notes");
+        assert_eq!(portable_file_name("..."), "_");
+    }
+
+    #[test]
+    fn test_extended_length_paths() {
+        assert_eq!(
+            extended_length_path(r"C:\backups\data").as_deref(),
+            Some(r"\\?\C:\backups\data")
+        );
+        assert_eq!(
+            extended_length_path(r"\\server\share\data").as_deref(),
+            Some(r"\\?\UNC\server\share\data")
+        );
+        assert_eq!(
+            extended_length_path(r"\\?\D:\data").as_deref(),
+            Some(r"\\?\D:\data")
+        );
+        assert_eq!(extended_length_path(r"\\.\pipe\shards"), None);
+        assert_eq!(extended_length_path(r"relative\data"), None);
+        assert_eq!(extended_length_path("/home/user/data"), None);
+
+        let long: String = format!(r"C:\backups\{}", "d".repeat(WINDOWS_PATH_LIMIT));
+        assert_eq!(
+            windows_long_path(Path::new(&long)),
+            Some(PathBuf::from(format!(r"\\?\{}", long)))
+        );
+        let share: String = format!(r"\\server\share\{}", "d".repeat(WINDOWS_PATH_LIMIT));
+        assert_eq!(
+            windows_long_path(Path::new(&share)),
+            Some(PathBuf::from(format!(r"\\?\UNC\{}", &share[2..])))
+        );
+        assert_eq!(windows_long_path(Path::new(r"C:\backups\data")), None);
+
+        let short: &Path = Path::new("shards");
+        assert_eq!(long_path(short), short);
+    }
+
+    #[test]
+    fn test_write_replacing_swaps_whole_files() -> io::Result<()> {
+        let dir: PathBuf = temp_dir("platform");
+        fs::create_dir_all(&dir)?;
+        let path: PathBuf = dir.join("data.manifest");
+
+        write_replacing(&path, b"first")?;
+        write_replacing(&path, b"second")?;
+        assert_eq!(fs::read(&path)?, b"second");
+        assert!(!partial_path(&path).exists());
+
+        let moved: PathBuf = dir.join("moved");
+        fs::write(&moved, b"third")?;
+        replace_file(&moved, &path)?;
+        assert_eq!(fs::read(&path)?, b"third");
+        assert!(!moved.exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_shard_files_under_long_paths() -> Result<(), crate::error::ReedSolomonError> {
+        use crate::codec::ReedSolomonCodec;
+        use crate::files::{decode_from_files, ShardManifest};
+
+        let root: PathBuf = temp_dir("long-paths");
+        let dir: PathBuf = long_path(&(0..6).fold(root.clone(), |dir: PathBuf, i: usize| {
+            dir.join(format!("{}{}", i, "d".repeat(60)))
+        }));
+        assert!(dir.as_os_str().len() > WINDOWS_PATH_LIMIT);
+        assert!(dir.to_string_lossy().starts_with(r"\\?\"));
+
+        let basename: String = portable_file_name("aux.log");
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: Vec<u8> = (0..3000u32).map(|i: u32| (i % 251) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_files(original_data.as_slice(), &dir, &basename)?;
+        for index in 0..5 {
+            let path: PathBuf = manifest.shard_path(&dir, &basename, index);
+            let name: String = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into();
+            assert!(!is_reserved_name(&name), "{}", name);
+        }
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_files(&dir, &basename, &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
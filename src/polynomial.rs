@@ -0,0 +1,547 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use reed_solomon_erasure::Error;
+
+use crate::backend::ErasureBackend;
+use crate::error::ReedSolomonError;
+#[cfg(feature = "std")]
+use crate::simd::{self, SimdLevel};
+
+/// x^8 + x^4 + x^3 + x^2 + 1, the polynomial `reed-solomon-erasure`, zfec
+/// and most other GF(2^8) Reed-Solomon libraries use.
+pub const DEFAULT_POLYNOMIAL: u16 = 0x11d;
+
+/// Products of one coefficient with every low nibble, then with every high
+/// nibble, in the layout the byte-shuffle instructions expect.
+pub(crate) type NibbleTables = [u8; 32];
+
+/// GF(2^8) built on any primitive polynomial of degree 8, with 2 as the
+/// generator.
+#[derive(Clone)]
+pub struct Gf8Field {
+    polynomial: u16,
+    exp: [u8; 510],
+    log: [u8; 256],
+}
+
+impl Gf8Field {
+    pub fn new(polynomial: u16) -> Result<Self, ReedSolomonError> {
+        if !(0x100..0x200).contains(&polynomial) {
+            return Err(ReedSolomonError::InvalidPolynomial(polynomial));
+        }
+
+        let mut exp: [u8; 510] = [0u8; 510];
+        let mut log: [u8; 256] = [0u8; 256];
+        let mut value: u16 = 1;
+        for i in 0..255 {
+            // Returning to 1 early means 2 does not generate the whole
+            // field, so the polynomial is not primitive.
+            if i > 0 && value == 1 {
+                return Err(ReedSolomonError::InvalidPolynomial(polynomial));
+            }
+            exp[i] = value as u8;
+            exp[i + 255] = value as u8;
+            log[value as usize] = i as u8;
+            value <<= 1;
+            if value & 0x100 != 0 {
+                value ^= polynomial;
+            }
+        }
+        if value != 1 {
+            return Err(ReedSolomonError::InvalidPolynomial(polynomial));
+        }
+
+        Ok(Self {
+            polynomial,
+            exp,
+            log,
+        })
+    }
+
+    pub fn polynomial(&self) -> u16 {
+        self.polynomial
+    }
+
+    pub fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    /// Panics if `b` is zero.
+    pub fn div(&self, a: u8, b: u8) -> u8 {
+        assert!(b != 0, "division by zero in GF(2^8)");
+        if a == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+    }
+
+    /// `base` raised to `power`, with `0^0 = 1`.
+    pub fn pow(&self, base: u8, power: usize) -> u8 {
+        if power == 0 {
+            return 1;
+        }
+        if base == 0 {
+            return 0;
+        }
+        self.exp[self.log[base as usize] as usize * power % 255]
+    }
+
+    pub(crate) fn nibble_tables(&self, coefficient: u8) -> NibbleTables {
+        let mut tables: NibbleTables = [0u8; 32];
+        for nibble in 0..16u8 {
+            tables[nibble as usize] = self.mul(coefficient, nibble);
+            tables[16 + nibble as usize] = self.mul(coefficient, nibble << 4);
+        }
+        tables
+    }
+
+    /// The parity rows of `reed-solomon-erasure`'s systematic matrix: a
+    /// Vandermonde matrix multiplied by the inverse of its top square.
+    pub(crate) fn parity_matrix(&self, data_shards: usize, parity_shards: usize) -> Vec<Vec<u8>> {
+        let vandermonde: Vec<Vec<u8>> = (0..data_shards + parity_shards)
+            .map(|row: usize| {
+                (0..data_shards)
+                    .map(|column: usize| self.pow(row as u8, column))
+                    .collect()
+            })
+            .collect();
+        let top_inverse: Vec<Vec<u8>> = self
+            .invert(&vandermonde[..data_shards])
+            .expect("the top of a Vandermonde matrix with distinct rows is invertible");
+
+        vandermonde[data_shards..]
+            .iter()
+            .map(|row: &Vec<u8>| {
+                (0..data_shards)
+                    .map(|column: usize| {
+                        row.iter().zip(&top_inverse).fold(
+                            0u8,
+                            |sum: u8, (value, inverse): (&u8, &Vec<u8>)| {
+                                sum ^ self.mul(*value, inverse[column])
+                            },
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Gauss-Jordan inversion, or `None` if the matrix is singular.
+    pub(crate) fn invert(&self, matrix: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
+        let size: usize = matrix.len();
+        let mut work: Vec<Vec<u8>> = matrix
+            .iter()
+            .enumerate()
+            .map(|(i, row): (usize, &Vec<u8>)| {
+                let mut row: Vec<u8> = row.clone();
+                row.extend((0..size).map(|j: usize| u8::from(i == j)));
+                row
+            })
+            .collect();
+
+        for column in 0..size {
+            let pivot: usize = (column..size).find(|row: &usize| work[*row][column] != 0)?;
+            work.swap(column, pivot);
+            let scale: u8 = self.div(1, work[column][column]);
+            for value in work[column].iter_mut() {
+                *value = self.mul(*value, scale);
+            }
+            let pivot_row: Vec<u8> = work[column].clone();
+            for (row, values) in work.iter_mut().enumerate() {
+                let factor: u8 = values[column];
+                if row == column || factor == 0 {
+                    continue;
+                }
+                for (value, pivot) in values.iter_mut().zip(&pivot_row) {
+                    *value ^= self.mul(factor, *pivot);
+                }
+            }
+        }
+
+        Some(
+            work.into_iter()
+                .map(|row: Vec<u8>| row[size..].to_vec())
+                .collect(),
+        )
+    }
+}
+
+impl Default for Gf8Field {
+    fn default() -> Self {
+        Self::new(DEFAULT_POLYNOMIAL).expect("the default polynomial is primitive")
+    }
+}
+
+impl fmt::Debug for Gf8Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gf8Field")
+            .field("polynomial", &format_args!("{:#x}", self.polynomial))
+            .finish()
+    }
+}
+
+pub(crate) fn mul_add_portable(tables: &NibbleTables, input: &[u8], out: &mut [u8]) {
+    for (out, byte) in out.iter_mut().zip(input) {
+        *out ^= tables[(byte & 0x0f) as usize] ^ tables[16 + (byte >> 4) as usize];
+    }
+}
+
+/// Checks that a full set of shards has equal, non-empty lengths and
+/// returns that length.
+pub(crate) fn check_shard_lens(
+    total: usize,
+    lens: impl Iterator<Item = usize>,
+) -> Result<usize, Error> {
+    let lens: Vec<usize> = lens.collect();
+    if lens.len() < total {
+        return Err(Error::TooFewShards);
+    }
+    if lens.len() > total {
+        return Err(Error::TooManyShards);
+    }
+    if lens[0] == 0 {
+        return Err(Error::EmptyShard);
+    }
+    if lens.iter().any(|len: &usize| *len != lens[0]) {
+        return Err(Error::IncorrectShardSize);
+    }
+    Ok(lens[0])
+}
+
+/// The GF(2^8) matrix code over a field with a chosen polynomial, so shards
+/// can be bit-compatible with libraries that do not use 0x11d. With the
+/// default polynomial its shards equal the built-in backend's.
+pub struct PolynomialBackend {
+    field: Gf8Field,
+    data_shards: usize,
+    /// One row per parity shard, one column per data shard.
+    parity: Vec<Vec<u8>>,
+    /// The parity matrix as nibble tables, row by row.
+    tables: Vec<NibbleTables>,
+    #[cfg(feature = "std")]
+    level: SimdLevel,
+}
+
+impl PolynomialBackend {
+    pub fn new(
+        data_shards: usize,
+        parity_shards: usize,
+        polynomial: u16,
+    ) -> Result<Self, ReedSolomonError> {
+        let error: Option<Error> = if data_shards == 0 {
+            Some(Error::TooFewDataShards)
+        } else if parity_shards == 0 {
+            Some(Error::TooFewParityShards)
+        } else if data_shards + parity_shards > 256 {
+            Some(Error::TooManyShards)
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return Err(ReedSolomonError::CodecError { error });
+        }
+
+        let field: Gf8Field = Gf8Field::new(polynomial)?;
+        let parity: Vec<Vec<u8>> = field.parity_matrix(data_shards, parity_shards);
+        let tables: Vec<NibbleTables> = parity
+            .iter()
+            .flatten()
+            .map(|coefficient: &u8| field.nibble_tables(*coefficient))
+            .collect();
+
+        Ok(Self {
+            field,
+            data_shards,
+            parity,
+            tables,
+            #[cfg(feature = "std")]
+            level: SimdLevel::detect(),
+        })
+    }
+
+    pub fn field(&self) -> &Gf8Field {
+        &self.field
+    }
+
+    fn total_shards(&self) -> usize {
+        self.data_shards + self.parity.len()
+    }
+
+    fn mul_add(&self, tables: &NibbleTables, input: &[u8], out: &mut [u8]) {
+        #[cfg(feature = "std")]
+        simd::mul_add(self.level, tables, input, out);
+        #[cfg(not(feature = "std"))]
+        mul_add_portable(tables, input, out);
+    }
+
+    fn fill_parity(&self, data: &[&[u8]], parity: &mut [&mut [u8]]) {
+        for (row, out) in parity.iter_mut().enumerate() {
+            out.fill(0);
+            for (column, input) in data.iter().enumerate() {
+                self.mul_add(&self.tables[row * self.data_shards + column], input, out);
+            }
+        }
+    }
+
+    /// Row `index` of the full systematic matrix.
+    fn matrix_row(&self, index: usize) -> Vec<u8> {
+        if index < self.data_shards {
+            (0..self.data_shards)
+                .map(|column: usize| u8::from(column == index))
+                .collect()
+        } else {
+            self.parity[index - self.data_shards].clone()
+        }
+    }
+}
+
+impl fmt::Debug for PolynomialBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolynomialBackend")
+            .field("data_shards", &self.data_shards)
+            .field("parity_shards", &self.parity.len())
+            .field("field", &self.field)
+            .finish()
+    }
+}
+
+impl ErasureBackend for PolynomialBackend {
+    fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    fn parity_shards(&self) -> usize {
+        self.parity.len()
+    }
+
+    fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+        check_shard_lens(
+            self.total_shards(),
+            shards.iter().map(|shard: &&mut [u8]| shard.len()),
+        )
+        .map_err(|error: Error| ReedSolomonError::EncodingError { error })?;
+
+        let (data, parity) = shards.split_at_mut(self.data_shards);
+        let data: Vec<&[u8]> = data.iter().map(|shard: &&mut [u8]| &**shard).collect();
+        self.fill_parity(&data, parity);
+        Ok(())
+    }
+
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        let decoding_error = |error: Error| ReedSolomonError::DecodingError { error };
+        if shards.len() != self.total_shards() {
+            return Err(decoding_error(if shards.len() < self.total_shards() {
+                Error::TooFewShards
+            } else {
+                Error::TooManyShards
+            }));
+        }
+
+        let present: Vec<usize> = (0..shards.len())
+            .filter(|index: &usize| shards[*index].is_some())
+            .collect();
+        if present.len() < self.data_shards {
+            return Err(decoding_error(Error::TooFewShardsPresent));
+        }
+        let shard_len: usize =
+            check_shard_lens(present.len(), shards.iter().flatten().map(Vec::len))
+                .map_err(decoding_error)?;
+
+        let rows: &[usize] = &present[..self.data_shards];
+        let inverse: Vec<Vec<u8>> = self
+            .field
+            .invert(
+                &rows
+                    .iter()
+                    .map(|row: &usize| self.matrix_row(*row))
+                    .collect::<Vec<Vec<u8>>>(),
+            )
+            .ok_or(ReedSolomonError::SingularMatrix)?;
+
+        let recovered: Vec<(usize, Vec<u8>)> = {
+            let inputs: Vec<&[u8]> = shards
+                .iter()
+                .flatten()
+                .take(self.data_shards)
+                .map(Vec::as_slice)
+                .collect();
+            (0..self.data_shards)
+                .filter(|index: &usize| shards[*index].is_none())
+                .map(|index: usize| {
+                    let mut out: Vec<u8> = vec![0u8; shard_len];
+                    for (coefficient, input) in inverse[index].iter().zip(&inputs) {
+                        self.mul_add(&self.field.nibble_tables(*coefficient), input, &mut out);
+                    }
+                    (index, out)
+                })
+                .collect()
+        };
+        for (index, shard) in recovered {
+            shards[index] = Some(shard);
+        }
+        if data_only {
+            return Ok(());
+        }
+
+        let missing: Vec<usize> = (self.data_shards..shards.len())
+            .filter(|index: &usize| shards[*index].is_none())
+            .collect();
+        let mut parity: Vec<Vec<u8>> = vec![vec![0u8; shard_len]; missing.len()];
+        {
+            let data: Vec<&[u8]> = shards[..self.data_shards]
+                .iter()
+                .flatten()
+                .map(Vec::as_slice)
+                .collect();
+            for (row, out) in missing.iter().zip(&mut parity) {
+                for (column, input) in data.iter().enumerate() {
+                    let table: &NibbleTables =
+                        &self.tables[(row - self.data_shards) * self.data_shards + column];
+                    self.mul_add(table, input, out);
+                }
+            }
+        }
+        for (index, shard) in missing.into_iter().zip(parity) {
+            shards[index] = Some(shard);
+        }
+        Ok(())
+    }
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+        let shard_len: usize = check_shard_lens(
+            self.total_shards(),
+            shards.iter().map(|shard: &&[u8]| shard.len()),
+        )
+        .map_err(|error: Error| ReedSolomonError::DecodingError { error })?;
+
+        let (data, parity) = shards.split_at(self.data_shards);
+        let mut expected: Vec<Vec<u8>> = vec![vec![0u8; shard_len]; parity.len()];
+        let mut outputs: Vec<&mut [u8]> = expected.iter_mut().map(Vec::as_mut_slice).collect();
+        self.fill_parity(data, &mut outputs);
+        Ok(expected
+            .iter()
+            .zip(parity)
+            .all(|(expected, stored): (&Vec<u8>, &&[u8])| expected == stored))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reed_solomon_erasure::galois_8;
+
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+    use crate::config::EncoderConfig;
+    use crate::header::{ContainerHeader, HEADER_SIZE};
+    use crate::shard::Shard;
+
+    #[test]
+    fn test_default_field_matches_reed_solomon_erasure() -> Result<(), ReedSolomonError> {
+        let field: Gf8Field = Gf8Field::default();
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(field.mul(a, b), galois_8::mul(a, b));
+                if b != 0 {
+                    assert_eq!(field.div(a, b), galois_8::div(a, b));
+                }
+            }
+            assert_eq!(field.pow(a, 7), galois_8::exp(a, 7));
+        }
+
+        let backend: PolynomialBackend = PolynomialBackend::new(5, 3, DEFAULT_POLYNOMIAL)?;
+        let plain: galois_8::ReedSolomon = galois_8::ReedSolomon::new(5, 3)
+            .map_err(|error: Error| ReedSolomonError::CodecError { error })?;
+        let mut expected: Vec<Vec<u8>> = (0..8u8)
+            .map(|i: u8| (0..64u8).map(|j: u8| j.wrapping_mul(i + 3)).collect())
+            .collect();
+        let mut shards: Vec<Vec<u8>> = expected.clone();
+        let mut slices: Vec<&mut [u8]> = expected.iter_mut().map(Vec::as_mut_slice).collect();
+        plain.encode_shards(&mut slices)?;
+        let mut slices: Vec<&mut [u8]> = shards.iter_mut().map(Vec::as_mut_slice).collect();
+        backend.encode_shards(&mut slices)?;
+        assert_eq!(shards, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_non_primitive_polynomials() {
+        assert!(Gf8Field::new(0x187).is_ok());
+        // x^8 + x^4 + x^3 + x + 1 is irreducible (AES) but 2 only has order 51.
+        assert!(matches!(
+            Gf8Field::new(0x11b),
+            Err(ReedSolomonError::InvalidPolynomial(0x11b))
+        ));
+        assert!(Gf8Field::new(0x8d).is_err());
+        assert!(Gf8Field::new(0x21d).is_err());
+        assert_eq!(
+            (0x100..0x200u16)
+                .filter(|polynomial: &u16| Gf8Field::new(*polynomial).is_ok())
+                .count(),
+            16
+        );
+    }
+
+    #[test]
+    fn test_custom_polynomial_reconstructs() -> Result<(), ReedSolomonError> {
+        let backend: PolynomialBackend = PolynomialBackend::new(10, 4, 0x187)?;
+        let default: PolynomialBackend = PolynomialBackend::new(10, 4, DEFAULT_POLYNOMIAL)?;
+        let mut shards: Vec<Vec<u8>> = (0..14u8)
+            .map(|i: u8| (0..100u8).map(|j: u8| j ^ i.wrapping_mul(37)).collect())
+            .collect();
+        let mut other: Vec<Vec<u8>> = shards.clone();
+        let mut slices: Vec<&mut [u8]> = shards.iter_mut().map(Vec::as_mut_slice).collect();
+        backend.encode_shards(&mut slices)?;
+        let mut slices: Vec<&mut [u8]> = other.iter_mut().map(Vec::as_mut_slice).collect();
+        default.encode_shards(&mut slices)?;
+        assert_ne!(shards[10..], other[10..]);
+
+        let mut damaged: Vec<Option<Vec<u8>>> = shards.iter().cloned().map(Some).collect();
+        damaged[0] = None;
+        damaged[2] = None;
+        damaged[11] = None;
+        backend.reconstruct(&mut damaged, true)?;
+        assert_eq!(damaged[0].as_ref(), Some(&shards[0]));
+        assert_eq!(damaged[2].as_ref(), Some(&shards[2]));
+        assert_eq!(damaged[11], None);
+        backend.reconstruct(&mut damaged, false)?;
+        assert_eq!(damaged.into_iter().flatten().collect::<Vec<_>>(), shards);
+
+        let slices: Vec<&[u8]> = shards.iter().map(Vec::as_slice).collect();
+        assert!(backend.verify(&slices)?);
+        assert!(!default.verify(&slices)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_with_custom_polynomial() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_polynomial(0x187)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..3000u32).map(|i: u32| (i % 253) as u8).collect();
+
+        let shards: Vec<Shard> = codec.encode_shards(&original_data)?;
+        let plain: Vec<Shard> = ReedSolomonCodec::new(4, 2)?.encode_shards(&original_data)?;
+        assert_eq!(shards[..4], plain[..4]);
+        assert_ne!(shards[4..], plain[4..]);
+
+        let mut encoded: Vec<u8> = codec.encode(&original_data)?;
+        let header: ContainerHeader = ContainerHeader::read(&encoded)?;
+        assert_eq!(header.polynomial, Some(0x187));
+        let shard_size: usize = header.shard_size as usize;
+        encoded[HEADER_SIZE + 1] ^= 0xff;
+        encoded[HEADER_SIZE + shard_size + 1] ^= 0xff;
+        assert_eq!(
+            ReedSolomonCodec::new(2, 2)?.decode(&encoded)?,
+            original_data
+        );
+        Ok(())
+    }
+}
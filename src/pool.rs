@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+pub const DEFAULT_POOL_BUFFERS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub pooled: usize,
+}
+
+#[derive(Debug)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl BufferPool {
+    pub fn new(max_buffers: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            max_buffers,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn take(&self, len: usize) -> Vec<u8> {
+        let pooled: Option<Vec<u8>> = self.lock().pop();
+
+        let mut buffer: Vec<u8> = match pooled {
+            Some(buffer) if buffer.capacity() >= len => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                buffer
+            }
+            other => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                other.unwrap_or_default()
+            }
+        };
+
+        buffer.clear();
+        buffer.resize(len, 0);
+        buffer
+    }
+
+    pub fn give(&self, buffer: Vec<u8>) {
+        let mut buffers: MutexGuard<'_, Vec<Vec<u8>>> = self.lock();
+        if buffers.len() < self.max_buffers && buffer.capacity() > 0 {
+            buffers.push(buffer);
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            pooled: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Vec<u8>>> {
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_POOL_BUFFERS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_reuses_returned_buffers() {
+        let pool: BufferPool = BufferPool::new(4);
+
+        let mut buffer: Vec<u8> = pool.take(128);
+        buffer.fill(0xff);
+        pool.give(buffer);
+
+        let reused: Vec<u8> = pool.take(64);
+        assert_eq!(reused, vec![0u8; 64]);
+        assert!(reused.capacity() >= 128);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                hits: 1,
+                misses: 1,
+                pooled: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_give_respects_capacity() {
+        let pool: BufferPool = BufferPool::new(2);
+
+        for _ in 0..3 {
+            pool.give(vec![0u8; 16]);
+        }
+
+        assert_eq!(pool.stats().pooled, 2);
+    }
+}
@@ -0,0 +1,374 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::ccsds::CCSDS_POLYNOMIAL;
+use crate::config::EncoderConfig;
+use crate::correction::locate_errors;
+use crate::error::ReedSolomonError;
+use crate::polynomial::{Gf8Field, DEFAULT_POLYNOMIAL};
+
+/// A classic Reed-Solomon code over GF(2^8): each codeword is `data_len`
+/// data symbols followed by the remainder of dividing them by a generator
+/// polynomial with roots `alpha^(root_step * j)` for `j` from `first_root`.
+/// Codewords shorter than 255 symbols are shortened codes. Decoding
+/// corrects up to `parity_len() / 2` symbols at unknown positions.
+///
+/// Unlike the shard code of `ReedSolomonCodec`, this produces the same
+/// bytes as the standards the presets describe.
+#[derive(Debug, Clone)]
+pub struct BlockCode {
+    field: Gf8Field,
+    /// Coefficients of the generator polynomial, lowest first.
+    generator: Vec<u8>,
+    codeword_len: usize,
+    data_len: usize,
+    first_root: usize,
+    root_step: usize,
+}
+
+impl BlockCode {
+    pub fn new(
+        polynomial: u16,
+        codeword_len: usize,
+        data_len: usize,
+        first_root: usize,
+        root_step: usize,
+    ) -> Result<Self, ReedSolomonError> {
+        if codeword_len > 255 || data_len == 0 || data_len >= codeword_len {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: codeword_len,
+                min: data_len + 1,
+                max: 255,
+            });
+        }
+        // Distinct locators for every position need a step coprime to 255.
+        if [3, 5, 17]
+            .iter()
+            .any(|factor: &usize| root_step.is_multiple_of(*factor))
+        {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: root_step,
+                min: 1,
+                max: 254,
+            });
+        }
+
+        let field: Gf8Field = Gf8Field::new(polynomial)?;
+        let mut generator: Vec<u8> = vec![1];
+        for j in first_root..first_root + codeword_len - data_len {
+            let root: u8 = field.pow(2, root_step * j);
+            let mut next: Vec<u8> = vec![0u8; generator.len() + 1];
+            for (i, coefficient) in generator.iter().enumerate() {
+                next[i + 1] ^= coefficient;
+                next[i] ^= field.mul(*coefficient, root);
+            }
+            generator = next;
+        }
+
+        Ok(Self {
+            field,
+            generator,
+            codeword_len,
+            data_len,
+            first_root,
+            root_step,
+        })
+    }
+
+    pub fn polynomial(&self) -> u16 {
+        self.field.polynomial()
+    }
+
+    pub fn codeword_len(&self) -> usize {
+        self.codeword_len
+    }
+
+    pub fn data_len(&self) -> usize {
+        self.data_len
+    }
+
+    pub fn parity_len(&self) -> usize {
+        self.codeword_len - self.data_len
+    }
+
+    pub fn first_root(&self) -> usize {
+        self.first_root
+    }
+
+    pub fn root_step(&self) -> usize {
+        self.root_step
+    }
+
+    /// Coefficients of the generator polynomial, lowest first.
+    pub fn generator(&self) -> &[u8] {
+        &self.generator
+    }
+
+    /// Encodes exactly `data_len()` symbols into a codeword, data first.
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        if data.len() != self.data_len {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: self.data_len,
+                given: data.len(),
+            });
+        }
+
+        let parity_len: usize = self.parity_len();
+        let mut parity: Vec<u8> = vec![0u8; parity_len];
+        for symbol in data {
+            let feedback: u8 = symbol ^ parity[0];
+            parity.copy_within(1.., 0);
+            parity[parity_len - 1] = 0;
+            if feedback != 0 {
+                for (j, value) in parity.iter_mut().enumerate() {
+                    *value ^= self.field.mul(feedback, self.generator[parity_len - 1 - j]);
+                }
+            }
+        }
+
+        let mut codeword: Vec<u8> = data.to_vec();
+        codeword.extend(parity);
+        Ok(codeword)
+    }
+
+    /// Corrects a codeword in place and returns how many symbols were
+    /// wrong. With more errors than can be corrected, returns
+    /// `TooManyErrors` and leaves the codeword untouched.
+    pub fn decode(&self, codeword: &mut [u8]) -> Result<usize, ReedSolomonError> {
+        if codeword.len() != self.codeword_len {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: self.codeword_len,
+                given: codeword.len(),
+            });
+        }
+        let syndromes: Vec<u8> = self.syndromes(codeword);
+        if syndromes.iter().all(|syndrome: &u8| *syndrome == 0) {
+            return Ok(0);
+        }
+
+        // Position p is the coefficient of x^(codeword_len - 1 - p).
+        let points: Vec<u8> = (0..self.codeword_len)
+            .map(|position: usize| {
+                self.field
+                    .pow(2, self.root_step * (self.codeword_len - 1 - position))
+            })
+            .collect();
+        let max: usize = self.parity_len() / 2;
+        let errors: Vec<(usize, u8)> = locate_errors(&self.field, &syndromes, &points)
+            .filter(|errors: &Vec<(usize, u8)>| errors.len() <= max)
+            .ok_or(ReedSolomonError::TooManyErrors { max })?;
+
+        let mut repaired: Vec<u8> = codeword.to_vec();
+        for (index, weighted) in &errors {
+            let scale: u8 = self.field.pow(points[*index], self.first_root);
+            repaired[*index] ^= self.field.div(*weighted, scale);
+        }
+        if self
+            .syndromes(&repaired)
+            .iter()
+            .any(|syndrome: &u8| *syndrome != 0)
+        {
+            return Err(ReedSolomonError::TooManyErrors { max });
+        }
+        codeword.copy_from_slice(&repaired);
+        Ok(errors.len())
+    }
+
+    /// The received word at each generator root.
+    fn syndromes(&self, word: &[u8]) -> Vec<u8> {
+        (self.first_root..self.first_root + self.parity_len())
+            .map(|j: usize| {
+                let root: u8 = self.field.pow(2, self.root_step * j);
+                word.iter().fold(0u8, |sum: u8, symbol: &u8| {
+                    self.field.mul(sum, root) ^ symbol
+                })
+            })
+            .collect()
+    }
+}
+
+/// Well-known Reed-Solomon parameter sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// DVB-T and DVB-S outer code: RS(204,188), shortened from RS(255,239).
+    DvbT,
+    /// CD-ROM CIRC inner code C1: RS(32,28).
+    CdC1,
+    /// CD-ROM CIRC outer code C2: RS(28,24).
+    CdC2,
+    /// CCSDS telemetry: RS(255,223), without the dual-basis symbols and
+    /// interleaving of `CcsdsCode`.
+    Ccsds,
+    /// QR code version 1 at error correction level L: RS(26,19).
+    QrL,
+    /// QR code version 1 at level M: RS(26,16).
+    QrM,
+    /// QR code version 1 at level Q: RS(26,13).
+    QrQ,
+    /// QR code version 1 at level H: RS(26,9).
+    QrH,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 8] = [
+        Preset::DvbT,
+        Preset::CdC1,
+        Preset::CdC2,
+        Preset::Ccsds,
+        Preset::QrL,
+        Preset::QrM,
+        Preset::QrQ,
+        Preset::QrH,
+    ];
+
+    /// The name used on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::DvbT => "dvb-t",
+            Preset::CdC1 => "cd-c1",
+            Preset::CdC2 => "cd-c2",
+            Preset::Ccsds => "ccsds",
+            Preset::QrL => "qr-1l",
+            Preset::QrM => "qr-1m",
+            Preset::QrQ => "qr-1q",
+            Preset::QrH => "qr-1h",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset: &Preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn polynomial(&self) -> u16 {
+        match self {
+            Preset::Ccsds => CCSDS_POLYNOMIAL,
+            _ => DEFAULT_POLYNOMIAL,
+        }
+    }
+
+    pub fn codeword_len(&self) -> usize {
+        match self {
+            Preset::DvbT => 204,
+            Preset::CdC1 => 32,
+            Preset::CdC2 => 28,
+            Preset::Ccsds => 255,
+            Preset::QrL | Preset::QrM | Preset::QrQ | Preset::QrH => 26,
+        }
+    }
+
+    pub fn data_len(&self) -> usize {
+        match self {
+            Preset::DvbT => 188,
+            Preset::CdC1 => 28,
+            Preset::CdC2 => 24,
+            Preset::Ccsds => 223,
+            Preset::QrL => 19,
+            Preset::QrM => 16,
+            Preset::QrQ => 13,
+            Preset::QrH => 9,
+        }
+    }
+
+    pub fn parity_len(&self) -> usize {
+        self.codeword_len() - self.data_len()
+    }
+
+    /// The generator's roots start at `alpha^(first_root * root_step)`.
+    pub fn first_root(&self) -> usize {
+        match self {
+            Preset::Ccsds => 112,
+            _ => 0,
+        }
+    }
+
+    pub fn root_step(&self) -> usize {
+        match self {
+            Preset::Ccsds => 11,
+            _ => 1,
+        }
+    }
+
+    /// The standard's own code, for bit-compatible codewords.
+    pub fn block_code(&self) -> Result<BlockCode, ReedSolomonError> {
+        BlockCode::new(
+            self.polynomial(),
+            self.codeword_len(),
+            self.data_len(),
+            self.first_root(),
+            self.root_step(),
+        )
+    }
+
+    /// A shard codec configuration with the same shard counts and field:
+    /// one shard per code symbol, tolerating `parity_len()` lost shards.
+    pub fn config(&self) -> Result<EncoderConfig, ReedSolomonError> {
+        EncoderConfig::new(self.data_len(), self.parity_len())?.with_polynomial(self.polynomial())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_known_answer() -> Result<(), ReedSolomonError> {
+        // "HELLO WORLD" as a version 1-M QR code.
+        let code: BlockCode = Preset::QrM.block_code()?;
+        let data: [u8; 16] = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        let codeword: Vec<u8> = code.encode(&data)?;
+        assert_eq!(
+            codeword[16..],
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+
+        let mut received: Vec<u8> = codeword.clone();
+        for index in [0, 7, 15, 20, 25] {
+            received[index] ^= 0x81;
+        }
+        assert_eq!(code.decode(&mut received)?, 5);
+        assert_eq!(received, codeword);
+
+        received[1] = 0;
+        received[2] = 0;
+        received[3] = 0;
+        received[4] = 0;
+        received[5] = 0;
+        received[6] = 0;
+        let before: Vec<u8> = received.clone();
+        assert!(code.decode(&mut received).is_err());
+        assert_eq!(received, before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_presets() -> Result<(), ReedSolomonError> {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_name(preset.name()), Some(preset));
+            let code: BlockCode = preset.block_code()?;
+            let data: Vec<u8> = (0..preset.data_len())
+                .map(|i: usize| i as u8 ^ 0x5a)
+                .collect();
+            let mut codeword: Vec<u8> = code.encode(&data)?;
+            assert_eq!(codeword.len(), preset.codeword_len());
+
+            let max: usize = preset.parity_len() / 2;
+            for byte in codeword.iter_mut().rev().take(max) {
+                *byte = !*byte;
+            }
+            assert_eq!(code.decode(&mut codeword)?, max);
+            assert_eq!(codeword[..preset.data_len()], data[..]);
+
+            let config: EncoderConfig = preset.config()?;
+            assert_eq!(config.total_shards(), preset.codeword_len());
+            assert_eq!(config.polynomial(), Some(preset.polynomial()));
+        }
+        assert_eq!(Preset::from_name("DVB-T"), Some(Preset::DvbT));
+        assert_eq!(Preset::from_name("dvb-s2"), None);
+        assert!(BlockCode::new(DEFAULT_POLYNOMIAL, 255, 223, 0, 15).is_err());
+        Ok(())
+    }
+}
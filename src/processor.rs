@@ -0,0 +1,559 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::config::DATA_SIZE_LIMITS;
+use crate::error::ReedSolomonError;
+use crate::header::DIGEST_SIZE;
+
+pub const CHECKSUM_SIZE: usize = 4;
+pub const PREFIX_SIZE: usize = 8;
+pub const LEGACY_PREFIX_SIZE: usize = 4;
+
+pub struct DataProcessor;
+
+#[cfg(feature = "std")]
+pub(crate) fn crc32c_checksum(data: &[u8]) -> u32 {
+    crc32c::crc32c(data)
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn crc32c_checksum(data: &[u8]) -> u32 {
+    crc32c_software(data)
+}
+
+pub(crate) fn blake3_digest(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    *blake3::hash(data).as_bytes()
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn blake3_digest_parts(parts: &[&[u8]]) -> [u8; DIGEST_SIZE] {
+    let mut hasher: blake3::Hasher = blake3::Hasher::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+#[cfg(any(not(feature = "std"), test))]
+fn crc32c_software(data: &[u8]) -> u32 {
+    let mut crc: u32 = !0;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0x82f6_3b78 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+impl DataProcessor {
+    pub fn prepare_data(data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        Self::validate_data_size(data)?;
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(data.len() + PREFIX_SIZE);
+        buffer.extend_from_slice(&Self::size_prefix(data.len() as u64, PREFIX_SIZE));
+        buffer.extend_from_slice(data);
+
+        Ok(buffer)
+    }
+
+    pub fn validate_data_size(data: &[u8]) -> Result<(), ReedSolomonError> {
+        DATA_SIZE_LIMITS.check(data.len())
+    }
+
+    pub fn shard_size(
+        data_len: usize,
+        prefix_size: usize,
+        data_shards: usize,
+        element_size: usize,
+    ) -> usize {
+        (data_len + prefix_size)
+            .div_ceil(data_shards)
+            .next_multiple_of(element_size)
+    }
+
+    pub fn split_into_shards(
+        data: &[u8],
+        data_shards: usize,
+        total_shards: usize,
+        element_size: usize,
+    ) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        #[cfg(feature = "tracing")]
+        let _span: tracing::span::EnteredSpan = tracing::trace_span!(
+            "split_into_shards",
+            data_len = data.len(),
+            data_shards = data_shards,
+            total_shards = total_shards,
+        )
+        .entered();
+
+        let shard_size: usize = data
+            .len()
+            .div_ceil(data_shards)
+            .next_multiple_of(element_size);
+        let mut shards: Vec<Vec<u8>> = vec![vec![0u8; shard_size]; total_shards];
+
+        for (i, chunk) in data.chunks(shard_size).enumerate().take(data_shards) {
+            shards[i][..chunk.len()].copy_from_slice(chunk);
+        }
+
+        Ok(shards)
+    }
+
+    pub fn fill_data_shards(data: &[u8], prefix_size: usize, shards: &mut [&mut [u8]]) {
+        #[cfg(feature = "tracing")]
+        let _span: tracing::span::EnteredSpan = tracing::trace_span!(
+            "split_into_shards",
+            data_len = data.len(),
+            data_shards = shards.len(),
+        )
+        .entered();
+
+        let mut offset: usize = 0;
+        for shard in shards.iter_mut() {
+            Self::fill_at(data, prefix_size, offset, shard);
+            offset += shard.len();
+        }
+    }
+
+    /// Fills data shards with the concatenation of `parts`, zero-padding
+    /// the rest. No size prefix is written.
+    pub fn fill_data_shards_vectored(parts: &[&[u8]], shards: &mut [&mut [u8]]) {
+        let mut remaining: &[&[u8]] = parts;
+        let mut part: &[u8] = &[];
+        for shard in shards.iter_mut() {
+            let mut filled: usize = 0;
+            while filled < shard.len() {
+                if part.is_empty() {
+                    let Some((next, rest)) = remaining.split_first() else {
+                        break;
+                    };
+                    part = next;
+                    remaining = rest;
+                    continue;
+                }
+                let take: usize = part.len().min(shard.len() - filled);
+                shard[filled..filled + take].copy_from_slice(&part[..take]);
+                part = &part[take..];
+                filled += take;
+            }
+            shard[filled..].fill(0);
+        }
+    }
+
+    /// Fills a single data shard, as `fill_data_shards` would with every
+    /// data shard `shard.len()` bytes long.
+    pub fn fill_data_shard(data: &[u8], prefix_size: usize, index: usize, shard: &mut [u8]) {
+        Self::fill_at(data, prefix_size, index * shard.len(), shard);
+    }
+
+    /// The big-endian length prefix, in the first `prefix_size` bytes.
+    fn size_prefix(len: u64, prefix_size: usize) -> [u8; PREFIX_SIZE] {
+        let mut size_prefix: [u8; PREFIX_SIZE] = [0u8; PREFIX_SIZE];
+        if prefix_size > 0 {
+            BigEndian::write_uint(&mut size_prefix, len, prefix_size);
+        }
+        size_prefix
+    }
+
+    fn fill_at(data: &[u8], prefix_size: usize, offset: usize, shard: &mut [u8]) {
+        let size_prefix: [u8; PREFIX_SIZE] = Self::size_prefix(data.len() as u64, prefix_size);
+
+        let end: usize = offset + shard.len();
+        let prefix: &[u8] = &size_prefix[offset.min(prefix_size)..end.min(prefix_size)];
+        let body: &[u8] = &data[offset.saturating_sub(prefix_size).min(data.len())
+            ..end.saturating_sub(prefix_size).min(data.len())];
+
+        let (head, rest) = shard.split_at_mut(prefix.len());
+        head.copy_from_slice(prefix);
+        let (middle, padding) = rest.split_at_mut(body.len());
+        middle.copy_from_slice(body);
+        padding.fill(0);
+    }
+
+    pub fn interleave_shards(bodies: &[&[u8]], total_shards: usize, out: &mut Vec<u8>) {
+        for i in 0..total_shards {
+            for body in bodies {
+                let shard_size: usize = body.len() / total_shards;
+                out.extend_from_slice(&body[i * shard_size..(i + 1) * shard_size]);
+            }
+        }
+    }
+
+    pub fn deinterleave_shards(
+        data: &[u8],
+        shard_sizes: &[usize],
+        total_shards: usize,
+    ) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        let stripe_len: usize = shard_sizes.iter().sum();
+        let expected: usize = stripe_len
+            .checked_mul(total_shards)
+            .ok_or(ReedSolomonError::SizeOverflow)?;
+        if expected != data.len() {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected,
+                given: data.len(),
+            });
+        }
+
+        let mut bodies: Vec<Vec<u8>> = shard_sizes
+            .iter()
+            .map(|shard_size: &usize| Vec::with_capacity(shard_size * total_shards))
+            .collect();
+
+        for stripe in data.chunks_exact(stripe_len.max(1)) {
+            let mut offset: usize = 0;
+            for (body, shard_size) in bodies.iter_mut().zip(shard_sizes) {
+                body.extend_from_slice(&stripe[offset..offset + shard_size]);
+                offset += shard_size;
+            }
+        }
+
+        Ok(bodies)
+    }
+
+    pub fn append_checksum(mut shard: Vec<u8>) -> Vec<u8> {
+        let mut checksum: [u8; CHECKSUM_SIZE] = [0u8; CHECKSUM_SIZE];
+        BigEndian::write_u32(&mut checksum, crc32c_checksum(&shard));
+
+        shard.extend_from_slice(&checksum);
+        shard
+    }
+
+    pub fn write_checksum(shard: &mut [u8]) {
+        let (payload, checksum) = shard.split_at_mut(shard.len() - CHECKSUM_SIZE);
+        BigEndian::write_u32(checksum, crc32c_checksum(payload));
+    }
+
+    pub fn verify_checksum(shard: &[u8]) -> Option<&[u8]> {
+        if shard.len() <= CHECKSUM_SIZE {
+            return None;
+        }
+
+        let (payload, checksum) = shard.split_at(shard.len() - CHECKSUM_SIZE);
+        if crc32c_checksum(payload) != BigEndian::read_u32(checksum) {
+            return None;
+        }
+
+        Some(payload)
+    }
+
+    pub fn validate_and_split_shares(
+        data: &[u8],
+        total_shards: usize,
+    ) -> Result<Vec<&[u8]>, ReedSolomonError> {
+        if data.is_empty() {
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: 0,
+                min: DATA_SIZE_LIMITS.min,
+                max: DATA_SIZE_LIMITS.max,
+            });
+        }
+
+        if !data.len().is_multiple_of(total_shards) {
+            return Err(ReedSolomonError::IndivisibleLength {
+                given: data.len(),
+                total_shards,
+            });
+        }
+
+        Ok(data.chunks_exact(data.len() / total_shards).collect())
+    }
+
+    pub fn join_data_shards(
+        shards: &[Option<Vec<u8>>],
+        data_shards: usize,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut buffer: Vec<u8> = Vec::new();
+        Self::join_data_shards_into(shards, data_shards, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    pub fn join_data_shards_into(
+        shards: &[Option<Vec<u8>>],
+        data_shards: usize,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), ReedSolomonError> {
+        buffer.clear();
+
+        for (i, shard) in shards.iter().take(data_shards).enumerate() {
+            let shard: &Vec<u8> = shard
+                .as_ref()
+                .ok_or(ReedSolomonError::MissingDataShard { index: i })?;
+            buffer.extend_from_slice(shard);
+        }
+
+        Ok(())
+    }
+
+    pub fn extract_original_data(
+        decoded: &[u8],
+        prefix_size: usize,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        Self::extract_from_shards(&[decoded], prefix_size)
+    }
+
+    /// Reads the payload straight out of the data shards, in order, without
+    /// joining them first. The prefix may straddle shard boundaries.
+    pub fn extract_from_shards(
+        shards: &[&[u8]],
+        prefix_size: usize,
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        let available: usize = shards.iter().map(|shard: &&[u8]| shard.len()).sum();
+        if available < prefix_size {
+            return Err(ReedSolomonError::Truncated {
+                needed: prefix_size,
+                given: available,
+            });
+        }
+
+        let mut size_prefix: [u8; PREFIX_SIZE] = [0u8; PREFIX_SIZE];
+        let mut bytes = shards.iter().flat_map(|shard: &&[u8]| shard.iter());
+        for (byte, value) in size_prefix[..prefix_size].iter_mut().zip(&mut bytes) {
+            *byte = *value;
+        }
+        let original_size: u64 = BigEndian::read_uint(&size_prefix[..prefix_size], prefix_size);
+        if original_size > (available - prefix_size) as u64 {
+            return Err(ReedSolomonError::InvalidSizePrefix);
+        }
+
+        let mut original: Vec<u8> = Vec::with_capacity(original_size as usize);
+        let mut skip: usize = prefix_size;
+        for shard in shards {
+            let remaining: usize = original_size as usize - original.len();
+            if remaining == 0 {
+                break;
+            }
+
+            let part: &[u8] = &shard[skip.min(shard.len())..];
+            skip -= skip.min(shard.len());
+            original.extend_from_slice(&part[..remaining.min(part.len())]);
+        }
+        Ok(original)
+    }
+
+    /// Takes the first `len` bytes of the data shards, for payloads whose
+    /// length is recorded outside them. The rest is padding.
+    pub fn extract_exact(shards: &[&[u8]], len: u64) -> Result<Vec<u8>, ReedSolomonError> {
+        let available: usize = shards.iter().map(|shard: &&[u8]| shard.len()).sum();
+        if len > available as u64 {
+            return Err(ReedSolomonError::Truncated {
+                needed: usize::try_from(len).unwrap_or(usize::MAX),
+                given: available,
+            });
+        }
+
+        let len: usize = len as usize;
+        let mut original: Vec<u8> = Vec::with_capacity(len);
+        for shard in shards {
+            let remaining: usize = len - original.len();
+            if remaining == 0 {
+                break;
+            }
+            original.extend_from_slice(&shard[..remaining.min(shard.len())]);
+        }
+        Ok(original)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_and_extract_roundtrip() -> Result<(), ReedSolomonError> {
+        let prepared: Vec<u8> = DataProcessor::prepare_data(b"payload")?;
+        let extracted: Vec<u8> = DataProcessor::extract_original_data(&prepared, PREFIX_SIZE)?;
+
+        assert_eq!(extracted, b"payload");
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_from_shards_reads_across_boundaries() -> Result<(), ReedSolomonError> {
+        let prepared: Vec<u8> = DataProcessor::prepare_data(b"scattered payload")?;
+        let mut padded: Vec<u8> = prepared.clone();
+        padded.resize(30, 0);
+
+        let shards: Vec<&[u8]> = padded.chunks(3).collect();
+        assert_eq!(
+            DataProcessor::extract_from_shards(&shards, PREFIX_SIZE)?,
+            b"scattered payload"
+        );
+        assert_eq!(
+            DataProcessor::extract_from_shards(&[&prepared[..5], &prepared[5..]], PREFIX_SIZE)?,
+            b"scattered payload"
+        );
+        assert!(matches!(
+            DataProcessor::extract_from_shards(&[&prepared[..4], &prepared[4..7]], PREFIX_SIZE),
+            Err(ReedSolomonError::Truncated {
+                needed: 8,
+                given: 7
+            })
+        ));
+        assert!(matches!(
+            DataProcessor::extract_from_shards(&shards[..4], PREFIX_SIZE),
+            Err(ReedSolomonError::InvalidSizePrefix)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_prefix_holds_lengths_above_u32() -> Result<(), ReedSolomonError> {
+        // Too large to allocate, so the prefix is built on its own.
+        let len: u64 = u32::MAX as u64 + 6;
+        let prefix: [u8; PREFIX_SIZE] = DataProcessor::size_prefix(len, PREFIX_SIZE);
+        assert_eq!(prefix, [0, 0, 0, 1, 0, 0, 0, 5]);
+        assert_eq!(BigEndian::read_uint(&prefix, PREFIX_SIZE), len);
+
+        // Read as four bytes, the length would wrap to 5 and the body fit.
+        let mut decoded: Vec<u8> = prefix.to_vec();
+        decoded.extend_from_slice(b"bytes");
+        assert!(matches!(
+            DataProcessor::extract_original_data(&decoded, PREFIX_SIZE),
+            Err(ReedSolomonError::InvalidSizePrefix)
+        ));
+        assert!(matches!(
+            DataProcessor::extract_from_shards(&[&decoded[..3], &decoded[3..]], PREFIX_SIZE),
+            Err(ReedSolomonError::InvalidSizePrefix)
+        ));
+        assert_eq!(
+            DataProcessor::extract_original_data(&decoded[4..], LEGACY_PREFIX_SIZE)?,
+            b"bytes"
+        );
+
+        assert!(matches!(
+            DataProcessor::extract_original_data(&prefix[..5], PREFIX_SIZE),
+            Err(ReedSolomonError::Truncated {
+                needed: 8,
+                given: 5
+            })
+        ));
+        assert!(matches!(
+            DataProcessor::extract_from_shards(&[&prefix[..2], &prefix[2..6]], PREFIX_SIZE),
+            Err(ReedSolomonError::Truncated {
+                needed: 8,
+                given: 6
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected() {
+        assert!(matches!(
+            DataProcessor::extract_original_data(&[0; 7], PREFIX_SIZE),
+            Err(ReedSolomonError::Truncated {
+                needed: 8,
+                given: 7
+            })
+        ));
+        assert!(matches!(
+            DataProcessor::extract_original_data(&[0, 0, 0, 2, 0xff], LEGACY_PREFIX_SIZE),
+            Err(ReedSolomonError::InvalidSizePrefix)
+        ));
+        assert!(matches!(
+            DataProcessor::extract_original_data(&[0xff; 12], PREFIX_SIZE),
+            Err(ReedSolomonError::InvalidSizePrefix)
+        ));
+        assert!(matches!(
+            DataProcessor::extract_original_data(&[0, 0, 0, 1, 0, 0, 0, 0, 0xff], PREFIX_SIZE),
+            Err(ReedSolomonError::InvalidSizePrefix)
+        ));
+        assert_eq!(
+            DataProcessor::extract_original_data(&[0, 0, 0, 1, 0xff, 0xee], LEGACY_PREFIX_SIZE)
+                .unwrap(),
+            [0xff]
+        );
+
+        assert!(DataProcessor::validate_and_split_shares(&[], 3).is_err());
+        assert!(DataProcessor::validate_and_split_shares(&[1, 2, 3], 0).is_err());
+        assert!(matches!(
+            DataProcessor::validate_and_split_shares(&[1, 2, 3, 4], 3),
+            Err(ReedSolomonError::IndivisibleLength {
+                given: 4,
+                total_shards: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_checksum_roundtrip() {
+        let sealed: Vec<u8> = DataProcessor::append_checksum(b"shard".to_vec());
+
+        assert_eq!(sealed.len(), 5 + CHECKSUM_SIZE);
+        assert_eq!(DataProcessor::verify_checksum(&sealed), Some(&b"shard"[..]));
+    }
+
+    #[test]
+    fn test_checksum_matches_software_crc32c() {
+        assert_eq!(crc32c_checksum(b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c_software(b"123456789"), 0xe306_9283);
+
+        let data: Vec<u8> = (0..1000u32).map(|i: u32| (i % 251) as u8).collect();
+        assert_eq!(crc32c_checksum(&data), crc32c_software(&data));
+    }
+
+    #[test]
+    fn test_fill_data_shards_matches_split() -> Result<(), ReedSolomonError> {
+        let data: &[u8] = b"prefix spans shards";
+        let expected: Vec<Vec<u8>> =
+            DataProcessor::split_into_shards(&DataProcessor::prepare_data(data)?, 10, 10, 1)?;
+
+        let mut buffer: Vec<u8> = vec![0xaa; 30];
+        let mut shards: Vec<&mut [u8]> = buffer.chunks_exact_mut(3).collect();
+        DataProcessor::fill_data_shards(data, PREFIX_SIZE, &mut shards);
+
+        assert_eq!(buffer, expected.concat());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_data_shards_vectored_matches_joined() {
+        let parts: [&[u8]; 4] = [b"split ", b"", b"across sev", b"eral buffers"];
+        let mut expected: Vec<u8> = vec![0xaa; 35];
+        let mut shards: Vec<&mut [u8]> = expected.chunks_exact_mut(7).collect();
+        DataProcessor::fill_data_shards(&parts.concat(), 0, &mut shards);
+
+        let mut buffer: Vec<u8> = vec![0xaa; 35];
+        let mut shards: Vec<&mut [u8]> = buffer.chunks_exact_mut(7).collect();
+        DataProcessor::fill_data_shards_vectored(&parts, &mut shards);
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_interleave_roundtrip() -> Result<(), ReedSolomonError> {
+        let first: Vec<u8> = (0..12u8).collect();
+        let second: Vec<u8> = (100..106u8).collect();
+
+        let mut interleaved: Vec<u8> = Vec::new();
+        DataProcessor::interleave_shards(&[&first, &second], 3, &mut interleaved);
+        assert_eq!(interleaved[..6], [0, 1, 2, 3, 100, 101]);
+
+        let bodies: Vec<Vec<u8>> = DataProcessor::deinterleave_shards(&interleaved, &[4, 2], 3)?;
+        assert_eq!(bodies, vec![first, second]);
+        assert!(DataProcessor::deinterleave_shards(&interleaved[1..], &[4, 2], 3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_detects_corruption() {
+        let mut sealed: Vec<u8> = DataProcessor::append_checksum(b"shard".to_vec());
+        sealed[1] ^= 0x01;
+
+        assert_eq!(DataProcessor::verify_checksum(&sealed), None);
+        assert_eq!(
+            DataProcessor::verify_checksum(&sealed[..CHECKSUM_SIZE]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_split_shares_rejects_uneven_length() {
+        assert!(DataProcessor::validate_and_split_shares(&[0u8; 10], 3).is_err());
+        assert!(DataProcessor::validate_and_split_shares(&[], 3).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressUpdate {
+    pub bytes: u64,
+    pub shards: u64,
+}
+
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, update: ProgressUpdate);
+}
+
+impl<F: Fn(ProgressUpdate) + Send + Sync> ProgressSink for F {
+    fn report(&self, update: ProgressUpdate) {
+        self(update)
+    }
+}
+
+impl fmt::Debug for dyn ProgressSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressSink(..)")
+    }
+}
+
+#[derive(Debug)]
+pub struct ProgressCounter {
+    started: Instant,
+    total_bytes: Option<u64>,
+    bytes: AtomicU64,
+    shards: AtomicU64,
+}
+
+impl ProgressCounter {
+    pub fn new(total_bytes: Option<u64>) -> Self {
+        Self {
+            started: Instant::now(),
+            total_bytes,
+            bytes: AtomicU64::new(0),
+            shards: AtomicU64::new(0),
+        }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn shards(&self) -> u64 {
+        self.shards.load(Ordering::Relaxed)
+    }
+
+    pub fn total_bytes(&self) -> Option<u64> {
+        self.total_bytes
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn fraction(&self) -> Option<f64> {
+        self.total_bytes
+            .filter(|total: &u64| *total > 0)
+            .map(|total: u64| (self.bytes() as f64 / total as f64).min(1.0))
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction: f64 = self.fraction().filter(|fraction: &f64| *fraction > 0.0)?;
+        Some(self.elapsed().mul_f64((1.0 - fraction) / fraction))
+    }
+}
+
+impl ProgressSink for ProgressCounter {
+    fn report(&self, update: ProgressUpdate) {
+        self.bytes.fetch_add(update.bytes, Ordering::Relaxed);
+        self.shards.fetch_add(update.shards, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_accumulates_updates() {
+        let counter: ProgressCounter = ProgressCounter::new(Some(100));
+        assert_eq!(counter.fraction(), Some(0.0));
+        assert_eq!(counter.eta(), None);
+
+        counter.report(ProgressUpdate {
+            bytes: 25,
+            shards: 6,
+        });
+        counter.report(ProgressUpdate {
+            bytes: 25,
+            shards: 6,
+        });
+        assert_eq!((counter.bytes(), counter.shards()), (50, 12));
+        assert_eq!(counter.fraction(), Some(0.5));
+        assert!(counter.eta().is_some());
+
+        assert_eq!(ProgressCounter::new(None).fraction(), None);
+    }
+}
@@ -0,0 +1,246 @@
+use prost::Message;
+
+use crate::config::{ErasureCode, GaloisField, ShardLayout};
+use crate::error::{ManifestError, ReedSolomonError};
+use crate::files::{ShardManifest, LEGACY_MANIFEST_VERSION, MANIFEST_VERSION};
+use crate::polynomial::DEFAULT_POLYNOMIAL;
+use crate::shard::{self, ShardKind};
+use crate::sparse::SparseDigest;
+
+/// The prost output for `proto/reed_solomon.proto`, checked in so that
+/// building does not need `protoc`.
+#[rustfmt::skip]
+#[allow(clippy::all)]
+pub mod v1 {
+    include!("proto/reed_solomon.v1.rs");
+}
+
+pub use v1::{Manifest, Shard};
+
+impl From<&shard::Shard> for Shard {
+    fn from(shard: &shard::Shard) -> Self {
+        let kind: v1::ShardKind = match shard.kind {
+            ShardKind::Data => v1::ShardKind::Data,
+            ShardKind::Parity => v1::ShardKind::Parity,
+        };
+        Self {
+            index: shard.index as u32,
+            kind: kind as i32,
+            payload: shard.payload.clone(),
+        }
+    }
+}
+
+impl TryFrom<Shard> for shard::Shard {
+    type Error = ReedSolomonError;
+
+    fn try_from(message: Shard) -> Result<Self, Self::Error> {
+        let index: u16 = u16::try_from(message.index).map_err(|_| invalid("index"))?;
+        let kind: ShardKind = match v1::ShardKind::try_from(message.kind) {
+            Ok(v1::ShardKind::Data) => ShardKind::Data,
+            Ok(v1::ShardKind::Parity) => ShardKind::Parity,
+            Err(_) => return Err(invalid("kind")),
+        };
+        Ok(Self {
+            index,
+            kind,
+            payload: message.payload,
+        })
+    }
+}
+
+impl From<&ShardManifest> for Manifest {
+    fn from(manifest: &ShardManifest) -> Self {
+        let field: v1::GaloisField = match manifest.field {
+            GaloisField::Gf8 => v1::GaloisField::Gf8,
+            GaloisField::Gf16 => v1::GaloisField::Gf16,
+        };
+        let layout: v1::ShardLayout = match manifest.layout {
+            ShardLayout::Systematic => v1::ShardLayout::Systematic,
+            ShardLayout::NonSystematic => v1::ShardLayout::NonSystematic,
+        };
+        let erasure_code: v1::ErasureCode = match manifest.erasure_code {
+            ErasureCode::Matrix => v1::ErasureCode::Matrix,
+            ErasureCode::Leopard => v1::ErasureCode::Leopard,
+            ErasureCode::Replication => v1::ErasureCode::Replication,
+        };
+        Self {
+            version: manifest.version,
+            field: field as i32,
+            polynomial: manifest.polynomial.map(u32::from),
+            layout: layout as i32,
+            erasure_code: erasure_code as i32,
+            data_shards: manifest.data_shards as u32,
+            parity_shards: manifest.parity_shards as u32,
+            block_size: manifest.block_size,
+            payload_len: manifest.payload_len,
+            generation: manifest.generation,
+            shard_alignment: manifest.shard_alignment as u64,
+            sparse: manifest
+                .sparse
+                .map(|sparse: SparseDigest| v1::SparseDigest {
+                    file_len: sparse.file_len,
+                    map_checksum: sparse.map_checksum,
+                }),
+        }
+    }
+}
+
+/// Validates the message the same way `ShardManifest::parse` validates text.
+impl TryFrom<Manifest> for ShardManifest {
+    type Error = ReedSolomonError;
+
+    fn try_from(message: Manifest) -> Result<Self, Self::Error> {
+        if !(LEGACY_MANIFEST_VERSION..=MANIFEST_VERSION).contains(&message.version) {
+            return Err(ManifestError::UnsupportedVersion(message.version as u64).into());
+        }
+
+        let field: GaloisField = match v1::GaloisField::try_from(message.field) {
+            Ok(v1::GaloisField::Gf8) => GaloisField::Gf8,
+            Ok(v1::GaloisField::Gf16) => GaloisField::Gf16,
+            Err(_) => return Err(invalid("field")),
+        };
+        let polynomial: Option<u16> = match (field, message.polynomial) {
+            (GaloisField::Gf8, None) => Some(DEFAULT_POLYNOMIAL),
+            (GaloisField::Gf8, Some(polynomial)) => {
+                Some(u16::try_from(polynomial).map_err(|_| invalid("polynomial"))?)
+            }
+            (GaloisField::Gf16, None) => None,
+            (GaloisField::Gf16, Some(_)) => return Err(invalid("polynomial")),
+        };
+        let layout: ShardLayout = match v1::ShardLayout::try_from(message.layout) {
+            Ok(v1::ShardLayout::Systematic) => ShardLayout::Systematic,
+            Ok(v1::ShardLayout::NonSystematic) => ShardLayout::NonSystematic,
+            Err(_) => return Err(invalid("layout")),
+        };
+        let erasure_code: ErasureCode = match v1::ErasureCode::try_from(message.erasure_code) {
+            Ok(v1::ErasureCode::Matrix) => ErasureCode::Matrix,
+            Ok(v1::ErasureCode::Leopard) => ErasureCode::Leopard,
+            Ok(v1::ErasureCode::Replication) => ErasureCode::Replication,
+            Err(_) => return Err(invalid("erasure_code")),
+        };
+
+        let manifest: ShardManifest = Self {
+            version: message.version,
+            field,
+            polynomial,
+            layout,
+            erasure_code,
+            data_shards: message.data_shards as usize,
+            parity_shards: message.parity_shards as usize,
+            block_size: message.block_size,
+            payload_len: message.payload_len,
+            generation: message.generation,
+            shard_alignment: usize::try_from(message.shard_alignment.max(1))
+                .map_err(|_| invalid("shard_alignment"))?,
+            sparse: message.sparse.map(|sparse: v1::SparseDigest| SparseDigest {
+                file_len: sparse.file_len,
+                map_checksum: sparse.map_checksum,
+            }),
+        };
+        if manifest.block_size == 0 {
+            return Err(ManifestError::ZeroBlockSize.into());
+        }
+        manifest.config()?;
+
+        Ok(manifest)
+    }
+}
+
+/// Decodes and validates a `Shard` message.
+pub fn decode_shard(bytes: &[u8]) -> Result<shard::Shard, ReedSolomonError> {
+    Shard::decode(bytes).map_err(malformed_protobuf)?.try_into()
+}
+
+/// Decodes and validates a `Manifest` message.
+pub fn decode_manifest(bytes: &[u8]) -> Result<ShardManifest, ReedSolomonError> {
+    Manifest::decode(bytes)
+        .map_err(malformed_protobuf)?
+        .try_into()
+}
+
+fn malformed_protobuf(error: prost::DecodeError) -> ReedSolomonError {
+    ManifestError::MalformedProtobuf(error.to_string()).into()
+}
+
+fn invalid(key: &str) -> ReedSolomonError {
+    ManifestError::InvalidValue(key.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+
+    #[test]
+    fn test_shards_round_trip() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: Vec<u8> = (0..700u32).map(|i: u32| (i * 11) as u8).collect();
+        let shards: Vec<shard::Shard> = codec.encode_shards(&original_data)?;
+
+        let mut restored: Vec<shard::Shard> = Vec::new();
+        for shard in &shards[..2] {
+            let bytes: Vec<u8> = Shard::from(shard).encode_to_vec();
+            restored.push(decode_shard(&bytes)?);
+        }
+        restored.push(Shard::from(&shards[4]).try_into()?);
+        assert_eq!(restored[2].kind, ShardKind::Parity);
+        assert_eq!(codec.decode_shards(restored)?, original_data);
+
+        let bad: Shard = Shard {
+            index: 1,
+            kind: 7,
+            payload: Vec::new(),
+        };
+        assert!(shard::Shard::try_from(bad).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_round_trip() -> Result<(), ReedSolomonError> {
+        let manifest: ShardManifest = ShardManifest {
+            version: MANIFEST_VERSION,
+            field: GaloisField::Gf8,
+            polynomial: Some(0x187),
+            layout: ShardLayout::NonSystematic,
+            erasure_code: ErasureCode::Matrix,
+            data_shards: 6,
+            parity_shards: 3,
+            block_size: 1 << 20,
+            payload_len: 12_345_678,
+            generation: 2,
+            shard_alignment: 4096,
+            sparse: Some(SparseDigest {
+                file_len: 1 << 40,
+                map_checksum: 0x1234_5678,
+            }),
+        };
+        let bytes: Vec<u8> = Manifest::from(&manifest).encode_to_vec();
+        assert_eq!(decode_manifest(&bytes)?, manifest);
+        assert!(matches!(
+            decode_manifest(&bytes[..bytes.len() - 1]),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::MalformedProtobuf(_)
+            ))
+        ));
+
+        let message: Manifest = Manifest::from(&manifest);
+
+        let unversioned: Manifest = Manifest {
+            version: 0,
+            ..message
+        };
+        assert!(matches!(
+            ShardManifest::try_from(unversioned),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::UnsupportedVersion(0)
+            ))
+        ));
+        let gf16_with_polynomial: Manifest = Manifest {
+            field: v1::GaloisField::Gf16 as i32,
+            ..message
+        };
+        assert!(ShardManifest::try_from(gf16_with_polynomial).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+// This file is @generated by prost-build.
+/// One stored shard. The payload ends with the 4-byte big-endian CRC32C
+/// of the bytes before it.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Shard {
+    #[prost(uint32, tag = "1")]
+    pub index: u32,
+    #[prost(enumeration = "ShardKind", tag = "2")]
+    pub kind: i32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub payload: ::prost::alloc::vec::Vec<u8>,
+}
+/// Everything needed to decode a set of shards besides the shards themselves.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Manifest {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(enumeration = "GaloisField", tag = "2")]
+    pub field: i32,
+    /// The field polynomial; absent means the field's default.
+    #[prost(uint32, optional, tag = "3")]
+    pub polynomial: ::core::option::Option<u32>,
+    #[prost(enumeration = "ShardLayout", tag = "4")]
+    pub layout: i32,
+    #[prost(enumeration = "ErasureCode", tag = "5")]
+    pub erasure_code: i32,
+    #[prost(uint32, tag = "6")]
+    pub data_shards: u32,
+    #[prost(uint32, tag = "7")]
+    pub parity_shards: u32,
+    #[prost(uint64, tag = "8")]
+    pub block_size: u64,
+    #[prost(uint64, tag = "9")]
+    pub payload_len: u64,
+    /// How many times the set has been rewritten under its basename.
+    #[prost(uint64, tag = "10")]
+    pub generation: u64,
+    /// Shards are padded to a multiple of this many bytes; 0 means 1.
+    #[prost(uint64, tag = "11")]
+    pub shard_alignment: u64,
+    /// Present when the payload is the data regions of a sparse file.
+    #[prost(message, optional, tag = "12")]
+    pub sparse: ::core::option::Option<SparseDigest>,
+}
+/// What a manifest records of the map of a sparse file's data regions.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SparseDigest {
+    #[prost(uint64, tag = "1")]
+    pub file_len: u64,
+    /// CRC32C of the map's text.
+    #[prost(uint32, tag = "2")]
+    pub map_checksum: u32,
+}
+/// Whether a shard holds data or parity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ShardKind {
+    Data = 0,
+    Parity = 1,
+}
+impl ShardKind {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Data => "SHARD_KIND_DATA",
+            Self::Parity => "SHARD_KIND_PARITY",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SHARD_KIND_DATA" => Some(Self::Data),
+            "SHARD_KIND_PARITY" => Some(Self::Parity),
+            _ => None,
+        }
+    }
+}
+/// The Galois field the code works over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum GaloisField {
+    Gf8 = 0,
+    Gf16 = 1,
+}
+impl GaloisField {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Gf8 => "GALOIS_FIELD_GF8",
+            Self::Gf16 => "GALOIS_FIELD_GF16",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "GALOIS_FIELD_GF8" => Some(Self::Gf8),
+            "GALOIS_FIELD_GF16" => Some(Self::Gf16),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ShardLayout {
+    Systematic = 0,
+    NonSystematic = 1,
+}
+impl ShardLayout {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Systematic => "SHARD_LAYOUT_SYSTEMATIC",
+            Self::NonSystematic => "SHARD_LAYOUT_NON_SYSTEMATIC",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SHARD_LAYOUT_SYSTEMATIC" => Some(Self::Systematic),
+            "SHARD_LAYOUT_NON_SYSTEMATIC" => Some(Self::NonSystematic),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ErasureCode {
+    Matrix = 0,
+    Leopard = 1,
+    Replication = 2,
+}
+impl ErasureCode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Matrix => "ERASURE_CODE_MATRIX",
+            Self::Leopard => "ERASURE_CODE_LEOPARD",
+            Self::Replication => "ERASURE_CODE_REPLICATION",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ERASURE_CODE_MATRIX" => Some(Self::Matrix),
+            "ERASURE_CODE_LEOPARD" => Some(Self::Leopard),
+            "ERASURE_CODE_REPLICATION" => Some(Self::Replication),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// The `#[pymethods]` expansion in pyo3 0.22 passes the error of every
+// returned `PyResult` through `From`, which clippy flags as an identity
+// conversion on the method signatures.
+#![allow(clippy::useless_conversion)]
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::{EncoderConfig, GaloisField};
+use crate::error::ReedSolomonError;
+use crate::shard::Shard;
+
+#[pyclass(name = "RSCodec", module = "reed_solomon_codec", frozen)]
+pub struct PyCodec {
+    codec: ReedSolomonCodec,
+}
+
+#[pymethods]
+impl PyCodec {
+    #[new]
+    #[pyo3(signature = (data, parity, gf16 = false))]
+    fn new(data: usize, parity: usize, gf16: bool) -> PyResult<Self> {
+        let field: GaloisField = if gf16 {
+            GaloisField::Gf16
+        } else {
+            GaloisField::Gf8
+        };
+        let config: EncoderConfig =
+            EncoderConfig::with_field(data, parity, field).map_err(to_py_err)?;
+
+        Ok(Self {
+            codec: ReedSolomonCodec::with_config(config).map_err(to_py_err)?,
+        })
+    }
+
+    #[getter]
+    fn data_shards(&self) -> usize {
+        self.codec.config().data_shards()
+    }
+
+    #[getter]
+    fn parity_shards(&self) -> usize {
+        self.codec.config().parity_shards()
+    }
+
+    fn encode<'py>(&self, py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+        let encoded: Vec<u8> = py
+            .allow_threads(|| self.codec.encode(data))
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new_bound(py, &encoded))
+    }
+
+    fn decode<'py>(&self, py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+        let decoded: Vec<u8> = py
+            .allow_threads(|| self.codec.decode(data))
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new_bound(py, &decoded))
+    }
+
+    fn encode_shards<'py>(
+        &self,
+        py: Python<'py>,
+        data: &[u8],
+    ) -> PyResult<Vec<Bound<'py, PyBytes>>> {
+        let shards: Vec<Vec<u8>> = py
+            .allow_threads(|| {
+                self.codec.encode_shards(data).map(|shards: Vec<Shard>| {
+                    shards
+                        .into_iter()
+                        .map(|shard: Shard| shard.payload)
+                        .collect::<Vec<Vec<u8>>>()
+                })
+            })
+            .map_err(to_py_err)?;
+
+        Ok(shards
+            .iter()
+            .map(|shard: &Vec<u8>| PyBytes::new_bound(py, shard))
+            .collect())
+    }
+
+    fn decode_shards<'py>(
+        &self,
+        py: Python<'py>,
+        shards: Vec<Option<Vec<u8>>>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let decoded: Vec<u8> = py
+            .allow_threads(|| self.codec.decode_positional(shards))
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new_bound(py, &decoded))
+    }
+}
+
+fn to_py_err(error: ReedSolomonError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+#[pymodule]
+fn reed_solomon_codec(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyCodec>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrappers_round_trip() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py: Python<'_>| {
+            let codec: PyCodec = PyCodec::new(4, 2, false)?;
+            assert_eq!((codec.data_shards(), codec.parity_shards()), (4, 2));
+            let original_data: Vec<u8> = (0..1000u32).map(|i: u32| (i % 251) as u8).collect();
+
+            let encoded: Bound<'_, PyBytes> = codec.encode(py, &original_data)?;
+            let decoded: Bound<'_, PyBytes> = codec.decode(py, encoded.as_bytes())?;
+            assert_eq!(decoded.as_bytes(), original_data.as_slice());
+
+            let mut shards: Vec<Option<Vec<u8>>> = codec
+                .encode_shards(py, &original_data)?
+                .iter()
+                .map(|shard: &Bound<'_, PyBytes>| Some(shard.as_bytes().to_vec()))
+                .collect();
+            assert_eq!(shards.len(), 6);
+            shards[0] = None;
+            shards[5] = None;
+            let decoded: Bound<'_, PyBytes> = codec.decode_shards(py, shards)?;
+            assert_eq!(decoded.as_bytes(), original_data.as_slice());
+
+            let error: PyErr = PyCodec::new(0, 2, false)
+                .err()
+                .expect("zero data shards were accepted");
+            assert!(error.is_instance_of::<PyValueError>(py));
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,351 @@
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "remote")]
+use s3::creds::Credentials;
+#[cfg(feature = "remote")]
+use s3::error::S3Error;
+#[cfg(feature = "remote")]
+use s3::{Bucket, Region};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::files::{ShardManifest, MANIFEST_EXTENSION};
+use crate::processor::DataProcessor;
+use crate::shard::Shard;
+use crate::stream::read_full;
+
+/// A flat key/value object store. Shards and manifests are written whole
+/// and read back whole.
+pub trait ObjectStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), ReedSolomonError>;
+
+    /// `None` when nothing is stored under `key`.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError>;
+}
+
+/// An S3 bucket, or any service that speaks the S3 API.
+#[cfg(feature = "remote")]
+#[derive(Debug)]
+pub struct S3Store {
+    bucket: Box<Bucket>,
+}
+
+#[cfg(feature = "remote")]
+impl S3Store {
+    pub fn new(bucket: Box<Bucket>) -> Self {
+        Self { bucket }
+    }
+
+    /// A path-style bucket on a self-hosted or third-party endpoint such as
+    /// MinIO, Ceph or R2.
+    pub fn custom(
+        name: &str,
+        region: &str,
+        endpoint: &str,
+        credentials: Credentials,
+    ) -> Result<Self, ReedSolomonError> {
+        let region: Region = Region::Custom {
+            region: region.to_string(),
+            endpoint: endpoint.to_string(),
+        };
+        let bucket: Box<Bucket> =
+            Bucket::new(name, region, credentials).map_err(ReedSolomonError::ObjectStore)?;
+        Ok(Self::new(bucket.with_path_style()))
+    }
+}
+
+#[cfg(feature = "remote")]
+impl ObjectStore for S3Store {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), ReedSolomonError> {
+        self.bucket
+            .put_object(key, data)
+            .map_err(ReedSolomonError::ObjectStore)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        match self.bucket.get_object(key) {
+            Ok(response) => Ok(Some(response.bytes().to_vec())),
+            Err(S3Error::HttpFailWithBody(404, _)) => Ok(None),
+            Err(error) => Err(ReedSolomonError::ObjectStore(error)),
+        }
+    }
+}
+
+pub fn shard_key(prefix: &str, index: usize) -> String {
+    format!("{}.shard.{:03}", prefix, index)
+}
+
+/// The shard index in a key made by `shard_key`.
+pub(crate) fn parse_shard_key(key: &str) -> Option<usize> {
+    key.rsplit_once(".shard.")
+        .and_then(|(_, index): (&str, &str)| index.parse().ok())
+}
+
+pub fn manifest_key(prefix: &str) -> String {
+    format!("{}.{}", prefix, MANIFEST_EXTENSION)
+}
+
+impl ReedSolomonCodec {
+    /// Stores each shard as one object and the manifest as another, with
+    /// the same layout as `encode_to_files`. Shard objects are assembled in
+    /// memory before upload, and the manifest goes up last.
+    pub fn encode_to_store<R: Read, S: ObjectStore + ?Sized>(
+        &self,
+        mut reader: R,
+        store: &S,
+        prefix: &str,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        let block_size: usize = self.config().block_size();
+        let mut objects: Vec<Vec<u8>> = vec![Vec::new(); self.config().total_shards()];
+        let mut block: Vec<u8> = vec![0u8; block_size];
+        let mut payload_len: u64 = 0;
+
+        loop {
+            let filled: usize = read_full(&mut reader, &mut block)?;
+            if filled == 0 {
+                break;
+            }
+
+            let shards: Vec<Shard> = self.encode_shards(&block[..filled])?;
+            for (object, shard) in objects.iter_mut().zip(&shards) {
+                object.extend_from_slice(&shard.payload);
+            }
+            payload_len += filled as u64;
+
+            if filled < block_size {
+                break;
+            }
+        }
+
+        for (index, object) in objects.iter().enumerate() {
+            store.put(&shard_key(prefix, index), object)?;
+        }
+        let manifest: ShardManifest = self.shard_manifest(payload_len);
+        store.put(&manifest_key(prefix), manifest.to_text().as_bytes())?;
+
+        Ok(manifest)
+    }
+}
+
+/// Downloads shard objects in index order until `data_shards` of them are
+/// intact, then decodes. Damaged or missing objects only cost one more
+/// download each.
+pub fn decode_from_store<S: ObjectStore + ?Sized, W: Write>(
+    store: &S,
+    prefix: &str,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    decode_from_store_by_cost(store, prefix, |_: usize| 0, writer)
+}
+
+/// Like `decode_from_store`, but downloads shard objects in order of
+/// `cost`, ties going to the lower index, so for instance parity held
+/// nearby can be preferred over data shards in another region.
+pub fn decode_from_store_by_cost<S: ObjectStore + ?Sized, W: Write>(
+    store: &S,
+    prefix: &str,
+    cost: impl Fn(usize) -> u64,
+    mut writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let key: String = manifest_key(prefix);
+    let text: Vec<u8> = store
+        .get(&key)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, key))?;
+    let manifest: ShardManifest = ShardManifest::parse(&String::from_utf8_lossy(&text))?;
+    let codec: ReedSolomonCodec =
+        ReedSolomonCodec::with_config(manifest.config()?)?.with_prefix_size(manifest.prefix_size());
+
+    let objects: Vec<Option<Vec<u8>>> = fetch_shards(store, prefix, &manifest, cost)?;
+    let mut offset: usize = 0;
+    let mut written: u64 = 0;
+    for block in 0..manifest.block_count() {
+        let shard_size: usize = manifest.shard_size(block);
+        let shards: Vec<Option<Vec<u8>>> = objects
+            .iter()
+            .map(|object: &Option<Vec<u8>>| {
+                object
+                    .as_deref()
+                    .and_then(|object: &[u8]| object.get(offset..offset + shard_size))
+                    .map(<[u8]>::to_vec)
+            })
+            .collect();
+
+        let decoded: Vec<u8> = codec.decode_positional(shards)?;
+        writer.write_all(&decoded)?;
+        written += decoded.len() as u64;
+        offset += shard_size;
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+fn fetch_shards<S: ObjectStore + ?Sized>(
+    store: &S,
+    prefix: &str,
+    manifest: &ShardManifest,
+    cost: impl Fn(usize) -> u64,
+) -> Result<Vec<Option<Vec<u8>>>, ReedSolomonError> {
+    let mut objects: Vec<Option<Vec<u8>>> =
+        vec![None; manifest.data_shards + manifest.parity_shards];
+    let mut order: Vec<usize> = (0..objects.len()).collect();
+    order.sort_by_key(|index: &usize| (cost(*index), *index));
+
+    let mut intact: usize = 0;
+    for index in order {
+        if intact == manifest.data_shards {
+            break;
+        }
+
+        let Some(object) = store.get(&shard_key(prefix, index))? else {
+            continue;
+        };
+        if is_intact(&object, manifest) {
+            intact += 1;
+        }
+        objects[index] = Some(object);
+    }
+    Ok(objects)
+}
+
+fn is_intact(object: &[u8], manifest: &ShardManifest) -> bool {
+    let mut rest: &[u8] = object;
+    for block in 0..manifest.block_count() {
+        let shard_size: usize = manifest.shard_size(block);
+        if rest.len() < shard_size || DataProcessor::verify_checksum(&rest[..shard_size]).is_none()
+        {
+            return false;
+        }
+        rest = &rest[shard_size..];
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::config::EncoderConfig;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: Mutex<HashMap<String, Vec<u8>>>,
+        downloads: Mutex<Vec<String>>,
+    }
+
+    impl ObjectStore for MemoryStore {
+        fn put(&self, key: &str, data: &[u8]) -> Result<(), ReedSolomonError> {
+            self.objects
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+            self.downloads.lock().unwrap().push(key.to_string());
+            Ok(self.objects.lock().unwrap().get(key).cloned())
+        }
+    }
+
+    impl MemoryStore {
+        fn take_downloads(&self) -> Vec<String> {
+            std::mem::take(&mut *self.downloads.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn test_decode_downloads_only_what_it_needs() -> Result<(), ReedSolomonError> {
+        let store: MemoryStore = MemoryStore::default();
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 239) as u8).collect();
+
+        let manifest: ShardManifest =
+            codec.encode_to_store(Cursor::new(&original_data), &store, "backups/data")?;
+        assert_eq!(manifest.payload_len, original_data.len() as u64);
+        assert_eq!(store.objects.lock().unwrap().len(), 7);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_store(&store, "backups/data", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(
+            store.take_downloads(),
+            vec![
+                "backups/data.manifest",
+                "backups/data.shard.000",
+                "backups/data.shard.001",
+                "backups/data.shard.002",
+                "backups/data.shard.003",
+            ]
+        );
+
+        store
+            .objects
+            .lock()
+            .unwrap()
+            .remove("backups/data.shard.001");
+        if let Some(object) = store
+            .objects
+            .lock()
+            .unwrap()
+            .get_mut("backups/data.shard.002")
+        {
+            object[300] ^= 0xff;
+        }
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_store(&store, "backups/data", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(store.take_downloads().len(), 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_downloads_the_cheapest_shards() -> Result<(), ReedSolomonError> {
+        let store: MemoryStore = MemoryStore::default();
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: &[u8] = b"data shard 0 lives far away";
+        codec.encode_to_store(Cursor::new(original_data), &store, "data")?;
+        store.take_downloads();
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_store_by_cost(
+            &store,
+            "data",
+            |index: usize| u64::from(index == 0),
+            &mut decoded,
+        )?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(
+            store.take_downloads(),
+            vec![
+                "data.manifest",
+                "data.shard.001",
+                "data.shard.002",
+                "data.shard.003",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_fails_without_enough_shards() -> Result<(), ReedSolomonError> {
+        let store: MemoryStore = MemoryStore::default();
+        assert!(matches!(
+            decode_from_store(&store, "absent", Vec::new()),
+            Err(ReedSolomonError::Io(_))
+        ));
+
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 1)?;
+        codec.encode_to_store(Cursor::new(b"too much lost"), &store, "data")?;
+        store.objects.lock().unwrap().remove("data.shard.000");
+        store.objects.lock().unwrap().remove("data.shard.002");
+        assert!(decode_from_store(&store, "data", Vec::new()).is_err());
+        Ok(())
+    }
+}
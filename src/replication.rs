@@ -0,0 +1,244 @@
+use alloc::vec::Vec;
+
+use reed_solomon_erasure::Error;
+
+use crate::backend::ErasureBackend;
+use crate::codec::ReedSolomonCodec;
+use crate::config::{EncoderConfig, ErasureCode, GF16_SHARD_LIMITS, SHARD_LIMITS};
+use crate::error::ReedSolomonError;
+
+/// `ErasureCode::Replication`: one data shard and a plain copy of it in
+/// every parity slot, so any single shard recovers the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicationBackend {
+    copies: usize,
+}
+
+impl ReplicationBackend {
+    pub fn new(copies: usize) -> Result<Self, ReedSolomonError> {
+        if !(2..=GF16_SHARD_LIMITS.max).contains(&copies) {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: copies,
+                min: 2,
+                max: GF16_SHARD_LIMITS.max,
+            });
+        }
+
+        Ok(Self { copies })
+    }
+
+    fn check_count(&self, given: usize) -> Result<(), Error> {
+        match given {
+            given if given < self.copies => Err(Error::TooFewShards),
+            given if given > self.copies => Err(Error::TooManyShards),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ErasureBackend for ReplicationBackend {
+    fn data_shards(&self) -> usize {
+        1
+    }
+
+    fn parity_shards(&self) -> usize {
+        self.copies - 1
+    }
+
+    fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+        self.check_count(shards.len())
+            .map_err(|error: Error| ReedSolomonError::EncodingError { error })?;
+
+        let (data, copies) = shards.split_at_mut(1);
+        for copy in copies {
+            if copy.len() != data[0].len() {
+                return Err(ReedSolomonError::EncodingError {
+                    error: Error::IncorrectShardSize,
+                });
+            }
+            copy.copy_from_slice(data[0]);
+        }
+        Ok(())
+    }
+
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        self.check_count(shards.len())
+            .map_err(|error: Error| ReedSolomonError::DecodingError { error })?;
+
+        let source: Vec<u8> = match shards.iter().flatten().next() {
+            Some(shard) => shard.clone(),
+            None => {
+                return Err(ReedSolomonError::DecodingError {
+                    error: Error::TooFewShardsPresent,
+                })
+            }
+        };
+        if shards
+            .iter()
+            .flatten()
+            .any(|shard: &Vec<u8>| shard.len() != source.len())
+        {
+            return Err(ReedSolomonError::DecodingError {
+                error: Error::IncorrectShardSize,
+            });
+        }
+
+        let missing: usize = if data_only { 1 } else { shards.len() };
+        for slot in &mut shards[..missing] {
+            if slot.is_none() {
+                *slot = Some(source.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+        self.check_count(shards.len())
+            .map_err(|error: Error| ReedSolomonError::DecodingError { error })?;
+        if shards
+            .iter()
+            .any(|shard: &&[u8]| shard.len() != shards[0].len())
+        {
+            return Err(ReedSolomonError::DecodingError {
+                error: Error::IncorrectShardSize,
+            });
+        }
+
+        Ok(shards.iter().all(|shard: &&[u8]| *shard == shards[0]))
+    }
+}
+
+/// Chooses between replication and erasure coding by payload size. Tiny
+/// payloads gain little from splitting, since every shard still carries a
+/// checksum and padding and a read has to reach `data_shards` hosts, so
+/// ones below `threshold` bytes are stored as `replicas` full copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HybridPolicy {
+    threshold: usize,
+    replicas: usize,
+}
+
+impl HybridPolicy {
+    pub fn new(threshold: usize, replicas: usize) -> Result<Self, ReedSolomonError> {
+        if !(2..=SHARD_LIMITS.max).contains(&replicas) {
+            return Err(ReedSolomonError::InvalidShardCount {
+                given: replicas,
+                min: 2,
+                max: SHARD_LIMITS.max,
+            });
+        }
+
+        Ok(Self {
+            threshold,
+            replicas,
+        })
+    }
+
+    /// Replicates as many copies as `config` has parity shards plus one,
+    /// so the same number of lost shards is tolerated either way.
+    pub fn matching(config: &EncoderConfig, threshold: usize) -> Result<Self, ReedSolomonError> {
+        Self::new(threshold, config.parity_shards() + 1)
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn replicas(&self) -> usize {
+        self.replicas
+    }
+
+    pub fn replicates(&self, data_len: usize) -> bool {
+        data_len < self.threshold
+    }
+}
+
+impl ReedSolomonCodec {
+    /// The codec that stores `data_len` bytes under the hybrid policy, or
+    /// `None` when they are erasure coded as configured. It keeps the
+    /// field, block size, compression and keys of this codec.
+    pub(crate) fn replica_codec(&self, data_len: usize) -> Result<Option<Self>, ReedSolomonError> {
+        let policy: HybridPolicy = match self.hybrid_policy() {
+            Some(policy) if policy.replicates(data_len) => policy,
+            _ => return Ok(None),
+        };
+
+        let config: EncoderConfig =
+            EncoderConfig::with_field(1, policy.replicas() - 1, self.config().field())?
+                .with_erasure_code(ErasureCode::Replication)?
+                .with_block_size(self.config().block_size())?
+                .with_interleave_depth(self.config().interleave_depth())?
+                .with_compression(self.config().compression())?
+                .with_limits_of(self.config())?;
+        self.for_config(config).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::header::ContainerHeader;
+    use crate::report::DecodeReport;
+
+    #[test]
+    fn test_replication_backend_copies_shards() -> Result<(), ReedSolomonError> {
+        let backend: ReplicationBackend = ReplicationBackend::new(3)?;
+        let mut buffers: Vec<Vec<u8>> = vec![b"copy".to_vec(), vec![0u8; 4], vec![0u8; 4]];
+        let mut shards: Vec<&mut [u8]> = buffers.iter_mut().map(Vec::as_mut_slice).collect();
+        backend.encode_shards(&mut shards)?;
+        assert!(buffers.iter().all(|shard: &Vec<u8>| shard == b"copy"));
+
+        let mut damaged: Vec<Option<Vec<u8>>> = vec![None, None, Some(b"copy".to_vec())];
+        backend.reconstruct(&mut damaged, true)?;
+        assert_eq!(
+            damaged,
+            vec![Some(b"copy".to_vec()), None, Some(b"copy".to_vec())]
+        );
+        backend.reconstruct(&mut damaged, false)?;
+        assert!(damaged
+            .iter()
+            .all(|shard: &Option<Vec<u8>>| shard.is_some()));
+
+        assert!(!backend.verify(&[b"copy", b"copy", b"cope"])?);
+        assert!(backend.reconstruct(&mut [None, None, None], false).is_err());
+        assert!(ReplicationBackend::new(1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hybrid_policy_replicates_small_payloads() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(10, 4)?
+            .with_hybrid_policy(HybridPolicy::matching(&EncoderConfig::new(10, 4)?, 64)?);
+
+        let small: Vec<u8> = codec.encode(b"tiny object")?;
+        let header: ContainerHeader = ContainerHeader::read(&small)?;
+        assert_eq!(header.erasure_code, ErasureCode::Replication);
+        assert_eq!((header.data_shards, header.parity_shards), (1, 4));
+        assert_eq!(small.len(), codec.encoded_len(11));
+        assert_eq!(codec.decode(&small)?, b"tiny object");
+
+        let mut damaged: Vec<u8> = small.clone();
+        let shard_size: usize = header.shard_size as usize;
+        for byte in &mut damaged[header.size()..header.size() + 4 * shard_size] {
+            *byte ^= 0xff;
+        }
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            ReedSolomonCodec::new(10, 4)?.decode_with_report(&damaged)?;
+        assert_eq!(decoded, b"tiny object");
+        assert_eq!(report.corrupted, vec![0, 1, 2, 3]);
+
+        let large: Vec<u8> = (0..1000u32).map(|i: u32| (i % 251) as u8).collect();
+        let encoded: Vec<u8> = codec.encode(&large)?;
+        let header: ContainerHeader = ContainerHeader::read(&encoded)?;
+        assert_eq!(header.erasure_code, ErasureCode::Matrix);
+        assert_eq!(codec.decode(&encoded)?, large);
+        assert!(HybridPolicy::new(64, 1).is_err());
+        Ok(())
+    }
+}
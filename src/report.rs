@@ -0,0 +1,46 @@
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodeReport {
+    pub missing: Vec<usize>,
+    pub corrupted: Vec<usize>,
+    pub reconstructed: Vec<usize>,
+    pub parity_used: bool,
+}
+
+impl DecodeReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty()
+    }
+
+    pub fn lost_shards(&self) -> usize {
+        self.missing.len() + self.corrupted.len()
+    }
+
+    /// Number of further shard losses the decode could have absorbed.
+    pub fn margin(&self, parity_shards: usize) -> usize {
+        parity_shards.saturating_sub(self.lost_shards())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_margin_counts_missing_and_corrupted() {
+        let report: DecodeReport = DecodeReport {
+            missing: vec![1],
+            corrupted: vec![5],
+            reconstructed: vec![1],
+            parity_used: true,
+        };
+
+        assert!(!report.is_clean());
+        assert_eq!(report.lost_shards(), 2);
+        assert_eq!(report.margin(3), 1);
+        assert_eq!(report.margin(1), 0);
+        assert!(DecodeReport::default().is_clean());
+    }
+}
@@ -0,0 +1,221 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+
+use crate::codec::ReedSolomonCodec;
+use crate::collector::ShardCollector;
+use crate::error::ReedSolomonError;
+use crate::shard::Shard;
+
+/// A shard of one block of a stream, as the sans-IO `Encoder` emits it and
+/// the `Decoder` takes it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockShard {
+    pub block: u64,
+    pub shard: Shard,
+}
+
+/// Splits bytes pushed in any amounts into blocks of the codec's block size
+/// and encodes each block as soon as it is full. Performs no I/O: the
+/// caller moves bytes in with `push_bytes` and shards out with `poll_shard`.
+#[derive(Debug)]
+pub struct Encoder<'a> {
+    codec: &'a ReedSolomonCodec,
+    pending: Vec<u8>,
+    shards: VecDeque<BlockShard>,
+    next_block: u64,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(codec: &'a ReedSolomonCodec) -> Self {
+        Self {
+            codec,
+            pending: Vec::new(),
+            shards: VecDeque::new(),
+            next_block: 0,
+        }
+    }
+
+    pub fn push_bytes(&mut self, mut data: &[u8]) -> Result<(), ReedSolomonError> {
+        let block_size: usize = self.codec.config().block_size();
+
+        while !data.is_empty() {
+            let take: usize = (block_size - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() == block_size {
+                self.encode_pending()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes the final, possibly short, block. Bytes pushed afterwards
+    /// start a new block.
+    pub fn finish(&mut self) -> Result<(), ReedSolomonError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.encode_pending()
+    }
+
+    pub fn poll_shard(&mut self) -> Option<BlockShard> {
+        self.shards.pop_front()
+    }
+
+    /// Blocks encoded so far.
+    pub fn blocks(&self) -> u64 {
+        self.next_block
+    }
+
+    fn encode_pending(&mut self) -> Result<(), ReedSolomonError> {
+        let block: u64 = self.next_block;
+        for shard in self.codec.encode_shards(&self.pending)? {
+            self.shards.push_back(BlockShard { block, shard });
+        }
+        self.pending.clear();
+        self.next_block += 1;
+        Ok(())
+    }
+}
+
+/// Gathers shards of a stream in any order and releases each block's data,
+/// in block order, once enough of its shards are in. Performs no I/O: the
+/// caller moves shards in with `push_shard` and data out with `poll_data`.
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    codec: &'a ReedSolomonCodec,
+    blocks: BTreeMap<u64, ShardCollector<'a>>,
+    next_block: u64,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(codec: &'a ReedSolomonCodec) -> Self {
+        Self {
+            codec,
+            blocks: BTreeMap::new(),
+            next_block: 0,
+        }
+    }
+
+    /// Adds a shard and reports whether its block can now be decoded.
+    /// Shards of blocks already released are ignored.
+    pub fn push_shard(&mut self, shard: BlockShard) -> Result<bool, ReedSolomonError> {
+        if shard.block < self.next_block {
+            return Ok(false);
+        }
+
+        let codec: &'a ReedSolomonCodec = self.codec;
+        let collector: &mut ShardCollector<'a> = self
+            .blocks
+            .entry(shard.block)
+            .or_insert_with(|| ShardCollector::new(codec));
+        if collector.ready() {
+            return Ok(true);
+        }
+        collector.push(shard.shard.index as usize, shard.shard.payload)
+    }
+
+    /// Decodes and returns the next block if enough of its shards are in.
+    pub fn poll_data(&mut self) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        let ready: bool = self
+            .blocks
+            .get(&self.next_block)
+            .is_some_and(ShardCollector::ready);
+        let Some(collector) = ready
+            .then(|| self.blocks.remove(&self.next_block))
+            .flatten()
+        else {
+            return Ok(None);
+        };
+
+        self.next_block += 1;
+        collector.decode().map(Some)
+    }
+
+    /// Blocks released by `poll_data` so far.
+    pub fn blocks(&self) -> u64 {
+        self.next_block
+    }
+
+    /// Whether shards are held for blocks not yet released.
+    pub fn has_pending(&self) -> bool {
+        !self.blocks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+
+    fn encode_all(
+        codec: &ReedSolomonCodec,
+        data: &[u8],
+    ) -> Result<Vec<BlockShard>, ReedSolomonError> {
+        let mut encoder: Encoder = Encoder::new(codec);
+        for chunk in data.chunks(333) {
+            encoder.push_bytes(chunk)?;
+        }
+        encoder.finish()?;
+
+        let mut shards: Vec<BlockShard> = Vec::new();
+        while let Some(shard) = encoder.poll_shard() {
+            shards.push(shard);
+        }
+        assert_eq!(encoder.blocks(), data.len().div_ceil(1024) as u64);
+        Ok(shards)
+    }
+
+    #[test]
+    fn test_encoder_decoder_roundtrip() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..3000u32).map(|i: u32| (i % 251) as u8).collect();
+        let shards: Vec<BlockShard> = encode_all(&codec, &original_data)?;
+        assert_eq!(shards.len(), 3 * 6);
+
+        let mut decoder: Decoder = Decoder::new(&codec);
+        let mut decoded: Vec<u8> = Vec::new();
+        for shard in shards.into_iter().rev() {
+            if shard.shard.index % 3 == 0 {
+                continue;
+            }
+            decoder.push_shard(shard)?;
+            while let Some(data) = decoder.poll_data()? {
+                decoded.extend_from_slice(&data);
+            }
+        }
+
+        assert_eq!(decoded, original_data);
+        assert_eq!(decoder.blocks(), 3);
+        assert!(!decoder.has_pending());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_releases_blocks_in_order() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(2, 1)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..2048u32).map(|i: u32| (i % 7) as u8).collect();
+        let shards: Vec<BlockShard> = encode_all(&codec, &original_data)?;
+
+        let mut decoder: Decoder = Decoder::new(&codec);
+        assert!(!decoder.push_shard(shards[3].clone())?);
+        assert!(decoder.push_shard(shards[4].clone())?);
+        assert_eq!(decoder.poll_data()?, None);
+
+        assert!(!decoder.push_shard(shards[0].clone())?);
+        assert!(decoder.push_shard(shards[2].clone())?);
+        assert_eq!(
+            decoder.poll_data()?.as_deref(),
+            Some(&original_data[..1024])
+        );
+        assert_eq!(
+            decoder.poll_data()?.as_deref(),
+            Some(&original_data[1024..])
+        );
+        assert_eq!(decoder.poll_data()?, None);
+        assert!(!decoder.push_shard(shards[1].clone())?);
+        Ok(())
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::ReedSolomonError;
+use crate::files::{
+    find_shard_sets, manifest_path, repair_files, shard_paths, ShardManifest, ShardSet,
+};
+use crate::json_manifest::malformed_json;
+use crate::platform::{partial_path, replace_file};
+use crate::processor::{DataProcessor, CHECKSUM_SIZE};
+use crate::verify::{verify_files, VerifyReport};
+
+/// The state a scrub left a shard set in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrubStatus {
+    /// Every block is intact, possibly after repair.
+    Healthy,
+    /// Damaged, but every block can still be decoded.
+    Degraded,
+    /// At least one block cannot be decoded, the set has no manifest, or
+    /// it could not be read at all.
+    Lost,
+}
+
+/// One shard set checked by `scrub`. The damage fields describe what the
+/// first pass found, before any repair.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ScrubItem {
+    pub dir: PathBuf,
+    pub basename: String,
+    pub generation: u64,
+    pub status: ScrubStatus,
+    pub blocks: u64,
+    pub payload_len: u64,
+    pub damaged_shards: Vec<usize>,
+    pub inconsistent_blocks: Vec<u64>,
+    pub lost_blocks: Vec<u64>,
+    /// Shards rewritten from the others, or corrected where they
+    /// disagreed with the parity.
+    pub repaired_shards: Vec<usize>,
+    /// Blocks with a parity mismatch that error correction could not fix,
+    /// because too many shards disagree or the code does not support it.
+    #[serde(default)]
+    pub uncorrectable_blocks: Vec<u64>,
+    pub error: Option<String>,
+}
+
+impl ScrubItem {
+    fn new(dir: &Path, set: &ShardSet) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            basename: set.basename.clone(),
+            generation: set.generation,
+            status: ScrubStatus::Lost,
+            blocks: 0,
+            payload_len: 0,
+            damaged_shards: Vec::new(),
+            inconsistent_blocks: Vec::new(),
+            lost_blocks: Vec::new(),
+            repaired_shards: Vec::new(),
+            uncorrectable_blocks: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn failed(self, error: &ReedSolomonError) -> Self {
+        Self {
+            status: ScrubStatus::Lost,
+            error: Some(error.to_string()),
+            ..self
+        }
+    }
+}
+
+/// What `scrub` found under its root, one item per shard set in the
+/// order the directories were walked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ScrubReport {
+    pub root: PathBuf,
+    pub healthy: usize,
+    pub degraded: usize,
+    pub lost: usize,
+    pub items: Vec<ScrubItem>,
+}
+
+impl ScrubReport {
+    pub fn is_healthy(&self) -> bool {
+        self.degraded == 0 && self.lost == 0
+    }
+
+    pub fn to_json(&self) -> Result<String, ReedSolomonError> {
+        serde_json::to_string_pretty(self).map_err(malformed_json)
+    }
+
+    /// The report on a single line, as each pass of a `Scrubber` appends
+    /// it to a JSON Lines log.
+    pub fn to_json_line(&self) -> Result<String, ReedSolomonError> {
+        serde_json::to_string(self).map_err(malformed_json)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReedSolomonError> {
+        serde_json::from_str(text).map_err(malformed_json)
+    }
+
+    fn push(&mut self, item: ScrubItem) {
+        match item.status {
+            ScrubStatus::Healthy => self.healthy += 1,
+            ScrubStatus::Degraded => self.degraded += 1,
+            ScrubStatus::Lost => self.lost += 1,
+        }
+        self.items.push(item);
+    }
+}
+
+/// Checks the checksums and parity of every shard set in `root` and the
+/// directories below it. With `repair`, damaged shards of sets that can
+/// still be decoded are rewritten, blocks whose parity disagrees with
+/// their data are run through `correct_errors`, and the set is checked
+/// again. Stale shards
+/// of earlier generations are left out; a set whose shards cannot be
+/// read is reported as lost rather than ending the scrub.
+pub fn scrub(root: &Path, repair: bool) -> Result<ScrubReport, ReedSolomonError> {
+    let mut report: ScrubReport = ScrubReport {
+        root: root.to_path_buf(),
+        ..ScrubReport::default()
+    };
+    for (dir, set) in current_shard_sets(root)? {
+        report.push(scrub_set(&dir, &set, repair));
+    }
+    Ok(report)
+}
+
+/// Scrubs the shard sets under a root on a schedule, so each set is
+/// checked again once `interval` has passed since its last scrub. Sets
+/// that appear later are picked up on the next pass.
+#[derive(Debug)]
+pub struct Scrubber {
+    root: PathBuf,
+    repair: bool,
+    interval: Duration,
+    /// When each set, by directory and basename, was last scrubbed.
+    last_scrubbed: BTreeMap<(PathBuf, String), SystemTime>,
+}
+
+impl Scrubber {
+    pub fn new(root: &Path, repair: bool, interval: Duration) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            repair,
+            interval,
+            last_scrubbed: BTreeMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// When the set `basename` in `dir` was last scrubbed, or `None` if it
+    /// has not been yet.
+    pub fn last_scrubbed(&self, dir: &Path, basename: &str) -> Option<SystemTime> {
+        self.last_scrubbed
+            .get(&(dir.to_path_buf(), basename.to_string()))
+            .copied()
+    }
+
+    /// Scrubs the sets that are due at `now`: those never scrubbed and
+    /// those last scrubbed at least `interval` before. Sets that are gone
+    /// are forgotten.
+    pub fn scrub_due(&mut self, now: SystemTime) -> Result<ScrubReport, ReedSolomonError> {
+        let mut report: ScrubReport = ScrubReport {
+            root: self.root.clone(),
+            ..ScrubReport::default()
+        };
+        let mut last_scrubbed: BTreeMap<(PathBuf, String), SystemTime> = BTreeMap::new();
+        for (dir, set) in current_shard_sets(&self.root)? {
+            let key: (PathBuf, String) = (dir, set.basename.clone());
+            let scrubbed: SystemTime = match self.last_scrubbed.get(&key) {
+                Some(scrubbed) if !is_due(*scrubbed, now, self.interval) => *scrubbed,
+                _ => {
+                    report.push(scrub_set(&key.0, &set, self.repair));
+                    now
+                }
+            };
+            last_scrubbed.insert(key, scrubbed);
+        }
+        self.last_scrubbed = last_scrubbed;
+        Ok(report)
+    }
+
+    /// Scrubs every set that is due, then sleeps until the next one is,
+    /// checking for new sets at least every `interval`. Passes that
+    /// scrubbed anything are handed to `on_report`. Runs until `on_report`
+    /// or the walk fails; a set that cannot be read is reported as lost.
+    pub fn run(
+        &mut self,
+        on_report: &mut dyn FnMut(ScrubReport) -> Result<(), ReedSolomonError>,
+    ) -> Result<(), ReedSolomonError> {
+        loop {
+            let report: ScrubReport = self.scrub_due(SystemTime::now())?;
+            if !report.items.is_empty() {
+                on_report(report)?;
+            }
+            std::thread::sleep(self.next_due(SystemTime::now()));
+        }
+    }
+
+    fn next_due(&self, now: SystemTime) -> Duration {
+        self.last_scrubbed
+            .values()
+            .map(|scrubbed: &SystemTime| {
+                (*scrubbed + self.interval)
+                    .duration_since(now)
+                    .unwrap_or_default()
+            })
+            .fold(self.interval, Duration::min)
+    }
+}
+
+fn is_due(scrubbed: SystemTime, now: SystemTime, interval: Duration) -> bool {
+    // A clock set back past the last scrub counts as the interval having
+    // passed, so a set is never skipped for good.
+    now.duration_since(scrubbed)
+        .map_or(true, |elapsed: Duration| elapsed >= interval)
+}
+
+/// Every shard set in `root` and below that is not stale, in walk order.
+fn current_shard_sets(root: &Path) -> Result<Vec<(PathBuf, ShardSet)>, ReedSolomonError> {
+    let mut sets: Vec<(PathBuf, ShardSet)> = Vec::new();
+    let mut dirs: Vec<PathBuf> = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut children: Vec<PathBuf> = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry: fs::DirEntry = entry?;
+            // Symbolic links are not followed, so a loop cannot trap the walk.
+            if entry.file_type()?.is_dir() {
+                children.push(entry.path());
+            }
+        }
+        children.sort();
+        dirs.extend(children.into_iter().rev());
+
+        for set in find_shard_sets(&dir)? {
+            if !set.stale {
+                sets.push((dir.clone(), set));
+            }
+        }
+    }
+    Ok(sets)
+}
+
+fn scrub_set(dir: &Path, set: &ShardSet, repair: bool) -> ScrubItem {
+    let item: ScrubItem = ScrubItem::new(dir, set);
+    if set.manifest.is_none() {
+        return ScrubItem {
+            error: Some("no manifest".to_string()),
+            ..item
+        };
+    }
+
+    let found: VerifyReport = match verify_files(dir, &set.basename) {
+        Ok(found) => found,
+        Err(error) => return item.failed(&error),
+    };
+    let mut item: ScrubItem = ScrubItem {
+        status: status(&found),
+        blocks: found.blocks,
+        payload_len: found.payload_len,
+        damaged_shards: found.damaged_shards.clone(),
+        inconsistent_blocks: found.inconsistent_blocks.clone(),
+        lost_blocks: found.lost_blocks.clone(),
+        ..item
+    };
+    if !repair || found.is_clean() || !found.is_recoverable() {
+        return item;
+    }
+
+    if !found.damaged_shards.is_empty() {
+        item.repaired_shards = match repair_files(dir, &set.basename) {
+            Ok(repaired) => repaired,
+            Err(error) => return item.failed(&error),
+        };
+    }
+    if !found.inconsistent_blocks.is_empty() {
+        match correct_blocks(dir, &set.basename, &found.inconsistent_blocks) {
+            Ok((corrected, uncorrectable)) => {
+                item.repaired_shards.extend(corrected);
+                item.repaired_shards.sort_unstable();
+                item.repaired_shards.dedup();
+                item.uncorrectable_blocks = uncorrectable;
+            }
+            Err(error) => return item.failed(&error),
+        }
+    }
+    match verify_files(dir, &set.basename) {
+        Ok(checked) => item.status = status(&checked),
+        Err(error) => return item.failed(&error),
+    }
+    item
+}
+
+/// Runs each of `blocks`, whose shards all pass their checksums but
+/// disagree with the parity, through `correct_errors`, which finds the
+/// shards that are wrong. Each corrected shard file is patched in a copy
+/// that then replaces it. Returns the corrected shards and the blocks that
+/// could not be corrected, which are left as they were.
+fn correct_blocks(
+    dir: &Path,
+    basename: &str,
+    blocks: &[u64],
+) -> Result<(Vec<usize>, Vec<u64>), ReedSolomonError> {
+    let manifest: ShardManifest = ShardManifest::read(&manifest_path(dir, basename))?;
+    let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(manifest.config()?)?;
+    let paths: Vec<PathBuf> = shard_paths(dir, basename, &manifest);
+    // Every block but the last has shards of the first block's size.
+    let stride: u64 = manifest.shard_size(0) as u64;
+
+    let mut patches: BTreeMap<usize, Vec<(u64, Vec<u8>)>> = BTreeMap::new();
+    let mut uncorrectable: Vec<u64> = Vec::new();
+    for block in blocks {
+        let offset: u64 = block * stride;
+        let shard_size: usize = manifest.shard_size(*block);
+        let mut payloads: Vec<Vec<u8>> = paths
+            .iter()
+            .map(|path: &PathBuf| {
+                let mut file: File = File::open(path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                let mut shard: Vec<u8> = vec![0u8; shard_size];
+                file.read_exact(&mut shard)?;
+                shard.truncate(shard_size - CHECKSUM_SIZE);
+                Ok(shard)
+            })
+            .collect::<Result<_, ReedSolomonError>>()?;
+
+        match codec.correct_errors(&mut payloads) {
+            Ok(corrected) if !corrected.is_empty() => {
+                for index in corrected {
+                    let shard: Vec<u8> =
+                        DataProcessor::append_checksum(std::mem::take(&mut payloads[index]));
+                    patches.entry(index).or_default().push((offset, shard));
+                }
+            }
+            Ok(_)
+            | Err(ReedSolomonError::TooManyErrors { .. })
+            | Err(ReedSolomonError::ErrorCorrectionUnsupported) => uncorrectable.push(*block),
+            Err(error) => return Err(error),
+        }
+    }
+
+    for (index, shards) in &patches {
+        patch_file(&paths[*index], shards)?;
+    }
+    Ok((patches.into_keys().collect(), uncorrectable))
+}
+
+/// Writes each of `patches`, an offset and the bytes to put there, into a
+/// copy of `path` that then replaces it.
+fn patch_file(path: &Path, patches: &[(u64, Vec<u8>)]) -> io::Result<()> {
+    let partial: PathBuf = partial_path(path);
+    let patched: io::Result<()> = fs::copy(path, &partial).and_then(|_| {
+        let mut file: File = OpenOptions::new().write(true).open(&partial)?;
+        for (offset, bytes) in patches {
+            file.seek(SeekFrom::Start(*offset))?;
+            file.write_all(bytes)?;
+        }
+        file.sync_all()
+    });
+    if let Err(error) = patched {
+        let _ = fs::remove_file(&partial);
+        return Err(error);
+    }
+    replace_file(&partial, path)
+}
+
+fn status(report: &VerifyReport) -> ScrubStatus {
+    if report.is_clean() {
+        ScrubStatus::Healthy
+    } else if report.is_recoverable() {
+        ScrubStatus::Degraded
+    } else {
+        ScrubStatus::Lost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+    use crate::config::{EncoderConfig, GaloisField};
+    use crate::files::decode_from_files;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_scrub_repairs_and_reports() -> Result<(), ReedSolomonError> {
+        let root: PathBuf = temp_dir("scrub");
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 251) as u8).collect();
+        let nested: PathBuf = root.join("nested");
+        let mut manifests: Vec<ShardManifest> = Vec::new();
+        for (dir, basename) in [(&root, "healthy"), (&root, "damaged"), (&nested, "lost")] {
+            manifests.push(codec.encode_to_files(Cursor::new(&original_data), dir, basename)?);
+        }
+
+        let damaged: PathBuf = manifests[1].shard_path(&root, "damaged", 3);
+        let mut shard: Vec<u8> = fs::read(&damaged)?;
+        shard[10] ^= 0xff;
+        fs::write(&damaged, shard)?;
+        fs::remove_file(manifests[1].shard_path(&root, "damaged", 5))?;
+        for index in 0..3 {
+            fs::remove_file(manifests[2].shard_path(&nested, "lost", index))?;
+        }
+
+        let report: ScrubReport = scrub(&root, false)?;
+        assert_eq!((report.healthy, report.degraded, report.lost), (1, 1, 1));
+        assert!(!report.is_healthy());
+
+        let report: ScrubReport = scrub(&root, true)?;
+        let names: Vec<(&str, ScrubStatus)> = report
+            .items
+            .iter()
+            .map(|item: &ScrubItem| (item.basename.as_str(), item.status))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("damaged", ScrubStatus::Healthy),
+                ("healthy", ScrubStatus::Healthy),
+                ("lost", ScrubStatus::Lost),
+            ]
+        );
+        assert_eq!(report.items[0].damaged_shards, vec![3, 5]);
+        assert_eq!(report.items[0].repaired_shards, vec![3, 5]);
+        assert_eq!(report.items[2].lost_blocks, vec![0, 1, 2, 3, 4]);
+        assert!(report.items[2].repaired_shards.is_empty());
+        assert_eq!(ScrubReport::parse(&report.to_json()?)?, report);
+        assert!(!report.to_json_line()?.contains('\n'));
+        assert_eq!(ScrubReport::parse(&report.to_json_line()?)?, report);
+        assert!(report.to_json()?.contains("\"status\": \"lost\""));
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_files(&root, "damaged", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_scrub_corrects_parity_mismatches() -> Result<(), ReedSolomonError> {
+        let root: PathBuf = temp_dir("scrub-correct");
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 251) as u8).collect();
+        let mut manifests: Vec<ShardManifest> = Vec::new();
+        for (basename, field) in [("gf8", GaloisField::Gf8), ("gf16", GaloisField::Gf16)] {
+            let config: EncoderConfig =
+                EncoderConfig::with_field(4, 2, field)?.with_block_size(1024)?;
+            let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+            manifests.push(codec.encode_to_files(Cursor::new(&original_data), &root, basename)?);
+        }
+
+        // Change a byte of shard 1 in block 2 and give it a matching
+        // checksum, so only the parity shows the damage.
+        for (manifest, basename) in manifests.iter().zip(["gf8", "gf16"]) {
+            let path: PathBuf = manifest.shard_path(&root, basename, 1);
+            let mut shard: Vec<u8> = fs::read(&path)?;
+            let start: usize = 2 * manifest.shard_size(0);
+            let block: &mut [u8] = &mut shard[start..start + manifest.shard_size(2)];
+            block[20] ^= 0x5a;
+            DataProcessor::write_checksum(block);
+            fs::write(&path, shard)?;
+        }
+
+        let report: ScrubReport = scrub(&root, false)?;
+        assert_eq!(report.degraded, 2);
+        assert!(report
+            .items
+            .iter()
+            .all(|item: &ScrubItem| item.inconsistent_blocks == vec![2]));
+
+        let report: ScrubReport = scrub(&root, true)?;
+        let gf16: &ScrubItem = &report.items[0];
+        assert_eq!(gf16.status, ScrubStatus::Degraded);
+        assert!(gf16.repaired_shards.is_empty());
+        assert_eq!(gf16.uncorrectable_blocks, vec![2]);
+        let gf8: &ScrubItem = &report.items[1];
+        assert_eq!(gf8.status, ScrubStatus::Healthy);
+        assert_eq!(gf8.repaired_shards, vec![1]);
+        assert!(gf8.uncorrectable_blocks.is_empty());
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_files(&root, "gf8", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_scrubber_rescrubs_sets_when_due() -> Result<(), ReedSolomonError> {
+        let root: PathBuf = temp_dir("scrubber");
+        fs::create_dir_all(&root)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        codec.encode_to_files(Cursor::new(vec![1u8; 3000]), &root, "first")?;
+
+        let interval: Duration = Duration::from_secs(3600);
+        let mut scrubber: Scrubber = Scrubber::new(&root, false, interval);
+        let start: SystemTime = SystemTime::now();
+        assert_eq!(scrubber.scrub_due(start)?.items.len(), 1);
+        assert_eq!(scrubber.last_scrubbed(&root, "first"), Some(start));
+        assert_eq!(scrubber.next_due(start), interval);
+
+        // A new set is scrubbed on the next pass; the first is not due yet.
+        codec.encode_to_files(Cursor::new(vec![2u8; 3000]), &root, "second")?;
+        let later: SystemTime = start + Duration::from_secs(60);
+        let report: ScrubReport = scrubber.scrub_due(later)?;
+        let names: Vec<&str> = report
+            .items
+            .iter()
+            .map(|item: &ScrubItem| item.basename.as_str())
+            .collect();
+        assert_eq!(names, vec!["second"]);
+        assert_eq!(scrubber.last_scrubbed(&root, "first"), Some(start));
+        assert_eq!(scrubber.next_due(later), interval - Duration::from_secs(60));
+
+        let due: SystemTime = start + interval;
+        assert_eq!(scrubber.scrub_due(due)?.items.len(), 1);
+        assert_eq!(scrubber.last_scrubbed(&root, "first"), Some(due));
+        assert_eq!(scrubber.last_scrubbed(&root, "second"), Some(later));
+        assert!(scrubber.last_scrubbed(&root, "missing").is_none());
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ShardKind {
+    Data,
+    Parity,
+}
+
+impl ShardKind {
+    pub fn for_index(index: usize, data_shards: usize) -> Self {
+        if index < data_shards {
+            ShardKind::Data
+        } else {
+            ShardKind::Parity
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shard {
+    pub index: u16,
+    pub kind: ShardKind,
+    pub payload: Vec<u8>,
+}
+
+impl Shard {
+    pub fn new(index: usize, data_shards: usize, payload: Vec<u8>) -> Self {
+        Self {
+            index: index as u16,
+            kind: ShardKind::for_index(index, data_shards),
+            payload,
+        }
+    }
+
+    pub fn is_data(&self) -> bool {
+        self.kind == ShardKind::Data
+    }
+
+    pub fn is_parity(&self) -> bool {
+        self.kind == ShardKind::Parity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_kind_from_index() {
+        let data: Shard = Shard::new(3, 4, vec![1, 2, 3]);
+        let parity: Shard = Shard::new(4, 4, vec![4, 5, 6]);
+
+        assert!(data.is_data());
+        assert!(parity.is_parity());
+        assert_eq!(parity.index, 4);
+    }
+}
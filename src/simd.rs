@@ -0,0 +1,313 @@
+use std::fmt;
+
+use reed_solomon_erasure::{galois_8, Error};
+
+use crate::backend::ErasureBackend;
+use crate::error::ReedSolomonError;
+use crate::polynomial::{check_shard_lens, mul_add_portable, Gf8Field, NibbleTables};
+
+/// The widest GF(2^8) multiply path the CPU supports, detected at runtime
+/// so one binary runs at full speed on every machine it lands on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    /// 32 bytes per step with AVX2 byte shuffles.
+    Avx2,
+    /// 16 bytes per step with NEON table lookups.
+    Neon,
+    /// One byte per step with nibble tables; available everywhere.
+    Portable,
+}
+
+impl SimdLevel {
+    pub fn detect() -> Self {
+        if SimdLevel::Avx2.is_available() {
+            SimdLevel::Avx2
+        } else if SimdLevel::Neon.is_available() {
+            SimdLevel::Neon
+        } else {
+            SimdLevel::Portable
+        }
+    }
+
+    pub fn is_available(self) -> bool {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2 => std::is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            SimdLevel::Portable => true,
+            _ => false,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SimdLevel::Avx2 => "avx2",
+            SimdLevel::Neon => "neon",
+            SimdLevel::Portable => "portable",
+        }
+    }
+}
+
+/// The GF(2^8) matrix backend with encoding and verification dispatched to
+/// the fastest multiply path of the running CPU. The parity matrix is the
+/// one `reed-solomon-erasure` builds, so its shards are interchangeable with
+/// the plain backend's. Reconstruction is left to `reed-solomon-erasure`.
+pub struct SimdBackend {
+    inner: galois_8::ReedSolomon,
+    /// One entry per parity row and data column, row by row.
+    tables: Vec<NibbleTables>,
+    level: SimdLevel,
+}
+
+impl SimdBackend {
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, Error> {
+        Self::with_level(data_shards, parity_shards, SimdLevel::detect())
+    }
+
+    /// Uses `level` if the CPU supports it and the portable path otherwise.
+    pub fn with_level(
+        data_shards: usize,
+        parity_shards: usize,
+        level: SimdLevel,
+    ) -> Result<Self, Error> {
+        let inner: galois_8::ReedSolomon = galois_8::ReedSolomon::new(data_shards, parity_shards)?;
+        let field: Gf8Field = Gf8Field::default();
+        let tables: Vec<NibbleTables> = field
+            .parity_matrix(data_shards, parity_shards)
+            .into_iter()
+            .flatten()
+            .map(|coefficient: u8| field.nibble_tables(coefficient))
+            .collect();
+        let level: SimdLevel = if level.is_available() {
+            level
+        } else {
+            SimdLevel::Portable
+        };
+
+        Ok(Self {
+            inner,
+            tables,
+            level,
+        })
+    }
+
+    pub fn level(&self) -> SimdLevel {
+        self.level
+    }
+
+    fn fill_parity(&self, data: &[&[u8]], parity: &mut [&mut [u8]]) {
+        let data_shards: usize = data.len();
+        for (row, out) in parity.iter_mut().enumerate() {
+            out.fill(0);
+            for (column, input) in data.iter().enumerate() {
+                mul_add(
+                    self.level,
+                    &self.tables[row * data_shards + column],
+                    input,
+                    out,
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SimdBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimdBackend")
+            .field("data_shards", &self.inner.data_shard_count())
+            .field("parity_shards", &self.inner.parity_shard_count())
+            .field("level", &self.level)
+            .finish()
+    }
+}
+
+impl ErasureBackend for SimdBackend {
+    fn data_shards(&self) -> usize {
+        self.inner.data_shard_count()
+    }
+
+    fn parity_shards(&self) -> usize {
+        self.inner.parity_shard_count()
+    }
+
+    fn encode_shards(&self, shards: &mut [&mut [u8]]) -> Result<(), ReedSolomonError> {
+        check_shard_lens(
+            self.inner.total_shard_count(),
+            shards.iter().map(|shard: &&mut [u8]| shard.len()),
+        )
+        .map_err(|error: Error| ReedSolomonError::EncodingError { error })?;
+
+        let (data, parity) = shards.split_at_mut(self.inner.data_shard_count());
+        let data: Vec<&[u8]> = data.iter().map(|shard: &&mut [u8]| &**shard).collect();
+        self.fill_parity(&data, parity);
+        Ok(())
+    }
+
+    fn reconstruct(
+        &self,
+        shards: &mut [Option<Vec<u8>>],
+        data_only: bool,
+    ) -> Result<(), ReedSolomonError> {
+        ErasureBackend::reconstruct(&self.inner, shards, data_only)
+    }
+
+    fn verify(&self, shards: &[&[u8]]) -> Result<bool, ReedSolomonError> {
+        let shard_len: usize = check_shard_lens(
+            self.inner.total_shard_count(),
+            shards.iter().map(|shard: &&[u8]| shard.len()),
+        )
+        .map_err(|error: Error| ReedSolomonError::DecodingError { error })?;
+
+        let (data, parity) = shards.split_at(self.inner.data_shard_count());
+        let mut expected: Vec<Vec<u8>> = vec![vec![0u8; shard_len]; parity.len()];
+        let mut outputs: Vec<&mut [u8]> = expected.iter_mut().map(Vec::as_mut_slice).collect();
+        self.fill_parity(data, &mut outputs);
+        Ok(expected
+            .iter()
+            .zip(parity)
+            .all(|(expected, stored): (&Vec<u8>, &&[u8])| expected == stored))
+    }
+}
+
+/// `out ^= coefficient * input` over GF(2^8), with the coefficient given as
+/// its nibble tables.
+pub(crate) fn mul_add(level: SimdLevel, tables: &NibbleTables, input: &[u8], out: &mut [u8]) {
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: `SimdBackend` only keeps `Avx2` after `is_available`
+        // confirmed the CPU supports it.
+        SimdLevel::Avx2 => unsafe { mul_add_avx2(tables, input, out) },
+        #[cfg(target_arch = "aarch64")]
+        // SAFETY: as above, for NEON.
+        SimdLevel::Neon => unsafe { mul_add_neon(tables, input, out) },
+        _ => mul_add_portable(tables, input, out),
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn mul_add_avx2(tables: &NibbleTables, input: &[u8], out: &mut [u8]) {
+    use std::arch::x86_64::*;
+
+    let len: usize = input.len().min(out.len());
+    let low: __m256i = _mm256_broadcastsi128_si256(_mm_loadu_si128(tables.as_ptr().cast()));
+    let high: __m256i = _mm256_broadcastsi128_si256(_mm_loadu_si128(tables[16..].as_ptr().cast()));
+    let mask: __m256i = _mm256_set1_epi8(0x0f);
+
+    let vectors: usize = len / 32;
+    for i in 0..vectors {
+        let bytes: __m256i = _mm256_loadu_si256(input.as_ptr().add(i * 32).cast());
+        let product: __m256i = _mm256_xor_si256(
+            _mm256_shuffle_epi8(low, _mm256_and_si256(bytes, mask)),
+            _mm256_shuffle_epi8(high, _mm256_and_si256(_mm256_srli_epi64(bytes, 4), mask)),
+        );
+        let target: *mut __m256i = out.as_mut_ptr().add(i * 32).cast();
+        _mm256_storeu_si256(
+            target,
+            _mm256_xor_si256(_mm256_loadu_si256(target), product),
+        );
+    }
+    mul_add_portable(
+        tables,
+        &input[vectors * 32..len],
+        &mut out[vectors * 32..len],
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn mul_add_neon(tables: &NibbleTables, input: &[u8], out: &mut [u8]) {
+    use std::arch::aarch64::*;
+
+    let len: usize = input.len().min(out.len());
+    let low: uint8x16_t = vld1q_u8(tables.as_ptr());
+    let high: uint8x16_t = vld1q_u8(tables[16..].as_ptr());
+    let mask: uint8x16_t = vdupq_n_u8(0x0f);
+
+    let vectors: usize = len / 16;
+    for i in 0..vectors {
+        let bytes: uint8x16_t = vld1q_u8(input.as_ptr().add(i * 16));
+        let product: uint8x16_t = veorq_u8(
+            vqtbl1q_u8(low, vandq_u8(bytes, mask)),
+            vqtbl1q_u8(high, vshrq_n_u8::<4>(bytes)),
+        );
+        let target: *mut u8 = out.as_mut_ptr().add(i * 16);
+        vst1q_u8(target, veorq_u8(vld1q_u8(target), product));
+    }
+    mul_add_portable(
+        tables,
+        &input[vectors * 16..len],
+        &mut out[vectors * 16..len],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+    use crate::config::EncoderConfig;
+
+    fn encode_with(
+        backend: &dyn ErasureBackend,
+        shards: &mut [Vec<u8>],
+    ) -> Result<(), ReedSolomonError> {
+        let mut slices: Vec<&mut [u8]> = shards.iter_mut().map(Vec::as_mut_slice).collect();
+        backend.encode_shards(&mut slices)
+    }
+
+    #[test]
+    fn test_every_level_matches_reed_solomon_erasure() -> Result<(), ReedSolomonError> {
+        let (data_shards, parity_shards): (usize, usize) = (7, 3);
+        let mut expected: Vec<Vec<u8>> = (0..data_shards + parity_shards)
+            .map(|i: usize| {
+                (0..101u32)
+                    .map(|j: u32| (j * 31 + i as u32 * 7) as u8)
+                    .collect()
+            })
+            .collect();
+        let plain: galois_8::ReedSolomon =
+            galois_8::ReedSolomon::new(data_shards, parity_shards)
+                .map_err(|error: Error| ReedSolomonError::CodecError { error })?;
+        encode_with(&plain, &mut expected)?;
+
+        for level in [SimdLevel::detect(), SimdLevel::Portable] {
+            let backend: SimdBackend =
+                SimdBackend::with_level(data_shards, parity_shards, level)
+                    .map_err(|error: Error| ReedSolomonError::CodecError { error })?;
+            let mut shards: Vec<Vec<u8>> = expected.clone();
+            for parity in &mut shards[data_shards..] {
+                parity.fill(0xaa);
+            }
+            encode_with(&backend, &mut shards)?;
+            assert_eq!(shards, expected);
+
+            let slices: Vec<&[u8]> = shards.iter().map(Vec::as_slice).collect();
+            assert!(backend.verify(&slices)?);
+            shards[data_shards][100] ^= 1;
+            let slices: Vec<&[u8]> = shards.iter().map(Vec::as_slice).collect();
+            assert!(!backend.verify(&slices)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_portable_codec_matches_plain_backend() -> Result<(), ReedSolomonError> {
+        assert!(SimdLevel::detect().is_available());
+        let backend: SimdBackend = SimdBackend::with_level(4, 2, SimdLevel::Portable)
+            .map_err(|error: Error| ReedSolomonError::CodecError { error })?;
+        assert_eq!(backend.level(), SimdLevel::Portable);
+
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_backend(EncoderConfig::new(4, 2)?, Box::new(backend))?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 241) as u8).collect();
+        let encoded: Vec<u8> = codec.encode(&original_data)?;
+        let plain: galois_8::ReedSolomon = galois_8::ReedSolomon::new(4, 2)
+            .map_err(|error: Error| ReedSolomonError::CodecError { error })?;
+        let plain: ReedSolomonCodec =
+            ReedSolomonCodec::with_backend(EncoderConfig::new(4, 2)?, Box::new(plain))?;
+        assert_eq!(encoded, plain.encode(&original_data)?);
+        assert_eq!(plain.decode(&encoded)?, original_data);
+        Ok(())
+    }
+}
@@ -0,0 +1,498 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::{ManifestError, ReedSolomonError};
+use crate::files::ShardManifest;
+use crate::platform::write_replacing;
+use crate::processor::crc32c_checksum;
+use crate::store::{decode_payload_from_shard_store, read_manifest, FileStore};
+
+pub const SPARSE_EXTENSION: &str = "sparse";
+
+/// What a manifest records of the `SparseMap` its set was encoded with, so
+/// a map that went missing or was replaced is noticed before decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseDigest {
+    pub file_len: u64,
+    /// CRC-32C of the map's text.
+    pub map_checksum: u32,
+}
+
+/// The regions of a file that hold data; everything between them is a
+/// hole that reads as zeros. Only the data regions are encoded, back to
+/// back, and the map is kept next to the manifest to put them back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseMap {
+    pub file_len: u64,
+    /// Offset and length of each data region, in file order.
+    pub extents: Vec<(u64, u64)>,
+}
+
+impl SparseMap {
+    /// Finds the holes of `file` with `SEEK_DATA` and `SEEK_HOLE`. Where
+    /// those are not available the whole file is one data region.
+    pub fn scan(file: &File) -> Result<Self, ReedSolomonError> {
+        let file_len: u64 = file.metadata()?.len();
+        Ok(Self {
+            file_len,
+            extents: data_extents(file, file_len)?,
+        })
+    }
+
+    pub fn data_len(&self) -> u64 {
+        self.extents.iter().map(|(_, len): &(u64, u64)| len).sum()
+    }
+
+    pub fn hole_len(&self) -> u64 {
+        self.file_len - self.data_len()
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        self.hole_len() > 0
+    }
+
+    pub fn digest(&self) -> SparseDigest {
+        SparseDigest {
+            file_len: self.file_len,
+            map_checksum: crc32c_checksum(self.to_text().as_bytes()),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text: String = format!("file_len={}\n", self.file_len);
+        for (offset, len) in &self.extents {
+            text.push_str(&format!("extent={},{}\n", offset, len));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReedSolomonError> {
+        let mut file_len: Option<u64> = None;
+        let mut extents: Vec<(u64, u64)> = Vec::new();
+
+        for line in text
+            .lines()
+            .map(str::trim)
+            .filter(|line: &&str| !line.is_empty())
+        {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ManifestError::MalformedLine(line.to_string()))?;
+            let invalid = || ManifestError::InvalidValue(key.trim().to_string());
+            match key.trim() {
+                "file_len" => file_len = Some(value.trim().parse().map_err(|_| invalid())?),
+                "extent" => {
+                    let (offset, len) = value.split_once(',').ok_or_else(invalid)?;
+                    extents.push((
+                        offset.trim().parse().map_err(|_| invalid())?,
+                        len.trim().parse().map_err(|_| invalid())?,
+                    ));
+                }
+                other => return Err(ManifestError::UnknownKey(other.to_string()).into()),
+            }
+        }
+
+        let map: SparseMap = Self {
+            file_len: file_len.ok_or(ManifestError::MissingKey("file_len"))?,
+            extents,
+        };
+        let mut end: u64 = 0;
+        for (offset, len) in &map.extents {
+            match offset.checked_add(*len) {
+                Some(extent_end) if *offset >= end && extent_end <= map.file_len => {
+                    end = extent_end;
+                }
+                _ => return Err(ManifestError::InvalidValue("extent".to_string()).into()),
+            }
+        }
+
+        Ok(map)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReedSolomonError> {
+        write_replacing(path, self.to_text().as_bytes())?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ReedSolomonError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+pub fn sparse_map_path(dir: &Path, basename: &str) -> PathBuf {
+    dir.join(format!("{}.{}", basename, SPARSE_EXTENSION))
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+fn data_extents(file: &File, file_len: u64) -> Result<Vec<(u64, u64)>, ReedSolomonError> {
+    use std::os::unix::io::AsRawFd;
+
+    let seek = |offset: u64, whence: libc::c_int| -> io::Result<Option<u64>> {
+        let offset: libc::off_t = libc::off_t::try_from(offset)
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        // SAFETY: lseek only moves the offset of a descriptor `file` keeps
+        // open for the duration of the call.
+        let found: libc::off_t = unsafe { libc::lseek(file.as_raw_fd(), offset, whence) };
+        if found >= 0 {
+            return Ok(Some(found as u64));
+        }
+        let error: io::Error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            // No data past `offset`.
+            Some(libc::ENXIO) => Ok(None),
+            _ => Err(error),
+        }
+    };
+
+    let mut extents: Vec<(u64, u64)> = Vec::new();
+    let mut offset: u64 = 0;
+    while offset < file_len {
+        let start: u64 = match seek(offset, libc::SEEK_DATA) {
+            Ok(Some(start)) => start,
+            Ok(None) => break,
+            // The file system cannot report holes.
+            Err(error) if error.raw_os_error() == Some(libc::EINVAL) => {
+                return Ok(vec![(0, file_len)]);
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let end: u64 = seek(start, libc::SEEK_HOLE)?
+            .unwrap_or(file_len)
+            .min(file_len);
+        if start >= end {
+            break;
+        }
+        extents.push((start, end - start));
+        offset = end;
+    }
+    Ok(extents)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+fn data_extents(_file: &File, file_len: u64) -> Result<Vec<(u64, u64)>, ReedSolomonError> {
+    Ok(if file_len == 0 {
+        Vec::new()
+    } else {
+        vec![(0, file_len)]
+    })
+}
+
+/// Reads the data regions of a file back to back.
+struct ExtentReader<'a, R: Read + Seek> {
+    inner: R,
+    extents: &'a [(u64, u64)],
+    remaining: u64,
+}
+
+impl<R: Read + Seek> Read for ExtentReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining == 0 {
+            let Some(((offset, len), rest)) = self.extents.split_first() else {
+                return Ok(0);
+            };
+            self.inner.seek(SeekFrom::Start(*offset))?;
+            self.remaining = *len;
+            self.extents = rest;
+        }
+
+        let len: usize = buf
+            .len()
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        let read: usize = self.inner.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Writes a stream of data regions back to their places in a file,
+/// seeking over the holes between them.
+struct ExtentWriter<'a, W: Write + Seek> {
+    inner: W,
+    extents: &'a [(u64, u64)],
+    remaining: u64,
+}
+
+impl<W: Write + Seek> Write for ExtentWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        while self.remaining == 0 && !buf.is_empty() {
+            let Some(((offset, len), rest)) = self.extents.split_first() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "more data than the sparse map has room for",
+                ));
+            };
+            self.inner.seek(SeekFrom::Start(*offset))?;
+            self.remaining = *len;
+            self.extents = rest;
+        }
+
+        let len: usize = buf
+            .len()
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        let written: usize = self.inner.write(&buf[..len])?;
+        self.remaining -= written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl ReedSolomonCodec {
+    /// Encodes the file at `path` like `encode_to_files`, but skips its
+    /// holes: only the data regions are encoded, and when there are holes
+    /// a `SparseMap` is written next to the manifest to restore them.
+    pub fn encode_sparse_file_to_files(
+        &self,
+        path: &Path,
+        dir: &Path,
+        basename: &str,
+    ) -> Result<(ShardManifest, SparseMap), ReedSolomonError> {
+        self.encode_sparse_file_to_file_store(path, &FileStore::new(dir), basename)
+    }
+
+    /// Encodes like `encode_sparse_file_to_files` into the directory of
+    /// `store`, with the I/O options it was built with.
+    pub fn encode_sparse_file_to_file_store(
+        &self,
+        path: &Path,
+        store: &FileStore,
+        basename: &str,
+    ) -> Result<(ShardManifest, SparseMap), ReedSolomonError> {
+        let dir: &Path = store.dir();
+        let file: File = File::open(path)?;
+        let map: SparseMap = SparseMap::scan(&file)?;
+        let reader: ExtentReader<'_, File> = ExtentReader {
+            inner: file,
+            extents: &map.extents,
+            remaining: 0,
+        };
+        let sparse: Option<SparseDigest> = map.is_sparse().then(|| map.digest());
+        let map_path: PathBuf = sparse_map_path(dir, basename);
+        if sparse.is_some() {
+            fs::create_dir_all(dir)?;
+            map.write(&map_path)?;
+        }
+        let manifest: ShardManifest =
+            self.encode_to_file_store_with(BufReader::new(reader), store, basename, sparse)?;
+        if manifest.payload_len != map.data_len() {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: map.data_len() as usize,
+                given: manifest.payload_len as usize,
+            });
+        }
+        if sparse.is_none() && map_path.exists() {
+            fs::remove_file(&map_path)?;
+        }
+        Ok((manifest, map))
+    }
+}
+
+/// Decodes the shard set `basename` in `dir` into `output`, leaving holes
+/// where its `SparseMap` has them, and returns the file length. A set the
+/// manifest does not record as sparse is decoded like `decode_from_files`.
+/// A map that is missing, or that the manifest does not record, is an
+/// error rather than a file restored with its holes squeezed out.
+pub fn decode_sparse_from_files(
+    dir: &Path,
+    basename: &str,
+    output: &File,
+) -> Result<u64, ReedSolomonError> {
+    let store: FileStore = FileStore::new(dir);
+    let manifest: ShardManifest = read_manifest(&store, basename)?;
+    let map_path: PathBuf = sparse_map_path(dir, basename);
+    let Some(sparse) = manifest.sparse else {
+        if map_path.exists() {
+            return Err(ManifestError::SparseMapMismatch.into());
+        }
+        output.set_len(0)?;
+        return decode_payload_from_shard_store(&store, basename, &manifest, output);
+    };
+
+    let map: SparseMap = match SparseMap::read(&map_path) {
+        Err(ReedSolomonError::Io(error)) if error.kind() == io::ErrorKind::NotFound => {
+            return Err(ManifestError::MissingSparseMap.into())
+        }
+        map => map?,
+    };
+    if map.digest() != sparse || map.data_len() != manifest.payload_len {
+        return Err(ManifestError::SparseMapMismatch.into());
+    }
+
+    output.set_len(0)?;
+    output.set_len(map.file_len)?;
+    let writer: ExtentWriter<'_, &File> = ExtentWriter {
+        inner: output,
+        extents: &map.extents,
+        remaining: 0,
+    };
+    let written: u64 =
+        decode_payload_from_shard_store(&store, basename, &manifest, BufWriter::new(writer))?;
+    if written != map.data_len() {
+        return Err(ReedSolomonError::LengthMismatch {
+            expected: map.data_len() as usize,
+            given: written as usize,
+        });
+    }
+    Ok(map.file_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::{decode_from_files, manifest_path};
+    use crate::test_util::temp_dir;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_sparse_map_roundtrip() -> Result<(), ReedSolomonError> {
+        let map: SparseMap = SparseMap {
+            file_len: 1 << 30,
+            extents: vec![(0, 4096), (1 << 20, 8192)],
+        };
+        assert_eq!(SparseMap::parse(&map.to_text())?, map);
+        assert_eq!(map.data_len(), 12288);
+        assert!(map.is_sparse());
+
+        assert!(SparseMap::parse("file_len=100\nextent=50,60\n").is_err());
+        assert!(SparseMap::parse("file_len=100\nextent=50,10\nextent=0,10\n").is_err());
+        assert!(SparseMap::parse("extent=0,10\n").is_err());
+
+        let file: Vec<u8> = (0..100u8).collect();
+        let extents: [(u64, u64); 2] = [(10, 5), (90, 10)];
+        let mut packed: Vec<u8> = Vec::new();
+        ExtentReader {
+            inner: Cursor::new(&file),
+            extents: &extents,
+            remaining: 0,
+        }
+        .read_to_end(&mut packed)?;
+        assert_eq!(packed, [&file[10..15], &file[90..]].concat());
+
+        let mut restored: Cursor<Vec<u8>> = Cursor::new(vec![0u8; 100]);
+        ExtentWriter {
+            inner: &mut restored,
+            extents: &extents,
+            remaining: 0,
+        }
+        .write_all(&packed)?;
+        assert_eq!(restored.get_ref()[10..15], file[10..15]);
+        assert_eq!(restored.get_ref()[90..], file[90..]);
+        assert!(restored.get_ref()[15..90]
+            .iter()
+            .all(|byte: &u8| *byte == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_file_roundtrip() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("sparse");
+        fs::create_dir_all(&dir)?;
+        let input: PathBuf = dir.join("disk.img");
+        let file_len: u64 = 64 << 20;
+        let mut file: File = File::create(&input)?;
+        file.set_len(file_len)?;
+        file.write_all(b"boot sector")?;
+        file.seek(SeekFrom::Start(32 << 20))?;
+        file.write_all(&[0xa5; 5000])?;
+        drop(file);
+
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let shards: PathBuf = dir.join("shards");
+        let (manifest, map): (ShardManifest, SparseMap) =
+            codec.encode_sparse_file_to_files(&input, &shards, "disk.img")?;
+        assert_eq!(manifest.payload_len, map.data_len());
+        assert_eq!(
+            sparse_map_path(&shards, "disk.img").exists(),
+            map.is_sparse()
+        );
+        if map.is_sparse() {
+            assert!(map.data_len() < file_len / 2);
+        }
+
+        let output: PathBuf = dir.join("restored.img");
+        let written: u64 = decode_sparse_from_files(&shards, "disk.img", &File::create(&output)?)?;
+        assert_eq!(written, file_len);
+        assert!(fs::read(&output)? == fs::read(&input)?);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_refuses_set_without_its_sparse_map() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("sparse-lost-map");
+        let shards: PathBuf = dir.join("shards");
+        fs::create_dir_all(&shards)?;
+        let data: Vec<u8> = (0..5000u32).map(|i: u32| (i * 7) as u8).collect();
+        let map: SparseMap = SparseMap {
+            file_len: 1 << 20,
+            extents: vec![(0, 1000), (1 << 19, 4000)],
+        };
+        let map_path: PathBuf = sparse_map_path(&shards, "disk.img");
+        map.write(&map_path)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+        let manifest: ShardManifest = codec.encode_to_file_store_with(
+            data.as_slice(),
+            &FileStore::new(&shards),
+            "disk.img",
+            Some(map.digest()),
+        )?;
+        assert_eq!(
+            ShardManifest::read(&manifest_path(&shards, "disk.img"))?,
+            manifest
+        );
+
+        let output: File = File::create(dir.join("restored.img"))?;
+        assert_eq!(
+            decode_sparse_from_files(&shards, "disk.img", &output)?,
+            1 << 20
+        );
+        assert!(matches!(
+            decode_from_files(&shards, "disk.img", io::sink()),
+            Err(ReedSolomonError::InvalidManifest(ManifestError::SparseFile))
+        ));
+
+        let moved: SparseMap = SparseMap {
+            extents: vec![(0, 1000), (1 << 18, 4000)],
+            ..map
+        };
+        moved.write(&map_path)?;
+        assert!(matches!(
+            decode_sparse_from_files(&shards, "disk.img", &output),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::SparseMapMismatch
+            ))
+        ));
+
+        fs::remove_file(&map_path)?;
+        assert!(matches!(
+            decode_sparse_from_files(&shards, "disk.img", &output),
+            Err(ReedSolomonError::InvalidManifest(
+                ManifestError::MissingSparseMap
+            ))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
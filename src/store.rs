@@ -0,0 +1,659 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::bundle::BundleIndex;
+use crate::codec::ReedSolomonCodec;
+use crate::direct::{DirectReader, DirectWriter};
+use crate::error::{ManifestError, ReedSolomonError};
+use crate::files::{
+    decode_shard_readers, legacy_shard_path, ShardFileName, ShardFileReader, ShardManifest,
+    MANIFEST_EXTENSION,
+};
+use crate::platform::{partial_path, replace_file};
+use crate::processor::DataProcessor;
+use crate::sparse::SparseDigest;
+use crate::stream::read_full;
+
+pub const RECIPE_EXTENSION: &str = "chunks";
+
+/// What a `ShardStore` keeps: the manifest of a shard set, one of its
+/// shards, or the recipe of a chunked encode. The `Display` form is the
+/// file name `encode_to_files` uses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ShardId {
+    /// The manifest of the set with this basename.
+    Manifest(String),
+    Shard(ShardFileName),
+    /// The list of chunk sets that make up the payload with this basename.
+    Recipe(String),
+}
+
+impl ShardId {
+    pub fn manifest(basename: &str) -> Self {
+        ShardId::Manifest(basename.to_string())
+    }
+
+    pub fn recipe(basename: &str) -> Self {
+        ShardId::Recipe(basename.to_string())
+    }
+
+    pub fn shard(basename: &str, manifest: &ShardManifest, index: usize) -> Self {
+        ShardId::Shard(ShardFileName {
+            basename: basename.to_string(),
+            data_shards: manifest.data_shards,
+            parity_shards: manifest.parity_shards,
+            generation: manifest.generation,
+            index,
+        })
+    }
+
+    /// The id a file name stands for, if any.
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(basename) = name.strip_suffix(&format!(".{}", MANIFEST_EXTENSION)) {
+            return (!basename.is_empty()).then(|| Self::manifest(basename));
+        }
+        if let Some(basename) = name.strip_suffix(&format!(".{}", RECIPE_EXTENSION)) {
+            return (!basename.is_empty()).then(|| Self::recipe(basename));
+        }
+        ShardFileName::parse(name).map(ShardId::Shard)
+    }
+}
+
+impl fmt::Display for ShardId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShardId::Manifest(basename) => write!(f, "{}.{}", basename, MANIFEST_EXTENSION),
+            ShardId::Shard(name) => name.fmt(f),
+            ShardId::Recipe(basename) => write!(f, "{}.{}", basename, RECIPE_EXTENSION),
+        }
+    }
+}
+
+/// Where shard sets live. Each shard and each manifest is one object under
+/// its `ShardId`. Only `put`, `get`, `list` and `delete` are required;
+/// `reader` and `writer` go through memory by default, and stores that can
+/// stream should override them.
+pub trait ShardStore {
+    fn put(&self, id: &ShardId, data: &[u8]) -> Result<(), ReedSolomonError>;
+
+    /// `None` when nothing is stored under `id`.
+    fn get(&self, id: &ShardId) -> Result<Option<Vec<u8>>, ReedSolomonError>;
+
+    /// Every stored id, sorted.
+    fn list(&self) -> Result<Vec<ShardId>, ReedSolomonError>;
+
+    /// Deleting an id that is not stored is not an error.
+    fn delete(&self, id: &ShardId) -> Result<(), ReedSolomonError>;
+
+    /// `None` when nothing is stored under `id`.
+    fn reader(&self, id: &ShardId) -> Result<Option<Box<dyn Read + '_>>, ReedSolomonError> {
+        Ok(self
+            .get(id)?
+            .map(|data: Vec<u8>| Box::new(Cursor::new(data)) as Box<dyn Read>))
+    }
+
+    fn writer(&self, id: &ShardId) -> Result<Box<dyn ShardWriter + '_>, ReedSolomonError> {
+        Ok(Box::new(BufferedWriter {
+            store: self,
+            id: id.clone(),
+            data: Vec::new(),
+        }))
+    }
+}
+
+/// An object being written to a `ShardStore`. Nothing is stored until
+/// `commit`, so a writer dropped half way leaves the old object in place.
+pub trait ShardWriter: Write {
+    fn commit(self: Box<Self>) -> Result<(), ReedSolomonError>;
+}
+
+struct BufferedWriter<'a, S: ?Sized> {
+    store: &'a S,
+    id: ShardId,
+    data: Vec<u8>,
+}
+
+impl<S: ShardStore + ?Sized> Write for BufferedWriter<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: ShardStore + ?Sized> ShardWriter for BufferedWriter<'_, S> {
+    fn commit(self: Box<Self>) -> Result<(), ReedSolomonError> {
+        self.store.put(&self.id, &self.data)
+    }
+}
+
+/// Shard sets held in memory, for tests and short-lived pipelines.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<BTreeMap<ShardId, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<ShardId, Vec<u8>>> {
+        self.objects.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ShardStore for MemoryStore {
+    fn put(&self, id: &ShardId, data: &[u8]) -> Result<(), ReedSolomonError> {
+        self.lock().insert(id.clone(), data.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, id: &ShardId) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        Ok(self.lock().get(id).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<ShardId>, ReedSolomonError> {
+        Ok(self.lock().keys().cloned().collect())
+    }
+
+    fn delete(&self, id: &ShardId) -> Result<(), ReedSolomonError> {
+        self.lock().remove(id);
+        Ok(())
+    }
+}
+
+/// Shard sets as files in one directory, laid out as `encode_to_files`
+/// writes them. Shards that only exist under their legacy
+/// `basename.shard.NNN` name are still read and deleted.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+    direct_io: bool,
+}
+
+impl FileStore {
+    /// The directory is created on the first write.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            direct_io: false,
+        }
+    }
+
+    /// Reads and writes shards with `O_DIRECT` on Linux, so large jobs do
+    /// not evict everything else from the page cache. Elsewhere, and on
+    /// file systems that refuse it, I/O stays buffered.
+    pub fn with_direct_io(mut self) -> Self {
+        self.direct_io = true;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self, id: &ShardId) -> PathBuf {
+        self.dir.join(id.to_string())
+    }
+
+    fn legacy_path(&self, id: &ShardId) -> Option<PathBuf> {
+        match id {
+            ShardId::Shard(name) if name.generation == 0 => {
+                Some(legacy_shard_path(&self.dir, &name.basename, name.index))
+            }
+            ShardId::Shard(_) | ShardId::Manifest(_) | ShardId::Recipe(_) => None,
+        }
+    }
+
+    fn open(&self, id: &ShardId) -> Result<Option<Box<dyn Read>>, ReedSolomonError> {
+        let legacy: Option<PathBuf> = self.legacy_path(id);
+        for path in std::iter::once(self.path(id)).chain(legacy) {
+            let opened: io::Result<Box<dyn Read>> = if self.direct_io {
+                DirectReader::open(&path)
+            } else {
+                File::open(&path).map(|file: File| Box::new(BufReader::new(file)) as Box<dyn Read>)
+            };
+            match opened {
+                Ok(reader) => return Ok(Some(reader)),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl ShardStore for FileStore {
+    fn put(&self, id: &ShardId, data: &[u8]) -> Result<(), ReedSolomonError> {
+        let mut writer: Box<dyn ShardWriter + '_> = self.writer(id)?;
+        writer.write_all(data)?;
+        writer.commit()
+    }
+
+    fn get(&self, id: &ShardId) -> Result<Option<Vec<u8>>, ReedSolomonError> {
+        let Some(mut reader) = self.open(id)? else {
+            return Ok(None);
+        };
+        let mut data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(Some(data))
+    }
+
+    fn list(&self) -> Result<Vec<ShardId>, ReedSolomonError> {
+        let entries: fs::ReadDir = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut ids: Vec<ShardId> = Vec::new();
+        for entry in entries {
+            if let Some(id) = entry?.file_name().to_str().and_then(ShardId::parse) {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn delete(&self, id: &ShardId) -> Result<(), ReedSolomonError> {
+        let legacy: Option<PathBuf> = self.legacy_path(id);
+        for path in std::iter::once(self.path(id)).chain(legacy) {
+            match fs::remove_file(&path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn reader(&self, id: &ShardId) -> Result<Option<Box<dyn Read + '_>>, ReedSolomonError> {
+        self.open(id)
+    }
+
+    fn writer(&self, id: &ShardId) -> Result<Box<dyn ShardWriter + '_>, ReedSolomonError> {
+        fs::create_dir_all(&self.dir)?;
+        let writer: FileWriter = if self.direct_io {
+            FileWriter::create_direct(self.path(id))?
+        } else {
+            FileWriter::create(self.path(id))?
+        };
+        Ok(Box::new(writer))
+    }
+}
+
+/// Writes next to `path` and renames over it on commit.
+pub(crate) struct FileWriter {
+    file: Box<dyn Write>,
+    partial: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl FileWriter {
+    pub(crate) fn create(path: PathBuf) -> io::Result<Self> {
+        let partial: PathBuf = partial_path(&path);
+        Ok(Self {
+            file: Box::new(BufWriter::new(File::create(&partial)?)),
+            partial,
+            path,
+            committed: false,
+        })
+    }
+
+    /// Like `create`, but writes with direct I/O where it is available.
+    pub(crate) fn create_direct(path: PathBuf) -> io::Result<Self> {
+        let partial: PathBuf = partial_path(&path);
+        Ok(Self {
+            file: Box::new(DirectWriter::create(&partial)?),
+            partial,
+            path,
+            committed: false,
+        })
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl ShardWriter for FileWriter {
+    fn commit(mut self: Box<Self>) -> Result<(), ReedSolomonError> {
+        self.file.flush()?;
+        replace_file(&self.partial, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.partial);
+        }
+    }
+}
+
+pub fn read_manifest<S: ShardStore + ?Sized>(
+    store: &S,
+    basename: &str,
+) -> Result<ShardManifest, ReedSolomonError> {
+    let id: ShardId = ShardId::manifest(basename);
+    let text: Vec<u8> = store
+        .get(&id)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, id.to_string()))?;
+    ShardManifest::parse(&String::from_utf8_lossy(&text))
+}
+
+fn shard_readers<'a, S: ShardStore + ?Sized>(
+    store: &'a S,
+    basename: &str,
+    manifest: &ShardManifest,
+) -> Result<Vec<Option<Box<dyn Read + 'a>>>, ReedSolomonError> {
+    (0..manifest.data_shards + manifest.parity_shards)
+        .map(|i: usize| store.reader(&ShardId::shard(basename, manifest, i)))
+        .collect()
+}
+
+impl ReedSolomonCodec {
+    /// Streams `reader` into one object per shard, committing them once
+    /// every block is written, and then stores the manifest. A set already
+    /// stored under `basename` is replaced by the next generation, and its
+    /// shards are deleted once the new manifest is in place.
+    pub fn encode_to_shard_store<R: Read, S: ShardStore + ?Sized>(
+        &self,
+        reader: R,
+        store: &S,
+        basename: &str,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        self.encode_to_shard_store_with(reader, store, basename, None)
+    }
+
+    /// Encodes like `encode_to_shard_store`, recording in the manifest the
+    /// sparse map the payload was read through.
+    pub(crate) fn encode_to_shard_store_with<R: Read, S: ShardStore + ?Sized>(
+        &self,
+        reader: R,
+        store: &S,
+        basename: &str,
+        sparse: Option<SparseDigest>,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        let previous: Option<ShardManifest> = read_manifest(store, basename).ok();
+        let generation: u64 = previous.map_or(0, |previous: ShardManifest| previous.generation + 1);
+        let naming: ShardManifest = ShardManifest {
+            generation,
+            ..self.shard_manifest(0)
+        };
+        let writers: Vec<Box<dyn ShardWriter + '_>> = (0..self.config().total_shards())
+            .map(|i: usize| store.writer(&ShardId::shard(basename, &naming, i)))
+            .collect::<Result<_, _>>()?;
+        let payload_len: u64 = self.write_shards(reader, writers)?;
+
+        let manifest: ShardManifest = ShardManifest {
+            generation,
+            sparse,
+            ..self.shard_manifest(payload_len)
+        };
+        store.put(&ShardId::manifest(basename), manifest.to_text().as_bytes())?;
+        if let Some(previous) = previous {
+            for index in 0..previous.data_shards + previous.parity_shards {
+                store.delete(&ShardId::shard(basename, &previous, index))?;
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Re-encodes the set `basename` with this codec's configuration, one
+    /// block at a time, as the next generation of the set. The new shards
+    /// and manifest are stored before the old shards are deleted, so a
+    /// failure part way leaves the old set readable.
+    pub fn reshard_shard_store<S: ShardStore + ?Sized>(
+        &self,
+        store: &S,
+        basename: &str,
+    ) -> Result<ShardManifest, ReedSolomonError> {
+        let manifest: ShardManifest = read_manifest(store, basename)?;
+        if manifest.data_shards == self.config().data_shards()
+            && manifest.parity_shards == self.config().parity_shards()
+        {
+            return Err(ReedSolomonError::UnchangedShardCounts {
+                data_shards: manifest.data_shards,
+                parity_shards: manifest.parity_shards,
+            });
+        }
+
+        let bundle: BundleIndex = BundleIndex::read(store, basename)?;
+        let reader: ShardFileReader = ShardFileReader::new(
+            ReedSolomonCodec::with_config(manifest.config()?)?,
+            &manifest,
+            shard_readers(store, basename, &manifest)?,
+        );
+        let resharded: ShardManifest =
+            self.encode_to_shard_store_with(reader, store, basename, manifest.sparse)?;
+        if !bundle.is_empty() {
+            bundle.write(store, basename, &resharded)?;
+        }
+        Ok(resharded)
+    }
+}
+
+pub fn decode_from_shard_store<S: ShardStore + ?Sized, W: Write>(
+    store: &S,
+    basename: &str,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let manifest: ShardManifest = read_manifest(store, basename)?;
+    if manifest.sparse.is_some() {
+        return Err(ManifestError::SparseFile.into());
+    }
+    decode_payload_from_shard_store(store, basename, &manifest, writer)
+}
+
+/// Decodes the payload of the set `basename` as it was encoded, which for a
+/// sparse file is its data regions back to back.
+pub(crate) fn decode_payload_from_shard_store<S: ShardStore + ?Sized, W: Write>(
+    store: &S,
+    basename: &str,
+    manifest: &ShardManifest,
+    writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let reader: ShardFileReader = ShardFileReader::new(
+        ReedSolomonCodec::with_config(manifest.config()?)?,
+        manifest,
+        shard_readers(store, basename, manifest)?,
+    );
+    decode_shard_readers(reader, writer)
+}
+
+/// Rewrites every shard of the set `basename` that is missing, fails a
+/// checksum in any block, or is longer than the manifest says, and returns
+/// their indices.
+pub fn repair_shard_store<S: ShardStore + ?Sized>(
+    store: &S,
+    basename: &str,
+) -> Result<Vec<usize>, ReedSolomonError> {
+    let manifest: ShardManifest = read_manifest(store, basename)?;
+    let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(manifest.config()?)?;
+    let ids: Vec<ShardId> = (0..manifest.data_shards + manifest.parity_shards)
+        .map(|i: usize| ShardId::shard(basename, &manifest, i))
+        .collect();
+    repair_shards(
+        &codec,
+        &manifest,
+        |index: usize| store.reader(&ids[index]),
+        |index: usize| store.writer(&ids[index]),
+    )
+}
+
+/// Streams every shard once, block by block. A shard found damaged is
+/// rewritten whole: its intact leading blocks are copied from a second
+/// reader, and from there on each block is either its own verified data
+/// or reconstructed. The rewritten shards are committed only once every
+/// block has been reconstructed.
+pub(crate) fn repair_shards<'a>(
+    codec: &ReedSolomonCodec,
+    manifest: &ShardManifest,
+    open: impl Fn(usize) -> Result<Option<Box<dyn Read + 'a>>, ReedSolomonError>,
+    create: impl Fn(usize) -> Result<Box<dyn ShardWriter + 'a>, ReedSolomonError>,
+) -> Result<Vec<usize>, ReedSolomonError> {
+    let total_shards: usize = manifest.data_shards + manifest.parity_shards;
+    let mut readers: Vec<Option<Box<dyn Read + 'a>>> =
+        (0..total_shards).map(&open).collect::<Result<_, _>>()?;
+    let mut writers: Vec<Option<Box<dyn ShardWriter + 'a>>> =
+        (0..total_shards).map(|_| None).collect();
+    let rewrite =
+        |index: usize, offset: u64| -> Result<Box<dyn ShardWriter + 'a>, ReedSolomonError> {
+            let mut writer: Box<dyn ShardWriter + 'a> = create(index)?;
+            if offset > 0 {
+                let reader: Box<dyn Read + 'a> =
+                    open(index)?.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+                io::copy(&mut reader.take(offset), &mut writer)?;
+            }
+            Ok(writer)
+        };
+
+    let mut offset: u64 = 0;
+    for block in 0..manifest.block_count() {
+        let shard_size: usize = manifest.shard_size(block);
+        let mut shards: Vec<Option<Vec<u8>>> = Vec::with_capacity(total_shards);
+        for (index, reader) in readers.iter_mut().enumerate() {
+            let shard: Option<Vec<u8>> = match reader {
+                Some(reader) => read_shard(reader, shard_size)?,
+                None => None,
+            };
+            if shard.is_none() && writers[index].is_none() {
+                writers[index] = Some(rewrite(index, offset)?);
+            }
+            shards.push(shard);
+        }
+
+        if shards.iter().any(Option::is_none) {
+            codec.reconstruct(&mut shards)?;
+        }
+        for (writer, shard) in writers.iter_mut().zip(shards) {
+            if let Some(writer) = writer {
+                writer.write_all(&DataProcessor::append_checksum(shard.unwrap_or_default()))?;
+            }
+        }
+        offset += shard_size as u64;
+    }
+
+    for (index, reader) in readers.iter_mut().enumerate() {
+        if let (Some(reader), None) = (reader, &writers[index]) {
+            if reader.read(&mut [0u8; 1])? > 0 {
+                writers[index] = Some(rewrite(index, offset)?);
+            }
+        }
+    }
+
+    let repaired: Vec<usize> = (0..total_shards)
+        .filter(|i: &usize| writers[*i].is_some())
+        .collect();
+    for writer in writers.into_iter().flatten() {
+        writer.commit()?;
+    }
+    Ok(repaired)
+}
+
+fn read_shard<R: Read>(reader: &mut R, shard_size: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut shard: Vec<u8> = vec![0u8; shard_size];
+    if read_full(reader, &mut shard)? < shard_size {
+        return Ok(None);
+    }
+    Ok(DataProcessor::verify_checksum(&shard).map(<[u8]>::to_vec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_memory_store_round_trip_and_repair() -> Result<(), ReedSolomonError> {
+        let store: MemoryStore = MemoryStore::new();
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 239) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_shard_store(original_data.as_slice(), &store, "data")?;
+        assert_eq!(store.list()?.len(), 7);
+        assert_eq!(store.list()?[0], ShardId::manifest("data"));
+
+        let shard_0: ShardId = ShardId::shard("data", &manifest, 0);
+        let shard_3: ShardId = ShardId::shard("data", &manifest, 3);
+        let original_3: Vec<u8> = store.get(&shard_3)?.unwrap_or_default();
+        store.delete(&shard_0)?;
+        let mut corrupted: Vec<u8> = original_3.clone();
+        corrupted[manifest.shard_size(0) * 4 + 7] ^= 0xff;
+        store.put(&shard_3, &corrupted)?;
+
+        let mut decoded: Vec<u8> = Vec::new();
+        assert_eq!(decode_from_shard_store(&store, "data", &mut decoded)?, 5000);
+        assert_eq!(decoded, original_data);
+
+        assert_eq!(repair_shard_store(&store, "data")?, vec![0, 3]);
+        assert_eq!(store.get(&shard_3)?, Some(original_3));
+        assert!(store.get(&shard_0)?.is_some());
+        assert_eq!(repair_shard_store(&store, "data")?, Vec::<usize>::new());
+
+        let resharded: ShardManifest =
+            ReedSolomonCodec::new(3, 3)?.reshard_shard_store(&store, "data")?;
+        assert_eq!(store.list()?.len(), 7);
+        assert!(store.get(&shard_0)?.is_none());
+        assert!(store.get(&ShardId::shard("data", &resharded, 5))?.is_some());
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_shard_store(&store, "data", &mut decoded)?;
+        assert_eq!(decoded, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_store_ids_and_writers() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("store-files");
+        let store: FileStore = FileStore::new(&dir);
+        assert_eq!(store.list()?, Vec::<ShardId>::new());
+
+        let manifest: ShardManifest = ReedSolomonCodec::new(2, 1)?.encode_to_shard_store(
+            &b"file store"[..],
+            &store,
+            "notes.txt",
+        )?;
+        fs::write(dir.join("unrelated.bin"), b"ignored")?;
+        let ids: Vec<ShardId> = store.list()?;
+        assert_eq!(ids.len(), 4);
+        for id in &ids {
+            assert_eq!(ShardId::parse(&id.to_string()).as_ref(), Some(id));
+            assert!(store.path(id).exists());
+        }
+
+        let id: ShardId = ShardId::shard("notes.txt", &manifest, 2);
+        let mut writer: Box<dyn ShardWriter + '_> = store.writer(&id)?;
+        writer.write_all(b"never committed")?;
+        drop(writer);
+        assert_ne!(store.get(&id)?, Some(b"never committed".to_vec()));
+        assert_eq!(fs::read_dir(&dir)?.count(), 5);
+
+        fs::rename(store.path(&id), legacy_shard_path(&dir, "notes.txt", 2))?;
+        assert!(store.get(&id)?.is_some());
+        store.delete(&id)?;
+        assert!(store.get(&id)?.is_none());
+        store.delete(&id)?;
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,535 @@
+use std::io::{self, Cursor, Read, Write};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::header::{ContainerHeader, GroupHeader, GROUP_HEADER_SIZE, GROUP_MAGIC, MAGIC};
+use crate::processor::DataProcessor;
+use crate::trailer::{is_trailer, TRAILER_HEADER_SIZE};
+
+const VERSION_END: usize = MAGIC.len() + 1;
+
+impl ReedSolomonCodec {
+    pub fn encode_blocks(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut encoded: Vec<u8> = Vec::new();
+        self.encode_stream(data, &mut encoded)?;
+        Ok(encoded)
+    }
+
+    pub fn decode_blocks(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut decoded: Vec<u8> = Vec::new();
+        self.decode_stream(Cursor::new(data), &mut decoded)?;
+        Ok(decoded)
+    }
+
+    pub fn encode_stream<R: Read, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> Result<u64, ReedSolomonError> {
+        self.encode_units(reader, writer, |_: &[u8]| Ok(()))
+    }
+
+    /// `encode_stream`, showing each encoded unit to `on_unit` before it
+    /// is written.
+    pub(crate) fn encode_units<R: Read, W: Write, F>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        mut on_unit: F,
+    ) -> Result<u64, ReedSolomonError>
+    where
+        F: FnMut(&[u8]) -> Result<(), ReedSolomonError>,
+    {
+        let group_size: usize = self.group_size()?;
+        let mut group: Vec<u8> = vec![0u8; group_size];
+        let mut encoded: Vec<u8> = self.pool().take(0);
+        let mut written: u64 = 0;
+
+        loop {
+            let filled: usize = read_full(&mut reader, &mut group)?;
+            if filled == 0 {
+                break;
+            }
+
+            self.encode_unit_into(&group[..filled], &mut encoded)?;
+            on_unit(&encoded)?;
+            self.throttle(encoded.len());
+            writer.write_all(&encoded)?;
+            written += encoded.len() as u64;
+
+            if filled < group_size {
+                break;
+            }
+        }
+
+        self.pool().give(encoded);
+        writer.flush()?;
+        Ok(written)
+    }
+
+    pub fn decode_stream<R: Read, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<u64, ReedSolomonError> {
+        let mut unit: Vec<u8> = self.pool().take(0);
+        let mut written: u64 = 0;
+
+        while read_unit(&mut reader, &mut unit)? {
+            let decoded: Vec<u8> = self.decode_unit(&unit)?;
+            self.throttle(decoded.len());
+            writer.write_all(&decoded)?;
+            written += decoded.len() as u64;
+            self.pool().give(decoded);
+        }
+
+        self.pool().give(unit);
+        writer.flush()?;
+        Ok(written)
+    }
+
+    pub fn decode_unit(&self, unit: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        if !unit.starts_with(&GROUP_MAGIC) {
+            return self.decode(unit);
+        }
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut container: Vec<u8> = self.pool().take(0);
+        for (header, body) in group_blocks(unit)? {
+            container.clear();
+            container.extend_from_slice(&header.to_bytes());
+            container.extend_from_slice(&body);
+            decoded.extend_from_slice(&self.decode(&container)?);
+        }
+        self.pool().give(container);
+
+        Ok(decoded)
+    }
+
+    pub(crate) fn group_size(&self) -> Result<usize, ReedSolomonError> {
+        self.config()
+            .block_size()
+            .checked_mul(self.config().interleave_depth())
+            .ok_or(ReedSolomonError::SizeOverflow)
+    }
+
+    pub(crate) fn encode_unit_into(
+        &self,
+        data: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), ReedSolomonError> {
+        out.clear();
+        if let Some(replica) = self.replica_codec(data.len())? {
+            return replica.encode_unit_into(data, out);
+        }
+
+        let blocks: Vec<&[u8]> = data.chunks(self.config().block_size()).collect();
+        if blocks.len() == 1 {
+            out.resize(self.coded_len(data.len()), 0);
+            let encoded_len: usize = self.encode_coded_into(data, out)?;
+            out.truncate(encoded_len);
+            return Ok(());
+        }
+
+        let containers: Vec<Vec<u8>> = blocks
+            .iter()
+            .map(|block: &&[u8]| -> Result<Vec<u8>, ReedSolomonError> {
+                let mut container: Vec<u8> = vec![0u8; self.coded_len(block.len())];
+                let encoded_len: usize = self.encode_coded_into(block, &mut container)?;
+                container.truncate(encoded_len);
+                Ok(container)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let header_size: usize = ContainerHeader::peek_size(&containers[0])?;
+        out.extend_from_slice(&GroupHeader::new(containers.len()).to_bytes());
+        for container in &containers {
+            out.extend_from_slice(&container[..header_size]);
+        }
+
+        let bodies: Vec<&[u8]> = containers
+            .iter()
+            .map(|container: &Vec<u8>| &container[header_size..])
+            .collect();
+        DataProcessor::interleave_shards(&bodies, self.config().total_shards(), out);
+        Ok(())
+    }
+}
+
+/// Splits an interleaved group into the header and body of each block.
+pub(crate) fn group_blocks(
+    unit: &[u8],
+) -> Result<Vec<(ContainerHeader, Vec<u8>)>, ReedSolomonError> {
+    let group: GroupHeader = GroupHeader::read(unit)?;
+    let header_size: usize = ContainerHeader::peek_size(&unit[GROUP_HEADER_SIZE..])?;
+    let headers_end: usize = GROUP_HEADER_SIZE + group.headers_len(header_size);
+    if unit.len() < headers_end {
+        return Err(ReedSolomonError::Truncated {
+            needed: headers_end,
+            given: unit.len(),
+        });
+    }
+
+    let headers: Vec<ContainerHeader> = unit[GROUP_HEADER_SIZE..headers_end]
+        .chunks_exact(header_size)
+        .map(ContainerHeader::read)
+        .collect::<Result<_, _>>()?;
+    if headers.iter().any(|header: &ContainerHeader| {
+        (
+            header.size(),
+            header.field,
+            header.data_shards,
+            header.parity_shards,
+        ) != (
+            header_size,
+            headers[0].field,
+            headers[0].data_shards,
+            headers[0].parity_shards,
+        )
+    }) {
+        return Err(HeaderError::Inconsistent.into());
+    }
+
+    let total_shards: usize = headers[0].data_shards as usize + headers[0].parity_shards as usize;
+    let shard_sizes: Vec<usize> = headers
+        .iter()
+        .map(|header: &ContainerHeader| header.shard_size as usize)
+        .collect();
+    let bodies: Vec<Vec<u8>> =
+        DataProcessor::deinterleave_shards(&unit[headers_end..], &shard_sizes, total_shards)?;
+
+    Ok(headers.into_iter().zip(bodies).collect())
+}
+
+pub fn leading_header(data: &[u8]) -> Result<ContainerHeader, ReedSolomonError> {
+    if data.starts_with(&GROUP_MAGIC) {
+        ContainerHeader::read(data.get(GROUP_HEADER_SIZE..).unwrap_or_default())
+    } else {
+        ContainerHeader::read(data)
+    }
+}
+
+pub fn split_blocks(data: &[u8]) -> Result<Vec<&[u8]>, ReedSolomonError> {
+    let mut blocks: Vec<&[u8]> = Vec::new();
+    let mut offset: usize = 0;
+
+    while offset < data.len() {
+        let remaining: &[u8] = &data[offset..];
+        let mut unit_len: usize = 0;
+
+        loop {
+            let pending: usize = pending_unit_len(&remaining[..unit_len])?;
+            if pending == 0 {
+                break;
+            }
+
+            unit_len = unit_len
+                .checked_add(pending)
+                .ok_or(ReedSolomonError::SizeOverflow)?;
+            if unit_len > remaining.len() {
+                return Err(ReedSolomonError::Truncated {
+                    needed: offset.saturating_add(unit_len),
+                    given: data.len(),
+                });
+            }
+        }
+
+        if is_trailer(remaining) {
+            break;
+        }
+        blocks.push(&remaining[..unit_len]);
+        offset += unit_len;
+    }
+
+    Ok(blocks)
+}
+
+pub(crate) fn pending_unit_len(unit: &[u8]) -> Result<usize, ReedSolomonError> {
+    if unit.len() < MAGIC.len() {
+        return Ok(MAGIC.len() - unit.len());
+    }
+
+    let required: Option<usize> = if is_trailer(unit) {
+        if unit.len() < TRAILER_HEADER_SIZE {
+            return Ok(TRAILER_HEADER_SIZE - unit.len());
+        }
+
+        let len: [u8; 8] = unit[8..16].try_into().expect("the length is 8 bytes");
+        usize::try_from(u64::from_be_bytes(len))
+            .ok()
+            .filter(|len: &usize| *len >= TRAILER_HEADER_SIZE)
+    } else if unit.starts_with(&GROUP_MAGIC) {
+        if unit.len() < GROUP_HEADER_SIZE + VERSION_END {
+            return Ok(GROUP_HEADER_SIZE + VERSION_END - unit.len());
+        }
+
+        let header_size: usize = ContainerHeader::peek_size(&unit[GROUP_HEADER_SIZE..])?;
+        let headers_end: usize =
+            GROUP_HEADER_SIZE + GroupHeader::read(unit)?.headers_len(header_size);
+        if unit.len() < headers_end {
+            return Ok(headers_end - unit.len());
+        }
+
+        unit[GROUP_HEADER_SIZE..headers_end]
+            .chunks_exact(header_size)
+            .try_fold(
+                Some(headers_end),
+                |required: Option<usize>, header: &[u8]| {
+                    let body_len: Option<usize> = ContainerHeader::read(header)?.body_len();
+                    Ok::<_, ReedSolomonError>(required.zip(body_len).and_then(
+                        |(required, body_len): (usize, usize)| required.checked_add(body_len),
+                    ))
+                },
+            )?
+    } else {
+        if unit.len() < VERSION_END {
+            return Ok(VERSION_END - unit.len());
+        }
+
+        let header_size: usize = ContainerHeader::peek_size(unit)?;
+        if unit.len() < header_size {
+            return Ok(header_size - unit.len());
+        }
+
+        ContainerHeader::read(unit)?
+            .body_len()
+            .and_then(|body_len: usize| body_len.checked_add(header_size))
+    };
+
+    let required: usize = required.ok_or(ReedSolomonError::SizeOverflow)?;
+    Ok(required.saturating_sub(unit.len()))
+}
+
+pub(crate) fn read_unit<R: Read>(
+    reader: &mut R,
+    unit: &mut Vec<u8>,
+) -> Result<bool, ReedSolomonError> {
+    unit.clear();
+
+    loop {
+        let pending: usize = pending_unit_len(unit)?;
+        if pending == 0 {
+            return Ok(!is_trailer(unit));
+        }
+
+        let read: usize = (&mut *reader).take(pending as u64).read_to_end(unit)?;
+        if read < pending {
+            if unit.is_empty() {
+                return Ok(false);
+            }
+            return Err(ReedSolomonError::Truncated {
+                needed: unit.len() - read + pending,
+                given: unit.len(),
+            });
+        }
+    }
+}
+
+pub(crate) fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled: usize = 0;
+
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::header::HEADER_SIZE;
+    use crate::progress::ProgressCounter;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_stream_roundtrip_multiple_blocks() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..10_000u32).map(|i: u32| (i % 251) as u8).collect();
+
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_stream(Cursor::new(&original_data), &mut encoded)?;
+        assert_eq!(split_blocks(&encoded)?.len(), 10);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let written: u64 = codec.decode_stream(Cursor::new(&encoded), &mut decoded)?;
+
+        assert_eq!(written, original_data.len() as u64);
+        assert_eq!(decoded, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_reports_progress() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let encoder: Arc<ProgressCounter> = Arc::new(ProgressCounter::new(Some(10_000)));
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_config(config)?.with_progress(encoder.clone());
+        let original_data: Vec<u8> = (0..10_000u32).map(|i: u32| (i % 251) as u8).collect();
+
+        let encoded: Vec<u8> = codec.encode_blocks(&original_data)?;
+        assert_eq!((encoder.bytes(), encoder.shards()), (10_000, 60));
+        assert_eq!(encoder.fraction(), Some(1.0));
+
+        let decoder: Arc<ProgressCounter> = Arc::new(ProgressCounter::new(None));
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_config(config)?.with_progress(decoder.clone());
+        codec.decode_blocks(&encoded)?;
+        assert_eq!((decoder.bytes(), decoder.shards()), (10_000, 60));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_empty_input() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+
+        let mut encoded: Vec<u8> = Vec::new();
+        assert_eq!(
+            codec.encode_stream(Cursor::new(Vec::new()), &mut encoded)?,
+            0
+        );
+
+        let mut decoded: Vec<u8> = Vec::new();
+        assert_eq!(codec.decode_stream(Cursor::new(encoded), &mut decoded)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_damaged_block_is_isolated() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(512)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..2000u32).map(|i: u32| (i % 199) as u8).collect();
+
+        let mut encoded: Vec<u8> = codec.encode_blocks(&original_data)?;
+        let second_block: usize = split_blocks(&encoded)?[0].len();
+        encoded[second_block + HEADER_SIZE + 3] ^= 0xff;
+
+        let blocks: Vec<&[u8]> = split_blocks(&encoded)?;
+        assert_eq!(codec.decode(blocks[1])?, original_data[512..1024]);
+        assert_eq!(codec.decode_blocks(&encoded)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_rejects_truncated_input() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(4, 2)?;
+
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_stream(Cursor::new(b"Hello, World!".to_vec()), &mut encoded)?;
+        encoded.truncate(encoded.len() - 3);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        assert!(codec
+            .decode_stream(Cursor::new(encoded), &mut decoded)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interleaving_survives_long_burst() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(256)?;
+        let original_data: Vec<u8> = (0..1024u32).map(|i: u32| (i % 193) as u8).collect();
+        let shard_len: usize = leading_header(
+            &ReedSolomonCodec::with_config(config)?.encode(&[0u8; 256])?,
+        )?
+        .shard_size as usize;
+        let burst_len: usize = 7 * shard_len;
+
+        let interleaved: ReedSolomonCodec =
+            ReedSolomonCodec::with_config(config.with_interleave_depth(4)?)?;
+        let mut encoded: Vec<u8> = interleaved.encode_blocks(&original_data)?;
+        assert_eq!(split_blocks(&encoded)?.len(), 1);
+
+        let body_start: usize = GROUP_HEADER_SIZE + 4 * HEADER_SIZE;
+        for byte in &mut encoded[body_start + 10..body_start + 10 + burst_len] {
+            *byte ^= 0xff;
+        }
+        assert_eq!(interleaved.decode_blocks(&encoded)?, original_data);
+
+        let plain: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let mut encoded: Vec<u8> = plain.encode_blocks(&original_data)?;
+        for byte in &mut encoded[HEADER_SIZE + 10..HEADER_SIZE + 10 + burst_len] {
+            *byte ^= 0xff;
+        }
+        assert!(plain.decode_blocks(&encoded).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interleaved_stream_with_partial_group() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(3, 2)?
+            .with_block_size(100)?
+            .with_interleave_depth(3)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..650u32).map(|i: u32| (i % 211) as u8).collect();
+
+        let encoded: Vec<u8> = codec.encode_blocks(&original_data)?;
+        let units: Vec<&[u8]> = split_blocks(&encoded)?;
+
+        assert_eq!(units.len(), 3);
+        assert!(units[0].starts_with(&GROUP_MAGIC));
+        assert!(units[2].starts_with(&MAGIC));
+        assert_eq!(leading_header(&encoded)?.payload_len, 100);
+        assert_eq!(codec.decode_unit(units[1])?, original_data[300..600]);
+        assert_eq!(codec.decode_blocks(&encoded)?, original_data);
+        assert!(codec.decode_blocks(&encoded[..encoded.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hybrid_stream_replicates_short_groups() -> Result<(), ReedSolomonError> {
+        use crate::config::ErasureCode;
+        use crate::replication::HybridPolicy;
+
+        let config: EncoderConfig = EncoderConfig::new(3, 2)?
+            .with_block_size(100)?
+            .with_interleave_depth(3)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?
+            .with_hybrid_policy(HybridPolicy::matching(&config, 64)?);
+        let original_data: Vec<u8> = (0..650u32).map(|i: u32| (i % 211) as u8).collect();
+
+        let encoded: Vec<u8> = codec.encode_blocks(&original_data)?;
+        let units: Vec<&[u8]> = split_blocks(&encoded)?;
+        assert_eq!(units.len(), 3);
+        assert_eq!(leading_header(units[1])?.erasure_code, ErasureCode::Matrix);
+        assert_eq!(
+            leading_header(units[2])?.erasure_code,
+            ErasureCode::Replication
+        );
+
+        let mut decoded: Vec<u8> = Vec::new();
+        ReedSolomonCodec::with_config(config)?
+            .decode_stream(Cursor::new(&encoded), &mut decoded)?;
+        assert_eq!(decoded, original_data);
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted_interleaved_stream() -> Result<(), ReedSolomonError> {
+        use crate::encryption::EncryptionKey;
+
+        let config: EncoderConfig = EncoderConfig::new(3, 2)?
+            .with_block_size(100)?
+            .with_interleave_depth(3)?;
+        let key: EncryptionKey = EncryptionKey::generate();
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_config(config)?.with_encryption_key(key);
+        let original_data: Vec<u8> = (0..650u32).map(|i: u32| (i % 211) as u8).collect();
+
+        let encoded: Vec<u8> = codec.encode_blocks(&original_data)?;
+        assert_eq!(split_blocks(&encoded)?.len(), 3);
+        assert_eq!(codec.decode_blocks(&encoded)?, original_data);
+        assert!(ReedSolomonCodec::with_config(config)?
+            .decode_blocks(&encoded)
+            .is_err());
+        Ok(())
+    }
+}
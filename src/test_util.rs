@@ -0,0 +1,17 @@
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A path under the system temp directory for the test called `name`,
+/// unique to this process, with whatever an earlier run left there
+/// removed.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir: PathBuf = std::env::temp_dir().join(format!(
+        "reed-solomon-codec-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
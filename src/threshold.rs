@@ -0,0 +1,250 @@
+use chacha20poly1305::aead::{Aead, AeadCore, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::codec::ReedSolomonCodec;
+use crate::encryption::{EncryptionKey, KEY_SIZE};
+use crate::error::ReedSolomonError;
+use crate::gf;
+use crate::header::NONCE_SIZE;
+use crate::processor::{DataProcessor, CHECKSUM_SIZE};
+use crate::report::DecodeReport;
+use crate::shard::Shard;
+
+/// The key share and its checksum in front of every split shard.
+pub const SHARE_HEADER_SIZE: usize = KEY_SIZE + CHECKSUM_SIZE;
+/// Shares are evaluated at the nonzero points of GF(2^8).
+pub const MAX_SPLIT_SHARDS: usize = 255;
+
+const SPLIT_AAD: &[u8] = b"reed-solomon-codec split";
+
+/// Splits `secret` into `shares` Shamir shares over GF(2^8), any
+/// `threshold` of which recover it while fewer reveal nothing. Share `i`
+/// is the random polynomial evaluated at `i + 1`.
+pub fn split_secret(
+    secret: &[u8; KEY_SIZE],
+    threshold: usize,
+    shares: usize,
+) -> Result<Vec<[u8; KEY_SIZE]>, ReedSolomonError> {
+    if threshold == 0 || threshold > shares || shares > MAX_SPLIT_SHARDS {
+        return Err(ReedSolomonError::InvalidShardCount {
+            given: shares,
+            min: threshold.max(1),
+            max: MAX_SPLIT_SHARDS,
+        });
+    }
+
+    let mut coefficients: Vec<[u8; KEY_SIZE]> = Vec::with_capacity(threshold);
+    coefficients.push(*secret);
+    coefficients.extend((1..threshold).map(|_| *EncryptionKey::generate().as_bytes()));
+
+    Ok((1..=shares)
+        .map(|x: usize| {
+            let mut share: [u8; KEY_SIZE] = [0u8; KEY_SIZE];
+            for (byte, value) in share.iter_mut().enumerate() {
+                let polynomial: Vec<u8> = coefficients
+                    .iter()
+                    .map(|row: &[u8; KEY_SIZE]| row[byte])
+                    .collect();
+                *value = gf::eval(&polynomial, x as u8);
+            }
+            share
+        })
+        .collect())
+}
+
+/// Recovers the secret from shares keyed by shard index, by Lagrange
+/// interpolation at zero. Indexes must be distinct; too few shares give an
+/// unrelated value rather than an error.
+pub fn combine_shares(shares: &[(usize, [u8; KEY_SIZE])]) -> [u8; KEY_SIZE] {
+    let mut secret: [u8; KEY_SIZE] = [0u8; KEY_SIZE];
+    for (j, (index, share)) in shares.iter().enumerate() {
+        let x_j: u8 = (*index + 1) as u8;
+        let weight: u8 = shares
+            .iter()
+            .enumerate()
+            .filter(|(m, _): &(usize, &(usize, [u8; KEY_SIZE]))| *m != j)
+            .fold(
+                1u8,
+                |weight: u8, (_, (other, _)): (usize, &(usize, [u8; KEY_SIZE]))| {
+                    let x_m: u8 = (*other + 1) as u8;
+                    gf::mul(weight, gf::div(x_m, gf::add(x_m, x_j)))
+                },
+            );
+        for (value, byte) in secret.iter_mut().zip(share) {
+            *value ^= gf::mul(weight, *byte);
+        }
+    }
+    secret
+}
+
+impl ReedSolomonCodec {
+    /// Encrypts `data` under a fresh random key, encodes the ciphertext
+    /// like `encode_shards` and puts a Shamir share of the key in front of
+    /// every shard. Any `data_shards` of the shards decrypt the data;
+    /// fewer hold neither the key nor the plaintext.
+    pub fn encode_shards_split(&self, data: &[u8]) -> Result<Vec<Shard>, ReedSolomonError> {
+        let key: EncryptionKey = EncryptionKey::generate();
+        let shares: Vec<[u8; KEY_SIZE]> = split_secret(
+            key.as_bytes(),
+            self.config().data_shards(),
+            self.config().total_shards(),
+        )?;
+
+        let nonce: XNonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext: Vec<u8> = key
+            .cipher()
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: data,
+                    aad: SPLIT_AAD,
+                },
+            )
+            .map_err(|_| ReedSolomonError::EncryptionFailed)?;
+        let mut sealed: Vec<u8> = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+
+        Ok(self
+            .encode_shards(&sealed)?
+            .into_iter()
+            .zip(shares)
+            .map(|(shard, share): (Shard, [u8; KEY_SIZE])| {
+                let mut payload: Vec<u8> = DataProcessor::append_checksum(share.to_vec());
+                payload.extend_from_slice(&shard.payload);
+                Shard { payload, ..shard }
+            })
+            .collect())
+    }
+
+    pub fn decode_shards_split(&self, shards: Vec<Shard>) -> Result<Vec<u8>, ReedSolomonError> {
+        self.decode_shards_split_with_report(shards)
+            .map(|(decoded, _): (Vec<u8>, DecodeReport)| decoded)
+    }
+
+    /// Recombines the key from the shares of `shards`, decodes the
+    /// ciphertext they carry and decrypts it. A shard whose share fails its
+    /// checksum still contributes its body and is reported as corrupted.
+    pub fn decode_shards_split_with_report(
+        &self,
+        shards: Vec<Shard>,
+    ) -> Result<(Vec<u8>, DecodeReport), ReedSolomonError> {
+        let mut shares: Vec<(usize, [u8; KEY_SIZE])> = Vec::with_capacity(shards.len());
+        let mut rejected: Vec<usize> = Vec::new();
+        let mut bodies: Vec<Shard> = Vec::with_capacity(shards.len());
+        for shard in shards {
+            if shard.payload.len() < SHARE_HEADER_SIZE {
+                rejected.push(shard.index as usize);
+                continue;
+            }
+            match DataProcessor::verify_checksum(&shard.payload[..SHARE_HEADER_SIZE]) {
+                Some(share) => {
+                    let mut bytes: [u8; KEY_SIZE] = [0u8; KEY_SIZE];
+                    bytes.copy_from_slice(share);
+                    shares.push((shard.index as usize, bytes));
+                }
+                None => rejected.push(shard.index as usize),
+            }
+            bodies.push(Shard {
+                payload: shard.payload[SHARE_HEADER_SIZE..].to_vec(),
+                ..shard
+            });
+        }
+
+        let needed: usize = self.config().data_shards();
+        if shares.len() < needed {
+            return Err(ReedSolomonError::TooFewShards {
+                needed,
+                given: shares.len(),
+            });
+        }
+
+        let (sealed, report): (Vec<u8>, DecodeReport) =
+            self.decode_shards_rejecting(bodies, rejected)?;
+        if sealed.len() < NONCE_SIZE {
+            return Err(ReedSolomonError::AuthenticationFailed);
+        }
+
+        let key: EncryptionKey = EncryptionKey::new(combine_shares(&shares[..needed]));
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        let decoded: Vec<u8> = key
+            .cipher()
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: SPLIT_AAD,
+                },
+            )
+            .map_err(|_| ReedSolomonError::AuthenticationFailed)?;
+        Ok((decoded, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_threshold_of_shares_recovers_secret() -> Result<(), ReedSolomonError> {
+        let secret: [u8; KEY_SIZE] = *EncryptionKey::generate().as_bytes();
+        let shares: Vec<[u8; KEY_SIZE]> = split_secret(&secret, 3, 5)?;
+        assert_eq!(shares.len(), 5);
+
+        for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1], [1, 2, 3]] {
+            let picked: Vec<(usize, [u8; KEY_SIZE])> =
+                subset.iter().map(|&i: &usize| (i, shares[i])).collect();
+            assert_eq!(combine_shares(&picked), secret);
+        }
+        assert_ne!(combine_shares(&[(0, shares[0]), (1, shares[1])]), secret);
+
+        assert!(split_secret(&secret, 0, 5).is_err());
+        assert!(split_secret(&secret, 6, 5).is_err());
+        assert!(split_secret(&secret, 3, MAX_SPLIT_SHARDS + 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_shards_need_data_shards_to_decode() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: &[u8] = b"a backup handed out among five friends, any three suffice";
+        let shards: Vec<Shard> = codec.encode_shards_split(original_data)?;
+        assert_eq!(shards.len(), 5);
+        assert!(!shards[0]
+            .payload
+            .windows(8)
+            .any(|window: &[u8]| window == &original_data[..8]));
+
+        let quorum: Vec<Shard> = vec![shards[1].clone(), shards[3].clone(), shards[4].clone()];
+        assert_eq!(codec.decode_shards_split(quorum)?, original_data);
+
+        assert!(matches!(
+            codec.decode_shards_split(shards[..2].to_vec()),
+            Err(ReedSolomonError::TooFewShards { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_damaged_share_is_reported() -> Result<(), ReedSolomonError> {
+        let codec: ReedSolomonCodec = ReedSolomonCodec::new(3, 2)?;
+        let original_data: &[u8] = b"shares carry their own checksum";
+        let mut shards: Vec<Shard> = codec.encode_shards_split(original_data)?;
+
+        shards[0].payload[0] ^= 0x01;
+        let (decoded, report): (Vec<u8>, DecodeReport) =
+            codec.decode_shards_split_with_report(shards.clone())?;
+        assert_eq!(decoded, original_data);
+        assert_eq!(report.corrupted, vec![0]);
+
+        shards.truncate(3);
+        assert!(matches!(
+            codec.decode_shards_split(shards),
+            Err(ReedSolomonError::TooFewShards {
+                needed: 3,
+                given: 2
+            })
+        ));
+        Ok(())
+    }
+}
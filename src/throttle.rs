@@ -0,0 +1,138 @@
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A token bucket that holds I/O to a steady number of bytes per second,
+/// with bursts of up to one second's worth. One limiter can be shared by
+/// several codecs and streams to cap their combined rate.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_second: u64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Negative while callers are paying off a request larger than what
+    /// was available.
+    available: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// `bytes_per_second` is raised to at least one.
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second: u64 = bytes_per_second.max(1);
+        Self {
+            bytes_per_second,
+            bucket: Mutex::new(Bucket {
+                available: bytes_per_second as f64,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second
+    }
+
+    /// Takes `bytes` from the budget, sleeping until the budget has
+    /// recovered if it is overspent.
+    pub fn acquire(&self, bytes: u64) {
+        let wait: Duration = {
+            let mut bucket: MutexGuard<'_, Bucket> =
+                self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+            let rate: f64 = self.bytes_per_second as f64;
+            let now: Instant = Instant::now();
+            bucket.available = (bucket.available
+                + now.duration_since(bucket.refilled).as_secs_f64() * rate)
+                .min(rate);
+            bucket.refilled = now;
+            bucket.available -= bytes as f64;
+            if bucket.available >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.available / rate)
+        };
+        thread::sleep(wait);
+    }
+}
+
+/// Wraps a reader or writer so every byte through it is taken from a
+/// `RateLimiter`, for I/O the codec does not perform itself.
+#[derive(Debug)]
+pub struct Throttled<T> {
+    inner: T,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<T> Throttled<T> {
+    pub fn new(inner: T, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read: usize = self.inner.read(buf)?;
+        self.limiter.acquire(read as u64);
+        Ok(read)
+    }
+}
+
+impl<W: Write> Write for Throttled<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written: usize = self.inner.write(buf)?;
+        self.limiter.acquire(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::codec::ReedSolomonCodec;
+    use crate::config::EncoderConfig;
+    use crate::error::ReedSolomonError;
+
+    #[test]
+    fn test_limiter_waits_once_burst_is_spent() {
+        let limiter: RateLimiter = RateLimiter::new(10_000);
+        let started: Instant = Instant::now();
+        limiter.acquire(10_000);
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        limiter.acquire(3_000);
+        assert!(started.elapsed() >= Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_rate_limited_stream_roundtrip() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let limiter: Arc<RateLimiter> = Arc::new(RateLimiter::new(20_000));
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_config(config)?.with_rate_limit(Arc::clone(&limiter));
+        let original_data: Vec<u8> = (0..10_000u32).map(|i: u32| (i % 251) as u8).collect();
+
+        let started: Instant = Instant::now();
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_stream(Cursor::new(&original_data), &mut encoded)?;
+        let mut decoded: Vec<u8> = Vec::new();
+        codec.decode_stream(Throttled::new(&encoded[..], limiter), &mut decoded)?;
+
+        assert_eq!(decoded, original_data);
+        assert!(started.elapsed() >= Duration::from_millis(800));
+        Ok(())
+    }
+}
@@ -0,0 +1,410 @@
+use std::borrow::Cow;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::codec::ReedSolomonCodec;
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::header::{ContainerHeader, GroupHeader, GROUP_HEADER_SIZE, GROUP_MAGIC};
+use crate::processor::{crc32c_checksum, CHECKSUM_SIZE};
+use crate::stream::split_blocks;
+
+pub const TRAILER_MAGIC: [u8; 4] = *b"RSTL";
+pub const TRAILER_VERSION: u8 = 1;
+/// Magic, version, reserved bytes, trailer length and unit count.
+pub const TRAILER_HEADER_SIZE: usize = 24;
+/// The trailer length and magic again, in the last bytes of the blob.
+pub const TRAILER_FOOTER_SIZE: usize = 12;
+
+const ENTRY_HEADER_SIZE: usize = 12;
+
+/// One unit of the stream: its length and a copy of the container or
+/// group headers in front of its shards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailerEntry {
+    pub unit_len: u64,
+    pub headers: Vec<u8>,
+}
+
+/// The end-of-file copy of every header in a block stream, so the stream
+/// can still be decoded when its first bytes, or any unit's headers, are
+/// damaged. It follows the last unit and ends with its own length, so
+/// readers find it from the end of the blob.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trailer {
+    pub entries: Vec<TrailerEntry>,
+}
+
+impl Trailer {
+    /// Records `unit`, an encoded container or interleaved group.
+    pub fn push(&mut self, unit: &[u8]) -> Result<(), ReedSolomonError> {
+        let headers_len: usize = if unit.starts_with(&GROUP_MAGIC) {
+            let header_size: usize = ContainerHeader::peek_size(&unit[GROUP_HEADER_SIZE..])?;
+            GROUP_HEADER_SIZE + GroupHeader::read(unit)?.headers_len(header_size)
+        } else {
+            ContainerHeader::peek_size(unit)?
+        };
+
+        self.entries.push(TrailerEntry {
+            unit_len: unit.len() as u64,
+            headers: unit[..headers_len].to_vec(),
+        });
+        Ok(())
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        let entries: usize = self
+            .entries
+            .iter()
+            .map(|entry: &TrailerEntry| ENTRY_HEADER_SIZE + entry.headers.len())
+            .sum();
+        TRAILER_HEADER_SIZE + entries + CHECKSUM_SIZE + TRAILER_FOOTER_SIZE
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let len: usize = self.encoded_len();
+        let mut bytes: Vec<u8> = vec![0u8; TRAILER_HEADER_SIZE];
+        bytes[..4].copy_from_slice(&TRAILER_MAGIC);
+        bytes[4] = TRAILER_VERSION;
+        BigEndian::write_u64(&mut bytes[8..16], len as u64);
+        BigEndian::write_u64(&mut bytes[16..24], self.entries.len() as u64);
+
+        for entry in &self.entries {
+            let mut entry_header: [u8; ENTRY_HEADER_SIZE] = [0u8; ENTRY_HEADER_SIZE];
+            BigEndian::write_u64(&mut entry_header[..8], entry.unit_len);
+            BigEndian::write_u32(&mut entry_header[8..], entry.headers.len() as u32);
+            bytes.extend_from_slice(&entry_header);
+            bytes.extend_from_slice(&entry.headers);
+        }
+
+        let checksum: u32 = crc32c_checksum(&bytes);
+        bytes.extend_from_slice(&checksum.to_be_bytes());
+        bytes.extend_from_slice(&(len as u64).to_be_bytes());
+        bytes.extend_from_slice(&TRAILER_MAGIC);
+        bytes
+    }
+
+    /// The trailer length recorded in the footer at the end of `data`, or
+    /// `None` when `data` does not end with one.
+    pub fn footer_len(data: &[u8]) -> Option<usize> {
+        let footer: &[u8] = data.get(data.len().checked_sub(TRAILER_FOOTER_SIZE)?..)?;
+        if footer[8..] != TRAILER_MAGIC {
+            return None;
+        }
+
+        let min: usize = TRAILER_HEADER_SIZE + CHECKSUM_SIZE + TRAILER_FOOTER_SIZE;
+        usize::try_from(BigEndian::read_u64(&footer[..8]))
+            .ok()
+            .filter(|len: &usize| (min..=data.len()).contains(len))
+    }
+
+    /// Parses the trailer at the end of `data`.
+    pub fn read(data: &[u8]) -> Result<Self, ReedSolomonError> {
+        let len: usize = Self::footer_len(data).ok_or(HeaderError::MissingMagic)?;
+        let trailer: &[u8] = &data[data.len() - len..];
+        if trailer[..4] != TRAILER_MAGIC {
+            return Err(HeaderError::MissingMagic.into());
+        }
+
+        let body_end: usize = len - TRAILER_FOOTER_SIZE - CHECKSUM_SIZE;
+        let checksum: u32 = BigEndian::read_u32(&trailer[body_end..body_end + CHECKSUM_SIZE]);
+        if crc32c_checksum(&trailer[..body_end]) != checksum {
+            return Err(HeaderError::ChecksumMismatch.into());
+        }
+        if trailer[4] != TRAILER_VERSION {
+            return Err(HeaderError::UnsupportedVersion(trailer[4]).into());
+        }
+
+        let count: u64 = BigEndian::read_u64(&trailer[16..24]);
+        let mut entries: Vec<TrailerEntry> = Vec::new();
+        let mut offset: usize = TRAILER_HEADER_SIZE;
+        for _ in 0..count {
+            let entry_header: &[u8] = trailer[..body_end]
+                .get(offset..offset + ENTRY_HEADER_SIZE)
+                .ok_or(HeaderError::Inconsistent)?;
+            let headers_len: usize = BigEndian::read_u32(&entry_header[8..]) as usize;
+            offset += ENTRY_HEADER_SIZE;
+            let headers: &[u8] = trailer[..body_end]
+                .get(offset..offset + headers_len)
+                .ok_or(HeaderError::Inconsistent)?;
+            offset += headers_len;
+
+            entries.push(TrailerEntry {
+                unit_len: BigEndian::read_u64(&entry_header[..8]),
+                headers: headers.to_vec(),
+            });
+        }
+        if offset != body_end {
+            return Err(HeaderError::Inconsistent.into());
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Parses the trailer at the end of a seekable `reader`, reading only
+    /// the trailer, and returns it with the length of the units before it.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<(Self, u64), ReedSolomonError> {
+        let (len, units_len): (usize, u64) =
+            Self::read_footer_len(reader)?.ok_or(HeaderError::MissingMagic)?;
+        let mut data: Vec<u8> = vec![0u8; len];
+        reader.seek(SeekFrom::Start(units_len))?;
+        reader.read_exact(&mut data)?;
+        Ok((Self::read(&data)?, units_len))
+    }
+
+    /// The trailer length recorded in the footer at the end of `reader`,
+    /// and where the trailer starts, or `None` when there is no footer.
+    fn read_footer_len<R: Read + Seek>(
+        reader: &mut R,
+    ) -> Result<Option<(usize, u64)>, ReedSolomonError> {
+        let total: u64 = reader.seek(SeekFrom::End(0))?;
+        if total < TRAILER_FOOTER_SIZE as u64 {
+            return Ok(None);
+        }
+        let mut footer: [u8; TRAILER_FOOTER_SIZE] = [0u8; TRAILER_FOOTER_SIZE];
+        reader.seek(SeekFrom::Start(total - TRAILER_FOOTER_SIZE as u64))?;
+        reader.read_exact(&mut footer)?;
+        if footer[8..] != TRAILER_MAGIC {
+            return Ok(None);
+        }
+
+        let min: u64 = (TRAILER_HEADER_SIZE + CHECKSUM_SIZE + TRAILER_FOOTER_SIZE) as u64;
+        let len: u64 = BigEndian::read_u64(&footer[..8]);
+        if !(min..=total).contains(&len) {
+            return Ok(None);
+        }
+        Ok(usize::try_from(len)
+            .ok()
+            .map(|trailer_len: usize| (trailer_len, total - len)))
+    }
+}
+
+pub(crate) fn is_trailer(unit: &[u8]) -> bool {
+    unit.starts_with(&TRAILER_MAGIC)
+}
+
+impl ReedSolomonCodec {
+    /// Encodes like `encode_stream`, then appends a `Trailer` with a copy
+    /// of every unit's headers. Readers that do not look for the trailer
+    /// stop at it, so the output still decodes with `decode_stream`.
+    pub fn encode_stream_with_trailer<R: Read, W: Write>(
+        &self,
+        reader: R,
+        mut writer: W,
+    ) -> Result<u64, ReedSolomonError> {
+        let mut trailer: Trailer = Trailer::default();
+        let written: u64 =
+            self.encode_units(reader, &mut writer, |unit: &[u8]| trailer.push(unit))?;
+
+        let bytes: Vec<u8> = trailer.to_bytes();
+        writer.write_all(&bytes)?;
+        writer.flush()?;
+        Ok(written + bytes.len() as u64)
+    }
+
+    /// Decodes a block stream, using its trailer, when there is one, to
+    /// replace the headers of any unit whose own headers no longer match.
+    /// Without an intact trailer this is `decode_blocks`.
+    pub fn decode_with_trailer(&self, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        let Some(trailer_len) = Trailer::footer_len(data) else {
+            return self.decode_blocks(data);
+        };
+        let units: &[u8] = &data[..data.len() - trailer_len];
+        let trailer: Trailer = match Trailer::read(data) {
+            Ok(trailer) => trailer,
+            Err(_) => return self.decode_units(split_blocks(units)?),
+        };
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut offset: usize = 0;
+        for entry in &trailer.entries {
+            let end: usize = usize::try_from(entry.unit_len)
+                .ok()
+                .and_then(|len: usize| offset.checked_add(len))
+                .ok_or(ReedSolomonError::SizeOverflow)?;
+            let unit: &[u8] = units.get(offset..end).ok_or(ReedSolomonError::Truncated {
+                needed: end,
+                given: units.len(),
+            })?;
+            decoded.extend_from_slice(&self.decode_trailer_unit(entry, unit)?);
+            offset = end;
+        }
+        if offset != units.len() {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: offset,
+                given: units.len(),
+            });
+        }
+
+        Ok(decoded)
+    }
+
+    /// Decodes like `decode_with_trailer`, but reads one unit at a time
+    /// from a seekable `reader`, so only the trailer and the unit being
+    /// decoded are held in memory. Returns the number of bytes written.
+    pub fn decode_file_with_trailer<R: Read + Seek, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<u64, ReedSolomonError> {
+        let Some((_, units_len)) = Trailer::read_footer_len(&mut reader)? else {
+            reader.rewind()?;
+            return self.decode_stream(reader, writer);
+        };
+        let trailer: Trailer = match Trailer::read_from(&mut reader) {
+            Ok((trailer, _)) => trailer,
+            Err(_) => {
+                reader.rewind()?;
+                return self.decode_stream(reader.take(units_len), writer);
+            }
+        };
+
+        reader.rewind()?;
+        let mut unit: Vec<u8> = self.pool().take(0);
+        let mut offset: u64 = 0;
+        let mut written: u64 = 0;
+        for entry in &trailer.entries {
+            let end: u64 = offset
+                .checked_add(entry.unit_len)
+                .ok_or(ReedSolomonError::SizeOverflow)?;
+            if end > units_len {
+                return Err(ReedSolomonError::Truncated {
+                    needed: usize::try_from(end).unwrap_or(usize::MAX),
+                    given: units_len as usize,
+                });
+            }
+            unit.clear();
+            (&mut reader).take(entry.unit_len).read_to_end(&mut unit)?;
+
+            let decoded: Vec<u8> = self.decode_trailer_unit(entry, &unit)?;
+            self.throttle(decoded.len());
+            writer.write_all(&decoded)?;
+            written += decoded.len() as u64;
+            offset = end;
+        }
+        self.pool().give(unit);
+        if offset != units_len {
+            return Err(ReedSolomonError::LengthMismatch {
+                expected: offset as usize,
+                given: units_len as usize,
+            });
+        }
+
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Decodes `unit`, first putting back the headers `entry` recorded for
+    /// it when its own no longer match.
+    fn decode_trailer_unit(
+        &self,
+        entry: &TrailerEntry,
+        unit: &[u8],
+    ) -> Result<Vec<u8>, ReedSolomonError> {
+        if unit.len() < entry.headers.len() {
+            return Err(HeaderError::Inconsistent.into());
+        }
+
+        let unit: Cow<'_, [u8]> = if unit.starts_with(&entry.headers) {
+            Cow::Borrowed(unit)
+        } else {
+            Cow::Owned([&entry.headers, &unit[entry.headers.len()..]].concat())
+        };
+        self.decode_unit(&unit)
+    }
+
+    fn decode_units(&self, units: Vec<&[u8]>) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut decoded: Vec<u8> = Vec::new();
+        for unit in units {
+            decoded.extend_from_slice(&self.decode_unit(unit)?);
+        }
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::header::HEADER_SIZE;
+    use std::io::Cursor;
+
+    fn encode(config: EncoderConfig, data: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut encoded: Vec<u8> = Vec::new();
+        ReedSolomonCodec::with_config(config)?.encode_stream_with_trailer(data, &mut encoded)?;
+        Ok(encoded)
+    }
+
+    fn decode_file(codec: &ReedSolomonCodec, encoded: &[u8]) -> Result<Vec<u8>, ReedSolomonError> {
+        let mut decoded: Vec<u8> = Vec::new();
+        codec.decode_file_with_trailer(Cursor::new(encoded), &mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn test_trailer_roundtrip() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1000)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..3500u32).map(|i: u32| (i % 251) as u8).collect();
+        let encoded: Vec<u8> = encode(config, &original_data)?;
+
+        let trailer: Trailer = Trailer::read(&encoded)?;
+        assert_eq!(trailer.entries.len(), 4);
+        assert_eq!(Trailer::footer_len(&encoded), Some(trailer.encoded_len()));
+        assert_eq!(
+            encoded.len() - trailer.encoded_len(),
+            codec.encode_blocks(&original_data)?.len()
+        );
+
+        assert_eq!(codec.decode_blocks(&encoded)?, original_data);
+        assert_eq!(codec.decode_with_trailer(&encoded)?, original_data);
+        assert_eq!(
+            codec.decode_with_trailer(&codec.encode_blocks(&original_data)?)?,
+            original_data
+        );
+        assert_eq!(
+            Trailer::read_from(&mut Cursor::new(&encoded))?,
+            (
+                trailer.clone(),
+                (encoded.len() - trailer.encoded_len()) as u64
+            )
+        );
+        assert_eq!(decode_file(&codec, &encoded)?, original_data);
+        assert_eq!(
+            decode_file(&codec, &codec.encode_blocks(&original_data)?)?,
+            original_data
+        );
+
+        let mut damaged: Vec<u8> = encoded.clone();
+        let checksum_end: usize = damaged.len() - TRAILER_FOOTER_SIZE;
+        damaged[checksum_end - 1] ^= 0x01;
+        assert!(Trailer::read(&damaged).is_err());
+        assert_eq!(codec.decode_with_trailer(&damaged)?, original_data);
+        assert_eq!(decode_file(&codec, &damaged)?, original_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailer_replaces_damaged_headers() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?
+            .with_block_size(500)?
+            .with_interleave_depth(2)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..2600u32).map(|i: u32| (i % 239) as u8).collect();
+        let mut encoded: Vec<u8> = encode(config, &original_data)?;
+
+        for byte in &mut encoded[..GROUP_HEADER_SIZE + HEADER_SIZE] {
+            *byte = 0;
+        }
+        assert!(codec.decode_blocks(&encoded).is_err());
+        assert_eq!(codec.decode_with_trailer(&encoded)?, original_data);
+        assert_eq!(decode_file(&codec, &encoded)?, original_data);
+
+        let trailer_start: usize = encoded.len() - Trailer::read(&encoded)?.encoded_len();
+        encoded[trailer_start + TRAILER_HEADER_SIZE] ^= 0x01;
+        assert!(Trailer::read(&encoded).is_err());
+        assert!(codec.decode_with_trailer(&encoded).is_err());
+        assert!(decode_file(&codec, &encoded).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::DataSizeLimits;
+use crate::error::ReedSolomonError;
+use crate::files::{manifest_path, shard_paths, ShardManifest};
+use crate::header::{ContainerHeader, GROUP_MAGIC};
+use crate::processor::DataProcessor;
+use crate::stream::{group_blocks, read_full, read_unit};
+
+/// What a verification pass found. Shard indices and block numbers are
+/// sorted and listed once.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub blocks: u64,
+    /// Payload bytes the checked blocks carry, after any compression or
+    /// encryption, as recorded in their headers or manifest.
+    pub payload_len: u64,
+    /// Shards that are missing, truncated or fail their checksum in at
+    /// least one block.
+    pub damaged_shards: Vec<usize>,
+    /// Blocks whose shards all pass their checksums but whose parity
+    /// disagrees with the data.
+    pub inconsistent_blocks: Vec<u64>,
+    /// Blocks with more damaged shards than parity shards.
+    pub lost_blocks: Vec<u64>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.damaged_shards.is_empty()
+            && self.inconsistent_blocks.is_empty()
+            && self.lost_blocks.is_empty()
+    }
+
+    pub fn is_recoverable(&self) -> bool {
+        self.lost_blocks.is_empty()
+    }
+
+    fn check_block(
+        &mut self,
+        codec: &ReedSolomonCodec,
+        shards: &[Option<&[u8]>],
+    ) -> Result<(), ReedSolomonError> {
+        let block: u64 = self.blocks;
+        self.blocks += 1;
+
+        let damaged: Vec<usize> = (0..shards.len())
+            .filter(|i: &usize| {
+                shards[*i]
+                    .and_then(DataProcessor::verify_checksum)
+                    .is_none()
+            })
+            .collect();
+        if damaged.is_empty() {
+            let stored: Vec<Vec<u8>> = shards
+                .iter()
+                .flatten()
+                .map(|s: &&[u8]| s.to_vec())
+                .collect();
+            if !codec.verify(&stored)? {
+                self.inconsistent_blocks.push(block);
+            }
+        } else if damaged.len() > codec.config().parity_shards() {
+            self.lost_blocks.push(block);
+        }
+
+        self.damaged_shards.extend(damaged);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Self {
+        self.damaged_shards.sort_unstable();
+        self.damaged_shards.dedup();
+        self
+    }
+}
+
+impl ReedSolomonCodec {
+    /// Checks the checksums and parity of every block in a container
+    /// stream without decoding it. Blocks written with a different
+    /// configuration are checked with the one in their header.
+    pub fn verify_stream<R: Read>(&self, mut reader: R) -> Result<VerifyReport, ReedSolomonError> {
+        let mut report: VerifyReport = VerifyReport::default();
+        let mut unit: Vec<u8> = Vec::new();
+
+        while read_unit(&mut reader, &mut unit)? {
+            if unit.starts_with(&GROUP_MAGIC) {
+                for (header, body) in group_blocks(&unit)? {
+                    self.verify_block(&header, &body, &mut report)?;
+                }
+            } else {
+                let header: ContainerHeader = ContainerHeader::read(&unit)?;
+                self.verify_block(&header, &unit[header.size()..], &mut report)?;
+            }
+        }
+
+        Ok(report.finish())
+    }
+
+    fn verify_block(
+        &self,
+        header: &ContainerHeader,
+        body: &[u8],
+        report: &mut VerifyReport,
+    ) -> Result<(), ReedSolomonError> {
+        if body.is_empty() {
+            let limits: DataSizeLimits = self.config().data_size_limits();
+            return Err(ReedSolomonError::InvalidDataSize {
+                given: 0,
+                min: limits.min,
+                max: limits.max,
+            });
+        }
+
+        let shards: Vec<Option<&[u8]>> = body
+            .chunks_exact(header.shard_size as usize)
+            .map(Some)
+            .collect();
+        report.payload_len += header.payload_len;
+        if header.matches(self.config()) {
+            report.check_block(self, &shards)
+        } else {
+            report.check_block(&self.for_header(header)?, &shards)
+        }
+    }
+}
+
+/// Checks the shard files `encode_to_files` wrote under `basename`,
+/// including files that are missing or longer than the manifest says.
+pub fn verify_files(dir: &Path, basename: &str) -> Result<VerifyReport, ReedSolomonError> {
+    let manifest: ShardManifest = ShardManifest::read(&manifest_path(dir, basename))?;
+    let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(manifest.config()?)?;
+    verify_shard_files(&codec, &manifest, &shard_paths(dir, basename, &manifest))
+}
+
+pub(crate) fn verify_shard_files(
+    codec: &ReedSolomonCodec,
+    manifest: &ShardManifest,
+    paths: &[PathBuf],
+) -> Result<VerifyReport, ReedSolomonError> {
+    let mut readers: Vec<Option<BufReader<File>>> = paths
+        .iter()
+        .map(|path: &PathBuf| File::open(path).ok().map(BufReader::new))
+        .collect();
+
+    let mut report: VerifyReport = VerifyReport {
+        payload_len: manifest.payload_len,
+        ..VerifyReport::default()
+    };
+    for block in 0..manifest.block_count() {
+        let shard_size: usize = manifest.shard_size(block);
+        let shards: Vec<Option<Vec<u8>>> = readers
+            .iter_mut()
+            .map(|reader: &mut Option<BufReader<File>>| {
+                let Some(file) = reader else {
+                    return Ok(None);
+                };
+                let mut shard: Vec<u8> = vec![0u8; shard_size];
+                let filled: usize = read_full(file, &mut shard)?;
+                Ok((filled == shard_size).then_some(shard))
+            })
+            .collect::<io::Result<_>>()?;
+        let shards: Vec<Option<&[u8]>> = shards.iter().map(Option::as_deref).collect();
+        report.check_block(codec, &shards)?;
+    }
+
+    for (index, reader) in readers.iter_mut().enumerate() {
+        if let Some(file) = reader {
+            if file.read(&mut [0u8; 1])? > 0 {
+                report.damaged_shards.push(index);
+            }
+        }
+    }
+
+    Ok(report.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::config::EncoderConfig;
+    use crate::processor::CHECKSUM_SIZE;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_verify_stream_finds_damage() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..3000u32).map(|i: u32| (i % 253) as u8).collect();
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_stream(Cursor::new(&original_data), &mut encoded)?;
+
+        let report: VerifyReport = ReedSolomonCodec::new(2, 1)?.verify_stream(&encoded[..])?;
+        assert_eq!(report.blocks, 3);
+        assert_eq!(report.payload_len, 3000);
+        assert!(report.is_clean());
+
+        let header: ContainerHeader = ContainerHeader::read(&encoded)?;
+        let (start, shard_size): (usize, usize) = (header.size(), header.shard_size as usize);
+        encoded[start + shard_size + 10] ^= 0xff;
+        let parity: usize = start + 4 * shard_size;
+        let mut stale: Vec<u8> = encoded[parity..parity + shard_size - CHECKSUM_SIZE].to_vec();
+        stale[0] ^= 0x01;
+        encoded[parity..parity + shard_size]
+            .copy_from_slice(&DataProcessor::append_checksum(stale));
+
+        let report: VerifyReport = codec.verify_stream(&encoded[..])?;
+        assert_eq!(report.damaged_shards, vec![1]);
+        assert_eq!(report.inconsistent_blocks, Vec::<u64>::new());
+        assert!(report.is_recoverable());
+
+        encoded[start + shard_size + 10] ^= 0xff;
+        let report: VerifyReport = codec.verify_stream(&encoded[..])?;
+        assert_eq!(report.damaged_shards, Vec::<usize>::new());
+        assert_eq!(report.inconsistent_blocks, vec![0]);
+        assert!(!report.is_clean());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_interleaved_stream() -> Result<(), ReedSolomonError> {
+        let config: EncoderConfig = EncoderConfig::new(3, 2)?
+            .with_block_size(512)?
+            .with_interleave_depth(3)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let mut encoded: Vec<u8> = Vec::new();
+        codec.encode_stream(Cursor::new(vec![7u8; 2000]), &mut encoded)?;
+
+        let report: VerifyReport = codec.verify_stream(&encoded[..])?;
+        assert_eq!(report.blocks, 4);
+        assert!(report.is_clean());
+
+        let len: usize = encoded.len();
+        encoded[len - 1] ^= 0xff;
+        assert!(!codec.verify_stream(&encoded[..])?.is_clean());
+        assert!(codec.verify_stream(&encoded[..len - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_files() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("verify-files");
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?.with_block_size(1024)?;
+        let codec: ReedSolomonCodec = ReedSolomonCodec::with_config(config)?;
+        let original_data: Vec<u8> = (0..5000u32).map(|i: u32| (i % 241) as u8).collect();
+        let manifest: ShardManifest =
+            codec.encode_to_files(Cursor::new(&original_data), &dir, "data")?;
+
+        let report: VerifyReport = verify_files(&dir, "data")?;
+        assert_eq!(report.blocks, 5);
+        assert_eq!(report.payload_len, 5000);
+        assert!(report.is_clean());
+
+        let mut longer: Vec<u8> = fs::read(manifest.shard_path(&dir, "data", 2))?;
+        longer.push(0);
+        fs::write(manifest.shard_path(&dir, "data", 2), longer)?;
+        fs::remove_file(manifest.shard_path(&dir, "data", 5))?;
+        let report: VerifyReport = verify_files(&dir, "data")?;
+        assert_eq!(report.damaged_shards, vec![2, 5]);
+        assert!(report.is_recoverable());
+
+        fs::remove_file(manifest.shard_path(&dir, "data", 0))?;
+        fs::remove_file(manifest.shard_path(&dir, "data", 1))?;
+        let report: VerifyReport = verify_files(&dir, "data")?;
+        assert_eq!(report.lost_blocks, vec![0, 1, 2, 3, 4]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
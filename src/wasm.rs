@@ -0,0 +1,64 @@
+use wasm_bindgen::prelude::*;
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::{EncoderConfig, GaloisField};
+
+#[wasm_bindgen(js_name = ReedSolomonCodec)]
+pub struct WasmCodec {
+    codec: ReedSolomonCodec,
+}
+
+#[wasm_bindgen(js_class = ReedSolomonCodec)]
+impl WasmCodec {
+    #[wasm_bindgen(constructor)]
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<WasmCodec, JsError> {
+        Ok(Self {
+            codec: ReedSolomonCodec::new(data_shards, parity_shards)?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = withGf16)]
+    pub fn with_gf16(data_shards: usize, parity_shards: usize) -> Result<WasmCodec, JsError> {
+        let config: EncoderConfig =
+            EncoderConfig::with_field(data_shards, parity_shards, GaloisField::Gf16)?;
+        Ok(Self {
+            codec: ReedSolomonCodec::with_config(config)?,
+        })
+    }
+
+    #[wasm_bindgen(getter, js_name = dataShards)]
+    pub fn data_shards(&self) -> usize {
+        self.codec.config().data_shards()
+    }
+
+    #[wasm_bindgen(getter, js_name = parityShards)]
+    pub fn parity_shards(&self) -> usize {
+        self.codec.config().parity_shards()
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, JsError> {
+        Ok(self.codec.encode(data)?)
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, JsError> {
+        Ok(self.codec.decode(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_roundtrip() {
+        let Ok(codec) = WasmCodec::new(4, 2) else {
+            panic!("valid shard counts were rejected");
+        };
+        let Ok(encoded) = codec.encode(b"chunk before upload") else {
+            panic!("encoding failed");
+        };
+
+        assert!(matches!(codec.decode(&encoded), Ok(decoded) if decoded == b"chunk before upload"));
+        assert_eq!(codec.data_shards(), 4);
+    }
+}
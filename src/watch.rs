@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::codec::ReedSolomonCodec;
+use crate::config::EncoderConfig;
+use crate::error::ReedSolomonError;
+use crate::files::{ShardManifest, MANIFEST_EXTENSION};
+use crate::platform::{portable_file_name, write_replacing};
+use crate::store::{FileStore, ShardId};
+
+/// How often the folder is scanned where there are no file system
+/// notifications.
+pub const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Extension of the file next to each manifest that names the file the set
+/// was encoded from.
+pub const SOURCE_EXTENSION: &str = "source";
+
+/// What `scan` and `run` report for each file they tried to encode.
+pub type WatchEvent = (String, Result<ShardManifest, ReedSolomonError>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: SystemTime,
+}
+
+/// Keeps a shard set in a `FileStore` for every file in a drop folder.
+/// Each file is encoded under its portable name with a block size picked
+/// from its length, and encoded again as the next generation of its set
+/// when it changes. A file whose manifest is newer than the file itself
+/// is current, so nothing is encoded twice across restarts. The set records
+/// which file it was encoded from, and a different file with the same
+/// portable name, such as `aux_.txt` next to `aux.txt`, is refused rather
+/// than encoded over it. Only the top level of the folder is watched;
+/// hidden and `.partial` files are skipped.
+#[derive(Debug)]
+pub struct DropFolder {
+    config: EncoderConfig,
+    dir: PathBuf,
+    store: FileStore,
+    /// Files as the last scan saw them, so a scan only encodes files that
+    /// have stopped changing.
+    seen: BTreeMap<String, FileStamp>,
+}
+
+impl DropFolder {
+    /// The shard sets cannot live in `dir` itself, or they would be
+    /// encoded in turn.
+    pub fn new(
+        config: EncoderConfig,
+        dir: &Path,
+        store: FileStore,
+    ) -> Result<Self, ReedSolomonError> {
+        if fs::canonicalize(dir)? == fs::canonicalize(store.dir()).unwrap_or_default() {
+            return Err(ReedSolomonError::InvalidFileName(store.dir().to_path_buf()));
+        }
+        Ok(Self {
+            config,
+            dir: dir.to_path_buf(),
+            store,
+            seen: BTreeMap::new(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Encodes the file `name` unless its shard set is current. Returns
+    /// `None` when there was nothing to do, including when the file is
+    /// gone or is not a regular file.
+    pub fn encode_file(&self, name: &str) -> Result<Option<ShardManifest>, ReedSolomonError> {
+        if !is_candidate(name) {
+            return Ok(None);
+        }
+        let path: PathBuf = self.dir.join(name);
+        let file: File = match File::open(&path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let metadata: fs::Metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+
+        let basename: String = portable_file_name(name);
+        let source_path: PathBuf = self
+            .store
+            .dir()
+            .join(format!("{}.{}", basename, SOURCE_EXTENSION));
+        let claimed: bool = match fs::read_to_string(&source_path) {
+            Ok(source) if source == name => true,
+            Ok(_) => return Err(ReedSolomonError::InvalidFileName(path)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => false,
+            Err(error) => return Err(error.into()),
+        };
+        let manifest_path: PathBuf = self.store.path(&ShardId::manifest(&basename));
+        if let Ok(manifest_metadata) = fs::metadata(&manifest_path) {
+            let current: bool = manifest_metadata.modified()? >= metadata.modified()?
+                && ShardManifest::read(&manifest_path)
+                    .is_ok_and(|manifest: ShardManifest| manifest.payload_len == metadata.len());
+            if current {
+                return Ok(None);
+            }
+        }
+
+        let codec: ReedSolomonCodec =
+            ReedSolomonCodec::with_config(self.config.with_auto_block_size(metadata.len())?)?;
+        let manifest: ShardManifest =
+            codec.encode_to_file_store(BufReader::new(file), &self.store, &basename)?;
+        if !claimed {
+            write_replacing(&source_path, name.as_bytes())?;
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Encodes every file in the folder whose shard set is not current.
+    pub fn sync(&self) -> Result<Vec<WatchEvent>, ReedSolomonError> {
+        let mut events: Vec<WatchEvent> = Vec::new();
+        for name in self.file_names()? {
+            if let Some(result) = self.encode_file(&name).transpose() {
+                events.push((name, result));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Like `sync`, but leaves out files whose length or modification
+    /// time changed since the previous scan, as they are likely still
+    /// being written.
+    pub fn scan(&mut self) -> Result<Vec<WatchEvent>, ReedSolomonError> {
+        let mut seen: BTreeMap<String, FileStamp> = BTreeMap::new();
+        let mut events: Vec<WatchEvent> = Vec::new();
+        for name in self.file_names()? {
+            let metadata: fs::Metadata = match fs::metadata(self.dir.join(&name)) {
+                Ok(metadata) => metadata,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error.into()),
+            };
+            let stamp: FileStamp = FileStamp {
+                len: metadata.len(),
+                modified: metadata.modified()?,
+            };
+            if self.seen.get(&name) == Some(&stamp) {
+                if let Some(result) = self.encode_file(&name).transpose() {
+                    events.push((name.clone(), result));
+                }
+            }
+            seen.insert(name, stamp);
+        }
+        self.seen = seen;
+        Ok(events)
+    }
+
+    /// Encodes what is already in the folder, then every file written or
+    /// moved into it, until an error other than a failure to encode one
+    /// file. On Linux it waits for inotify events; elsewhere it calls
+    /// `scan` every `WATCH_POLL_INTERVAL`.
+    pub fn run(&mut self, on_event: &mut dyn FnMut(WatchEvent)) -> Result<(), ReedSolomonError> {
+        #[cfg(target_os = "linux")]
+        {
+            // Watch before the first sync so nothing lands unseen in between.
+            let mut notifier: Notifier = Notifier::new(&self.dir)?;
+            self.sync()?.into_iter().for_each(&mut *on_event);
+            loop {
+                for name in notifier.wait()? {
+                    match name {
+                        Some(name) => {
+                            if let Some(result) = self.encode_file(&name).transpose() {
+                                on_event((name, result));
+                            }
+                        }
+                        None => self.sync()?.into_iter().for_each(&mut *on_event),
+                    }
+                }
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.sync()?.into_iter().for_each(&mut *on_event);
+            loop {
+                std::thread::sleep(WATCH_POLL_INTERVAL);
+                self.scan()?.into_iter().for_each(&mut *on_event);
+            }
+        }
+    }
+
+    fn file_names(&self) -> Result<Vec<String>, ReedSolomonError> {
+        let mut names: Vec<String> = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry: fs::DirEntry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            if let Some(name) = entry
+                .file_name()
+                .to_str()
+                .filter(|name: &&str| is_candidate(name))
+            {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+fn is_candidate(name: &str) -> bool {
+    !name.starts_with('.')
+        && !name.ends_with(".partial")
+        && !name.ends_with(&format!(".{}", MANIFEST_EXTENSION))
+}
+
+/// Reports files closed after writing, or moved into a directory.
+#[cfg(target_os = "linux")]
+struct Notifier {
+    file: File,
+    buffer: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+impl Notifier {
+    fn new(dir: &Path) -> io::Result<Self> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::io::FromRawFd;
+
+        let path: CString = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        // SAFETY: inotify_init1 takes no pointers.
+        let fd: libc::c_int = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `fd` is a new descriptor that nothing else owns, so the
+        // file closes it exactly once.
+        let file: File = unsafe { File::from_raw_fd(fd) };
+        // SAFETY: `path` is NUL-terminated and outlives the call, and `fd`
+        // stays open while `file` is alive.
+        let watch: libc::c_int = unsafe {
+            libc::inotify_add_watch(fd, path.as_ptr(), libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO)
+        };
+        if watch < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            file,
+            buffer: vec![0u8; 64 << 10],
+        })
+    }
+
+    /// Blocks until files are written or moved in and returns their names.
+    /// `None` stands for events the kernel dropped because its queue
+    /// overflowed.
+    fn wait(&mut self) -> io::Result<Vec<Option<String>>> {
+        use byteorder::{ByteOrder, NativeEndian};
+        use std::io::Read;
+
+        const EVENT_SIZE: usize = std::mem::size_of::<libc::inotify_event>();
+        let read: usize = self.file.read(&mut self.buffer)?;
+        let mut names: Vec<Option<String>> = Vec::new();
+        let mut offset: usize = 0;
+        while offset + EVENT_SIZE <= read {
+            let event: &[u8] = &self.buffer[offset..];
+            let mask: u32 = NativeEndian::read_u32(&event[4..]);
+            let name_len: usize = NativeEndian::read_u32(&event[12..]) as usize;
+            let name: &[u8] = &event[EVENT_SIZE..EVENT_SIZE + name_len];
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                names.push(None);
+            } else if let Some(name) = name.split(|byte: &u8| *byte == 0).next() {
+                if !name.is_empty() {
+                    names.push(Some(String::from_utf8_lossy(name).into_owned()));
+                }
+            }
+            offset += EVENT_SIZE + name_len;
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_drop_folder_encodes_new_and_changed_files() -> Result<(), ReedSolomonError> {
+        let root: PathBuf = temp_dir("watch");
+        let (dir, out): (PathBuf, PathBuf) = (root.join("drop"), root.join("shards"));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("report.pdf"), vec![7u8; 5000])?;
+        fs::write(dir.join(".hidden"), b"skipped")?;
+        fs::write(dir.join("upload.partial"), b"skipped")?;
+
+        let config: EncoderConfig = EncoderConfig::new(4, 2)?;
+        assert!(DropFolder::new(config, &dir, FileStore::new(&dir)).is_err());
+        let mut folder: DropFolder = DropFolder::new(config, &dir, FileStore::new(&out))?;
+
+        let events: Vec<WatchEvent> = folder.sync()?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "report.pdf");
+        assert!(folder.sync()?.is_empty());
+
+        // A new file is only encoded once two scans agree on it.
+        fs::write(dir.join("aux.txt"), b"notes")?;
+        assert!(folder.scan()?.is_empty());
+        let events: Vec<WatchEvent> = folder.scan()?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "aux.txt");
+        let store: FileStore = FileStore::new(&out);
+        assert!(store.path(&ShardId::manifest("aux_.txt")).exists());
+
+        // `aux_.txt` has the same portable name, so it must not replace the
+        // set of `aux.txt`, however its manifest compares.
+        fs::write(dir.join("aux_.txt"), b"other notes")?;
+        assert!(matches!(
+            folder.encode_file("aux_.txt"),
+            Err(ReedSolomonError::InvalidFileName(_))
+        ));
+        fs::write(dir.join("aux_.txt"), b"notes")?;
+        assert!(folder.encode_file("aux_.txt").is_err());
+        assert_eq!(fs::read_to_string(out.join("aux_.txt.source"))?, "aux.txt");
+        assert!(folder.encode_file("aux.txt")?.is_none());
+
+        fs::write(dir.join("report.pdf"), vec![9u8; 6000])?;
+        let manifest: ShardManifest = folder
+            .encode_file("report.pdf")?
+            .ok_or(ReedSolomonError::VerificationFailed)?;
+        assert_eq!((manifest.payload_len, manifest.generation), (6000, 1));
+        assert!(folder.encode_file("missing")?.is_none());
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
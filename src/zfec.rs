@@ -0,0 +1,597 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{HeaderError, ReedSolomonError};
+use crate::gf;
+use crate::stream::read_full;
+
+pub const ZFEC_EXTENSION: &str = "fec";
+pub const ZFEC_CHUNK_SIZE: usize = 4096;
+pub const ZFEC_MAX_SHARES: usize = 256;
+
+fn mul_add(out: &mut [u8], input: &[u8], factor: u8) {
+    match factor {
+        0 => {}
+        1 => {
+            for (out, input) in out.iter_mut().zip(input) {
+                *out ^= input;
+            }
+        }
+        _ => {
+            let log_factor: usize = gf::LOG_TABLE[factor as usize] as usize;
+            for (out, input) in out.iter_mut().zip(input) {
+                if *input != 0 {
+                    *out ^= gf::EXP_TABLE[gf::LOG_TABLE[*input as usize] as usize + log_factor];
+                }
+            }
+        }
+    }
+}
+
+fn invert(matrix: &mut [u8], k: usize) -> Result<(), ReedSolomonError> {
+    let mut inverse: Vec<u8> = vec![0u8; k * k];
+    for i in 0..k {
+        inverse[i * k + i] = 1;
+    }
+
+    for col in 0..k {
+        let pivot: usize = (col..k)
+            .find(|row: &usize| matrix[row * k + col] != 0)
+            .ok_or(ReedSolomonError::SingularMatrix)?;
+        for i in 0..k {
+            matrix.swap(col * k + i, pivot * k + i);
+            inverse.swap(col * k + i, pivot * k + i);
+        }
+
+        let scale: u8 = gf::inv(matrix[col * k + col]);
+        for i in 0..k {
+            matrix[col * k + i] = gf::mul(matrix[col * k + i], scale);
+            inverse[col * k + i] = gf::mul(inverse[col * k + i], scale);
+        }
+
+        for row in (0..k).filter(|row: &usize| *row != col) {
+            let factor: u8 = matrix[row * k + col];
+            if factor == 0 {
+                continue;
+            }
+            for i in 0..k {
+                matrix[row * k + i] ^= gf::mul(factor, matrix[col * k + i]);
+                inverse[row * k + i] ^= gf::mul(factor, inverse[col * k + i]);
+            }
+        }
+    }
+
+    matrix.copy_from_slice(&inverse);
+    Ok(())
+}
+
+fn log_ceil(n: usize) -> usize {
+    n.next_power_of_two().trailing_zeros() as usize
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZfecHeader {
+    pub required_shares: usize,
+    pub total_shares: usize,
+    pub padding: usize,
+    pub share_index: usize,
+}
+
+impl ZfecHeader {
+    pub fn new(
+        required_shares: usize,
+        total_shares: usize,
+        padding: usize,
+        share_index: usize,
+    ) -> Result<Self, ReedSolomonError> {
+        validate_share_counts(required_shares, total_shares)?;
+        if padding >= required_shares {
+            return Err(HeaderError::InvalidPadding {
+                padding,
+                max: required_shares,
+            }
+            .into());
+        }
+        if share_index >= total_shares {
+            return Err(ReedSolomonError::ShardIndexOutOfRange {
+                index: share_index,
+                total: total_shares,
+            });
+        }
+
+        Ok(Self {
+            required_shares,
+            total_shares,
+            padding,
+            share_index,
+        })
+    }
+
+    fn bit_widths(required_shares: usize, total_shares: usize) -> (usize, usize, usize) {
+        (
+            log_ceil(total_shares),
+            log_ceil(required_shares),
+            log_ceil(total_shares),
+        )
+    }
+
+    fn size_for(required_shares: usize, total_shares: usize) -> usize {
+        let (k_bits, pad_bits, index_bits) = Self::bit_widths(required_shares, total_shares);
+        (8 + k_bits + pad_bits + index_bits).div_ceil(8).max(2)
+    }
+
+    pub fn size(&self) -> usize {
+        Self::size_for(self.required_shares, self.total_shares)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (k_bits, pad_bits, index_bits) =
+            Self::bit_widths(self.required_shares, self.total_shares);
+
+        let mut value: u32 = (self.total_shares - 1) as u32;
+        value = (value << k_bits) | (self.required_shares - 1) as u32;
+        value = (value << pad_bits) | self.padding as u32;
+        value = (value << index_bits) | self.share_index as u32;
+
+        let size: usize = self.size();
+        value <<= size * 8 - (8 + k_bits + pad_bits + index_bits);
+        value.to_be_bytes()[4 - size..].to_vec()
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, ReedSolomonError> {
+        if data.len() < 2 {
+            return Err(HeaderError::Truncated {
+                needed: 2,
+                given: data.len(),
+            }
+            .into());
+        }
+
+        let total_shares: usize = data[0] as usize + 1;
+        let k_bits: usize = log_ceil(total_shares);
+        let required_shares: usize = ((data[1] as usize) >> (8 - k_bits)) + 1;
+        validate_share_counts(required_shares, total_shares)?;
+
+        let size: usize = Self::size_for(required_shares, total_shares);
+        if data.len() < size {
+            return Err(HeaderError::Truncated {
+                needed: size,
+                given: data.len(),
+            }
+            .into());
+        }
+
+        let (_, pad_bits, index_bits) = Self::bit_widths(required_shares, total_shares);
+        let mut bytes: [u8; 4] = [0u8; 4];
+        bytes[4 - size..].copy_from_slice(&data[..size]);
+        let value: u32 =
+            u32::from_be_bytes(bytes) >> (size * 8 - (8 + k_bits + pad_bits + index_bits));
+
+        let share_index: usize = (value & ((1 << index_bits) - 1)) as usize;
+        let padding: usize = ((value >> index_bits) & ((1 << pad_bits) - 1)) as usize;
+        Self::new(required_shares, total_shares, padding, share_index)
+    }
+}
+
+fn validate_share_counts(
+    required_shares: usize,
+    total_shares: usize,
+) -> Result<(), ReedSolomonError> {
+    if !(1..=ZFEC_MAX_SHARES).contains(&total_shares) {
+        return Err(ReedSolomonError::InvalidShardCount {
+            given: total_shares,
+            min: 1,
+            max: ZFEC_MAX_SHARES,
+        });
+    }
+    if !(1..=total_shares).contains(&required_shares) {
+        return Err(ReedSolomonError::InvalidShardCount {
+            given: required_shares,
+            min: 1,
+            max: total_shares,
+        });
+    }
+    Ok(())
+}
+
+fn block_len(blocks: &[&[u8]]) -> Result<usize, ReedSolomonError> {
+    let block_len: usize = blocks[0].len();
+    match blocks
+        .iter()
+        .find(|block: &&&[u8]| block.len() != block_len)
+    {
+        Some(block) => Err(ReedSolomonError::LengthMismatch {
+            expected: block_len,
+            given: block.len(),
+        }),
+        None => Ok(block_len),
+    }
+}
+
+pub struct ZfecCodec {
+    required_shares: usize,
+    total_shares: usize,
+    matrix: Vec<u8>,
+}
+
+impl ZfecCodec {
+    pub fn new(required_shares: usize, total_shares: usize) -> Result<Self, ReedSolomonError> {
+        validate_share_counts(required_shares, total_shares)?;
+
+        let k: usize = required_shares;
+        let mut vandermonde: Vec<u8> = vec![0u8; total_shares * k];
+        vandermonde[0] = 1;
+        for row in 1..total_shares {
+            for col in 0..k {
+                vandermonde[row * k + col] = gf::exp((row - 1) * col);
+            }
+        }
+
+        let mut top: Vec<u8> = vandermonde[..k * k].to_vec();
+        invert(&mut top, k)?;
+
+        let mut matrix: Vec<u8> = vec![0u8; total_shares * k];
+        for i in 0..k {
+            matrix[i * k + i] = 1;
+        }
+        for row in k..total_shares {
+            for col in 0..k {
+                matrix[row * k + col] = (0..k).fold(0u8, |acc: u8, i: usize| {
+                    acc ^ gf::mul(vandermonde[row * k + i], top[i * k + col])
+                });
+            }
+        }
+
+        Ok(Self {
+            required_shares,
+            total_shares,
+            matrix,
+        })
+    }
+
+    pub fn required_shares(&self) -> usize {
+        self.required_shares
+    }
+
+    pub fn total_shares(&self) -> usize {
+        self.total_shares
+    }
+
+    pub fn encode(&self, blocks: &[&[u8]]) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        let k: usize = self.required_shares;
+        if blocks.len() != k {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: k,
+                given: blocks.len(),
+            });
+        }
+        let block_len: usize = block_len(blocks)?;
+
+        Ok((k..self.total_shares)
+            .map(|row: usize| {
+                let mut parity: Vec<u8> = vec![0u8; block_len];
+                for (col, block) in blocks.iter().enumerate() {
+                    mul_add(&mut parity, block, self.matrix[row * k + col]);
+                }
+                parity
+            })
+            .collect())
+    }
+
+    pub fn decode(
+        &self,
+        blocks: &[&[u8]],
+        share_indices: &[usize],
+    ) -> Result<Vec<Vec<u8>>, ReedSolomonError> {
+        let k: usize = self.required_shares;
+        if blocks.len() != k || share_indices.len() != k {
+            return Err(ReedSolomonError::ShardCountMismatch {
+                expected: k,
+                given: blocks.len().min(share_indices.len()),
+            });
+        }
+        let block_len: usize = block_len(blocks)?;
+
+        let mut seen: Vec<bool> = vec![false; self.total_shares];
+        let mut decode_matrix: Vec<u8> = Vec::with_capacity(k * k);
+        for index in share_indices {
+            if *index >= self.total_shares {
+                return Err(ReedSolomonError::ShardIndexOutOfRange {
+                    index: *index,
+                    total: self.total_shares,
+                });
+            }
+            if seen[*index] {
+                return Err(ReedSolomonError::DuplicateShard { index: *index });
+            }
+            seen[*index] = true;
+            decode_matrix.extend_from_slice(&self.matrix[index * k..(index + 1) * k]);
+        }
+        invert(&mut decode_matrix, k)?;
+
+        Ok((0..k)
+            .map(|row: usize| {
+                let mut primary: Vec<u8> = vec![0u8; block_len];
+                for (col, block) in blocks.iter().enumerate() {
+                    mul_add(&mut primary, block, decode_matrix[row * k + col]);
+                }
+                primary
+            })
+            .collect())
+    }
+
+    fn split_chunk(&self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        let block_len: usize = chunk.len().div_ceil(self.required_shares);
+        (0..self.required_shares)
+            .map(|i: usize| {
+                let start: usize = (i * block_len).min(chunk.len());
+                let end: usize = ((i + 1) * block_len).min(chunk.len());
+                let mut block: Vec<u8> = chunk[start..end].to_vec();
+                block.resize(block_len, 0);
+                block
+            })
+            .collect()
+    }
+}
+
+pub fn zfec_share_path(dir: &Path, prefix: &str, index: usize, total_shares: usize) -> PathBuf {
+    let width: usize = total_shares.to_string().len();
+    dir.join(format!(
+        "{}.{:0width$}_{:0width$}.{}",
+        prefix,
+        index,
+        total_shares,
+        ZFEC_EXTENSION,
+        width = width
+    ))
+}
+
+pub fn encode_to_zfec_files<R: Read>(
+    mut reader: R,
+    dir: &Path,
+    prefix: &str,
+    required_shares: usize,
+    total_shares: usize,
+) -> Result<Vec<PathBuf>, ReedSolomonError> {
+    let codec: ZfecCodec = ZfecCodec::new(required_shares, total_shares)?;
+    fs::create_dir_all(dir)?;
+
+    let paths: Vec<PathBuf> = (0..total_shares)
+        .map(|i: usize| zfec_share_path(dir, prefix, i, total_shares))
+        .collect();
+    let mut files: Vec<File> = paths.iter().map(File::create).collect::<Result<_, _>>()?;
+    for (i, file) in files.iter_mut().enumerate() {
+        file.write_all(&ZfecHeader::new(required_shares, total_shares, 0, i)?.to_bytes())?;
+    }
+
+    let read_size: usize = required_shares * ZFEC_CHUNK_SIZE;
+    let mut chunk: Vec<u8> = vec![0u8; read_size];
+    let mut total_len: u64 = 0;
+
+    loop {
+        let filled: usize = read_full(&mut reader, &mut chunk)?;
+        if filled == 0 {
+            break;
+        }
+
+        let blocks: Vec<Vec<u8>> = codec.split_chunk(&chunk[..filled]);
+        let primary: Vec<&[u8]> = blocks.iter().map(Vec::as_slice).collect();
+        let secondary: Vec<Vec<u8>> = codec.encode(&primary)?;
+        for (file, block) in files.iter_mut().zip(blocks.iter().chain(&secondary)) {
+            file.write_all(block)?;
+        }
+        total_len += filled as u64;
+
+        if filled < read_size {
+            break;
+        }
+    }
+
+    let padding: usize = ((required_shares as u64 - total_len % required_shares as u64)
+        % required_shares as u64) as usize;
+    for (i, file) in files.iter_mut().enumerate() {
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&ZfecHeader::new(required_shares, total_shares, padding, i)?.to_bytes())?;
+        file.flush()?;
+    }
+
+    Ok(paths)
+}
+
+pub fn find_zfec_shares(share: &Path) -> Result<Vec<PathBuf>, ReedSolomonError> {
+    let invalid = || ReedSolomonError::InvalidFileName(share.to_path_buf());
+
+    let stem: &str = share
+        .file_name()
+        .and_then(OsStr::to_str)
+        .and_then(|name: &str| name.strip_suffix(&format!(".{}", ZFEC_EXTENSION)))
+        .ok_or_else(invalid)?;
+    let (prefix, numbers) = stem.rsplit_once('.').ok_or_else(invalid)?;
+    let total_shares: usize = numbers
+        .split_once('_')
+        .and_then(|(_, total): (&str, &str)| total.parse().ok())
+        .ok_or_else(invalid)?;
+
+    let dir: &Path = share.parent().unwrap_or(Path::new("."));
+    Ok((0..total_shares)
+        .map(|i: usize| zfec_share_path(dir, prefix, i, total_shares))
+        .filter(|path: &PathBuf| path.is_file())
+        .collect())
+}
+
+pub fn decode_from_zfec_files<W: Write>(
+    paths: &[PathBuf],
+    mut writer: W,
+) -> Result<u64, ReedSolomonError> {
+    let mut shares: Vec<(ZfecHeader, BufReader<File>)> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let mut reader: BufReader<File> = BufReader::new(File::open(path)?);
+        let mut header_bytes: Vec<u8> = vec![0u8; 2];
+        reader.read_exact(&mut header_bytes)?;
+        let total_shares: usize = header_bytes[0] as usize + 1;
+        let k_bits: usize = log_ceil(total_shares);
+        let required_shares: usize = ((header_bytes[1] as usize) >> (8 - k_bits)) + 1;
+        header_bytes.resize(ZfecHeader::size_for(required_shares, total_shares), 0);
+        reader.read_exact(&mut header_bytes[2..])?;
+
+        let header: ZfecHeader = ZfecHeader::read(&header_bytes)?;
+        if shares
+            .iter()
+            .any(|(other, _): &(ZfecHeader, BufReader<File>)| {
+                other.share_index == header.share_index
+            })
+        {
+            continue;
+        }
+        shares.push((header, reader));
+    }
+
+    let first: ZfecHeader = shares
+        .first()
+        .map(|(header, _): &(ZfecHeader, BufReader<File>)| *header)
+        .ok_or(ReedSolomonError::TooFewShards {
+            needed: 1,
+            given: 0,
+        })?;
+    if shares
+        .iter()
+        .any(|(header, _): &(ZfecHeader, BufReader<File>)| {
+            (header.required_shares, header.total_shares, header.padding)
+                != (first.required_shares, first.total_shares, first.padding)
+        })
+    {
+        return Err(HeaderError::Inconsistent.into());
+    }
+
+    if shares.len() < first.required_shares {
+        return Err(ReedSolomonError::TooFewShards {
+            needed: first.required_shares,
+            given: shares.len(),
+        });
+    }
+    shares.sort_by_key(|(header, _): &(ZfecHeader, BufReader<File>)| header.share_index);
+    shares.truncate(first.required_shares);
+
+    let codec: ZfecCodec = ZfecCodec::new(first.required_shares, first.total_shares)?;
+    let indices: Vec<usize> = shares
+        .iter()
+        .map(|(header, _): &(ZfecHeader, BufReader<File>)| header.share_index)
+        .collect();
+
+    let mut pending: Vec<u8> = Vec::new();
+    let mut written: u64 = 0;
+    loop {
+        let mut chunks: Vec<Vec<u8>> = Vec::with_capacity(shares.len());
+        for (_, reader) in shares.iter_mut() {
+            let mut chunk: Vec<u8> = vec![0u8; ZFEC_CHUNK_SIZE];
+            let filled: usize = read_full(reader, &mut chunk)?;
+            chunk.truncate(filled);
+            chunks.push(chunk);
+        }
+
+        let blocks: Vec<&[u8]> = chunks.iter().map(Vec::as_slice).collect();
+        if block_len(&blocks)? == 0 {
+            break;
+        }
+
+        writer.write_all(&pending)?;
+        written += pending.len() as u64;
+
+        pending = codec.decode(&blocks, &indices)?.concat();
+    }
+
+    let end: usize =
+        pending
+            .len()
+            .checked_sub(first.padding)
+            .ok_or(ReedSolomonError::Truncated {
+                needed: first.padding,
+                given: pending.len(),
+            })?;
+    writer.write_all(&pending[..end])?;
+    written += end as u64;
+
+    writer.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_header_bit_layout() -> Result<(), ReedSolomonError> {
+        let header: ZfecHeader = ZfecHeader::new(3, 10, 1, 4)?;
+        assert_eq!(header.to_bytes(), [0x09, 0x25, 0x00]);
+        assert_eq!(ZfecHeader::read(&header.to_bytes())?, header);
+        assert_eq!(ZfecHeader::new(1, 1, 0, 0)?.to_bytes(), [0x00, 0x00]);
+
+        for (k, m) in [(1, 2), (2, 3), (16, 20), (100, 256), (256, 256)] {
+            for (padding, index) in [(0, 0), (k - 1, m - 1)] {
+                let header: ZfecHeader = ZfecHeader::new(k, m, padding, index)?;
+                assert_eq!(ZfecHeader::read(&header.to_bytes())?, header);
+            }
+        }
+        assert!(ZfecHeader::new(4, 3, 0, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encoding_matrix() -> Result<(), ReedSolomonError> {
+        let codec: ZfecCodec = ZfecCodec::new(2, 3)?;
+        assert_eq!(codec.matrix, [1, 0, 0, 1, 3, 2]);
+
+        let parity: Vec<Vec<u8>> = codec.encode(&[&[1, 0], &[0, 1]])?;
+        assert_eq!(parity, vec![vec![3, 2]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_recovers_from_any_k_shares() -> Result<(), ReedSolomonError> {
+        let codec: ZfecCodec = ZfecCodec::new(3, 6)?;
+        let primary: Vec<Vec<u8>> = (0..3u8)
+            .map(|i: u8| (0..16u8).map(|j: u8| i.wrapping_mul(31) ^ j).collect())
+            .collect();
+        let blocks: Vec<&[u8]> = primary.iter().map(Vec::as_slice).collect();
+        let all: Vec<Vec<u8>> = primary
+            .iter()
+            .cloned()
+            .chain(codec.encode(&blocks)?)
+            .collect();
+
+        for indices in [[0, 1, 2], [3, 4, 5], [5, 1, 3]] {
+            let chosen: Vec<&[u8]> = indices.iter().map(|i: &usize| all[*i].as_slice()).collect();
+            assert_eq!(codec.decode(&chosen, &indices)?, primary);
+        }
+        assert!(codec.decode(&blocks, &[0, 0, 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_files_roundtrip_with_missing_shares() -> Result<(), ReedSolomonError> {
+        let dir: PathBuf = temp_dir("zfec");
+        let original_data: Vec<u8> = (0..20_000u32).map(|i: u32| (i % 251) as u8).collect();
+
+        let paths: Vec<PathBuf> =
+            encode_to_zfec_files(Cursor::new(&original_data), &dir, "data", 3, 10)?;
+        assert_eq!(paths[4], dir.join("data.04_10.fec"));
+        assert_eq!(ZfecHeader::read(&fs::read(&paths[0])?)?.padding, 1);
+
+        fs::remove_file(&paths[0])?;
+        fs::remove_file(&paths[2])?;
+        let found: Vec<PathBuf> = find_zfec_shares(&paths[5])?;
+        assert_eq!(found.len(), 8);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decode_from_zfec_files(&found, &mut decoded)?;
+        assert_eq!(decoded, original_data);
+        assert!(decode_from_zfec_files(&found[..2], &mut Vec::new()).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}